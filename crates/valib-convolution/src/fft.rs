@@ -0,0 +1,133 @@
+//! Real-valued FFT used by the convolver.
+//!
+//! With the `realfft` feature, transforms are delegated to the `realfft` crate. Otherwise, the
+//! radix-2 FFT of [`valib_core::math::fft`] is used, which requires the transform length to be a
+//! power of two.
+#[cfg(feature = "realfft")]
+pub use backend::FftScalar;
+#[cfg(not(feature = "realfft"))]
+pub use builtin::FftScalar;
+
+#[cfg(feature = "realfft")]
+pub use backend::RealFft;
+#[cfg(not(feature = "realfft"))]
+pub use builtin::RealFft;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+#[cfg(not(feature = "realfft"))]
+mod builtin {
+    use super::sealed::Sealed;
+    use valib_core::Scalar;
+
+    /// Sample types the convolver can transform, that is `f32` and `f64`.
+    pub trait FftScalar: Scalar + Sealed {}
+
+    impl FftScalar for f32 {}
+    impl FftScalar for f64 {}
+
+    /// Radix-2 real FFT from `valib_core`.
+    pub type RealFft<T> = valib_core::math::fft::RealFft<T>;
+}
+
+#[cfg(feature = "realfft")]
+mod backend {
+    use std::sync::Arc;
+
+    use super::sealed::Sealed;
+    use nalgebra::Complex;
+    use realfft::{ComplexToReal, FftNum, RealFftPlanner, RealToComplex};
+    use valib_core::Scalar;
+
+    /// Sample types the convolver can transform, that is `f32` and `f64`.
+    pub trait FftScalar: Scalar + FftNum + Sealed {}
+
+    /// Zero spectrum bin
+    fn zero<T: Scalar>() -> Complex<T> {
+        Complex::new(T::zero(), T::zero())
+    }
+
+    impl FftScalar for f32 {}
+    impl FftScalar for f64 {}
+
+    /// Real FFT planned with `realfft`.
+    pub struct RealFft<T: FftScalar> {
+        forward: Arc<dyn RealToComplex<T>>,
+        inverse: Arc<dyn ComplexToReal<T>>,
+        real: Box<[T]>,
+        spectrum: Box<[Complex<T>]>,
+        scratch: Box<[Complex<T>]>,
+    }
+
+    impl<T: FftScalar> RealFft<T> {
+        /// Create a new FFT of the given length. Lengths which are powers of two are the fastest.
+        pub fn new(len: usize) -> Self {
+            let mut planner = RealFftPlanner::new();
+            let forward = planner.plan_fft_forward(len);
+            let inverse = planner.plan_fft_inverse(len);
+            let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+            Self {
+                forward,
+                inverse,
+                real: vec![T::zero(); len].into_boxed_slice(),
+                spectrum: vec![zero(); len / 2 + 1].into_boxed_slice(),
+                scratch: vec![zero(); scratch_len].into_boxed_slice(),
+            }
+        }
+
+        /// Forward transform of `input`, writing the `len / 2 + 1` non-negative frequency bins.
+        pub fn forward(&mut self, input: &[T], output: &mut [Complex<T>]) {
+            // realfft uses its input as scratch space
+            self.real.copy_from_slice(input);
+            self.forward
+                .process_with_scratch(&mut self.real, output, &mut self.scratch)
+                .expect("Buffer lengths match the planned FFT");
+        }
+
+        /// Unnormalized inverse transform of the `len / 2 + 1` non-negative frequency bins.
+        pub fn inverse(&mut self, input: &[Complex<T>], output: &mut [T]) {
+            self.spectrum.copy_from_slice(input);
+            // The DC and Nyquist bins of a real signal are real; rounding errors in the imaginary
+            // parts would make realfft return an error.
+            let last = self.spectrum.len() - 1;
+            self.spectrum[0].im = T::zero();
+            self.spectrum[last].im = T::zero();
+            self.inverse
+                .process_with_scratch(&mut self.spectrum, output, &mut self.scratch)
+                .expect("Buffer lengths match the planned FFT");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "realfft"))]
+mod tests {
+    use super::*;
+    use nalgebra::Complex;
+
+    #[test]
+    fn realfft_matches_builtin_fft() {
+        const N: usize = 64;
+        let input = (0..N)
+            .map(|i| f64::sin(i as f64 * 0.3) + 0.2 * f64::cos(i as f64 * 2.1))
+            .collect::<Vec<_>>();
+        let zero = Complex::new(0.0, 0.0);
+        let (mut expected, mut actual) = (vec![zero; N / 2 + 1], vec![zero; N / 2 + 1]);
+        valib_core::math::fft::RealFft::new(N).forward(&input, &mut expected);
+        let mut fft = RealFft::new(N);
+        fft.forward(&input, &mut actual);
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!((a - e).norm() < 1e-9, "{a} != {e}");
+        }
+
+        let mut output = vec![0.0; N];
+        fft.inverse(&actual, &mut output);
+        for (x, y) in input.iter().zip(&output) {
+            assert!((x - y / N as f64).abs() < 1e-9, "{x} != {y}");
+        }
+    }
+}
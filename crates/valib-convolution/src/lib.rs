@@ -0,0 +1,264 @@
+#![warn(missing_docs)]
+//! # Convolution
+//!
+//! This crate provides fast FFT-based convolution for `valib`, for applying long impulse responses
+//! such as cabinet simulations or convolution reverbs.
+//!
+//! The FFT is computed by a built-in radix-2 implementation by default. Enabling the `realfft`
+//! feature switches to the [`realfft`](https://docs.rs/realfft) crate instead.
+use nalgebra::Complex;
+use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use valib_core::dsp::{DSPMeta, DSPProcessBlock};
+use valib_core::Scalar;
+
+pub use fft::FftScalar;
+use fft::RealFft;
+
+pub mod fft;
+
+/// Uniformly-partitioned FFT convolver.
+///
+/// The impulse response is split into partitions of the block size, which are each convolved with
+/// the input through overlap-add FFT convolution, and the results summed in the frequency domain.
+/// The cost per sample grows with the number of partitions, that is with the length of the impulse
+/// response divided by the block size; larger blocks are cheaper but add more latency.
+///
+/// The input is buffered until a full block is available, which delays the output by one block;
+/// this is reported as the latency of the processor. The processor can be called with blocks of
+/// any size regardless of its own block size.
+pub struct Convolver<T: FftScalar> {
+    block_size: usize,
+    fft: RealFft<T>,
+    /// Spectra of the impulse response partitions, one after the other
+    ir_spectra: Box<[Complex<T>]>,
+    /// Spectra of the past input blocks, one after the other, as a ring buffer
+    input_spectra: Box<[Complex<T>]>,
+    /// Position of the spectrum of the latest input block in the ring buffer
+    spectra_pos: usize,
+    input: Box<[T]>,
+    output: Box<[T]>,
+    overlap: Box<[T]>,
+    pos: usize,
+    time: Box<[T]>,
+    accumulator: Box<[Complex<T>]>,
+}
+
+impl<T: FftScalar> Convolver<T> {
+    /// Create a new convolver with the given impulse response.
+    ///
+    /// # Arguments
+    ///
+    /// * `ir`: Impulse response to convolve the input with
+    /// * `block_size`: Partition size, rounded up to the next power of two. This is also the
+    ///     latency of the convolver.
+    ///
+    /// returns: Convolver<T>
+    pub fn new(ir: &[T], block_size: usize) -> Self {
+        let block_size = block_size.max(1).next_power_of_two();
+        let num_partitions = ir.len().div_ceil(block_size).max(1);
+        let bins = block_size + 1;
+        let zero = Complex::new(T::zero(), T::zero());
+        let mut this = Self {
+            block_size,
+            fft: RealFft::new(2 * block_size),
+            ir_spectra: vec![zero; num_partitions * bins].into_boxed_slice(),
+            input_spectra: vec![zero; num_partitions * bins].into_boxed_slice(),
+            spectra_pos: 0,
+            input: vec![T::zero(); block_size].into_boxed_slice(),
+            output: vec![T::zero(); block_size].into_boxed_slice(),
+            overlap: vec![T::zero(); block_size].into_boxed_slice(),
+            pos: 0,
+            time: vec![T::zero(); 2 * block_size].into_boxed_slice(),
+            accumulator: vec![zero; bins].into_boxed_slice(),
+        };
+        this.load_ir(ir);
+        this
+    }
+
+    /// Replace the impulse response, keeping the block size. The state of the convolver is reset.
+    ///
+    /// This allocates, and is therefore not real-time safe.
+    pub fn set_ir(&mut self, ir: &[T]) {
+        *self = Self::new(ir, self.block_size);
+    }
+
+    /// Partition size of the convolver, which is also its latency (samples)
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Number of partitions the impulse response is split into
+    pub fn num_partitions(&self) -> usize {
+        self.ir_spectra.len() / (self.block_size + 1)
+    }
+
+    fn load_ir(&mut self, ir: &[T]) {
+        let bins = self.block_size + 1;
+        // Scale the impulse response by the normalization of the inverse FFT once and for all
+        let scale = <T as Scalar>::from_f64(1.0 / self.time.len() as f64);
+        for (p, spectrum) in self.ir_spectra.chunks_exact_mut(bins).enumerate() {
+            let start = (p * self.block_size).min(ir.len());
+            let end = (start + self.block_size).min(ir.len());
+            self.time.fill(T::zero());
+            for (t, &h) in self.time.iter_mut().zip(&ir[start..end]) {
+                *t = h * scale;
+            }
+            self.fft.forward(&self.time, spectrum);
+        }
+    }
+
+    /// Convolve the full input block with the impulse response
+    fn process_partition(&mut self) {
+        let bins = self.block_size + 1;
+        let num_partitions = self.num_partitions();
+
+        let (head, tail) = self.time.split_at_mut(self.block_size);
+        head.copy_from_slice(&self.input);
+        tail.fill(T::zero());
+        let start = self.spectra_pos * bins;
+        self.fft
+            .forward(&self.time, &mut self.input_spectra[start..start + bins]);
+
+        // Input block k - p is multiplied with impulse response partition p
+        self.accumulator.fill(Complex::new(T::zero(), T::zero()));
+        for (p, h) in self.ir_spectra.chunks_exact(bins).enumerate() {
+            let k = (self.spectra_pos + num_partitions - p) % num_partitions;
+            let x = &self.input_spectra[k * bins..(k + 1) * bins];
+            for ((acc, x), h) in self.accumulator.iter_mut().zip(x).zip(h) {
+                *acc += *x * *h;
+            }
+        }
+        self.spectra_pos = (self.spectra_pos + 1) % num_partitions;
+
+        self.fft.inverse(&self.accumulator, &mut self.time);
+        let (head, tail) = self.time.split_at(self.block_size);
+        for (((y, o), &a), &b) in self
+            .output
+            .iter_mut()
+            .zip(self.overlap.iter_mut())
+            .zip(head)
+            .zip(tail)
+        {
+            *y = a + *o;
+            *o = b;
+        }
+    }
+}
+
+impl<T: FftScalar> DSPMeta for Convolver<T> {
+    type Sample = T;
+
+    fn latency(&self) -> usize {
+        self.block_size
+    }
+
+    fn reset(&mut self) {
+        self.input_spectra.fill(Complex::new(T::zero(), T::zero()));
+        self.spectra_pos = 0;
+        self.input.fill(T::zero());
+        self.output.fill(T::zero());
+        self.overlap.fill(T::zero());
+        self.pos = 0;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: FftScalar> DSPProcessBlock<1, 1> for Convolver<T> {
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, 1>,
+        mut outputs: AudioBufferMut<Self::Sample, 1>,
+    ) {
+        for (x, y) in inputs
+            .get_channel(0)
+            .iter()
+            .zip(outputs.get_channel_mut(0).iter_mut())
+        {
+            self.input[self.pos] = *x;
+            *y = self.output[self.pos];
+            self.pos += 1;
+            if self.pos == self.block_size {
+                self.process_partition();
+                self.pos = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use valib_core::dsp::buffer::AudioBuffer;
+
+    /// Run the convolver over the input, in blocks of the given size
+    fn run(conv: &mut Convolver<f64>, input: &[f64], block: usize) -> Vec<f64> {
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input.chunks(block).zip(output.chunks_mut(block)) {
+            let x = AudioBuffer::new([x]).unwrap();
+            let y = AudioBuffer::new([y]).unwrap();
+            conv.process_block(x, y);
+        }
+        output
+    }
+
+    #[test]
+    fn delayed_impulse_delays_input() {
+        const DELAY: usize = 150;
+        let mut ir = vec![0.0; DELAY + 1];
+        ir[DELAY] = 1.0;
+        let mut conv = Convolver::new(&ir, 50);
+        assert_eq!(64, conv.latency());
+        assert_eq!(3, conv.num_partitions());
+
+        let input = (0..1000)
+            .map(|i| f64::sin(i as f64 * 0.05) + 0.3 * f64::cos(i as f64 * 1.3))
+            .collect::<Vec<_>>();
+        // Odd block size not aligned with the partitions
+        let output = run(&mut conv, &input, 37);
+        for (i, y) in output.into_iter().enumerate() {
+            let expected = i.checked_sub(DELAY + 64).map(|j| input[j]).unwrap_or(0.0);
+            assert!(
+                (y - expected).abs() < 1e-9,
+                "Sample {i}: expected {expected}, got {y}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(1, 16)]
+    #[case(16, 16)]
+    #[case(100, 32)]
+    #[case(1000, 128)]
+    fn matches_direct_convolution(#[case] ir_len: usize, #[case] block_size: usize) {
+        let ir = (0..ir_len)
+            .map(|i| f64::exp(-(i as f64) / 40.0) * f64::sin(i as f64 * 0.7))
+            .collect::<Vec<_>>();
+        let input = (0..2000)
+            .map(|i| ((i * 7919) % 101) as f64 / 50.0 - 1.0)
+            .collect::<Vec<_>>();
+        let mut conv = Convolver::new(&ir, block_size);
+        let output = run(&mut conv, &input, 256);
+
+        for (i, y) in output.into_iter().enumerate() {
+            let Some(n) = i.checked_sub(block_size) else {
+                assert_eq!(0.0, y);
+                continue;
+            };
+            let expected = ir
+                .iter()
+                .enumerate()
+                .filter_map(|(k, h)| Some(h * input.get(n.checked_sub(k)?)?))
+                .sum::<f64>();
+            assert!(
+                (y - expected).abs() < 1e-9,
+                "Sample {i}: expected {expected}, got {y}"
+            );
+        }
+
+        // Reset clears the tail of the previous input
+        conv.reset();
+        let output = run(&mut conv, &vec![0.0; 2000], 256);
+        assert!(output.iter().all(|&y| y == 0.0));
+    }
+}
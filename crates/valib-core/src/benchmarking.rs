@@ -2,7 +2,10 @@
 
 use crate::dsp::DSPProcess;
 use num_traits::Zero;
+use simba::simd::SimdValue;
+use std::fmt;
 use std::hint::black_box;
+use std::time::{Duration, Instant};
 
 /// Benchmark a DSP process by sending it a zeroed stream `amount` times. This automatically
 /// blackboxes the process and input to ensure benchmarking provides relevant results.
@@ -23,3 +26,70 @@ pub fn benchmark_dsp<P: DSPProcess<I, O>, const I: usize, const O: usize>(
         black_box(black_box(&mut dsp).process(black_box(frame)));
     }
 }
+
+/// Result of [`compare_simd`], comparing the throughput of a scalar and a SIMD version of the
+/// same processor.
+#[derive(Debug, Copy, Clone)]
+pub struct SimdComparison {
+    /// Time taken by the scalar processor
+    pub scalar_time: Duration,
+    /// Time taken by the SIMD processor
+    pub simd_time: Duration,
+    /// Number of lanes of the SIMD processor's sample type, relative to the scalar one
+    pub lanes: usize,
+}
+
+impl SimdComparison {
+    /// Throughput speedup of the SIMD processor over the scalar one, accounting for the number of
+    /// channels processed at once in each SIMD sample.
+    pub fn speedup(&self) -> f64 {
+        self.lanes as f64 * self.scalar_time.as_secs_f64() / self.simd_time.as_secs_f64()
+    }
+}
+
+impl fmt::Display for SimdComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scalar: {:?}, simd ({} lanes): {:?}, speedup: {:.2}x",
+            self.scalar_time,
+            self.lanes,
+            self.simd_time,
+            self.speedup()
+        )
+    }
+}
+
+/// Compare the throughput of a scalar and a SIMD version of the same processor, by benchmarking
+/// both with [`benchmark_dsp`] over the same number of samples.
+///
+/// # Arguments
+///
+/// * `amount`: Number of samples to process with each processor
+/// * `scalar_dsp`: Scalar version of the processor
+/// * `simd_dsp`: SIMD version of the processor
+///
+/// returns: SimdComparison
+pub fn compare_simd<PScalar, PSimd, const I: usize, const O: usize>(
+    amount: usize,
+    scalar_dsp: PScalar,
+    simd_dsp: PSimd,
+) -> SimdComparison
+where
+    PScalar: DSPProcess<I, O>,
+    PSimd: DSPProcess<I, O>,
+{
+    let start = Instant::now();
+    benchmark_dsp(amount, scalar_dsp);
+    let scalar_time = start.elapsed();
+
+    let start = Instant::now();
+    benchmark_dsp(amount, simd_dsp);
+    let simd_time = start.elapsed();
+
+    SimdComparison {
+        scalar_time,
+        simd_time,
+        lanes: PSimd::Sample::LANES / PScalar::Sample::LANES,
+    }
+}
@@ -1,15 +1,20 @@
 //! Small [`DSPProcess`] building blocks for reusability.
 
-use std::borrow::Cow;
-use std::marker::PhantomData;
-
-use nalgebra::{Complex, ComplexField, SMatrix, SVector};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use nalgebra::{Complex, ComplexField, SMatrix, SVector, SimdBool, SimdPartialOrd, SimdValue};
 use num_traits::{Euclid, One, Zero};
 use numeric_literals::replace_float_literals;
 
 use crate::dsp::{
+    buffer::{AudioBufferMut, AudioBufferRef},
     parameter::{ParamId, ParamName},
-    DSPMeta, DSPProcess,
+    DSPMeta, DSPProcess, DSPProcessBlock,
 };
 use crate::Scalar;
 use crate::{dsp::analysis::DspAnalysis, util::lerp};
@@ -134,7 +139,7 @@ impl<T: Scalar> HasParameters for P1<T> {
 
     fn set_parameter(&mut self, param: Self::Name, value: f32) {
         match param {
-            P1Params::Cutoff => self.fc = T::from_f64(value as _),
+            P1Params::Cutoff => self.fc = T::from_f32(value),
         }
     }
 }
@@ -349,6 +354,13 @@ macro_rules! series_tuple {
                 )*
             }
 
+            fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+                let Self(($($p),*)) = self;
+                $(
+                $p.set_oversampling_context(factor, base_samplerate);
+                )*
+            }
+
             fn latency(&self) -> usize {
                 let Self(($($p),*)) = self;
                 0 $(
@@ -439,6 +451,12 @@ impl<P: DSPMeta, const C: usize> DSPMeta for Series<[P; C]> {
         }
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        for p in &mut self.0 {
+            p.set_oversampling_context(factor, base_samplerate);
+        }
+    }
+
     fn latency(&self) -> usize {
         self.0.iter().map(|p| p.latency()).sum()
     }
@@ -472,7 +490,7 @@ where
     fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; N]; N] {
         self.0.iter().fold([[Complex::one(); N]; N], |acc, f| {
             let ret = f.h_z(z);
-            std::array::from_fn(|i| std::array::from_fn(|j| acc[i][j] * ret[i][j]))
+            core::array::from_fn(|i| core::array::from_fn(|j| acc[i][j] * ret[i][j]))
         })
     }
 }
@@ -486,6 +504,12 @@ impl<'a, P: DSPMeta> DSPMeta for Series<&'a mut [P]> {
         }
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        for p in &mut *self.0 {
+            p.set_oversampling_context(factor, base_samplerate);
+        }
+    }
+
     fn latency(&self) -> usize {
         self.0.iter().map(|p| p.latency()).sum()
     }
@@ -567,6 +591,11 @@ where
         self.2.set_samplerate(samplerate);
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.0.set_oversampling_context(factor, base_samplerate);
+        self.2.set_oversampling_context(factor, base_samplerate);
+    }
+
     fn latency(&self) -> usize {
         let Self(a, _, b) = self;
         a.latency() + b.latency()
@@ -633,6 +662,13 @@ macro_rules! parallel_tuple {
                 )*
             }
 
+            fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+                let Self(($($p),*)) = self;
+                $(
+                $p.set_oversampling_context(factor, base_samplerate);
+                )*
+            }
+
             fn reset(&mut self) {
                 let Self(($($p),*)) = self;
                 $(
@@ -692,6 +728,12 @@ impl<P: DSPMeta, const C: usize> DSPMeta for Parallel<[P; C]> {
         }
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        for s in &mut self.0 {
+            s.set_oversampling_context(factor, base_samplerate);
+        }
+    }
+
     fn latency(&self) -> usize {
         self.0.iter().fold(0, |max, dsp| max.max(dsp.latency()))
     }
@@ -719,7 +761,7 @@ where
                 dsp.process(x)
             })
             .fold([Self::Sample::from_f64(0.0); O], |out, dsp| {
-                std::array::from_fn(|i| out[i] + dsp[i])
+                core::array::from_fn(|i| out[i] + dsp[i])
             })
     }
 }
@@ -732,7 +774,7 @@ where
     fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; O]; I] {
         self.0.iter().fold([[Complex::zero(); O]; I], |acc, f| {
             let ret = f.h_z(z);
-            std::array::from_fn(|i| std::array::from_fn(|j| acc[i][j] + ret[i][j]))
+            core::array::from_fn(|i| core::array::from_fn(|j| acc[i][j] + ret[i][j]))
         })
     }
 }
@@ -777,7 +819,7 @@ impl<T: Scalar, const I: usize, const O: usize> HasParameters for ModMatrix<T, I
 
     fn set_parameter(&mut self, param: Self::Name, value: f32) {
         match param {
-            ModMatrixParams(inp, out) => self.weights[(out, inp)] = T::from_f64(value as _),
+            ModMatrixParams(inp, out) => self.weights[(out, inp)] = T::from_f32(value),
         }
     }
 }
@@ -808,7 +850,7 @@ where
 {
     fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
         let res = self.weights * SVector::from(x);
-        std::array::from_fn(|i| res[i])
+        core::array::from_fn(|i| res[i])
     }
 }
 
@@ -883,6 +925,13 @@ where
         self.feedback.set_samplerate(samplerate);
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.feedforward
+            .set_oversampling_context(factor, base_samplerate);
+        self.feedback
+            .set_oversampling_context(factor, base_samplerate);
+    }
+
     fn latency(&self) -> usize {
         self.feedforward.latency()
     }
@@ -904,6 +953,11 @@ where
         self.feedforward.set_samplerate(samplerate);
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.feedforward
+            .set_oversampling_context(factor, base_samplerate);
+    }
+
     fn latency(&self) -> usize {
         self.feedforward.latency()
     }
@@ -924,7 +978,7 @@ where
             .mix
             .each_mut()
             .map(|p| p.next_sample_as::<FF::Sample>());
-        let x = std::array::from_fn(|i| self.memory[i] * mix[i] + x[i]);
+        let x = core::array::from_fn(|i| self.memory[i] * mix[i] + x[i]);
         let y = self.feedforward.process(x);
         self.memory = y;
         y
@@ -944,7 +998,7 @@ where
             .each_mut()
             .map(|p| p.next_sample_as::<FF::Sample>());
         let fb = self.feedback.process(self.memory);
-        let x = std::array::from_fn(|i| fb[i] * mix[i] + x[i]);
+        let x = core::array::from_fn(|i| fb[i] * mix[i] + x[i]);
         let y = self.feedforward.process(x);
         self.memory = y;
         y
@@ -1056,6 +1110,13 @@ impl<A: DSPMeta, B: DSPMeta<Sample = A::Sample>> DSPMeta for SwitchAB<A, B> {
         self.b.set_samplerate(samplerate);
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.switch
+            .set_oversampling_context(factor, base_samplerate);
+        self.a.set_oversampling_context(factor, base_samplerate);
+        self.b.set_oversampling_context(factor, base_samplerate);
+    }
+
     fn latency(&self) -> usize {
         let la = if self.is_a_active() {
             self.a.latency()
@@ -1093,8 +1154,1114 @@ impl<
             (true, true) => {
                 let a = self.a.process(x);
                 let b = self.b.process(x);
-                std::array::from_fn(|i| lerp(t, a[i], b[i]))
+                core::array::from_fn(|i| lerp(t, a[i], b[i]))
+            }
+        }
+    }
+}
+
+/// Crossfades to a new instance of a processor when its configuration changes.
+///
+/// Some changes, like switching the type of a filter, cannot be applied to a running processor
+/// without a click, as the internal state doesn't carry over between configurations. This adaptor
+/// instead runs the previous instance alongside the new one, and crossfades from the former to the
+/// latter over a fixed number of samples, after which the previous instance is dropped.
+///
+/// Switching again during a crossfade drops the oldest instance immediately, which can produce a
+/// click if it still had a significant contribution to the output.
+pub struct Crossfader<P> {
+    /// Current processor instance, which the output is fading towards
+    pub current: P,
+    previous: Option<P>,
+    samplerate: f32,
+    fade_len: usize,
+    fade_pos: usize,
+}
+
+impl<P: DSPMeta> Crossfader<P> {
+    /// Create a new crossfader.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the processor will run
+    /// * `inner`: Initial processor instance
+    /// * `fade_len`: Length of the crossfades (samples)
+    ///
+    /// returns: Crossfader<P>
+    pub fn new(samplerate: f32, mut inner: P, fade_len: usize) -> Self {
+        inner.set_samplerate(samplerate);
+        Self {
+            current: inner,
+            previous: None,
+            samplerate,
+            fade_len: fade_len.max(1),
+            fade_pos: 0,
+        }
+    }
+
+    /// Switch to the given freshly configured processor instance, crossfading the output to it
+    /// from the current instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `dsp`: New processor instance. Its sample rate is set to the one of the crossfader.
+    ///
+    /// returns: ()
+    pub fn switch_to(&mut self, mut dsp: P) {
+        dsp.set_samplerate(self.samplerate);
+        self.previous = Some(core::mem::replace(&mut self.current, dsp));
+        self.fade_pos = 0;
+    }
+
+    /// Sets the length of the crossfades (samples). An ongoing crossfade keeps its progress.
+    pub fn set_fade_len(&mut self, fade_len: usize) {
+        self.fade_len = fade_len.max(1);
+        self.fade_pos = self.fade_pos.min(self.fade_len);
+    }
+
+    /// Returns true if the output is currently crossfading between two instances.
+    pub fn is_transitioning(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Unwrap this adaptor and give back the current DSP instance.
+    pub fn into_inner(self) -> P {
+        self.current
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for Crossfader<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.current.set_samplerate(samplerate);
+        if let Some(previous) = &mut self.previous {
+            previous.set_samplerate(samplerate);
+        }
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.current
+            .set_oversampling_context(factor, base_samplerate);
+        if let Some(previous) = &mut self.previous {
+            previous.set_oversampling_context(factor, base_samplerate);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        let previous = self.previous.as_ref().map_or(0, |p| p.latency());
+        self.current.latency().max(previous)
+    }
+
+    fn reset(&mut self) {
+        self.previous = None;
+        self.fade_pos = 0;
+        self.current.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O> for Crossfader<P> {
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        let y = self.current.process(x);
+        let Some(previous) = &mut self.previous else {
+            return y;
+        };
+        let old = previous.process(x);
+        self.fade_pos += 1;
+        let t = Self::Sample::from_f64(self.fade_pos as f64 / self.fade_len as f64);
+        if self.fade_pos >= self.fade_len {
+            self.previous = None;
+        }
+        core::array::from_fn(|i| lerp(t, old[i], y[i]))
+    }
+}
+
+/// Adaptor running the inner processor at a control rate, divided by `DIV` from the audio rate.
+///
+/// The inner processor is called with the current input once every `DIV` samples, and its output
+/// is either held or linearly interpolated in between. This is useful for expensive computations
+/// which don't need audio-rate updates, such as envelopes or filter coefficients. The input is
+/// not filtered before being decimated, and as such should be a slowly varying signal.
+///
+/// Linear interpolation ramps towards the latest computed output over the next `DIV` samples,
+/// which adds `DIV - 1` samples of latency.
+#[derive(Debug, Copy, Clone)]
+pub struct ControlRate<P: DSPMeta, const DIV: usize, const O: usize = 1> {
+    /// Inner processor, running at the control rate
+    pub inner: P,
+    /// Linearly interpolate between control-rate outputs (when false, outputs are held)
+    pub interpolate: bool,
+    counter: usize,
+    previous: [P::Sample; O],
+    current: [P::Sample; O],
+}
+
+impl<P: DSPMeta, const DIV: usize, const O: usize> ControlRate<P, DIV, O> {
+    /// Create a new control-rate adaptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Inner processor, which will have its sample rate divided by `DIV`
+    /// * `interpolate`: Linearly interpolate between control-rate outputs instead of holding them
+    ///
+    /// returns: ControlRate<P, DIV, O>
+    pub fn new(inner: P, interpolate: bool) -> Self {
+        assert!(DIV >= 1, "Control rate divider must be at least 1");
+        Self {
+            inner,
+            interpolate,
+            counter: 0,
+            previous: [P::Sample::zero(); O],
+            current: [P::Sample::zero(); O],
+        }
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta, const DIV: usize, const O: usize> DSPMeta for ControlRate<P, DIV, O> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate / DIV as f32);
+    }
+
+    fn latency(&self) -> usize {
+        let interpolation = if self.interpolate { DIV - 1 } else { 0 };
+        self.inner.latency() * DIV + interpolation
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.counter = 0;
+        self.previous = [P::Sample::zero(); O];
+        self.current = [P::Sample::zero(); O];
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize, const DIV: usize> DSPProcess<I, O>
+    for ControlRate<P, DIV, O>
+{
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        if self.counter == 0 {
+            self.previous = self.current;
+            self.current = self.inner.process(x);
+        }
+        self.counter += 1;
+        let y = if self.interpolate {
+            let t = Self::Sample::from_f64(self.counter as f64 / DIV as f64);
+            core::array::from_fn(|i| lerp(t, self.previous[i], self.current[i]))
+        } else {
+            self.current
+        };
+        if self.counter == DIV {
+            self.counter = 0;
+        }
+        y
+    }
+}
+
+/// Integer delay line, with a delay adjustable at runtime up to a maximum set on creation.
+///
+/// As a processor, the input is delayed by the current delay, which is also reported as its
+/// latency. Feedback structures can instead [`Self::read`] the delayed signal before
+/// [`Self::push`]ing the next input sample.
+#[derive(Debug, Clone)]
+pub struct DelayLine<T> {
+    buffer: Box<[T]>,
+    delay: usize,
+    pos: usize,
+}
+
+impl<T: Scalar> DelayLine<T> {
+    /// Create a new delay line, with its delay set to the maximum delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_delay`: Maximum delay of the line (samples)
+    ///
+    /// returns: DelayLine<T>
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![T::zero(); max_delay + 1].into_boxed_slice(),
+            delay: max_delay,
+            pos: 0,
+        }
+    }
+
+    /// Maximum delay of the line (samples)
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 1
+    }
+
+    /// Current delay of the line (samples)
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    /// Sets the delay (samples), clamped to the maximum delay of the line.
+    pub fn set_delay(&mut self, delay: usize) {
+        self.delay = delay.min(self.max_delay());
+    }
+
+    /// Returns the sample pushed `delay` samples ago, before the next sample is pushed. A delay of
+    /// 0 reads the latest pushed sample, as a delay of 1 would.
+    pub fn read(&self) -> T {
+        let len = self.buffer.len();
+        self.buffer[(self.pos + len - self.delay.max(1)) % len]
+    }
+
+    /// Push the next sample into the delay line.
+    pub fn push(&mut self, x: T) {
+        self.buffer[self.pos] = x;
+        self.pos = (self.pos + 1) % self.buffer.len();
+    }
+}
+
+impl<T: Scalar> DSPMeta for DelayLine<T> {
+    type Sample = T;
+
+    fn latency(&self) -> usize {
+        self.delay
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(T::zero());
+        self.pos = 0;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for DelayLine<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let y = if self.delay == 0 { x } else { self.read() };
+        self.push(x);
+        [y]
+    }
+}
+
+/// Maximum feedback amount of [`FeedbackLoop`]
+const MAX_FEEDBACK: f64 = 0.999;
+
+/// Feedback delay loop, with an inner processor in the feedback path.
+///
+/// Each sample, the delayed output is run through the inner processor, scaled by the feedback
+/// amount, and summed with the input. This is the basis of Karplus-Strong string synthesis,
+/// resonators and feedback distortion effects.
+///
+/// The feedback amount is clamped to be below 1 in absolute value, which keeps the loop stable
+/// as long as the inner processor does not amplify the signal.
+#[derive(Debug, Clone)]
+pub struct FeedbackLoop<P: DSPMeta> {
+    /// Inner processor, applied on the delayed feedback signal
+    pub inner: P,
+    feedback: P::Sample,
+    delay: DelayLine<P::Sample>,
+}
+
+impl<P: DSPMeta> FeedbackLoop<P> {
+    /// Create a new feedback loop, with no feedback.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_delay`: Maximum delay of the loop (samples)
+    /// * `inner`: Inner processor, applied on the delayed feedback signal
+    ///
+    /// returns: FeedbackLoop<P>
+    pub fn new(max_delay: usize, inner: P) -> Self {
+        Self {
+            inner,
+            feedback: P::Sample::zero(),
+            delay: DelayLine::new(max_delay.max(1)),
+        }
+    }
+
+    /// Delay of the loop (samples)
+    pub fn delay_samples(&self) -> usize {
+        self.delay.delay()
+    }
+
+    /// Sets the delay of the loop (samples). The delay is clamped between 1 and the maximum delay
+    /// given at construction.
+    pub fn set_delay_samples(&mut self, delay: usize) {
+        self.delay.set_delay(delay.max(1));
+    }
+
+    /// Feedback amount
+    pub fn feedback(&self) -> P::Sample {
+        self.feedback
+    }
+
+    /// Sets the feedback amount. The value is clamped to be below 1 in absolute value.
+    pub fn set_feedback(&mut self, feedback: P::Sample) {
+        let max = P::Sample::from_f64(MAX_FEEDBACK);
+        self.feedback = feedback.simd_clamp(-max, max);
+    }
+
+    /// Unwrap this adapter and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for FeedbackLoop<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.inner.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.delay.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<1, 1>> DSPProcess<1, 1> for FeedbackLoop<P> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [fb] = self.inner.process([self.delay.read()]);
+        let y = x + self.feedback * fb;
+        self.delay.push(y);
+        [y]
+    }
+}
+
+/// Wrapper replacing non-finite (NaN or infinite) output samples of the inner processor with
+/// zero, preventing them from propagating to the rest of the processing chain.
+///
+/// Optionally, the inner processor is also reset when it outputs a non-finite sample, which
+/// recovers processors whose internal state got corrupted, such as feedback filters. The reset
+/// applies to all lanes of SIMD processors.
+#[derive(Debug, Copy, Clone)]
+pub struct NanGuard<P> {
+    /// Inner processor being guarded
+    pub inner: P,
+    /// Reset the inner processor when it outputs a non-finite sample
+    pub reset_inner: bool,
+}
+
+impl<P> NanGuard<P> {
+    /// Wrap a processor to replace its non-finite outputs with zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Processor to guard
+    /// * `reset_inner`: Reset the inner processor when it outputs a non-finite sample
+    ///
+    /// returns: NanGuard<P>
+    pub fn new(inner: P, reset_inner: bool) -> Self {
+        Self { inner, reset_inner }
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> NanGuard<P> {
+    /// Replaces the non-finite lanes of the sample with zero, returning whether any was found.
+    fn flush(y: &mut P::Sample) -> bool {
+        let finite = y.simd_is_finite();
+        *y = y.select(finite, P::Sample::zero());
+        !finite.all()
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for NanGuard<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.inner.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O> for NanGuard<P> {
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        let mut y = self.inner.process(x);
+        let mut flushed = false;
+        for y in &mut y {
+            flushed |= Self::flush(y);
+        }
+        if flushed && self.reset_inner {
+            self.inner.reset();
+        }
+        y
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcessBlock<I, O>, const I: usize, const O: usize> DSPProcessBlock<I, O>
+    for NanGuard<P>
+{
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        mut outputs: AudioBufferMut<Self::Sample, O>,
+    ) {
+        self.inner.process_block(inputs, outputs.as_mut());
+        let mut flushed = false;
+        for ch in 0..O {
+            for y in outputs.get_channel_mut(ch).iter_mut() {
+                flushed |= Self::flush(y);
+            }
+        }
+        if flushed && self.reset_inner {
+            self.inner.reset();
+        }
+    }
+
+    fn max_block_size(&self) -> Option<usize> {
+        self.inner.max_block_size()
+    }
+}
+
+/// Wrapper measuring the time spent processing in the inner processor.
+///
+/// This is a lightweight alternative to the `profiling` instrumentation, which is always
+/// available and does not need a profiler backend, making it usable to monitor CPU usage of
+/// release builds. Each call to [`DSPProcessBlock::process_block`] is timed as one block; with
+/// [`DSPProcess`], each sample is its own block, and timer overhead will dominate the measurements.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct Metered<P> {
+    /// Inner processor being timed
+    pub inner: P,
+    total: Duration,
+    peak: Duration,
+    num_blocks: u64,
+}
+
+#[cfg(feature = "std")]
+impl<P> Metered<P> {
+    /// Wrap a processor to measure its processing time.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Processor to time
+    ///
+    /// returns: Metered<P>
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            total: Duration::ZERO,
+            peak: Duration::ZERO,
+            num_blocks: 0,
+        }
+    }
+
+    /// Average time spent processing one block, or zero if no blocks were processed yet.
+    pub fn average_block_time(&self) -> Duration {
+        if self.num_blocks == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.num_blocks as f64)
+        }
+    }
+
+    /// Longest time spent processing one block.
+    pub fn peak_block_time(&self) -> Duration {
+        self.peak
+    }
+
+    /// Total time spent processing.
+    pub fn total_time(&self) -> Duration {
+        self.total
+    }
+
+    /// Number of blocks processed since creation, or since the last call to
+    /// [`Self::reset_timings`].
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// Clears the accumulated timings. Resetting the processor with [`DSPMeta::reset`] leaves the
+    /// timings untouched.
+    pub fn reset_timings(&mut self) {
+        self.total = Duration::ZERO;
+        self.peak = Duration::ZERO;
+        self.num_blocks = 0;
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn metered<R>(&mut self, f: impl FnOnce(&mut P) -> R) -> R {
+        let start = Instant::now();
+        let ret = f(&mut self.inner);
+        let elapsed = start.elapsed();
+        self.total += elapsed;
+        self.peak = self.peak.max(elapsed);
+        self.num_blocks += 1;
+        ret
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: DSPMeta> DSPMeta for Metered<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.inner.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O> for Metered<P> {
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        self.metered(|inner| inner.process(x))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: DSPProcessBlock<I, O>, const I: usize, const O: usize> DSPProcessBlock<I, O>
+    for Metered<P>
+{
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        outputs: AudioBufferMut<Self::Sample, O>,
+    ) {
+        self.metered(|inner| inner.process_block(inputs, outputs))
+    }
+
+    fn max_block_size(&self) -> Option<usize> {
+        self.inner.max_block_size()
+    }
+}
+
+/// Crossfade curve used when mixing two signals.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum MixCurve {
+    /// Linear crossfade, where the gains sum to 1. Best for correlated signals, which would
+    /// otherwise get a boost in the middle of the crossfade.
+    Linear,
+    /// Equal-power crossfade, where the squared gains sum to 1. Best for uncorrelated signals,
+    /// which would otherwise get a dip in the middle of the crossfade.
+    #[default]
+    EqualPower,
+}
+
+impl MixCurve {
+    /// Compute the gains of both signals for the given mix amount.
+    ///
+    /// # Arguments
+    ///
+    /// * `mix`: Mix amount, 0 being only the first signal and 1 only the second one
+    ///
+    /// returns: [T; 2]
+    pub fn gains<T: Scalar>(&self, mix: T) -> [T; 2] {
+        let mix = mix.simd_clamp(T::zero(), T::one());
+        match self {
+            Self::Linear => [T::one() - mix, mix],
+            Self::EqualPower => {
+                // Both gains are computed the same way so that the extremes are exact
+                let quarter = T::simd_frac_pi_2();
+                [
+                    ((T::one() - mix) * quarter).simd_sin(),
+                    (mix * quarter).simd_sin(),
+                ]
             }
         }
     }
 }
+
+/// Dry/wet mixer, mixing the output of the inner processor with its input.
+///
+/// The dry signal is delayed by the latency of the inner processor, so that both paths stay
+/// aligned. The latency is queried on creation and when the sample rate changes; the delay line is
+/// reallocated at those points if needed.
+pub struct DryWet<P: DSPMeta> {
+    /// Inner processor, producing the wet signal
+    pub inner: P,
+    mix: P::Sample,
+    curve: MixCurve,
+    gains: [P::Sample; 2],
+    dry_delay: DelayLine<P::Sample>,
+}
+
+impl<P: DSPMeta> DryWet<P> {
+    /// Create a new dry/wet mixer.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Inner processor, producing the wet signal
+    /// * `mix`: Mix amount, 0 being fully dry and 1 fully wet
+    /// * `curve`: Crossfade curve between the dry and wet signals
+    ///
+    /// returns: DryWet<P>
+    pub fn new(inner: P, mix: P::Sample, curve: MixCurve) -> Self {
+        let mut this = Self {
+            inner,
+            mix,
+            curve,
+            gains: curve.gains(mix),
+            dry_delay: DelayLine::new(0),
+        };
+        this.update_latency();
+        this
+    }
+
+    /// Current mix amount
+    pub fn mix(&self) -> P::Sample {
+        self.mix
+    }
+
+    /// Sets the mix amount, 0 being fully dry and 1 fully wet.
+    pub fn set_mix(&mut self, mix: P::Sample) {
+        self.mix = mix;
+        self.gains = self.curve.gains(mix);
+    }
+
+    /// Sets the crossfade curve between the dry and wet signals.
+    pub fn set_curve(&mut self, curve: MixCurve) {
+        self.curve = curve;
+        self.gains = curve.gains(self.mix);
+    }
+
+    /// Resizes the dry delay line to match the latency of the inner processor. This allocates when
+    /// the latency changed, and therefore should not be called from the audio thread.
+    pub fn update_latency(&mut self) {
+        let latency = self.inner.latency();
+        if latency != self.dry_delay.max_delay() {
+            self.dry_delay = DelayLine::new(latency);
+        }
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for DryWet<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+        self.update_latency();
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.inner.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.dry_delay.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<1, 1>> DSPProcess<1, 1> for DryWet<P> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [wet] = self.inner.process([x]);
+        let [dry] = self.dry_delay.process([x]);
+        let [dry_gain, wet_gain] = self.gains;
+        [dry * dry_gain + wet * wet_gain]
+    }
+}
+
+/// Adaptor running an independent instance of a mono processor on each of `N` channels.
+///
+/// Each channel has its own processor, and thus its own state, which makes for true multichannel
+/// processing out of any mono processor. This is an alternative to packing the channels into the
+/// lanes of a SIMD sample type (for example `AutoF32x2` for stereo), which processes all channels
+/// at once for better performance, but requires all channels to share the same control flow and
+/// the processor to support SIMD samples.
+#[derive(Debug, Copy, Clone)]
+pub struct PerChannel<P, const N: usize>(pub [P; N]);
+
+impl<P, const N: usize> PerChannel<P, N> {
+    /// Create a new per-channel adaptor from one processor per channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Processors for each channel
+    ///
+    /// returns: PerChannel<P, { N }>
+    pub fn new(inner: [P; N]) -> Self {
+        Self(inner)
+    }
+
+    /// Create a new per-channel adaptor by copying the given processor into each channel.
+    pub fn splat(inner: P) -> Self
+    where
+        P: Clone,
+    {
+        Self(core::array::from_fn(|_| inner.clone()))
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instances.
+    pub fn into_inner(self) -> [P; N] {
+        self.0
+    }
+}
+
+impl<P: DSPMeta, const N: usize> DSPMeta for PerChannel<P, N> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        for dsp in &mut self.0 {
+            dsp.set_samplerate(samplerate);
+        }
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        for dsp in &mut self.0 {
+            dsp.set_oversampling_context(factor, base_samplerate);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.0.iter().fold(0, |max, dsp| max.max(dsp.latency()))
+    }
+
+    fn reset(&mut self) {
+        for dsp in &mut self.0 {
+            dsp.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<1, 1>, const N: usize> DSPProcess<N, N> for PerChannel<P, N> {
+    fn process(&mut self, x: [Self::Sample; N]) -> [Self::Sample; N] {
+        core::array::from_fn(|i| self.0[i].process([x[i]])[0])
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcessBlock<1, 1>, const N: usize> DSPProcessBlock<N, N> for PerChannel<P, N> {
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, N>,
+        mut outputs: AudioBufferMut<Self::Sample, N>,
+    ) {
+        for (ch, dsp) in self.0.iter_mut().enumerate() {
+            let input = AudioBufferRef::new([inputs.get_channel(ch)]).unwrap();
+            let output = AudioBufferMut::new([&mut **outputs.get_channel_mut(ch)]).unwrap();
+            dsp.process_block(input, output);
+        }
+    }
+
+    fn max_block_size(&self) -> Option<usize> {
+        self.0.iter().filter_map(|dsp| dsp.max_block_size()).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_wet_aligns_and_mixes() {
+        /// Inverts and delays its input by 3 samples
+        struct Delayed([f32; 3]);
+        impl DSPMeta for Delayed {
+            type Sample = f32;
+
+            fn latency(&self) -> usize {
+                3
+            }
+
+            fn reset(&mut self) {
+                self.0 = [0.0; 3];
+            }
+        }
+        impl DSPProcess<1, 1> for Delayed {
+            fn process(&mut self, [x]: [f32; 1]) -> [f32; 1] {
+                let [a, b, c] = self.0;
+                self.0 = [b, c, x];
+                [-0.5 * a]
+            }
+        }
+
+        let input = (0..32).map(|i| (i as f32 * 0.3).sin()).collect::<Vec<_>>();
+        for curve in [MixCurve::Linear, MixCurve::EqualPower] {
+            let mut dry_wet = DryWet::new(Delayed([0.0; 3]), 0.0, curve);
+            assert_eq!(3, dry_wet.latency());
+            for (i, &x) in input.iter().enumerate() {
+                let expected = i.checked_sub(3).map(|j| input[j]).unwrap_or(0.0);
+                assert_eq!([expected], dry_wet.process([x]), "{curve:?}, sample {i}");
+            }
+
+            dry_wet.reset();
+            dry_wet.set_mix(1.0);
+            let mut wet = Delayed([0.0; 3]);
+            for &x in &input {
+                assert_eq!(wet.process([x]), dry_wet.process([x]), "{curve:?}");
+            }
+        }
+
+        let [dry, wet] = MixCurve::EqualPower.gains(0.5f32);
+        assert!((dry * dry + wet * wet - 1.0).abs() < 1e-6);
+        assert_eq!([0.5, 0.5], MixCurve::Linear.gains(0.5f32));
+    }
+
+    #[test]
+    fn per_channel_has_independent_state() {
+        use crate::dsp::BlockAdapter;
+
+        const SAMPLERATE: f32 = 1000.0;
+        let mut stereo = PerChannel::<_, 2>::splat(P1::new(SAMPLERATE, 50.0));
+        let mut reference = P1::new(SAMPLERATE, 50.0);
+
+        // Impulse on the left channel only
+        for i in 0..64 {
+            let x = if i == 0 { 1.0 } else { 0.0 };
+            let [left, right] = stereo.process([x, 0.0]);
+            let [expected] = reference.process([x]);
+            assert_eq!(expected, left);
+            assert_eq!(
+                0.0, right,
+                "Left channel state leaked into the right at {i}"
+            );
+        }
+        assert_ne!(0.0, stereo.0[0].s);
+        assert_eq!(0.0, stereo.0[1].s);
+
+        // Block processing goes through the same per-channel state
+        let left = [0.5f32; 16];
+        let right = [0.0f32; 16];
+        let mut out_left = [0.0; 16];
+        let mut out_right = [0.0; 16];
+        let mut stereo = PerChannel::<_, 2>::splat(BlockAdapter(P1::new(SAMPLERATE, 50.0)));
+        stereo.process_block(
+            AudioBufferRef::new([&left[..], &right[..]]).unwrap(),
+            AudioBufferMut::new([&mut out_left[..], &mut out_right[..]]).unwrap(),
+        );
+        assert!(out_left.iter().all(|&y| y > 0.0));
+        assert_eq!([0.0; 16], out_right);
+    }
+
+    #[test]
+    fn control_rate_matches_full_rate() {
+        const SAMPLERATE: f32 = 48e3;
+        let input = |i: usize| f32::sin(std::f32::consts::TAU * 2.0 * i as f32 / SAMPLERATE);
+        let mut reference = P1::new(SAMPLERATE, 20.0);
+        let mut control_rate = ControlRate::<_, 16>::new(P1::new(SAMPLERATE, 20.0), true);
+        control_rate.set_samplerate(SAMPLERATE);
+
+        for i in 0..48000 {
+            let [expected] = reference.process([input(i)]);
+            let [actual] = control_rate.process([input(i)]);
+            assert!(
+                (expected - actual).abs() < 1e-2,
+                "Sample {i}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn delay_line_delays_input() {
+        let mut delay = DelayLine::<f32>::new(8);
+        delay.set_delay(3);
+        assert_eq!(3, delay.latency());
+        let output = (0..8)
+            .map(|i| delay.process([i as f32 + 1.0])[0])
+            .collect::<Vec<_>>();
+        assert_eq!([0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0], output[..]);
+        assert_eq!(6.0, delay.read());
+
+        delay.set_delay(100);
+        assert_eq!(8, delay.delay());
+        delay.set_delay(0);
+        assert_eq!([9.0], delay.process([9.0]));
+    }
+
+    #[test]
+    fn feedback_loop_karplus_strong() {
+        const SAMPLERATE: f32 = 48e3;
+        const PERIOD: usize = 100;
+        let mut string = FeedbackLoop::new(1024, P1::new(SAMPLERATE, 8000.0));
+        string.set_delay_samples(PERIOD);
+        string.set_feedback(0.995);
+
+        let output = (0..24000)
+            .map(|i| string.process([if i == 0 { 1.0 } else { 0.0 }])[0])
+            .collect::<Vec<f32>>();
+
+        // Pitched: the autocorrelation peaks at the loop period
+        let autocorrelation = |lag: usize| {
+            output[..4000]
+                .iter()
+                .zip(&output[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+        };
+        let lag = (PERIOD / 2..2 * PERIOD - PERIOD / 2)
+            .max_by(|&a, &b| autocorrelation(a).total_cmp(&autocorrelation(b)))
+            .unwrap();
+        assert!(lag.abs_diff(PERIOD) <= 1, "Period of {lag} samples");
+
+        // Decaying
+        let energy = |block: &[f32]| block.iter().map(|x| x * x).sum::<f32>();
+        let first = energy(&output[..4800]);
+        let last = energy(&output[19200..]);
+        assert!(last > 0.0 && last < 0.1 * first, "Energy {first} -> {last}");
+    }
+
+    #[test]
+    fn feedback_loop_clamps_feedback() {
+        let mut feedback = FeedbackLoop::new(16, Bypass::<f32>::default());
+        feedback.set_feedback(2.0);
+        assert!(feedback.feedback() < 1.0);
+        feedback.set_feedback(-2.0);
+        assert!(feedback.feedback() > -1.0);
+    }
+
+    #[test]
+    fn metered_matches_inner() {
+        use crate::dsp::buffer::AudioBufferBox;
+        use crate::dsp::BlockAdapter;
+
+        const SAMPLERATE: f32 = 48e3;
+        let input = AudioBufferBox::new([(0..512)
+            .map(|i| f32::sin(std::f32::consts::TAU * 440.0 * i as f32 / SAMPLERATE))
+            .collect::<Box<[f32]>>()])
+        .unwrap();
+        let mut expected = AudioBufferBox::<f32, 1>::zeroed(512);
+        let mut actual = AudioBufferBox::<f32, 1>::zeroed(512);
+
+        let mut reference = BlockAdapter(P1::new(SAMPLERATE, 1000.0));
+        let mut metered = Metered::new(BlockAdapter(P1::new(SAMPLERATE, 1000.0)));
+        for _ in 0..4 {
+            reference.process_block(input.as_ref(), expected.as_mut());
+            metered.process_block(input.as_ref(), actual.as_mut());
+            assert_eq!(expected.get_channel(0), actual.get_channel(0));
+        }
+
+        assert_eq!(4, metered.num_blocks());
+        assert!(metered.average_block_time() > Duration::ZERO);
+        assert!(metered.peak_block_time() >= metered.average_block_time());
+    }
+
+    #[test]
+    fn nan_guard_flushes_and_resets() {
+        /// Accumulates its input, and outputs NaN once the accumulator goes above 1
+        struct Unstable(f32);
+        impl DSPMeta for Unstable {
+            type Sample = f32;
+
+            fn reset(&mut self) {
+                self.0 = 0.0;
+            }
+        }
+        impl DSPProcess<1, 1> for Unstable {
+            fn process(&mut self, [x]: [f32; 1]) -> [f32; 1] {
+                self.0 += x;
+                [if self.0 > 1.0 { f32::NAN } else { self.0 }]
+            }
+        }
+
+        let mut guard = NanGuard::new(Unstable(0.0), false);
+        let mut downstream = P1::new(48e3, 1000.0);
+        let output = (0..8)
+            .map(|_| {
+                let [lp, _, _] = downstream.process(guard.process([0.4]));
+                lp
+            })
+            .collect::<Vec<_>>();
+        assert!(output.iter().all(|y| y.is_finite()), "{output:?}");
+        // Without reset, the inner processor stays stuck outputting NaN
+        assert_eq!([0.0], guard.process([0.0]));
+
+        let mut guard = NanGuard::new(Unstable(0.0), true);
+        let output = (0..4).map(|_| guard.process([0.4])[0]).collect::<Vec<_>>();
+        assert_eq!(vec![0.4, 0.8, 0.0, 0.4], output);
+    }
+
+    #[test]
+    fn crossfader_switches_without_clicks() {
+        /// One-pole filter, either lowpass or highpass
+        struct OnePole {
+            highpass: bool,
+            s: f64,
+        }
+        impl DSPMeta for OnePole {
+            type Sample = f64;
+        }
+        impl DSPProcess<1, 1> for OnePole {
+            fn process(&mut self, [x]: [f64; 1]) -> [f64; 1] {
+                self.s += 0.5 * (x - self.s);
+                [if self.highpass { x - self.s } else { self.s }]
+            }
+        }
+        let lowpass = || OnePole {
+            highpass: false,
+            s: 0.0,
+        };
+        let highpass = || OnePole {
+            highpass: true,
+            s: 0.0,
+        };
+        let input = |i: usize| f64::sin(i as f64 * 0.01);
+
+        // Largest difference between successive output samples, switching filter type midway
+        let max_step = |switch: &mut dyn FnMut(&mut Crossfader<OnePole>)| {
+            let mut dsp = Crossfader::new(48e3, highpass(), 256);
+            let mut last = 0.0;
+            let mut max_step = 0.0f64;
+            for i in 0..3000 {
+                // Switch at a peak of the input, where both filter types differ the most
+                if i == 1414 {
+                    switch(&mut dsp);
+                }
+                let [y] = dsp.process([input(i)]);
+                max_step = max_step.max((y - last).abs());
+                last = y;
+            }
+            assert!(!dsp.is_transitioning());
+            assert!(!dsp.current.highpass);
+            max_step
+        };
+
+        let hard = max_step(&mut |dsp| dsp.current = lowpass());
+        assert!(hard > 0.4, "Hard switch only jumped by {hard}");
+        let faded = max_step(&mut |dsp| dsp.switch_to(lowpass()));
+        assert!(faded < 0.02, "Crossfade jumped by {faded}");
+    }
+}
@@ -3,12 +3,45 @@
 //! This module contains an audio buffer type that is container agnostic. A "container" here is
 //! defined to be any type that derefs into `[T]`.
 
+use alloc::boxed::Box;
+use core::fmt;
+use core::ops::Bound;
+use core::ops::{Deref, DerefMut, Index, IndexMut, Range, RangeBounds};
+
 use num_traits::Zero;
-use std::collections::Bound;
-use std::ops::{Deref, DerefMut, Index, IndexMut, Range, RangeBounds};
+use simba::simd::SimdBool;
 
 use crate::Scalar;
 
+/// Error returned when converting slices into an audio buffer fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AudioBufferError {
+    /// The channels have mismatching lengths.
+    ChannelLengthMismatch,
+    /// The length of the interleaved slice is not a multiple of the number of channels.
+    InterleavedLengthMismatch {
+        /// Length of the interleaved slice
+        len: usize,
+        /// Number of channels of the buffer
+        channels: usize,
+    },
+}
+
+impl fmt::Display for AudioBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChannelLengthMismatch => write!(f, "Channels have mismatching lengths"),
+            Self::InterleavedLengthMismatch { len, channels } => write!(
+                f,
+                "Interleaved length {len} is not a multiple of the {channels} channels"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AudioBufferError {}
+
 /// AudioBuffer abstraction over containers of contiguous slices. This supports owned and non-owned,
 /// immutable and mutable slices.
 #[derive(Debug, Copy, Clone)]
@@ -120,7 +153,7 @@ impl<T, const LENGTH: usize, const CHANNELS: usize> AudioBuffer<[T; LENGTH], CHA
     pub fn array_slice(&self, bounds: impl RangeBounds<usize>) -> AudioBufferRef<T, CHANNELS> {
         let range = bounds_into_range(bounds, 0..self.inner_size);
         AudioBuffer {
-            containers: std::array::from_fn(|i| &self.containers[i][range.clone()]),
+            containers: core::array::from_fn(|i| &self.containers[i][range.clone()]),
             inner_size: range.len(),
         }
     }
@@ -201,7 +234,7 @@ impl<T, C: Deref<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS>
     /// let [left, right] = stereo_buffer.frame_ref(0);
     /// ```
     pub fn frame_ref(&self, index: usize) -> [&T; CHANNELS] {
-        std::array::from_fn(|ch| &self.containers[ch][index])
+        core::array::from_fn(|ch| &self.containers[ch][index])
     }
 
     /// Get a multichannel sample at the given index. Returns a copy of the audio samples.
@@ -225,7 +258,21 @@ impl<T, C: Deref<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS>
     where
         T: Clone,
     {
-        std::array::from_fn(|ch| self.containers[ch][index].clone())
+        core::array::from_fn(|ch| self.containers[ch][index].clone())
+    }
+
+    /// Get a multi-channel sample at the given index, or `None` if the index is out of bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: Index into the buffer.
+    ///
+    /// returns: Option<[T; CHANNELS]>
+    pub fn try_get_frame(&self, index: usize) -> Option<[T; CHANNELS]>
+    where
+        T: Clone,
+    {
+        (index < self.inner_size).then(|| self.get_frame(index))
     }
 
     /// Return an iterator of frames in this buffer.
@@ -239,7 +286,7 @@ impl<T, C: Deref<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS>
     /// Return a non-owning buffer that refers to the content of this audio buffer.
     pub fn as_ref(&self) -> AudioBufferRef<T, CHANNELS> {
         AudioBuffer {
-            containers: std::array::from_fn(|i| self.containers[i].deref()),
+            containers: core::array::from_fn(|i| self.containers[i].deref()),
             inner_size: self.inner_size,
         }
     }
@@ -264,7 +311,7 @@ impl<T, C: Deref<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS>
         let range = bounds_into_range(bounds, 0..self.inner_size);
         AudioBuffer {
             inner_size: range.len(),
-            containers: std::array::from_fn(|i| &self.containers[i][range.clone()]),
+            containers: core::array::from_fn(|i| &self.containers[i][range.clone()]),
         }
     }
 }
@@ -279,9 +326,9 @@ impl<T, C: DerefMut<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNEL
         // already a safe method on slices; we only need `MaybeUninit` to create a static array of the correct size for
         // usage in the `AudioBufferMut` we want to create.
         let containers = {
-            use std::mem::MaybeUninit;
+            use core::mem::MaybeUninit;
 
-            let mut containers = std::array::from_fn(|_| MaybeUninit::uninit());
+            let mut containers = core::array::from_fn(|_| MaybeUninit::uninit());
             let mut data = &mut self.containers as &mut [C];
             let mut i = 0;
             while let Some((head, rest)) = data.split_first_mut() {
@@ -384,6 +431,17 @@ impl<T: Copy, C: DerefMut<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, C
             channel[index] = sample;
         }
     }
+
+    /// Set a multi-channel sample at the given index, if it is within bounds.
+    ///
+    /// returns: bool: `true` if the frame was written, `false` if the index is out of bounds.
+    pub fn try_set_frame(&mut self, index: usize, frame: [T; CHANNELS]) -> bool {
+        let in_bounds = index < self.inner_size;
+        if in_bounds {
+            self.set_frame(index, frame);
+        }
+        in_bounds
+    }
 }
 
 impl<T: Scalar, C: DerefMut<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS> {
@@ -405,6 +463,136 @@ impl<T: Scalar, C: DerefMut<Target = [T]>, const CHANNELS: usize> AudioBuffer<C,
             }
         }
     }
+
+    /// Add another buffer into this buffer, sample by sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffers do not have the same length, as reported by [`Self::samples()`].
+    pub fn add_from(&mut self, other: AudioBufferRef<T, CHANNELS>) {
+        self.mix_from(other, T::one());
+    }
+
+    /// Add another buffer into this buffer, sample by sample, applying the given gain to the other
+    /// buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: Buffer to mix into this buffer
+    /// * `gain`: Gain applied to all channels of the other buffer
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffers do not have the same length, as reported by [`Self::samples()`].
+    pub fn mix_from(&mut self, other: AudioBufferRef<T, CHANNELS>, gain: T) {
+        assert_eq!(
+            self.inner_size, other.inner_size,
+            "Mixing buffers of different lengths"
+        );
+        for (this_channel, other_channel) in self.containers.iter_mut().zip(other.containers) {
+            for (y, x) in this_channel.iter_mut().zip(other_channel) {
+                *y += *x * gain;
+            }
+        }
+    }
+}
+
+impl<T: Scalar, C: Deref<Target = [T]>, const CHANNELS: usize> AudioBuffer<C, CHANNELS> {
+    /// Returns true if all samples of this buffer, in all SIMD lanes, have a magnitude strictly
+    /// below the given threshold.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold`: Linear amplitude below which samples are considered silent
+    ///
+    /// returns: bool
+    pub fn is_silent(&self, threshold: T) -> bool {
+        self.containers
+            .iter()
+            .flat_map(|channel| channel.iter())
+            .all(|x| x.simd_abs().simd_lt(threshold).all())
+    }
+
+    /// Convert this buffer into a newly allocated buffer with a different number of channels.
+    ///
+    /// The conversion follows this matrix, with `N` the number of channels of this buffer and `M`
+    /// the number of channels of the output:
+    ///
+    /// * When downmixing (`M <= N`), output channel `j` is the average of input channels `i` where
+    ///   `i % M == j`. In particular, converting to mono averages all channels, and converting to
+    ///   the same channel count copies the buffer.
+    /// * When upmixing (`M > N`), output channel `j` is a copy of input channel `j % N`. In
+    ///   particular, converting from mono duplicates the channel into all outputs.
+    ///
+    /// Converting from a 0-channel buffer produces silence.
+    ///
+    /// returns: AudioBufferBox<T, M>
+    pub fn convert_channels<const M: usize>(&self) -> AudioBufferBox<T, M> {
+        let mut output = AudioBufferBox::zeroed(self.inner_size);
+        if CHANNELS == 0 {
+            return output;
+        }
+        if M > CHANNELS {
+            for (j, channel) in output.containers.iter_mut().enumerate() {
+                channel.copy_from_slice(&self.containers[j % CHANNELS]);
+            }
+        } else {
+            // Each output channel sums an equal number of input channels only when M divides N,
+            // so the average is computed from the actual count
+            for (j, channel) in output.containers.iter_mut().enumerate() {
+                let inputs = self.containers[j..].iter().step_by(M);
+                let gain = T::from_f64(inputs.len() as f64).simd_recip();
+                for input in inputs {
+                    for (y, x) in channel.iter_mut().zip(input.iter()) {
+                        *y += *x * gain;
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// Downmix this buffer into a newly allocated mono buffer, by averaging all channels.
+    ///
+    /// See [`Self::convert_channels`] for the conversion matrix.
+    pub fn downmix_to_mono(&self) -> AudioBufferBox<T, 1> {
+        self.convert_channels()
+    }
+}
+
+impl<T: Scalar, C: Deref<Target = [T]>> AudioBuffer<C, 1> {
+    /// Convert this mono buffer into a newly allocated stereo buffer, by duplicating the channel.
+    pub fn to_stereo(&self) -> AudioBufferBox<T, 2> {
+        self.convert_channels()
+    }
+}
+
+impl<T, C: Deref<Target = [T]>, const CHANNELS: usize> TryFrom<[C; CHANNELS]>
+    for AudioBuffer<C, CHANNELS>
+{
+    type Error = AudioBufferError;
+
+    /// Create an audio buffer from its channels, failing when the channels have mismatching
+    /// lengths. This is [`AudioBuffer::new`] with an error type.
+    fn try_from(containers: [C; CHANNELS]) -> Result<Self, Self::Error> {
+        Self::new(containers).ok_or(AudioBufferError::ChannelLengthMismatch)
+    }
+}
+
+impl<'a, T, C: Deref<Target = [T]>, const CHANNELS: usize> From<&'a AudioBuffer<C, CHANNELS>>
+    for AudioBufferRef<'a, T, CHANNELS>
+{
+    fn from(value: &'a AudioBuffer<C, CHANNELS>) -> Self {
+        value.as_ref()
+    }
+}
+
+impl<'a, T, C: DerefMut<Target = [T]>, const CHANNELS: usize> From<&'a mut AudioBuffer<C, CHANNELS>>
+    for AudioBufferMut<'a, T, CHANNELS>
+{
+    fn from(value: &'a mut AudioBuffer<C, CHANNELS>) -> Self {
+        value.as_mut()
+    }
 }
 
 impl<C> AudioBuffer<C, 0> {
@@ -465,6 +653,28 @@ impl<T> FromIterator<T> for AudioBufferBox<T, 1> {
     }
 }
 
+impl<T: Clone, const CHANNELS: usize> TryFrom<&[T]> for AudioBufferBox<T, CHANNELS> {
+    type Error = AudioBufferError;
+
+    /// Deinterleave the slice into a new audio buffer, where the samples of each frame follow each
+    /// other in the slice. Fails if the length of the slice is not a multiple of the number of
+    /// channels.
+    fn try_from(value: &[T]) -> Result<Self, Self::Error> {
+        if CHANNELS == 0 || value.len() % CHANNELS != 0 {
+            return Err(AudioBufferError::InterleavedLengthMismatch {
+                len: value.len(),
+                channels: CHANNELS,
+            });
+        }
+        Ok(Self {
+            containers: core::array::from_fn(|ch| {
+                value.iter().skip(ch).step_by(CHANNELS).cloned().collect()
+            }),
+            inner_size: value.len() / CHANNELS,
+        })
+    }
+}
+
 impl<T: Zero, const CHANNELS: usize> AudioBufferBox<T, CHANNELS> {
     /// Allocate a new audio buffer with zeroed out contents.
     ///
@@ -475,8 +685,8 @@ impl<T: Zero, const CHANNELS: usize> AudioBufferBox<T, CHANNELS> {
     /// returns: AudioBuffer<Box<[T], Global>, { CHANNELS }>
     pub fn zeroed(size: usize) -> Self {
         Self {
-            containers: std::array::from_fn(|_| {
-                std::iter::repeat_with(T::zero).take(size).collect()
+            containers: core::array::from_fn(|_| {
+                core::iter::repeat_with(T::zero).take(size).collect()
             }),
             inner_size: size,
         }
@@ -495,4 +705,125 @@ mod tests {
 
         assert_eq!(1, buffer[0][0]);
     }
+
+    #[test]
+    fn test_mix_from() {
+        let mut buffer =
+            AudioBufferBox::new([Box::from([1.0, 2.0, 3.0]), Box::from([-1.0, -2.0, -3.0])])
+                .unwrap();
+        let other =
+            AudioBufferBox::new([Box::from([0.5, 0.25, 0.125]), Box::from([4.0, 8.0, 16.0])])
+                .unwrap();
+
+        buffer.add_from(other.as_ref());
+        assert_eq!([1.5, 2.25, 3.125], *buffer[0]);
+        assert_eq!([3.0, 6.0, 13.0], *buffer[1]);
+
+        buffer.mix_from(other.as_ref(), -0.5);
+        assert_eq!([1.25, 2.125, 3.0625], *buffer[0]);
+        assert_eq!([1.0, 2.0, 5.0], *buffer[1]);
+    }
+
+    #[test]
+    fn test_mono_to_stereo() {
+        let mono = AudioBufferBox::<f32, 1>::from_iter([1.0, -2.0, 3.0]);
+        let stereo = mono.as_ref().to_stereo();
+        assert_eq!([1.0, -2.0, 3.0], *stereo[0]);
+        assert_eq!([1.0, -2.0, 3.0], *stereo[1]);
+    }
+
+    #[test]
+    fn test_stereo_to_mono() {
+        let stereo =
+            AudioBufferBox::new([Box::from([1.0, 2.0, 3.0]), Box::from([3.0, -2.0, 0.0])]).unwrap();
+        let mono = stereo.downmix_to_mono();
+        assert_eq!([2.0, 0.0, 1.5], *mono[0]);
+    }
+
+    #[test]
+    fn test_convert_channels_matrix() {
+        let quad = AudioBufferBox::new([
+            Box::from([1.0]),
+            Box::from([2.0]),
+            Box::from([3.0]),
+            Box::from([4.0]),
+        ])
+        .unwrap();
+        let stereo = quad.convert_channels::<2>();
+        assert_eq!([2.0, 3.0], stereo.get_frame(0));
+
+        let hexa = stereo.convert_channels::<6>();
+        assert_eq!([2.0, 3.0, 2.0, 3.0, 2.0, 3.0], hexa.get_frame(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mix_from_length_mismatch() {
+        let mut buffer = AudioBufferBox::<f32, 1>::zeroed(4);
+        let other = AudioBufferBox::<f32, 1>::zeroed(3);
+        buffer.add_from(other.as_ref());
+    }
+
+    #[test]
+    fn test_slice_conversions() {
+        let mut data = [1.0, 2.0, 3.0];
+        let buffer = AudioBufferRef::from(&data[..]);
+        assert_eq!(3, buffer.samples());
+        assert_eq!([2.0], buffer.get_frame(1));
+
+        let mut buffer = AudioBufferMut::from(&mut data[..]);
+        buffer.set_frame(0, [4.0]);
+        assert_eq!([4.0, 2.0, 3.0], data);
+    }
+
+    #[test]
+    fn test_owned_to_borrowed_conversions() {
+        let mut buffer = AudioBufferBox::<f32, 2>::zeroed(4);
+        AudioBufferMut::from(&mut buffer).set_frame(2, [1.0, -1.0]);
+        let borrowed = AudioBufferRef::from(&buffer);
+        assert_eq!(4, borrowed.samples());
+        assert_eq!([1.0, -1.0], borrowed.get_frame(2));
+    }
+
+    #[test]
+    fn test_try_from_channels() {
+        let (left, right) = ([1.0, 2.0], [3.0, 4.0]);
+        let buffer = AudioBufferRef::try_from([&left[..], &right[..]]).unwrap();
+        assert_eq!([2.0, 4.0], buffer.get_frame(1));
+
+        assert_eq!(
+            Err(AudioBufferError::ChannelLengthMismatch),
+            AudioBufferRef::try_from([&left[..], &right[..1]]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_try_from_interleaved() {
+        let interleaved = [1.0, -1.0, 2.0, -2.0, 3.0, -3.0];
+        let buffer = AudioBufferBox::<f32, 2>::try_from(&interleaved[..]).unwrap();
+        assert_eq!([1.0, 2.0, 3.0], *buffer[0]);
+        assert_eq!([-1.0, -2.0, -3.0], *buffer[1]);
+
+        let mono = AudioBufferBox::<f32, 1>::try_from(&interleaved[..]).unwrap();
+        assert_eq!(interleaved, *mono[0]);
+
+        assert_eq!(
+            Err(AudioBufferError::InterleavedLengthMismatch {
+                len: 6,
+                channels: 4
+            }),
+            AudioBufferBox::<f32, 4>::try_from(&interleaved[..]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_try_frame_bounds() {
+        let mut buffer = AudioBufferBox::<f32, 2>::zeroed(4);
+        assert!(buffer.try_set_frame(3, [1.0, 2.0]));
+        assert_eq!(Some([1.0, 2.0]), buffer.try_get_frame(3));
+
+        assert!(!buffer.try_set_frame(4, [3.0, 4.0]));
+        assert_eq!(None, buffer.try_get_frame(4));
+        assert_eq!(None, buffer.as_ref().try_get_frame(usize::MAX));
+    }
 }
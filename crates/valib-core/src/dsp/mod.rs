@@ -8,11 +8,13 @@ use crate::dsp::parameter::HasParameters;
 use crate::Scalar;
 
 use self::analysis::DspAnalysis;
+use self::state::DspState;
 
 pub mod analysis;
 pub mod blocks;
 pub mod buffer;
 pub mod parameter;
+pub mod state;
 
 /// Trait for interacting with a DSP algorithm, outside of processing. Shared by processors of both
 /// per-sample algorithms and block-based algorithms.
@@ -24,6 +26,30 @@ pub trait DSPMeta {
     /// Sets the processing samplerate for this [`DSPProcess`] instance.
     fn set_samplerate(&mut self, samplerate: f32) {}
 
+    /// Sets the processing samplerate for this [`DSPProcess`] instance, returning whether the
+    /// samplerate actually changed (and therefore whether derived state has been recomputed).
+    ///
+    /// The default implementation cannot know the previous samplerate, so it always calls
+    /// [`Self::set_samplerate`] and returns `true`. Implementors which store their samplerate
+    /// should override this to skip redundant updates.
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.set_samplerate(samplerate);
+        true
+    }
+
+    /// Informs this instance of the oversampling it runs under, when wrapped in an oversampler.
+    /// This is called after [`Self::set_samplerate`] has been given the oversampled rate, and
+    /// allows adapting the processing to the oversampling factor, for example to skip
+    /// anti-aliasing measures which are not needed when running oversampled.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: Current oversampling factor
+    /// * `base_samplerate`: Sample rate before oversampling
+    ///
+    /// returns: ()
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {}
+
     /// Report the latency of this DSP instance, that is the time, in samples, it takes for an input sample to be
     /// output back.
     fn latency(&self) -> usize {
@@ -71,6 +97,46 @@ pub trait DSPProcessBlock<const I: usize, const O: usize>: DSPMeta {
     fn max_block_size(&self) -> Option<usize> {
         None
     }
+
+    /// Process a block of audio, and return whether the output is silent, that is whether all of
+    /// its samples are below [`SILENCE_THRESHOLD`]. Hosts can use this to skip processing
+    /// downstream of the processor, or to stop running voices whose tail has decayed.
+    ///
+    /// The default implementation processes the block with [`Self::process_block`] and scans the
+    /// output. Implementors which know when their output is silent can override it to skip the
+    /// scan.
+    fn process_block_detect_silence(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        mut outputs: AudioBufferMut<Self::Sample, O>,
+    ) -> bool {
+        self.process_block(inputs, outputs.as_mut());
+        outputs.is_silent(Self::Sample::from_f64(SILENCE_THRESHOLD))
+    }
+}
+
+/// Linear amplitude below which audio is considered silent (-100 dB), as reported by
+/// [`DSPProcessBlock::process_block_detect_silence`].
+pub const SILENCE_THRESHOLD: f64 = 1e-5;
+
+/// Trait for block processes which write their output into an internal buffer, and return a borrow
+/// of it. Chaining such processors by feeding the output of one into the next avoids allocating or
+/// copying into intermediate buffers.
+///
+/// Documentation of [`DSPProcessBlock`] still applies in here, including the maximum block size.
+pub trait DSPProcessBlockBorrowed<const I: usize, const O: usize>: DSPMeta {
+    /// Process a block of audio, returning a borrow of the internal buffer holding the output. The
+    /// output has the same length as the inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: Input buffer
+    ///
+    /// returns: AudioBufferRef<Self::Sample, O>
+    fn process_block_borrowed(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+    ) -> AudioBufferRef<Self::Sample, O>;
 }
 
 /// Adapter for per-sample processes implementing [`DSPProcess`], so that they work as a [`DSPProcessBlock`].
@@ -87,6 +153,38 @@ impl<P: HasParameters> HasParameters for BlockAdapter<P> {
 
 impl<P: DSPMeta> DSPMeta for BlockAdapter<P> {
     type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.0.set_samplerate(samplerate);
+    }
+
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.0.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.0.try_set_samplerate(samplerate)
+    }
+
+    fn latency(&self) -> usize {
+        self.0.latency()
+    }
+
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+impl<P: DspState> DspState for BlockAdapter<P> {
+    type State = P::State;
+
+    fn snapshot(&self) -> Self::State {
+        self.0.snapshot()
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.0.restore(state);
+    }
 }
 
 impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O> for BlockAdapter<P> {
@@ -132,7 +230,7 @@ where
     inner: P,
 }
 
-impl<P, const I: usize, const O: usize> std::ops::Deref for SampleAdapter<P, I, O>
+impl<P, const I: usize, const O: usize> core::ops::Deref for SampleAdapter<P, I, O>
 where
     P: DSPProcessBlock<I, O>,
 {
@@ -143,7 +241,7 @@ where
     }
 }
 
-impl<P, const I: usize, const O: usize> std::ops::DerefMut for SampleAdapter<P, I, O>
+impl<P, const I: usize, const O: usize> core::ops::DerefMut for SampleAdapter<P, I, O>
 where
     P: DSPProcessBlock<I, O>,
 {
@@ -210,6 +308,14 @@ where
         self.inner.set_samplerate(samplerate);
     }
 
+    fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+        self.inner.set_oversampling_context(factor, base_samplerate);
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.inner.try_set_samplerate(samplerate)
+    }
+
     fn latency(&self) -> usize {
         (self.inner.latency() + self.input_buffer.samples()).saturating_sub(1)
     }
@@ -304,4 +410,38 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_detect_silence_after_tail() {
+        /// One-pole lowpass, whose impulse response decays exponentially
+        struct Decay(f32);
+
+        impl DSPMeta for Decay {
+            type Sample = f32;
+        }
+
+        impl DSPProcess<1, 1> for Decay {
+            fn process(&mut self, [x]: [f32; 1]) -> [f32; 1] {
+                self.0 = x + 0.9 * self.0;
+                [self.0]
+            }
+        }
+
+        let mut filter = BlockAdapter(Decay(0.0));
+        let mut input = [0.0; 16];
+        let mut output = [0.0; 16];
+        input[0] = 1.0;
+        let mut blocks = 0;
+        while !filter.process_block_detect_silence(
+            AudioBufferRef::from(&input[..]),
+            AudioBufferMut::from(&mut output[..]),
+        ) {
+            input[0] = 0.0;
+            blocks += 1;
+            assert!(blocks < 100, "Filter tail never reported as silent");
+        }
+        // 0.9^n drops below -100 dB after 110 samples, within the 7th block
+        assert_eq!(7, blocks);
+        assert!(output.iter().all(|y| y.abs() < 1e-5));
+    }
 }
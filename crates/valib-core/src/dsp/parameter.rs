@@ -10,13 +10,17 @@
 //!
 //! [`Series`]: crate::dsp::blocks::Series
 //! [`Parallel`]: crate::dsp::blocks::Parallel
-use std::borrow::Cow;
-use std::marker::PhantomData;
-use std::ops;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
-
-use portable_atomic::{AtomicBool, AtomicF32};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use num_traits::Float;
+use portable_atomic::{AtomicBool, AtomicF32, AtomicU32};
 
 pub use valib_derive::ParamName;
 
@@ -77,8 +81,8 @@ impl Smoothing {
 
     fn is_changing(&self, value: f32) -> bool {
         match self {
-            Self::Exponential { state, .. } => (value - state).abs() < 1e-6,
-            Self::Linear { last_out, .. } => (value - last_out).abs() < 1e-6,
+            Self::Exponential { state, .. } => Float::abs(value - state) < 1e-6,
+            Self::Linear { last_out, .. } => Float::abs(value - last_out) < 1e-6,
         }
     }
 }
@@ -185,7 +189,7 @@ impl SmoothedParam {
 
     /// Computes the next sample of the smoother, casting it into a `T`.
     pub fn next_sample_as<T: Scalar>(&mut self) -> T {
-        T::from_f64(self.next_sample() as _)
+        T::from_f32(self.next_sample())
     }
 
     /// Returns true when the smoother is still in the process of smoothing the change to the raw value.
@@ -229,6 +233,16 @@ pub trait ParamName: Copy {
     /// Return a user-friendly name for this parameter name.
     fn name(&self) -> Cow<'static, str>;
 
+    /// Return the range of values this parameter takes, as `(min, max)`. Defaults to `(0.0, 1.0)`.
+    fn range(&self) -> (f32, f32) {
+        (0.0, 1.0)
+    }
+
+    /// Return the unit of this parameter's values, for display purposes. Defaults to no unit.
+    fn unit(&self) -> &'static str {
+        ""
+    }
+
     /// Create an iterator returning all values for this type, that is, all values converted from
     /// IDs in sequence in the range `0..Self::count()`.
     fn iter() -> impl Iterator<Item = Self> {
@@ -327,7 +341,7 @@ impl<P: ParamName, T: Default> Default for ParamMap<P, T> {
 
 /// Type which implements [`Iterator`] listing the parameters and their associated value.
 pub struct ParamMapIntoIter<P, T> {
-    data: std::vec::IntoIter<T>,
+    data: alloc::vec::IntoIter<T>,
     item: u64,
     __param: PhantomData<P>,
 }
@@ -440,24 +454,91 @@ pub trait HasParametersErased {
     fn set_parameter_raw(&mut self, param_id: ParamId, value: f32);
 }
 
+/// Single-producer, single-consumer queue of per-sample parameter values.
+struct RampQueue {
+    data: Box<[AtomicF32]>,
+    read: AtomicUsize,
+    write: AtomicUsize,
+}
+
+impl RampQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: (0..capacity).map(|_| AtomicF32::new(0.0)).collect(),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, values: &[f32]) -> usize {
+        let read = self.read.load(Ordering::Acquire);
+        let write = self.write.load(Ordering::Relaxed);
+        let free = self.data.len() - write.wrapping_sub(read);
+        let len = values.len().min(free);
+        for (i, &value) in values[..len].iter().enumerate() {
+            self.data[write.wrapping_add(i) % self.data.len()].store(value, Ordering::Relaxed);
+        }
+        self.write.store(write.wrapping_add(len), Ordering::Release);
+        len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.read.load(Ordering::Acquire) == self.write.load(Ordering::Acquire)
+    }
+
+    fn pop(&self) -> Option<f32> {
+        let read = self.read.load(Ordering::Relaxed);
+        let write = self.write.load(Ordering::Acquire);
+        if read == write {
+            return None;
+        }
+        let value = self.data[read % self.data.len()].load(Ordering::Relaxed);
+        self.read.store(read.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
 /// Proxy parameter updates to another type. This allows thread-safe control of processors via their
 /// parameters.
 pub struct ParamsProxy<P: ParamName> {
     params: ParamMap<P, Arc<AtomicF32>>,
     param_changed: ParamMap<P, Arc<AtomicBool>>,
+    applied: ParamMap<P, AtomicF32>,
+    applied_generation: ParamMap<P, AtomicU32>,
+    ramps: ParamMap<P, RampQueue>,
 }
 
 /// Type alias for the type that allows remote control of processors via their parameters.
 pub type RemoteControl<P> = Arc<ParamsProxy<P>>;
 
 impl<P: ParamName> ParamsProxy<P> {
-    /// Create a new param proxy.
+    /// Create a new param proxy. No per-sample values can be queued with
+    /// [`Self::set_parameter_block`]; use [`Self::with_ramp_capacity`] for that.
     pub fn new() -> Arc<Self> {
+        Self::with_ramp_capacity(0)
+    }
+
+    /// Create a new param proxy, which can queue up to `capacity` per-sample values for each
+    /// parameter with [`Self::set_parameter_block`].
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: Maximum number of per-sample values queued per parameter, usually the
+    ///     maximum block size of the remote type
+    ///
+    /// returns: Arc<ParamsProxy<P>>
+    pub fn with_ramp_capacity(capacity: usize) -> Arc<Self> {
         let params = ParamMap::new(|_| Arc::new(AtomicF32::new(0.0)));
         let param_changed = ParamMap::new(|_| Arc::new(AtomicBool::new(false)));
+        let applied = ParamMap::new(|_| AtomicF32::new(0.0));
+        let applied_generation = ParamMap::new(|_| AtomicU32::new(0));
+        let ramps = ParamMap::new(|_| RampQueue::new(capacity));
         Arc::new(Self {
             params,
             param_changed,
+            applied,
+            applied_generation,
+            ramps,
         })
     }
 
@@ -474,6 +555,68 @@ impl<P: ParamName> ParamsProxy<P> {
         self.params[param].store(value, Ordering::SeqCst);
     }
 
+    /// Queue per-sample values for a parameter of a remote type, for sample-accurate automation.
+    ///
+    /// The values are consumed one per sample by [`RemoteControlled`], after regular parameter
+    /// updates; the block processing path splits the block at every sample while values are
+    /// queued. Values are appended to the ones already queued, up to the capacity given in
+    /// [`Self::with_ramp_capacity`]; the remaining values are dropped.
+    ///
+    /// Queuing values for the same parameter from several threads at once is not supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `param`: Parameter to set
+    /// * `values`: Values to set, one per sample
+    ///
+    /// returns: usize (number of values queued)
+    pub fn set_parameter_block(&self, param: P, values: &[f32]) -> usize {
+        self.ramps[param].push(values)
+    }
+
+    /// Returns the last value of the parameter that has been applied to the remote type.
+    ///
+    /// # Arguments
+    ///
+    /// * `param`: Parameter to read
+    ///
+    /// returns: f32
+    pub fn applied_value(&self, param: P) -> f32 {
+        self.applied[param].load(Ordering::SeqCst)
+    }
+
+    /// Subscribe to the parameter values applied to the remote type. The returned subscriber
+    /// receives every change applied after its creation, without having to poll the remote type
+    /// itself.
+    pub fn subscribe(self: &Arc<Self>) -> ParamsSubscriber<P> {
+        ParamsSubscriber {
+            seen: ParamMap::new(|p: P| self.applied_generation[p].load(Ordering::SeqCst)),
+            proxy: self.clone(),
+        }
+    }
+
+    fn set_applied(&self, param: P, value: f32) {
+        self.applied[param].store(value, Ordering::SeqCst);
+        self.applied_generation[param].fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Consume up to `samples` queued per-sample values of the parameter, returning the last one.
+    fn get_ramp_update(&self, param: P, samples: usize) -> Option<f32> {
+        let ramp = &self.ramps[param];
+        let mut last = None;
+        for _ in 0..samples {
+            let Some(value) = ramp.pop() else {
+                break;
+            };
+            last = Some(value);
+        }
+        last
+    }
+
+    fn has_ramps(&self) -> bool {
+        P::iter().any(|param| !self.ramps[param].is_empty())
+    }
+
     fn get_update(&self, param: P) -> Option<f32> {
         let has_changed = self.param_changed[param]
             .compare_exchange(true, false, Ordering::SeqCst, Ordering::SeqCst)
@@ -485,7 +628,111 @@ impl<P: ParamName> ParamsProxy<P> {
     }
 }
 
+/// Receiving end of the parameter values applied through a [`RemoteControl`], created with
+/// [`ParamsProxy::subscribe`].
+///
+/// Only the latest applied value of each parameter is kept; intermediate values applied between
+/// two reads are not reported.
+pub struct ParamsSubscriber<P: ParamName> {
+    proxy: RemoteControl<P>,
+    seen: ParamMap<P, u32>,
+}
+
+impl<P: ParamName> ParamsSubscriber<P> {
+    /// Returns the next parameter that has been applied since it was last received, along with
+    /// its applied value, or `None` if no parameter has changed.
+    pub fn try_recv(&mut self) -> Option<(P, f32)> {
+        for (param, seen) in self.seen.iter_mut() {
+            let generation = self.proxy.applied_generation[param].load(Ordering::SeqCst);
+            if generation != *seen {
+                *seen = generation;
+                return Some((param, self.proxy.applied_value(param)));
+            }
+        }
+        None
+    }
+}
+
+/// Answer of an automation callback to [`DSPProcessBlockAutomated::process_block_with`], queried
+/// at a given sample offset into the block.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParamUpdate<P> {
+    /// Set the parameter to the value at the queried offset. The callback is queried again at
+    /// the same offset for further changes.
+    Set(P, f32),
+    /// No more changes at the queried offset; the next change happens at the given offset into
+    /// the block. Offsets which are not past the queried offset are treated as the next sample.
+    NextChangeAt(usize),
+    /// No more changes for the rest of the block.
+    Done,
+}
+
+/// Trait for block processes which can apply parameter changes at any point within a block.
+///
+/// This is implemented for all [`DSPProcessBlock`] types with parameters, by splitting the block
+/// at each change point and applying the changes in between sub-blocks.
+pub trait DSPProcessBlockAutomated<const I: usize, const O: usize>:
+    DSPProcessBlock<I, O> + HasParameters
+{
+    /// Process a block of audio, querying the automation callback for parameter changes within it.
+    ///
+    /// The callback is first queried at offset 0, and then at each offset it returns through
+    /// [`ParamUpdate::NextChangeAt`].
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: Input buffer
+    /// * `outputs`: Output buffer
+    /// * `automation`: Callback returning parameter updates at the given offset into the block
+    ///
+    /// returns: ()
+    fn process_block_with(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        outputs: AudioBufferMut<Self::Sample, O>,
+        automation: &mut dyn FnMut(usize) -> ParamUpdate<Self::Name>,
+    );
+}
+
+impl<P: HasParameters + DSPProcessBlock<I, O>, const I: usize, const O: usize>
+    DSPProcessBlockAutomated<I, O> for P
+{
+    fn process_block_with(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        mut outputs: AudioBufferMut<Self::Sample, O>,
+        automation: &mut dyn FnMut(usize) -> ParamUpdate<Self::Name>,
+    ) {
+        let len = outputs.samples();
+        let max_block_size = self.max_block_size().unwrap_or(len).max(1);
+        let mut pos = 0;
+        let mut next_change = Some(0);
+        while pos < len {
+            if next_change == Some(pos) {
+                next_change = loop {
+                    match automation(pos) {
+                        ParamUpdate::Set(param, value) => self.set_parameter(param, value),
+                        ParamUpdate::NextChangeAt(next) => break Some(next.max(pos + 1)),
+                        ParamUpdate::Done => break None,
+                    }
+                };
+            }
+            let end = next_change
+                .unwrap_or(len)
+                .min(len)
+                .min(pos + max_block_size);
+            self.process_block(inputs.slice(pos..end), outputs.slice_mut(pos..end));
+            pos = end;
+        }
+    }
+}
+
 /// Type which remote controls the type `P` through its [`RemoteControlled::proxy`].
+///
+/// Parameter changes sent through the proxy are not applied immediately; instead, the remote
+/// control checks for changes at a fixed update frequency, and transfers the latest value of each
+/// changed parameter to the inner processor. No smoothing is performed on the values, which
+/// should be done by the inner processor itself (for example with [`SmoothedParam`]) when needed.
 pub struct RemoteControlled<P: HasParameters> {
     /// Remote-controlled type
     pub inner: P,
@@ -495,6 +742,70 @@ pub struct RemoteControlled<P: HasParameters> {
     update_params_step: f32,
 }
 
+/// Builder for [`RemoteControlled`], created with [`RemoteControlled::builder`].
+#[derive(Debug, Copy, Clone)]
+pub struct RemoteControlledBuilder<P> {
+    samplerate: f32,
+    update_frequency: f32,
+    max_block_size: usize,
+    __inner: PhantomData<P>,
+}
+
+impl<P> Default for RemoteControlledBuilder<P> {
+    fn default() -> Self {
+        Self {
+            samplerate: 44100.0,
+            update_frequency: 1e3,
+            max_block_size: 0,
+            __inner: PhantomData,
+        }
+    }
+}
+
+impl<P: HasParameters> RemoteControlledBuilder<P> {
+    /// Sets the sample rate at which the processor and remote control will run. Defaults to
+    /// 44.1 kHz.
+    pub fn samplerate(mut self, samplerate: f32) -> Self {
+        self.samplerate = samplerate;
+        self
+    }
+
+    /// Sets the frequency (in Hz) at which the remote control checks for updated parameters.
+    /// Defaults to 1 kHz. See [`RemoteControlled::new`] for more details.
+    pub fn update_frequency(mut self, update_frequency: f32) -> Self {
+        self.update_frequency = update_frequency;
+        self
+    }
+
+    /// Sets the frequency (in Hz) at which parameter changes are transferred to the processor,
+    /// which bounds how fast the parameters follow the remote control. This is the same as
+    /// [`Self::update_frequency`].
+    pub fn smoothing_hz(self, smoothing_hz: f32) -> Self {
+        self.update_frequency(smoothing_hz)
+    }
+
+    /// Sets the maximum number of per-sample values that can be queued for each parameter with
+    /// [`ParamsProxy::set_parameter_block`], which should be the maximum block size the
+    /// processor will run at. Defaults to 0, where no per-sample values can be queued.
+    pub fn max_block_size(mut self, max_block_size: usize) -> Self {
+        self.max_block_size = max_block_size;
+        self
+    }
+
+    /// Create the remote control, controlling the passed in processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Inner processor, that is going to be controlled by this.
+    ///
+    /// returns: RemoteControlled<P>
+    pub fn build(self, inner: P) -> RemoteControlled<P> {
+        let mut dsp = RemoteControlled::new(self.samplerate, self.update_frequency, inner);
+        dsp.proxy = ParamsProxy::with_ramp_capacity(self.max_block_size);
+        dsp
+    }
+}
+
 impl<P: HasParameters + DSPMeta> DSPMeta for RemoteControlled<P> {
     type Sample = P::Sample;
 }
@@ -504,10 +815,11 @@ impl<P: HasParameters + DSPProcess<I, O>, const I: usize, const O: usize> DSPPro
 {
     fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
         self.update_params_phase += self.update_params_step;
-        if self.update_params_phase > 1.0 {
+        if self.update_params_phase >= 1.0 {
             self.update_params_phase -= 1.0;
             self.update_parameters();
         }
+        self.advance_ramps(1);
 
         self.inner.process(x)
     }
@@ -520,14 +832,27 @@ impl<P: HasParameters + DSPProcessBlock<I, O>, const I: usize, const O: usize> D
     fn process_block(
         &mut self,
         inputs: AudioBufferRef<Self::Sample, I>,
-        outputs: AudioBufferMut<Self::Sample, O>,
+        mut outputs: AudioBufferMut<Self::Sample, O>,
     ) {
-        self.update_params_phase += self.update_params_step * inputs.samples() as f32;
-        if self.update_params_phase > 1.0 {
+        let len = inputs.samples();
+        self.update_params_phase += self.update_params_step * len as f32;
+        if self.update_params_phase >= 1.0 {
             self.update_parameters();
-            self.update_params_phase = self.update_params_phase.fract();
+            self.update_params_phase = Float::fract(self.update_params_phase);
+        }
+        // Queued per-sample values are applied one sample at a time, and the rest of the block
+        // is processed at once
+        let mut pos = 0;
+        while pos < len && self.proxy.has_ramps() {
+            self.advance_ramps(1);
+            self.inner
+                .process_block(inputs.slice(pos..pos + 1), outputs.slice_mut(pos..pos + 1));
+            pos += 1;
+        }
+        if pos < len {
+            self.inner
+                .process_block(inputs.slice(pos..len), outputs.slice_mut(pos..len));
         }
-        self.inner.process_block(inputs, outputs);
     }
 
     fn max_block_size(&self) -> Option<usize> {
@@ -542,11 +867,14 @@ impl<P: HasParameters> RemoteControlled<P> {
     ///
     /// * `samplerate`: Sample rate at which the processor and remote control will run
     /// * `update_frequency`: Frequency (in Hz) at which the remote control will check for updated
-    ///     parameters, and transfer them to the inner processor.
+    ///     parameters, and transfer them to the inner processor. This is clamped between 1 Hz and
+    ///     the Nyquist frequency, such that parameters are checked at most every other sample.
     /// * `inner`: Inner processor, that is going to be controlled by this.
     ///
     /// returns: RemoteControlled<P>
     pub fn new(samplerate: f32, update_frequency: f32, inner: P) -> Self {
+        // `max` also replaces a NaN frequency with the lower bound
+        let update_frequency = update_frequency.max(1.0).min(samplerate / 2.0);
         Self {
             inner,
             proxy: ParamsProxy::new(),
@@ -554,6 +882,34 @@ impl<P: HasParameters> RemoteControlled<P> {
             update_params_step: update_frequency * samplerate.recip(),
         }
     }
+
+    /// Create a builder for a remote control, allowing to name the construction arguments.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use valib_core::dsp::blocks::P1;
+    /// use valib_core::dsp::parameter::RemoteControlled;
+    /// let dsp = RemoteControlled::builder()
+    ///     .samplerate(48000.0)
+    ///     .update_frequency(500.0)
+    ///     .build(P1::new(48000.0, 1000.0));
+    /// ```
+    pub fn builder() -> RemoteControlledBuilder<P> {
+        RemoteControlledBuilder::default()
+    }
+
+    /// Frequency (in Hz) at which the remote control checks for updated parameters, relative to
+    /// the sample rate given at construction.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the processor runs
+    ///
+    /// returns: f32
+    pub fn update_frequency(&self, samplerate: f32) -> f32 {
+        self.update_params_step * samplerate
+    }
 }
 
 #[profiling::all_functions]
@@ -564,7 +920,213 @@ impl<P: HasParameters> RemoteControlled<P> {
         for param in P::Name::iter() {
             if let Some(value) = self.proxy.get_update(param) {
                 self.inner.set_parameter(param, value);
+                self.proxy.set_applied(param, value);
             }
         }
     }
+
+    /// Advance the per-sample values queued with [`ParamsProxy::set_parameter_block`] by the given
+    /// number of samples, and transmit the latest values to the inner processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples`: Number of samples to advance the queued values by
+    ///
+    /// returns: ()
+    pub fn advance_ramps(&mut self, samples: usize) {
+        if !self.proxy.has_ramps() {
+            return;
+        }
+        for param in P::Name::iter() {
+            if let Some(value) = self.proxy.get_ramp_update(param, samples) {
+                self.inner.set_parameter(param, value);
+                self.proxy.set_applied(param, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, ParamName)]
+    enum TestParams {
+        #[param_name(unit = "dB", min = -24, max = 24)]
+        Gain,
+        Offset,
+    }
+
+    #[test]
+    fn derived_metadata() {
+        assert_eq!((-24.0, 24.0), TestParams::Gain.range());
+        assert_eq!("dB", TestParams::Gain.unit());
+        assert_eq!((0.0, 1.0), TestParams::Offset.range());
+        assert_eq!("", TestParams::Offset.unit());
+    }
+
+    #[derive(Debug, Default)]
+    struct TestDsp {
+        gain: f32,
+        offset: f32,
+    }
+
+    impl HasParameters for TestDsp {
+        type Name = TestParams;
+
+        fn set_parameter(&mut self, param: Self::Name, value: f32) {
+            match param {
+                TestParams::Gain => self.gain = value,
+                TestParams::Offset => self.offset = value,
+            }
+        }
+    }
+
+    impl DSPMeta for TestDsp {
+        type Sample = f32;
+    }
+
+    impl DSPProcess<1, 1> for TestDsp {
+        fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+            [self.gain * x + self.offset]
+        }
+    }
+
+    #[test]
+    fn automated_block_matches_per_sample_processing() {
+        use crate::dsp::buffer::AudioBuffer;
+        use crate::dsp::BlockAdapter;
+
+        let input = (0..64).map(|i| (i as f32 * 0.2).sin()).collect::<Vec<_>>();
+        let ramp = |i: usize| i as f32 / 64.0;
+
+        let mut dsp = TestDsp::default();
+        let expected = input
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                dsp.set_parameter(TestParams::Gain, ramp(i));
+                if i == 40 {
+                    dsp.set_parameter(TestParams::Offset, 0.5);
+                }
+                dsp.process([x])[0]
+            })
+            .collect::<Vec<_>>();
+
+        let mut dsp = BlockAdapter(TestDsp::default());
+        let mut output = vec![0.0; input.len()];
+        // Each offset sets the parameters one at a time, then points to the next sample
+        let mut step = 0;
+        dsp.process_block_with(
+            AudioBuffer::new([&input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+            &mut |i| {
+                step += 1;
+                match step {
+                    1 => ParamUpdate::Set(TestParams::Gain, ramp(i)),
+                    2 if i == 40 => ParamUpdate::Set(TestParams::Offset, 0.5),
+                    _ => {
+                        step = 0;
+                        ParamUpdate::NextChangeAt(i + 1)
+                    }
+                }
+            },
+        );
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn remote_control_subscribe_receives_applied_values() {
+        let mut dsp = RemoteControlled::new(1000.0, 100.0, TestDsp::default());
+        let mut subscriber = dsp.proxy.subscribe();
+        assert_eq!(None, subscriber.try_recv());
+
+        dsp.proxy.set_parameter(TestParams::Offset, 0.5);
+        // Not applied until the remote control checks for updates
+        assert_eq!(None, subscriber.try_recv());
+        for _ in 0..20 {
+            dsp.process([0.0]);
+        }
+        assert_eq!(Some((TestParams::Offset, 0.5)), subscriber.try_recv());
+        assert_eq!(None, subscriber.try_recv());
+        assert_eq!(0.5, dsp.proxy.applied_value(TestParams::Offset));
+    }
+
+    #[test]
+    fn remote_control_update_frequency_clamped_to_nyquist() {
+        let dsp = RemoteControlled::builder()
+            .samplerate(1000.0)
+            .smoothing_hz(2000.0)
+            .build(TestDsp::default());
+        assert_eq!(500.0, dsp.update_frequency(1000.0));
+
+        let dsp = RemoteControlled::new(1000.0, 100.0, TestDsp::default());
+        assert_eq!(100.0, dsp.update_frequency(1000.0));
+    }
+
+    #[test]
+    fn remote_control_updates_every_other_sample_at_nyquist() {
+        let mut dsp = RemoteControlled::new(1000.0, 500.0, TestDsp::default());
+        for i in 0..8 {
+            dsp.proxy.set_parameter(TestParams::Offset, i as f32);
+            dsp.process([0.0]);
+            assert_eq!([i as f32], dsp.process([0.0]));
+        }
+    }
+
+    #[test]
+    fn remote_control_parameter_block_is_sample_accurate() {
+        let mut dsp = RemoteControlled::builder()
+            .samplerate(1000.0)
+            .update_frequency(100.0)
+            .max_block_size(64)
+            .build(TestDsp::default());
+        let ramp = (1..=64).map(|i| i as f32 / 64.0).collect::<Vec<_>>();
+        assert_eq!(64, dsp.proxy.set_parameter_block(TestParams::Gain, &ramp));
+
+        let output = (0..64).map(|_| dsp.process([1.0])[0]).collect::<Vec<_>>();
+        assert_eq!(ramp, output);
+        assert!(output.windows(2).all(|w| w[1] > w[0]));
+        // The last value holds once the ramp is consumed
+        assert_eq!([1.0], dsp.process([1.0]));
+
+        // Values past the capacity are dropped
+        assert_eq!(
+            64,
+            dsp.proxy.set_parameter_block(TestParams::Gain, &[0.5; 100])
+        );
+    }
+
+    #[test]
+    fn remote_control_parameter_block_applies_within_blocks() {
+        use crate::dsp::buffer::AudioBuffer;
+        use crate::dsp::BlockAdapter;
+
+        let mut dsp = RemoteControlled::builder()
+            .samplerate(1000.0)
+            .update_frequency(100.0)
+            .max_block_size(64)
+            .build(BlockAdapter(TestDsp::default()));
+        let ramp = (1..=32).map(|i| i as f32 / 32.0).collect::<Vec<_>>();
+        assert_eq!(32, dsp.proxy.set_parameter_block(TestParams::Gain, &ramp));
+
+        let input = [1.0; 64];
+        let mut output = [0.0; 64];
+        dsp.process_block(
+            AudioBuffer::new([&input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+        );
+        assert_eq!(ramp[..], output[..32]);
+        // The last value holds for the rest of the block
+        assert!(output[32..].iter().all(|&y| y == 1.0));
+    }
+
+    #[test]
+    fn remote_control_clamps_invalid_update_frequency() {
+        let dsp = RemoteControlled::new(1000.0, 0.0, TestDsp::default());
+        assert_eq!(1.0, dsp.update_frequency(1000.0));
+
+        let dsp = RemoteControlled::new(1000.0, f32::NAN, TestDsp::default());
+        assert_eq!(1.0, dsp.update_frequency(1000.0));
+    }
 }
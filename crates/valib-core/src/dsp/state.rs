@@ -0,0 +1,25 @@
+//! Defines the methods to capture and restore the internal state of DSP processes.
+use crate::dsp::DSPMeta;
+
+/// Trait for DSP structs whose internal state (filter memories, envelopes, ...) can be captured
+/// and later restored, for example to implement A/B comparisons or undo in plugins.
+///
+/// Only the state evolving during processing is captured; parameters are expected to be saved
+/// and restored separately. Restoring a snapshot and processing the same input again gives the
+/// exact same output as when the snapshot was taken.
+pub trait DspState: DSPMeta {
+    /// Captured state of the process.
+    type State: Clone;
+
+    /// Capture the current internal state of the process.
+    fn snapshot(&self) -> Self::State;
+
+    /// Restore the internal state of the process from a previously captured snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: Captured state, as returned by [`Self::snapshot`].
+    ///
+    /// returns: ()
+    fn restore(&mut self, state: &Self::State);
+}
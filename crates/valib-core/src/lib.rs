@@ -2,16 +2,27 @@
 //!
 //! Provides the basic definitions for all of `valib`. Contains basic DSP definitions and useful math
 //! constructs.
+//!
+//! The `std` feature is enabled by default. Disabling it builds the crate on `core` and `alloc`
+//! only, for use on embedded targets; the benchmarking helpers and the `wide` SIMD types are then
+//! unavailable.
 #![warn(missing_docs)]
 #![feature(generic_const_exprs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
-use az::CastFrom;
+use az::{CastFrom, SaturatingCastFrom};
 use num_traits::Zero;
-use simba::simd::{AutoSimd, Simd, SimdRealField, SimdValue};
+#[cfg(feature = "std")]
+use simba::simd::Simd;
+use simba::simd::{AutoSimd, SimdRealField, SimdValue};
 
+#[cfg(feature = "std")]
 use crate::util::{as_nested_arrays, as_nested_arrays_mut};
 pub use simba::simd;
 
+#[cfg(feature = "std")]
 pub mod benchmarking;
 pub mod dsp;
 pub mod math;
@@ -27,6 +38,10 @@ pub trait Scalar: Copy + SimdRealField {
     /// multiple lanes, should have all lanes being this value.
     fn from_f64(value: f64) -> Self;
 
+    /// Create a new [`Scalar`] from a single `f32` value. The resulting type, if it is a SIMD with
+    /// multiple lanes, should have all lanes being this value.
+    fn from_f32(value: f32) -> Self;
+
     /// Create a new [`Scalar`] containing the values passed in the array.
     fn from_values(values: [Self::Element; <Self as SimdValue>::LANES]) -> Self
     where
@@ -37,13 +52,27 @@ pub trait Scalar: Copy + SimdRealField {
     where
         [Self::Element; <Self as SimdValue>::LANES]:,
     {
-        std::array::from_fn(|i| self.extract(i))
+        core::array::from_fn(|i| self.extract(i))
     }
 
     /// Return an iterator of the elements contained in this scalar.
     fn into_iter(self) -> impl ExactSizeIterator<Item = Self::Element> {
         (0..Self::LANES).map(move |i| self.extract(i))
     }
+
+    /// Returns a mask of the lanes which are finite, that is neither infinite nor NaN.
+    #[inline]
+    fn simd_is_finite(self) -> Self::SimdBool {
+        // Infinities and NaNs are the only values for which `x * 0` is not zero
+        let zero = Self::zero();
+        (self * zero).simd_eq(zero)
+    }
+
+    /// Returns a mask of the lanes which are NaN.
+    #[inline]
+    fn simd_is_nan(self) -> Self::SimdBool {
+        self.simd_ne(self)
+    }
 }
 
 impl<T: Copy + SimdRealField> Scalar for T
@@ -54,6 +83,11 @@ where
         Self::from_subset(&value)
     }
 
+    #[inline]
+    fn from_f32(value: f32) -> Self {
+        Self::from_subset(&value)
+    }
+
     #[allow(clippy::needless_range_loop)]
     fn from_values(values: [Self::Element; <Self as SimdValue>::LANES]) -> Self
     where
@@ -92,7 +126,7 @@ where
     type Output = AutoSimd<[E2; N]>;
 
     fn cast(self) -> Self::Output {
-        let mut ret: AutoSimd<[E2; N]> = unsafe { std::mem::zeroed() };
+        let mut ret: AutoSimd<[E2; N]> = unsafe { core::mem::zeroed() };
         for i in 0..N {
             ret.replace(i, E2::cast_from(self.extract(i)));
         }
@@ -127,6 +161,7 @@ impl_simdcast_primitives!(i32);
 impl_simdcast_primitives!(i64);
 impl_simdcast_primitives!(i128);
 
+#[cfg(feature = "std")]
 macro_rules! impl_simdcast_wide {
     ($name:ty : [$prim:ty; $lanes:literal]) => {
         impl<E2> SimdCast<E2> for $name
@@ -147,10 +182,112 @@ macro_rules! impl_simdcast_wide {
     };
 }
 
+#[cfg(feature = "std")]
 impl_simdcast_wide!(simd::WideF32x4 : [f32; 4]);
+#[cfg(feature = "std")]
 impl_simdcast_wide!(simd::WideF32x8 : [f32; 8]);
+#[cfg(feature = "std")]
 impl_simdcast_wide!(simd::WideF64x4 : [f64; 4]);
 
+/// Trait for SIMD values which can be cast with well-defined behavior on out-of-range values.
+///
+/// Unlike [`SimdCast`], values outside the range of the output element type are clamped to its
+/// bounds, and NaN values are mapped to zero before casting.
+pub trait SimdCastSaturating<E>: SimdValue {
+    /// Output type. This should be an SIMD value containing the same number of lanes as the input.
+    type Output: SimdValue<Element = E>;
+
+    /// Perform the saturating cast.
+    fn saturating_cast(self) -> Self::Output;
+}
+
+/// Shortcut method for saturating casting a SIMD value into another one.
+pub fn simd_cast_saturating<E, In: SimdCastSaturating<E>>(value: In) -> In::Output {
+    value.saturating_cast()
+}
+
+/// Casts a single element, replacing NaN values (which are the only values not equal to
+/// themselves) with zero.
+#[inline]
+fn saturating_cast_element<E1: Copy + PartialEq + Zero, E2: SaturatingCastFrom<E1>>(x: E1) -> E2 {
+    #[allow(clippy::eq_op)]
+    let x = if x != x { E1::zero() } else { x };
+    E2::saturating_cast_from(x)
+}
+
+impl<E1, E2, const N: usize> SimdCastSaturating<E2> for AutoSimd<[E1; N]>
+where
+    Self: SimdValue<Element = E1>,
+    AutoSimd<[E2; N]>: Zero + SimdValue<Element = E2>,
+    E1: Copy + PartialEq + Zero,
+    E2: SaturatingCastFrom<E1>,
+{
+    type Output = AutoSimd<[E2; N]>;
+
+    fn saturating_cast(self) -> Self::Output {
+        let mut ret = <Self::Output as Zero>::zero();
+        for i in 0..N {
+            ret.replace(i, saturating_cast_element(self.extract(i)));
+        }
+        ret
+    }
+}
+
+macro_rules! impl_simdcast_saturating_primitives {
+    ($ty:ty) => {
+        impl<E2: SimdValue<Element = E2>> SimdCastSaturating<E2> for $ty
+        where
+            E2: SaturatingCastFrom<$ty>,
+        {
+            type Output = E2;
+            fn saturating_cast(self) -> Self::Output {
+                saturating_cast_element(self)
+            }
+        }
+    };
+}
+
+impl_simdcast_saturating_primitives!(f32);
+impl_simdcast_saturating_primitives!(f64);
+impl_simdcast_saturating_primitives!(u8);
+impl_simdcast_saturating_primitives!(u16);
+impl_simdcast_saturating_primitives!(u32);
+impl_simdcast_saturating_primitives!(u64);
+impl_simdcast_saturating_primitives!(u128);
+impl_simdcast_saturating_primitives!(i8);
+impl_simdcast_saturating_primitives!(i16);
+impl_simdcast_saturating_primitives!(i32);
+impl_simdcast_saturating_primitives!(i64);
+impl_simdcast_saturating_primitives!(i128);
+
+#[cfg(feature = "std")]
+macro_rules! impl_simdcast_saturating_wide {
+    ($name:ty : [$prim:ty; $lanes:literal]) => {
+        impl<E2> SimdCastSaturating<E2> for $name
+        where
+            E2: SaturatingCastFrom<$prim>,
+            simba::simd::AutoSimd<[E2; $lanes]>: Zero + SimdValue<Element = E2>,
+        {
+            type Output = simba::simd::AutoSimd<[E2; $lanes]>;
+
+            fn saturating_cast(self) -> Self::Output {
+                let mut ret = <Self::Output as Zero>::zero();
+                for i in 0..$lanes {
+                    ret.replace(i, saturating_cast_element(self.extract(i)));
+                }
+                ret
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+impl_simdcast_saturating_wide!(simd::WideF32x4 : [f32; 4]);
+#[cfg(feature = "std")]
+impl_simdcast_saturating_wide!(simd::WideF32x8 : [f32; 8]);
+#[cfg(feature = "std")]
+impl_simdcast_saturating_wide!(simd::WideF64x4 : [f64; 4]);
+
 /// Trait for SIMD values which have a transparent repr with arrays, and as such can be directly
 /// transmuted from them.
 ///
@@ -166,6 +303,7 @@ pub unsafe trait SimdFromSlice: Scalar {
     fn from_slice_mut(data: &mut [Self::Element]) -> (&mut [Self], &mut [Self::Element]);
 }
 
+#[cfg(feature = "std")]
 unsafe impl<T, const N: usize> SimdFromSlice for Simd<[T; N]>
 where
     Self: Scalar<Element = T>,
@@ -173,13 +311,13 @@ where
     fn from_slice(data: &[Self::Element]) -> (&[Self], &[Self::Element]) {
         let (inner, remaining) = as_nested_arrays::<_, N>(data);
         // Satefy: Simd<N> is repr(transparent)
-        let ret = unsafe { std::mem::transmute::<&[[T; N]], &[Simd<[T; N]>]>(inner) };
+        let ret = unsafe { core::mem::transmute::<&[[T; N]], &[Simd<[T; N]>]>(inner) };
         (ret, remaining)
     }
     fn from_slice_mut(data: &mut [Self::Element]) -> (&mut [Self], &mut [Self::Element]) {
         let (inner, remaining) = as_nested_arrays_mut::<_, N>(data);
         // Satefy: Simd<N> is repr(transparent)
-        let ret = unsafe { std::mem::transmute::<&mut [[T; N]], &mut [Simd<[T; N]>]>(inner) };
+        let ret = unsafe { core::mem::transmute::<&mut [[T; N]], &mut [Simd<[T; N]>]>(inner) };
         (ret, remaining)
     }
 }
@@ -217,4 +355,61 @@ mod tests {
         is_cast_compatible::<simd::AutoF32x4, usize>();
         is_cast_compatible::<simd::AutoF64x4, usize>();
     }
+
+    #[test]
+    fn test_saturating_cast_scalar() {
+        assert_eq!(0i32, simd_cast_saturating(f32::NAN));
+        assert_eq!(i32::MAX, simd_cast_saturating(f32::INFINITY));
+        assert_eq!(i32::MIN, simd_cast_saturating(f64::NEG_INFINITY));
+        assert_eq!(i32::MAX, simd_cast_saturating(1e12f64));
+        assert_eq!(0usize, simd_cast_saturating(-3.5f32));
+        assert_eq!(3usize, simd_cast_saturating(3.5f32));
+    }
+
+    #[test]
+    fn test_saturating_cast_simd() {
+        let input = [f32::NAN, f32::INFINITY, 3e9, -3e9];
+        let expected = [0, i32::MAX, i32::MAX, i32::MIN];
+
+        let auto: simba::simd::AutoSimd<[i32; 4]> = simd::AutoF32x4::from(input).saturating_cast();
+        assert_eq!(expected, auto.0);
+
+        let wide: simba::simd::AutoSimd<[i32; 4]> = simd::WideF32x4::from(input).saturating_cast();
+        assert_eq!(expected, wide.0);
+
+        let wide: simba::simd::AutoSimd<[i32; 4]> =
+            simd::WideF64x4::from(input.map(f64::from)).saturating_cast();
+        assert_eq!(expected, wide.0);
+    }
+
+    #[test]
+    fn test_from_f32_matches_from_f64() {
+        fn check<T: Scalar + PartialEq + std::fmt::Debug>() {
+            for value in [
+                0.0,
+                -0.0,
+                1.0,
+                -2.5,
+                0.1,
+                440.0,
+                f32::MIN_POSITIVE,
+                f32::MAX,
+            ] {
+                assert_eq!(T::from_f64(value as f64), T::from_f32(value));
+            }
+        }
+        check::<f32>();
+        check::<f64>();
+        check::<simd::AutoF32x4>();
+        check::<simd::AutoF64x2>();
+    }
+
+    #[test]
+    fn test_simd_is_finite() {
+        let x = simd::AutoF32x4::from([1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY]);
+        assert_eq!([true, false, false, false], x.simd_is_finite().0);
+        assert_eq!([false, true, false, false], x.simd_is_nan().0);
+        assert!(0.0f64.simd_is_finite());
+        assert!(f64::NAN.simd_is_nan());
+    }
 }
@@ -0,0 +1,170 @@
+//! # Fast Fourier transform
+//!
+//! Provides radix-2 FFTs for spectral processing and analysis. Transform lengths must be powers of
+//! two.
+use alloc::boxed::Box;
+use alloc::vec;
+
+use nalgebra::Complex;
+
+use crate::Scalar;
+
+/// Zero spectrum bin
+fn zero<T: Scalar>() -> Complex<T> {
+    Complex::new(T::zero(), T::zero())
+}
+
+/// Radix-2 complex FFT, computed in-place with precomputed twiddle factors.
+#[derive(Debug, Clone)]
+pub struct Fft<T> {
+    len: usize,
+    twiddles: Box<[Complex<T>]>,
+}
+
+impl<T: Scalar> Fft<T> {
+    /// Create a new FFT of the given length, which must be a power of two.
+    pub fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two(), "FFT length must be a power of two");
+        let step = -T::simd_two_pi() / T::from_f64(len as _);
+        let twiddles = (0..len / 2)
+            .map(|k| {
+                let (sin, cos) = (step * T::from_f64(k as _)).simd_sin_cos();
+                Complex::new(cos, sin)
+            })
+            .collect();
+        Self { len, twiddles }
+    }
+
+    /// In-place forward transform. The buffer must be of the length of the FFT.
+    pub fn forward(&self, buf: &mut [Complex<T>]) {
+        let len = self.len;
+        assert_eq!(
+            len,
+            buf.len(),
+            "Buffer length does not match the FFT length"
+        );
+        let bits = len.trailing_zeros();
+        if bits == 0 {
+            return;
+        }
+        for i in 0..len {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if j > i {
+                buf.swap(i, j);
+            }
+        }
+        let mut size = 2;
+        while size <= len {
+            let half = size / 2;
+            let stride = len / size;
+            for chunk in buf.chunks_exact_mut(size) {
+                let (lo, hi) = chunk.split_at_mut(half);
+                for (k, (a, b)) in lo.iter_mut().zip(hi.iter_mut()).enumerate() {
+                    let t = *b * self.twiddles[k * stride];
+                    *b = *a - t;
+                    *a += t;
+                }
+            }
+            size *= 2;
+        }
+    }
+
+    /// In-place unnormalized inverse transform. The buffer must be of the length of the FFT.
+    pub fn inverse(&self, buf: &mut [Complex<T>]) {
+        // The inverse transform is the conjugate of the forward transform of the conjugate
+        for x in buf.iter_mut() {
+            *x = x.conj();
+        }
+        self.forward(buf);
+        for x in buf.iter_mut() {
+            *x = x.conj();
+        }
+    }
+}
+
+/// Real FFT, computed as a complex FFT of the same length.
+///
+/// Only the `len / 2 + 1` non-negative frequency bins are given out, as the others are their
+/// complex conjugates.
+#[derive(Debug, Clone)]
+pub struct RealFft<T> {
+    fft: Fft<T>,
+    buffer: Box<[Complex<T>]>,
+}
+
+impl<T: Scalar> RealFft<T> {
+    /// Create a new FFT of the given length, which must be a power of two.
+    pub fn new(len: usize) -> Self {
+        Self {
+            fft: Fft::new(len),
+            buffer: vec![zero(); len].into_boxed_slice(),
+        }
+    }
+
+    /// Forward transform of `input`, writing the `len / 2 + 1` non-negative frequency bins.
+    pub fn forward(&mut self, input: &[T], output: &mut [Complex<T>]) {
+        for (b, &x) in self.buffer.iter_mut().zip(input) {
+            *b = Complex::new(x, T::zero());
+        }
+        self.fft.forward(&mut self.buffer);
+        output.copy_from_slice(&self.buffer[..output.len()]);
+    }
+
+    /// Unnormalized inverse transform of the `len / 2 + 1` non-negative frequency bins.
+    pub fn inverse(&mut self, input: &[Complex<T>], output: &mut [T]) {
+        // Forward transform of the conjugate; the conjugate of the output is dropped as only the
+        // real part is kept.
+        let len = self.buffer.len();
+        for (k, x) in input.iter().enumerate() {
+            self.buffer[k] = x.conj();
+            if k > 0 && 2 * k < len {
+                self.buffer[len - k] = *x;
+            }
+        }
+        self.fft.forward(&mut self.buffer);
+        for (y, b) in output.iter_mut().zip(self.buffer.iter()) {
+            *y = b.re;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_matches_dft() {
+        const N: usize = 32;
+        let input = (0..N)
+            .map(|i| Complex::new(f64::sin(i as f64 * 0.7), f64::cos(i as f64 * 1.3)))
+            .collect::<Vec<_>>();
+        let mut spectrum = input.clone();
+        Fft::new(N).forward(&mut spectrum);
+        for (k, x) in spectrum.iter().enumerate() {
+            let expected = input
+                .iter()
+                .enumerate()
+                .map(|(n, &x)| {
+                    x * Complex::from_polar(1.0, -std::f64::consts::TAU * (k * n) as f64 / N as f64)
+                })
+                .sum::<Complex<f64>>();
+            assert!((x - expected).norm() < 1e-9, "Bin {k}: {x} != {expected}");
+        }
+    }
+
+    #[test]
+    fn real_fft_round_trip() {
+        const N: usize = 64;
+        let input = (0..N)
+            .map(|i| f32::sin(i as f32 * 0.3) + 0.2 * f32::cos(i as f32 * 2.1))
+            .collect::<Vec<_>>();
+        let mut fft = RealFft::new(N);
+        let mut spectrum = vec![Complex::new(0.0, 0.0); N / 2 + 1];
+        let mut output = vec![0.0; N];
+        fft.forward(&input, &mut spectrum);
+        fft.inverse(&spectrum, &mut output);
+        for (x, y) in input.iter().zip(&output) {
+            assert!((x - y / N as f32).abs() < 1e-5, "{x} != {y}");
+        }
+    }
+}
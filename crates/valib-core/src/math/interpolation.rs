@@ -54,7 +54,7 @@ pub trait Interpolate<T, const N: usize> {
     {
         let input_frac = t.simd_fract();
         let input_index = t.simd_floor().cast();
-        let taps_ix: [_; N] = std::array::from_fn(|i| {
+        let taps_ix: [_; N] = core::array::from_fn(|i| {
             let mut output = input_index;
             for j in 0..<T as SimdCast<usize>>::Output::LANES {
                 output.replace(j, Self::indices(output.extract(j))[i]);
@@ -196,7 +196,7 @@ pub struct Lanczos;
 
 impl<T: Scalar> Interpolate<T, 7> for Lanczos {
     fn indices(index: usize) -> [usize; 7] {
-        std::array::from_fn(|i| (index + i).saturating_sub(3))
+        core::array::from_fn(|i| (index + i).saturating_sub(3))
     }
 
     fn interpolate(&self, t: T, taps: [T; 7]) -> T {
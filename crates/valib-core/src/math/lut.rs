@@ -1,6 +1,6 @@
 //! Types providing Lookup Tables
 
-use std::ops::Range;
+use core::ops::Range;
 
 use crate::{Scalar, SimdCast};
 use numeric_literals::replace_float_literals;
@@ -62,7 +62,7 @@ impl<T: Scalar, const N: usize> Lut<T, N> {
         let start = range.start;
         let r = range.end - range.start;
         let rsize = T::from_f64(N as f64).simd_recip();
-        let array = std::array::from_fn(|i| {
+        let array = core::array::from_fn(|i| {
             let n = T::from_f64(i as f64) * rsize;
             f(start + n * r)
         });
@@ -8,11 +8,16 @@ use simba::simd::{SimdBool, SimdComplexField};
 
 use crate::Scalar;
 
+pub use transform::{bilinear_transform, matched_z};
+
+pub mod fft;
 pub mod interpolation;
 pub mod lut;
 pub mod nr;
 #[cfg(feature = "math-polynom")]
 pub mod polynom;
+pub mod transform;
+pub mod window;
 
 /// Return the complex number in the z-plane corresponding to the frequency `f` at sample rate
 /// `samplerate`.
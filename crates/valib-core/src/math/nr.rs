@@ -1,11 +1,11 @@
 //! Module for working with numerical root finding, using the Newton-Rhapson method.
 use crate::math;
 use crate::Scalar;
+use core::num::NonZeroUsize;
 use nalgebra as na;
 use nalgebra::{Dim, OMatrix, OVector, VectorView, VectorViewMut};
 use num_traits::Float;
-use simba::simd::{SimdBool, SimdPartialOrd};
-use std::num::NonZeroUsize;
+use simba::simd::{SimdBool, SimdPartialOrd, SimdValue};
 
 /// Trait desciring a multivariate root equation. Root equations are solved with numerical methods such as
 /// Newton-Rhapson, when linear algebra cannot be used (e.g. in the case of nonlinear systems).
@@ -118,6 +118,10 @@ where
 
     /// Run the root-finding algorithm, using the provided view as initial guess and result.
     ///
+    /// When the scalar type is a SIMD type, each lane converges independently: lanes within the
+    /// tolerance, or whose update is not finite, are frozen while the iteration continues on the
+    /// other lanes. The returned iteration count is the one at which the last lane finished.
+    ///
     /// # Arguments
     ///
     /// * `value`:  Initial guess to use as first value into the iteration scheme.
@@ -133,6 +137,8 @@ where
             "Current Newron-Rhapson solver configuration would lead to infinite loop"
         );
 
+        let zero = Equ::Scalar::from_f64(0.0);
+        let mut done = zero.simd_ne(zero);
         for i in self.iterations_iter() {
             let Some(ret) = self
                 .equation
@@ -141,14 +147,16 @@ where
             else {
                 return i;
             };
-            let all_finite = ret
+            let not_finite = ret
                 .iter()
-                .copied()
-                .flat_map(|v| v.into_iter())
-                .all(|v| v.is_finite());
+                .fold(zero.simd_ne(zero), |acc, &v| acc | !v.simd_is_finite());
 
-            value -= ret;
-            if !all_finite || self.check_tolerance(ret.as_view()) {
+            let frozen = done | not_finite;
+            for (v, &r) in value.iter_mut().zip(ret.iter()) {
+                *v = v.select(frozen, *v - r);
+            }
+            done = frozen | self.check_tolerance(ret.as_view());
+            if done.all() {
                 return i;
             }
         }
@@ -184,11 +192,12 @@ where
     fn check_tolerance(
         &self,
         value: VectorView<Equ::Scalar, Equ::Dim, impl Dim, impl Dim>,
-    ) -> bool {
+    ) -> <Equ::Scalar as SimdValue>::SimdBool {
+        let zero = Equ::Scalar::from_f64(0.0);
         if let Some(tol) = self.tolerance {
-            math::rms(value).simd_lt(tol).all()
+            math::rms(value).simd_lt(tol)
         } else {
-            false
+            zero.simd_ne(zero)
         }
     }
 }
@@ -271,4 +280,56 @@ mod tests {
 
         assert_eq!(0, nr.run_in_place(vector_view_mut(&mut actual)));
     }
+
+    #[test]
+    fn test_simd_lanes_converge_independently() {
+        struct SimdSqrt<T> {
+            squared: T,
+        }
+
+        impl<T: Scalar> RootEq for SimdSqrt<T> {
+            type Scalar = T;
+            type Dim = na::U1;
+
+            fn eval(
+                &self,
+                input: VectorView<Self::Scalar, Self::Dim, impl Dim, impl Dim>,
+            ) -> OVector<Self::Scalar, Self::Dim> {
+                [self.squared - input[0] * input[0]].into()
+            }
+
+            fn j_inv(
+                &self,
+                input: VectorView<Self::Scalar, Self::Dim, impl Dim, impl Dim>,
+            ) -> Option<OMatrix<Self::Scalar, Self::Dim, Self::Dim>> {
+                Some([(T::from_f64(-2.0) * input[0]).simd_recip()].into())
+            }
+        }
+
+        use crate::simd::AutoF64x2;
+        // The first lane converges right away, the second one needs many more iterations
+        let squared = [1.0001, 1e12];
+        fn solve<T: Scalar<Element: Float>>(squared: T, initial_guess: T) -> T {
+            NewtonRhapson::new(
+                SimdSqrt { squared },
+                Some(T::from_f64(1e-3)),
+                NonZeroUsize::new(100),
+            )
+            .run(SVector::<_, 1>::new(initial_guess))[0]
+        }
+        let actual = solve(
+            AutoF64x2::new(squared[0], squared[1]),
+            AutoF64x2::splat(1.0),
+        );
+
+        for (i, squared) in squared.into_iter().enumerate() {
+            let lane = actual.extract(i);
+            assert!(
+                (lane - squared.sqrt()).abs() < 1e-6,
+                "Lane {i} did not converge: {lane}"
+            );
+            // Lanes must match solving each value on its own
+            assert_eq!(solve(squared, 1.0), lane);
+        }
+    }
 }
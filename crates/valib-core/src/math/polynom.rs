@@ -1,4 +1,4 @@
-use std::{iter, ops};
+use core::{iter, ops};
 
 use num_traits::real::Real;
 use num_traits::{One, Zero};
@@ -0,0 +1,199 @@
+//! # Analog to digital transforms
+//!
+//! Mappings from the s-plane to the z-plane, for designing digital filters from analog
+//! prototypes.
+//!
+//! Analog polynomials are given in ascending powers of `s`, and digital polynomials in ascending
+//! powers of `z^-1`, such that `[1, a1, a2]` is the denominator `1 + a1 z^-1 + a2 z^-2`.
+use nalgebra::Complex;
+
+use crate::Scalar;
+
+/// Multiply the polynomial in place by `(1 + c x)`. The polynomial must have room for the
+/// additional degree.
+fn mul_linear<T: Scalar>(poly: &mut [T], c: T) {
+    for i in (1..poly.len()).rev() {
+        poly[i] += c * poly[i - 1];
+    }
+}
+
+/// Transform an analog transfer function into a digital one, using the bilinear transform.
+///
+/// Both polynomials are padded to the same length `N`, with the filter being of order `N - 1`.
+/// The resulting coefficients are normalized such that the first denominator coefficient is 1.
+///
+/// # Arguments
+///
+/// * `b`: Numerator of the analog transfer function, in ascending powers of `s`
+/// * `a`: Denominator of the analog transfer function, in ascending powers of `s`
+/// * `samplerate`: Sample rate of the digital filter
+/// * `prewarp`: Frequency (in Hz) at which the digital response exactly matches the analog one.
+///     When [`None`], no pre-warping is performed and frequencies get compressed towards Nyquist.
+///
+/// returns: ([T; N], [T; N]) (numerator and denominator, in ascending powers of `z^-1`)
+pub fn bilinear_transform<T: Scalar, const N: usize>(
+    b: [T; N],
+    a: [T; N],
+    samplerate: T,
+    prewarp: Option<T>,
+) -> ([T; N], [T; N]) {
+    let two = T::from_f64(2.0);
+    let k = match prewarp {
+        Some(f0) => {
+            let w0 = T::simd_two_pi() * f0;
+            w0 / (w0 / (two * samplerate)).simd_tan()
+        }
+        None => two * samplerate,
+    };
+
+    // s = k (1 - z^-1) / (1 + z^-1); multiplying through by (1 + z^-1)^(N - 1), the term in s^i
+    // becomes k^i (1 - z^-1)^i (1 + z^-1)^(N - 1 - i)
+    let mut num = [T::zero(); N];
+    let mut den = [T::zero(); N];
+    let mut ki = T::one();
+    for i in 0..N {
+        let mut term = [T::zero(); N];
+        term[0] = ki;
+        for _ in 0..i {
+            mul_linear(&mut term, -T::one());
+        }
+        for _ in i + 1..N {
+            mul_linear(&mut term, T::one());
+        }
+        for ((n, d), t) in num.iter_mut().zip(den.iter_mut()).zip(term) {
+            *n += b[i] * t;
+            *d += a[i] * t;
+        }
+        ki *= k;
+    }
+
+    let a0 = den[0];
+    (num.map(|x| x / a0), den.map(|x| x / a0))
+}
+
+/// Transform analog poles and zeros into a digital transfer function, using the matched-Z
+/// transform, which maps each root `r` of the s-plane to `exp(r / samplerate)` in the z-plane.
+///
+/// Complex roots must come in conjugate pairs for the resulting coefficients to be real; only the
+/// real parts are returned. Both polynomials are monic, and the gain of the filter is not matched:
+/// callers should scale the numerator to match the gain of the analog filter at a reference
+/// frequency, usually DC.
+///
+/// # Arguments
+///
+/// * `zeros`: Zeros of the analog transfer function, at most `N - 1` of them
+/// * `poles`: Poles of the analog transfer function, at most `N - 1` of them
+/// * `samplerate`: Sample rate of the digital filter
+///
+/// returns: ([T; N], [T; N]) (numerator and denominator, in ascending powers of `z^-1`)
+pub fn matched_z<T: Scalar, const N: usize>(
+    zeros: &[Complex<T>],
+    poles: &[Complex<T>],
+    samplerate: T,
+) -> ([T; N], [T; N]) {
+    let expand = |roots: &[Complex<T>]| {
+        assert!(
+            roots.len() < N,
+            "Too many roots for a polynomial of order {}",
+            N - 1
+        );
+        let mut poly = [Complex::new(T::zero(), T::zero()); N];
+        poly[0] = Complex::new(T::one(), T::zero());
+        for r in roots {
+            let (sin, cos) = (r.im / samplerate).simd_sin_cos();
+            let magnitude = (r.re / samplerate).simd_exp();
+            let z = Complex::new(magnitude * cos, magnitude * sin);
+            for i in (1..N).rev() {
+                let prev = poly[i - 1];
+                poly[i] -= z * prev;
+            }
+        }
+        poly.map(|c| c.re)
+    };
+    (expand(zeros), expand(poles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_1_SQRT_2, TAU};
+
+    /// Roots of `1 + a1 z^-1 + a2 z^-2`, as complex values of `z`
+    fn quadratic_roots([_, a1, a2]: [f64; 3]) -> [Complex<f64>; 2] {
+        let disc = Complex::new(a1 * a1 - 4.0 * a2, 0.0).sqrt();
+        [(-a1 + disc) / 2.0, (-a1 - disc) / 2.0]
+    }
+
+    fn assert_roots_eq(expected: [Complex<f64>; 2], actual: [Complex<f64>; 2]) {
+        // Roots may come in any order
+        let matches = |[a, b]: [Complex<f64>; 2]| {
+            (a - expected[0]).norm() < 1e-9 && (b - expected[1]).norm() < 1e-9
+        };
+        assert!(
+            matches(actual) || matches([actual[1], actual[0]]),
+            "Expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn bilinear_butterworth_lowpass() {
+        const FS: f64 = 48e3;
+        const FC: f64 = 1e3;
+        let wc = TAU * FC;
+        // H(s) = wc^2 / (s^2 + sqrt(2) wc s + wc^2)
+        let (b, a) = bilinear_transform(
+            [wc * wc, 0.0, 0.0],
+            [wc * wc, 2.0 * FRAC_1_SQRT_2 * wc, 1.0],
+            FS,
+            Some(FC),
+        );
+
+        // Both zeros at Nyquist, that is a numerator proportional to (1 + z^-1)^2
+        assert!((b[1] / b[0] - 2.0).abs() < 1e-9);
+        assert!((b[2] / b[0] - 1.0).abs() < 1e-9);
+
+        // Poles are the pre-warped analog poles, mapped with z = (k + s) / (k - s)
+        let k = wc / (wc / (2.0 * FS)).tan();
+        let expected = [1.0, -1.0].map(|sign| {
+            let s = Complex::new(-FRAC_1_SQRT_2, sign * FRAC_1_SQRT_2) * wc;
+            (k + s) / (k - s)
+        });
+        assert_roots_eq(expected, quadratic_roots(a));
+
+        // Unity gain at DC, and -3 dB at the cutoff thanks to pre-warping
+        let gain = |f: f64| {
+            let zinv = Complex::from_polar(1.0, -TAU * f / FS);
+            let eval = |c: [f64; 3]| c[0] + zinv * (c[1] + zinv * c[2]);
+            (eval(b) / eval(a)).norm()
+        };
+        assert!((gain(0.0) - 1.0).abs() < 1e-9);
+        assert!((gain(FC) - FRAC_1_SQRT_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bilinear_first_order() {
+        // Integrator-like lowpass H(s) = 1 / (1 + s), without pre-warping
+        let (b, a) = bilinear_transform([1.0, 0.0], [1.0, 1.0], 0.5, None);
+        // k = 1: H(z) = (1 + z^-1) / 2
+        assert_eq!([0.5, 0.5], b);
+        assert_eq!([1.0, 0.0], a);
+    }
+
+    #[test]
+    fn matched_z_maps_roots() {
+        const FS: f64 = 48e3;
+        let pole = Complex::new(-500.0, TAU * 2e3);
+        let zero = Complex::new(0.0, TAU * 10e3);
+        let (b, a) = matched_z::<f64, 3>(&[zero, zero.conj()], &[pole, pole.conj()], FS);
+
+        let exp = |s: Complex<f64>| (s / FS).exp();
+        assert_roots_eq([exp(pole), exp(pole.conj())], quadratic_roots(a));
+        assert_roots_eq([exp(zero), exp(zero.conj())], quadratic_roots(b));
+        assert_eq!(1.0, b[0]);
+        assert!((a[2] - (-1000.0 / FS).exp()).abs() < 1e-12);
+
+        // A single real pole leaves the last coefficient at zero
+        let (_, a) = matched_z::<f64, 3>(&[], &[Complex::new(-100.0, 0.0)], FS);
+        assert_eq!([1.0, -(-100.0 / FS).exp(), 0.0], a);
+    }
+}
@@ -0,0 +1,211 @@
+//! # Window functions
+//!
+//! Window functions for spectral analysis and FIR filter design, along with helpers to compensate
+//! for their effect on measured magnitudes.
+//!
+//! All windows are symmetric, which is what FIR filter design requires. For spectral analysis, a
+//! periodic window of length `N` is obtained by computing a symmetric window of length `N + 1` and
+//! dropping the last sample.
+use numeric_literals::replace_float_literals;
+use simba::simd::SimdBool;
+
+use crate::Scalar;
+
+/// Window function, for selecting a window at runtime.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WindowFunction {
+    /// Rectangular window, leaving the signal untouched
+    Rectangular,
+    /// Hann window, good general-purpose frequency resolution
+    Hann,
+    /// Hamming window, with a lower first sidelobe than the Hann window
+    Hamming,
+    /// 4-term Blackman-Harris window, with sidelobes below -92 dB
+    BlackmanHarris,
+    /// Kaiser window with the given shape parameter, trading sidelobe level for main lobe width
+    Kaiser(f64),
+    /// Flat top window, with a flat main lobe for accurate amplitude measurements
+    FlatTop,
+}
+
+impl WindowFunction {
+    /// Fill the slice with this window function.
+    ///
+    /// # Arguments
+    ///
+    /// * `out`: Slice to fill, whose length is the length of the window
+    ///
+    /// returns: ()
+    pub fn fill<T: Scalar>(&self, out: &mut [T]) {
+        match *self {
+            Self::Rectangular => out.fill(T::one()),
+            Self::Hann => hann(out),
+            Self::Hamming => hamming(out),
+            Self::BlackmanHarris => blackman_harris(out),
+            Self::Kaiser(beta) => kaiser(out, T::from_f64(beta)),
+            Self::FlatTop => flat_top(out),
+        }
+    }
+}
+
+/// Fill the slice with a sum-of-cosines window, with the given coefficients for each harmonic.
+#[replace_float_literals(T::from_f64(literal))]
+fn cosine_sum<T: Scalar>(out: &mut [T], coeffs: &[f64]) {
+    if out.len() == 1 {
+        out[0] = 1.0;
+        return;
+    }
+    let step = T::simd_two_pi() / T::from_f64((out.len() - 1) as _);
+    for (i, s) in out.iter_mut().enumerate() {
+        let x = step * T::from_f64(i as _);
+        *s = coeffs.iter().enumerate().fold(T::zero(), |acc, (k, &a)| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            acc + sign * T::from_f64(a) * T::simd_cos(T::from_f64(k as _) * x)
+        });
+    }
+}
+
+/// Fill the slice with a Hann window.
+pub fn hann<T: Scalar>(out: &mut [T]) {
+    cosine_sum(out, &[0.5, 0.5]);
+}
+
+/// Fill the slice with a Hamming window.
+pub fn hamming<T: Scalar>(out: &mut [T]) {
+    cosine_sum(out, &[0.54, 0.46]);
+}
+
+/// Fill the slice with a 4-term Blackman-Harris window.
+pub fn blackman_harris<T: Scalar>(out: &mut [T]) {
+    cosine_sum(out, &[0.35875, 0.48829, 0.14128, 0.01168]);
+}
+
+/// Fill the slice with a flat top window.
+pub fn flat_top<T: Scalar>(out: &mut [T]) {
+    cosine_sum(
+        out,
+        &[
+            0.21557895,
+            0.41663158,
+            0.277263158,
+            0.083578947,
+            0.006947368,
+        ],
+    );
+}
+
+/// Fill the slice with a Kaiser window.
+///
+/// # Arguments
+///
+/// * `out`: Slice to fill, whose length is the length of the window
+/// * `beta`: Shape parameter, trading sidelobe level for main lobe width. 0 gives a rectangular
+///     window.
+///
+/// returns: ()
+#[replace_float_literals(T::from_f64(literal))]
+pub fn kaiser<T: Scalar>(out: &mut [T], beta: T) {
+    if out.len() == 1 {
+        out[0] = 1.0;
+        return;
+    }
+    let center = T::from_f64((out.len() - 1) as _) / 2.0;
+    let norm = bessel_i0(beta);
+    for (i, s) in out.iter_mut().enumerate() {
+        let r = (T::from_f64(i as _) - center) / center;
+        *s = bessel_i0(beta * T::simd_sqrt(1.0 - r * r)) / norm;
+    }
+}
+
+/// Zeroth-order modified Bessel function of the first kind, used to compute the Kaiser window.
+#[replace_float_literals(T::from_f64(literal))]
+pub fn bessel_i0<T: Scalar>(x: T) -> T {
+    let mut sum = T::one();
+    let mut term = T::one();
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= half_x / T::from_f64(k as _);
+        let t2 = term * term;
+        sum += t2;
+        if t2.simd_lt(sum * 1e-16).all() {
+            break;
+        }
+    }
+    sum
+}
+
+/// Coherent gain of the window, that is the amplitude of a sinusoid at the center of a frequency
+/// bin relative to an unwindowed one. Divide magnitudes by it to measure the amplitude of tonal
+/// components.
+pub fn coherent_gain<T: Scalar>(window: &[T]) -> T {
+    let sum = window.iter().copied().fold(T::zero(), |acc, w| acc + w);
+    sum / T::from_f64(window.len() as _)
+}
+
+/// Equivalent noise bandwidth of the window, in bins. Divide power spectra by it to measure the
+/// power spectral density of noise components.
+pub fn enbw<T: Scalar>(window: &[T]) -> T {
+    let (sum, sum_sq) = window
+        .iter()
+        .copied()
+        .fold((T::zero(), T::zero()), |(s, s2), w| (s + w, s2 + w * w));
+    T::from_f64(window.len() as _) * sum_sq / (sum * sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const WINDOWS: [WindowFunction; 6] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+        WindowFunction::BlackmanHarris,
+        WindowFunction::Kaiser(8.0),
+        WindowFunction::FlatTop,
+    ];
+
+    #[test]
+    fn windows_are_symmetric() {
+        for window in WINDOWS {
+            for len in [1, 2, 63, 64] {
+                let mut out = vec![0.0; len];
+                window.fill::<f64>(&mut out);
+                for i in 0..len {
+                    assert!(
+                        (out[i] - out[len - 1 - i]).abs() < 1e-12,
+                        "{window:?} of length {len} is not symmetric at {i}"
+                    );
+                }
+                // Peak at the center
+                let max = out.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                assert!((out[len / 2] - max).abs() < 1e-2, "{window:?}: {out:?}");
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(WindowFunction::Rectangular, 1.0, 1.0)]
+    #[case(WindowFunction::Hann, 0.5, 1.5)]
+    #[case(WindowFunction::Hamming, 0.54, 1.3628)]
+    #[case(WindowFunction::BlackmanHarris, 0.35875, 2.0044)]
+    #[case(WindowFunction::FlatTop, 0.21557895, 3.7702)]
+    fn window_gains(#[case] window: WindowFunction, #[case] gain: f64, #[case] bandwidth: f64) {
+        // Periodic window, for which the gains match their textbook values
+        let mut out = vec![0.0; 4097];
+        window.fill::<f64>(&mut out);
+        let out = &out[..4096];
+        let actual = coherent_gain(out);
+        assert!((actual - gain).abs() < 1e-6, "Coherent gain: {actual}");
+        let actual = enbw(out);
+        assert!((actual - bandwidth).abs() < 1e-3, "ENBW: {actual}");
+    }
+
+    #[test]
+    fn kaiser_zero_is_rectangular() {
+        let mut out = [0.0f32; 16];
+        kaiser(&mut out, 0.0);
+        assert_eq!([1.0; 16], out);
+    }
+}
@@ -9,6 +9,16 @@ use num_traits::{AsPrimitive, Float, Zero};
 use numeric_literals::replace_float_literals;
 use simba::simd::SimdValue;
 
+pub mod denormal;
+pub mod dither;
+pub mod goertzel;
+pub mod meter;
+pub mod precision;
+pub mod rng;
+pub mod scale;
+
+pub use scale::Scale;
+
 /// Transmutes a slice into a slice of static arrays, putting the remainder of the slice not fitting
 /// as a separate slice.
 ///
@@ -34,7 +44,7 @@ pub fn as_nested_arrays<T, const N: usize>(data: &[T]) -> (&[[T; N]], &[T]) {
     let outer_len = complete.len() / N;
 
     // Safety: Static arrays of N elements have the same representation as N contiguous elements
-    let result = unsafe { std::slice::from_raw_parts(complete.as_ptr() as *const _, outer_len) };
+    let result = unsafe { core::slice::from_raw_parts(complete.as_ptr() as *const _, outer_len) };
     (result, remaining)
 }
 
@@ -67,7 +77,7 @@ pub fn as_nested_arrays_mut<T, const N: usize>(data: &mut [T]) -> (&mut [[T; N]]
 
     // Safety: Static arrays of N elements have the same representation as N contiguous elements
     let result =
-        unsafe { std::slice::from_raw_parts_mut(complete.as_mut_ptr() as *mut _, outer_len) };
+        unsafe { core::slice::from_raw_parts_mut(complete.as_mut_ptr() as *mut _, outer_len) };
     (result, remaining)
 }
 
@@ -184,9 +194,95 @@ pub fn lerp<T: Scalar>(t: T, a: T, b: T) -> T {
 /// * `midi_note`: MIDI note number
 ///
 /// returns: T
+///
+/// # Examples
+///
+/// ```
+/// use valib_core::util::{freq_to_midi, midi_to_freq};
+/// assert_eq!(440.0, midi_to_freq::<f32>(69));
+/// assert_eq!(69.0, freq_to_midi(midi_to_freq::<f32>(69)));
+/// ```
 #[replace_float_literals(T::from_f64(literal))]
 pub fn midi_to_freq<T: Scalar>(midi_note: u8) -> T {
-    440.0 * semitone_to_ratio(T::from_f64(midi_note as _) - 69.0)
+    midi_to_freq_tuned(midi_note, 440.0)
+}
+
+/// Computes the frequency of a MIDI note number, assuming 12TET and the given reference tuning
+/// for A4.
+///
+/// # Arguments
+///
+/// * `midi_note`: MIDI note number
+/// * `a4_hz`: Frequency of A4 (MIDI note 69), in Hz
+///
+/// returns: T
+///
+/// # Examples
+///
+/// ```
+/// use valib_core::util::midi_to_freq_tuned;
+/// assert_eq!(432.0, midi_to_freq_tuned::<f32>(69, 432.0));
+/// ```
+pub fn midi_to_freq_tuned<T: Scalar>(midi_note: u8, a4_hz: T) -> T {
+    note_to_freq_tuned(T::from_f64(midi_note as _), a4_hz)
+}
+
+/// Computes the frequency of a fractional note number, assuming 12TET and the given reference
+/// tuning for A4. Each lane of a SIMD note is converted independently.
+///
+/// # Arguments
+///
+/// * `note`: Note number, on the same scale as MIDI note numbers
+/// * `a4_hz`: Frequency of A4 (note 69), in Hz
+///
+/// returns: T
+///
+/// # Examples
+///
+/// ```
+/// use valib_core::simd::{AutoF32x4, SimdValue};
+/// use valib_core::util::note_to_freq_tuned;
+/// let freq = note_to_freq_tuned(AutoF32x4::new(57.0, 69.0, 81.0, 93.0), AutoF32x4::splat(440.0));
+/// assert_eq!([220.0, 440.0, 880.0, 1760.0], freq.0);
+/// ```
+#[replace_float_literals(T::from_f64(literal))]
+pub fn note_to_freq_tuned<T: Scalar>(note: T, a4_hz: T) -> T {
+    a4_hz * semitone_to_ratio(note - 69.0)
+}
+
+/// Computes the fractional MIDI note number of a frequency, assuming 12TET and A4 = 440 Hz. This
+/// is the inverse of [`midi_to_freq`].
+///
+/// # Arguments
+///
+/// * `freq`: Frequency in Hz
+///
+/// returns: T
+#[replace_float_literals(T::from_f64(literal))]
+pub fn freq_to_midi<T: Scalar>(freq: T) -> T {
+    freq_to_midi_tuned(freq, 440.0)
+}
+
+/// Computes the fractional MIDI note number of a frequency, assuming 12TET and the given reference
+/// tuning for A4. This is the inverse of [`note_to_freq_tuned`].
+///
+/// # Arguments
+///
+/// * `freq`: Frequency in Hz
+/// * `a4_hz`: Frequency of A4 (MIDI note 69), in Hz
+///
+/// returns: T
+///
+/// # Examples
+///
+/// ```
+/// use valib_core::util::freq_to_midi_tuned;
+/// assert_eq!(69.0, freq_to_midi_tuned::<f32>(432.0, 432.0));
+/// assert_eq!(81.0, freq_to_midi_tuned::<f32>(864.0, 432.0));
+/// ```
+#[replace_float_literals(T::from_f64(literal))]
+pub fn freq_to_midi_tuned<T: Scalar>(freq: T, a4_hz: T) -> T {
+    69.0 + 12.0 * (freq / a4_hz).simd_log2()
 }
 
 /// Compute the ratio corresponding to the given semitone change, such that multiplying a frequency
@@ -0,0 +1,205 @@
+//! # Denormal prevention
+//!
+//! Provides injection of a tiny inaudible offset into signals, which keeps the state of decaying
+//! feedback paths (filters, reverbs, ...) from reaching the denormal range, where arithmetic is
+//! much slower on most CPUs.
+use crate::dsp::{DSPMeta, DSPProcess};
+use crate::Scalar;
+
+/// Generator of an anti-denormal offset, alternating in sign every few samples.
+///
+/// The offset is far below audibility, but large enough for signals it is added to never decay
+/// into the denormal range of `f32`. Alternating the sign keeps the offset from accumulating into
+/// a DC component in integrating feedback paths, while staying slow enough that lowpass filters do
+/// not cancel it out.
+#[derive(Debug, Copy, Clone)]
+pub struct AntiDenormal<T> {
+    amount: T,
+    period: usize,
+    counter: usize,
+}
+
+impl<T: Scalar> Default for AntiDenormal<T> {
+    /// Anti-denormal offset of 1e-20 (-400 dB), changing sign every 64 samples.
+    fn default() -> Self {
+        Self::new(T::from_f64(1e-20), 64)
+    }
+}
+
+impl<T: Scalar> AntiDenormal<T> {
+    /// Create a new anti-denormal offset generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount`: Magnitude of the offset
+    /// * `period`: Number of samples after which the sign of the offset changes
+    ///
+    /// returns: AntiDenormal<T>
+    pub fn new(amount: T, period: usize) -> Self {
+        assert!(
+            period > 0,
+            "Anti-denormal period must be at least one sample"
+        );
+        Self {
+            amount,
+            period,
+            counter: 0,
+        }
+    }
+
+    /// Magnitude of the offset
+    pub fn amount(&self) -> T {
+        self.amount
+    }
+
+    /// Return the offset for the next sample.
+    pub fn next_offset(&mut self) -> T {
+        let positive = self.counter < self.period;
+        self.counter = (self.counter + 1) % (2 * self.period);
+        if positive {
+            self.amount
+        } else {
+            -self.amount
+        }
+    }
+
+    /// Add the anti-denormal offset to the signal, in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `signal`: Signal to add the offset to
+    ///
+    /// returns: ()
+    pub fn apply(&mut self, signal: &mut [T]) {
+        for x in signal {
+            *x += self.next_offset();
+        }
+    }
+
+    /// Reset the sign alternation
+    pub fn reset(&mut self) {
+        self.counter = 0;
+    }
+}
+
+/// Processor wrapper adding an anti-denormal offset to all of its inputs, keeping the state of the
+/// inner processor out of the denormal range when its input goes silent.
+#[derive(Debug, Copy, Clone)]
+pub struct DenormalPrevented<P: DSPMeta> {
+    /// Inner processor
+    pub inner: P,
+    anti_denormal: AntiDenormal<P::Sample>,
+}
+
+impl<P: DSPMeta> DenormalPrevented<P> {
+    /// Wrap the processor, using the default anti-denormal offset.
+    pub fn new(inner: P) -> Self {
+        Self::with_anti_denormal(inner, AntiDenormal::default())
+    }
+
+    /// Wrap the processor, using the given anti-denormal offset generator.
+    pub fn with_anti_denormal(inner: P, anti_denormal: AntiDenormal<P::Sample>) -> Self {
+        Self {
+            inner,
+            anti_denormal,
+        }
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for DenormalPrevented<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.inner.try_set_samplerate(samplerate)
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.anti_denormal.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O>
+    for DenormalPrevented<P>
+{
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        let offset = self.anti_denormal.next_offset();
+        self.inner.process(x.map(|x| x + offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two-pole resonator in direct form, with poles at radius 0.99
+    #[derive(Debug, Copy, Clone)]
+    struct Resonator([f32; 2]);
+
+    impl DSPMeta for Resonator {
+        type Sample = f32;
+    }
+
+    impl DSPProcess<1, 1> for Resonator {
+        fn process(&mut self, [x]: [f32; 1]) -> [f32; 1] {
+            let y = x + 1.9701 * self.0[0] - 0.9801 * self.0[1];
+            self.0 = [y, self.0[0]];
+            [y]
+        }
+    }
+
+    #[test]
+    fn anti_denormal_is_inaudible() {
+        let mut anti_denormal = AntiDenormal::<f32>::default();
+        let mut signal = [0.0; 256];
+        anti_denormal.apply(&mut signal);
+        assert!(signal.iter().all(|x| x.abs() < 1e-15));
+        // Alternating, so that it has no DC component
+        let positive = signal.iter().filter(|&&x| x > 0.0).count();
+        assert_eq!(signal.len() / 2, positive);
+    }
+
+    #[test]
+    fn anti_denormal_keeps_state_normal() {
+        const LEN: usize = 30_000;
+        let impulse = |i: usize| if i == 0 { 1.0 } else { 0.0 };
+
+        // Without prevention, the decaying state goes through the denormal range
+        let mut resonator = Resonator([0.0; 2]);
+        let denormal = (0..LEN).any(|i| {
+            resonator.process([impulse(i)]);
+            resonator.0.iter().any(|s| s.is_subnormal())
+        });
+        assert!(denormal, "Test resonator did not reach denormals");
+
+        let mut resonator = DenormalPrevented::new(Resonator([0.0; 2]));
+        let mut tail = 0f32;
+        for i in 0..LEN {
+            let [y] = resonator.process([impulse(i)]);
+            let state = resonator.inner.0;
+            assert!(
+                state.iter().all(|s| !s.is_subnormal()),
+                "Denormal at sample {i}"
+            );
+            if i > LEN / 2 {
+                tail = tail.max(y.abs());
+            }
+        }
+        // The offset stays far below audibility once the impulse has decayed
+        assert!(tail < 1e-10, "Tail amplitude of {tail}");
+    }
+}
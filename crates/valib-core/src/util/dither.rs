@@ -0,0 +1,220 @@
+//! # Dither
+//!
+//! Provides dithered quantization of signals to a lower bit depth, for rendering to fixed-point
+//! formats. The random number generator is seedable, so that the output is reproducible.
+use crate::dsp::{DSPMeta, DSPProcess};
+use crate::util::rng::Rng;
+use crate::Scalar;
+
+/// Probability density function of the dither noise.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum DitherMode {
+    /// No dither: the signal is only rounded, which keeps the quantization distortion. Useful for
+    /// lo-fi effects, or when the signal is already noisy enough.
+    None,
+    /// Rectangular PDF: uniform noise spanning one quantization step. Removes the distortion
+    /// of the quantization, but leaves the noise power dependent on the signal.
+    Rpdf,
+    /// Triangular PDF: sum of two uniform noises, spanning two quantization steps. Makes the
+    /// quantization noise independent of the signal, at the cost of a higher noise floor.
+    #[default]
+    Tpdf,
+}
+
+/// Dithered quantizer.
+///
+/// Adds dither noise to the input before rounding it to the given bit depth, where the full scale
+/// is `[-1, 1]`. The output is not clipped. With noise shaping enabled, the quantization error is
+/// fed back with a first-order highpass response, moving the noise towards high frequencies.
+///
+/// Each lane of SIMD scalars is dithered with its own random values.
+#[derive(Debug, Copy, Clone)]
+pub struct Dither<T> {
+    mode: DitherMode,
+    noise_shaping: bool,
+    bits: u32,
+    step: T,
+    seed: u64,
+    rng: Rng,
+    error: T,
+}
+
+impl<T: Scalar> Dither<T> {
+    /// Create a new dithered quantizer, without noise shaping.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed`: Seed of the random number generator. Quantizers with the same seed output the
+    ///     same sequence of values.
+    /// * `bits`: Bit depth to quantize to, clamped between 1 and 63 bits
+    /// * `mode`: Distribution of the dither noise
+    ///
+    /// returns: Dither<T>
+    pub fn new(seed: u64, bits: u32, mode: DitherMode) -> Self {
+        Self {
+            mode,
+            noise_shaping: false,
+            bits: Self::clamp_bits(bits),
+            step: Self::step_size(bits),
+            seed,
+            rng: Rng::new(seed),
+            error: T::zero(),
+        }
+    }
+
+    /// Enables or disables first-order noise shaping, in a builder fashion.
+    pub fn with_noise_shaping(mut self, noise_shaping: bool) -> Self {
+        self.set_noise_shaping(noise_shaping);
+        self
+    }
+
+    /// Enables or disables first-order noise shaping.
+    pub fn set_noise_shaping(&mut self, noise_shaping: bool) {
+        self.noise_shaping = noise_shaping;
+        self.error = T::zero();
+    }
+
+    /// Sets the bit depth to quantize to, between 1 and 63 bits.
+    pub fn set_bits(&mut self, bits: u32) {
+        self.bits = Self::clamp_bits(bits);
+        self.step = Self::step_size(bits);
+    }
+
+    /// Bit depth the signal is quantized to
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Sets the distribution of the dither noise.
+    pub fn set_mode(&mut self, mode: DitherMode) {
+        self.mode = mode;
+    }
+
+    /// Size of one quantization step.
+    pub fn step(&self) -> T {
+        self.step
+    }
+
+    fn clamp_bits(bits: u32) -> u32 {
+        bits.clamp(1, 63)
+    }
+
+    fn step_size(bits: u32) -> T {
+        T::from_f64(2.0 / (1u64 << Self::clamp_bits(bits)) as f64)
+    }
+
+    /// Returns the next dither value, in units of quantization steps.
+    fn next_dither(&mut self) -> T {
+        if self.mode == DitherMode::None {
+            return T::zero();
+        }
+        let mut value = T::zero();
+        for lane in 0..T::LANES {
+            let x = match self.mode {
+                DitherMode::None => 0.0,
+                DitherMode::Rpdf => self.rng.next_unit() - 0.5,
+                DitherMode::Tpdf => self.rng.next_unit() + self.rng.next_unit() - 1.0,
+            };
+            value.replace(lane, T::from_f64(x).extract(0));
+        }
+        value
+    }
+}
+
+impl<T: Scalar> DSPMeta for Dither<T> {
+    type Sample = T;
+
+    fn reset(&mut self) {
+        self.rng = Rng::new(self.seed);
+        self.error = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Dither<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let x = if self.noise_shaping {
+            x - self.error
+        } else {
+            x
+        };
+        let y = (x / self.step + self.next_dither()).simd_round() * self.step;
+        if self.noise_shaping {
+            self.error = y - x;
+        }
+        [y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tpdf_is_triangular() {
+        const N: usize = 200_000;
+        const BINS: usize = 20;
+        let mut dither = Dither::<f64>::new(42, 16, DitherMode::Tpdf);
+        let mut histogram = [0usize; BINS];
+        for _ in 0..N {
+            let d = dither.next_dither();
+            assert!((-1.0..1.0).contains(&d));
+            histogram[((d + 1.0) * BINS as f64 / 2.0) as usize] += 1;
+        }
+
+        for (i, count) in histogram.into_iter().enumerate() {
+            let center = (i as f64 + 0.5) * 2.0 / BINS as f64 - 1.0;
+            let expected = (1.0 - center.abs()) * 2.0 / BINS as f64;
+            let actual = count as f64 / N as f64;
+            assert!(
+                (actual - expected).abs() < 5e-3,
+                "Bin {i}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn dither_is_unbiased() {
+        const N: usize = 100_000;
+        for mode in [DitherMode::Rpdf, DitherMode::Tpdf] {
+            let mut dither = Dither::<f64>::new(1234, 8, mode);
+            // Sits between two quantization steps, which plain rounding would bias to 0
+            let x = 0.3 * dither.step();
+            let mean = (0..N).map(|_| dither.process([x])[0]).sum::<f64>() / N as f64;
+            assert!(
+                (mean - x).abs() < 0.01 * dither.step(),
+                "{mode:?}: expected mean {x}, got {mean}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_dither_rounds() {
+        let mut dither = Dither::<f64>::new(0, 8, DitherMode::None);
+        let step = dither.step();
+        assert_eq!([0.0], dither.process([0.3 * step]));
+        assert_eq!([step], dither.process([0.7 * step]));
+        assert_eq!([-2.0 * step], dither.process([-2.2 * step]));
+    }
+
+    #[test]
+    fn bits_are_clamped() {
+        let mut dither = Dither::<f64>::new(0, 0, DitherMode::None);
+        assert_eq!(1, dither.bits());
+        assert_eq!(1.0, dither.step());
+
+        dither.set_bits(100);
+        assert_eq!(63, dither.bits());
+        dither.set_bits(0);
+        assert_eq!(1, dither.bits());
+    }
+
+    #[test]
+    fn dither_is_reproducible() {
+        let mut a = Dither::<f32>::new(7, 12, DitherMode::Tpdf).with_noise_shaping(true);
+        let first = (0..64).map(|_| a.process([0.1])[0]).collect::<Vec<_>>();
+        a.reset();
+        let second = (0..64).map(|_| a.process([0.1])[0]).collect::<Vec<_>>();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,106 @@
+//! # Goertzel algorithm
+//!
+//! Single-bin DFT, useful for cheaply detecting the presence of a tone at a known frequency.
+use numeric_literals::replace_float_literals;
+
+use crate::Scalar;
+
+/// Single-frequency detector using the Goertzel algorithm.
+///
+/// Computing the magnitude at a single frequency is `O(N)` for a block of `N` samples, which makes
+/// it much cheaper than a full FFT when only a handful of frequencies are of interest.
+#[derive(Debug, Copy, Clone)]
+pub struct Goertzel<T> {
+    coeff: T,
+}
+
+impl<T: Scalar> Goertzel<T> {
+    /// Create a new Goertzel detector for the given target frequency.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq`: Target frequency (Hz)
+    /// * `samplerate`: Sample rate of the analyzed signal
+    ///
+    /// returns: Goertzel<T>
+    pub fn new(freq: T, samplerate: T) -> Self {
+        let mut this = Self { coeff: T::zero() };
+        this.set_target(freq, samplerate);
+        this
+    }
+
+    /// Sets the target frequency of the detector.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq`: Target frequency (Hz)
+    /// * `samplerate`: Sample rate of the analyzed signal
+    ///
+    /// returns: ()
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn set_target(&mut self, freq: T, samplerate: T) {
+        self.coeff = 2.0 * (T::simd_two_pi() * freq / samplerate).simd_cos();
+    }
+
+    /// Computes the amplitude of the target frequency over the given block.
+    ///
+    /// The result is normalized such that a sine wave at the target frequency with amplitude `A`
+    /// returns `A`, provided the block contains a whole number of periods of the sine wave.
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: Input block
+    ///
+    /// returns: T
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn process_block(&self, block: &[T]) -> T {
+        if block.is_empty() {
+            return 0.0;
+        }
+
+        let mut s1 = T::zero();
+        let mut s2 = T::zero();
+        for x in block.iter().copied() {
+            let s = x + self.coeff * s1 - s2;
+            s2 = s1;
+            s1 = s;
+        }
+        let power = s1 * s1 + s2 * s2 - self.coeff * s1 * s2;
+        2.0 * power.simd_max(0.0).simd_sqrt() / T::from_f64(block.len() as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    fn sine(freq: f32, amplitude: f32) -> Vec<f32> {
+        (0..480)
+            .map(|i| amplitude * f32::sin(std::f32::consts::TAU * freq * i as f32 / SAMPLERATE))
+            .collect()
+    }
+
+    #[test]
+    fn detects_target_tone() {
+        let goertzel = Goertzel::new(1000.0, SAMPLERATE);
+        let magnitude = goertzel.process_block(&sine(1000.0, 0.5));
+        assert!(
+            (magnitude - 0.5).abs() < 1e-3,
+            "Unexpected magnitude {magnitude}"
+        );
+    }
+
+    #[test]
+    fn rejects_off_target_tones() {
+        let goertzel = Goertzel::new(1000.0, SAMPLERATE);
+        for freq in [500.0, 1500.0, 2000.0, 5000.0] {
+            let magnitude = goertzel.process_block(&sine(freq, 1.0));
+            assert!(
+                magnitude < 1e-3,
+                "Off-target frequency {freq} Hz has magnitude {magnitude}"
+            );
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! # Metering
+//!
+//! Peak meters with ballistics, including true-peak detection for loudness-aware processing, and
+//! a processor wrapper publishing the levels of its inputs and outputs for UIs to display.
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+
+use num_traits::{Float, ToPrimitive};
+use portable_atomic::AtomicF32;
+use simba::simd::SimdValue;
+
+use crate::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use crate::dsp::{DSPMeta, DSPProcessBlock};
+use crate::Scalar;
+
+/// Number of taps per phase of the true-peak interpolation filter.
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Polyphase coefficients of the 4x interpolation filter used for true-peak detection, as given in
+/// ITU-R BS.1770-4, Annex 2.
+const TRUE_PEAK_COEFFS: [[f64; TRUE_PEAK_TAPS]; 4] = [
+    [
+        0.0017089843750,
+        0.0109863281250,
+        -0.0196533203125,
+        0.0332031250000,
+        -0.0594482421875,
+        0.1373291015625,
+        0.9721679687500,
+        -0.1022949218750,
+        0.0476074218750,
+        -0.0266113281250,
+        0.0148925781250,
+        -0.0083007812500,
+    ],
+    [
+        -0.0291748046875,
+        0.0292968750000,
+        -0.0517578125000,
+        0.0891113281250,
+        -0.1665039062500,
+        0.4650878906250,
+        0.7797851562500,
+        -0.2003173828125,
+        0.1015625000000,
+        -0.0582275390625,
+        0.0330810546875,
+        -0.0189208984375,
+    ],
+    [
+        -0.0189208984375,
+        0.0330810546875,
+        -0.0582275390625,
+        0.1015625000000,
+        -0.2003173828125,
+        0.7797851562500,
+        0.4650878906250,
+        -0.1665039062500,
+        0.0891113281250,
+        -0.0517578125000,
+        0.0292968750000,
+        -0.0291748046875,
+    ],
+    [
+        -0.0083007812500,
+        0.0148925781250,
+        -0.0266113281250,
+        0.0476074218750,
+        -0.1022949218750,
+        0.9721679687500,
+        0.1373291015625,
+        -0.0594482421875,
+        0.0332031250000,
+        -0.0196533203125,
+        0.0109863281250,
+        0.0017089843750,
+    ],
+];
+
+/// 4x polyphase interpolator reconstructing the inter-sample values of the signal.
+#[derive(Debug, Copy, Clone)]
+struct TruePeakInterpolator<T> {
+    history: [T; TRUE_PEAK_TAPS],
+    pos: usize,
+}
+
+impl<T: Scalar> TruePeakInterpolator<T> {
+    fn new() -> Self {
+        Self {
+            history: [T::zero(); TRUE_PEAK_TAPS],
+            pos: 0,
+        }
+    }
+
+    /// Push a new sample in the interpolator, and return the maximum absolute value of the 4
+    /// reconstructed samples.
+    fn push(&mut self, x: T) -> T {
+        self.history[self.pos] = x;
+        self.pos = (self.pos + 1) % TRUE_PEAK_TAPS;
+
+        let mut max = T::zero();
+        for phase in &TRUE_PEAK_COEFFS {
+            let mut y = T::zero();
+            // Iterate over the history from the oldest to the newest sample
+            for (i, c) in phase.iter().copied().enumerate() {
+                let h = self.history[(self.pos + i) % TRUE_PEAK_TAPS];
+                y += h * T::from_f64(c);
+            }
+            max = max.simd_max(y.simd_abs());
+        }
+        max
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(T::zero());
+        self.pos = 0;
+    }
+}
+
+/// Peak meter with instantaneous attack and exponential release.
+///
+/// The meter can optionally detect true peaks, that is peaks occurring in-between samples, by
+/// upsampling the signal 4x internally with the interpolation filter specified in ITU-R BS.1770.
+#[derive(Debug, Copy, Clone)]
+pub struct PeakMeter<T> {
+    peak: T,
+    release: T,
+    interpolator: Option<TruePeakInterpolator<T>>,
+}
+
+impl<T: Scalar> PeakMeter<T> {
+    /// Create a new sample-peak meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `release`: Release time constant (s)
+    /// * `samplerate`: Sample rate of the metered signal
+    ///
+    /// returns: PeakMeter<T>
+    pub fn new(release: f32, samplerate: f32) -> Self {
+        let mut this = Self {
+            peak: T::zero(),
+            release: T::zero(),
+            interpolator: None,
+        };
+        this.set_release(release, samplerate);
+        this
+    }
+
+    /// Create a new true-peak meter. The signal is upsampled 4x internally to detect inter-sample
+    /// peaks.
+    ///
+    /// Note that this introduces a latency of 6 samples to the reported peak values.
+    ///
+    /// # Arguments
+    ///
+    /// * `release`: Release time constant (s)
+    /// * `samplerate`: Sample rate of the metered signal
+    ///
+    /// returns: PeakMeter<T>
+    pub fn true_peak(release: f32, samplerate: f32) -> Self {
+        Self {
+            interpolator: Some(TruePeakInterpolator::new()),
+            ..Self::new(release, samplerate)
+        }
+    }
+
+    /// Returns true if this meter detects true peaks.
+    pub fn is_true_peak(&self) -> bool {
+        self.interpolator.is_some()
+    }
+
+    /// Sets the release time of the meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds`: Release time constant (s)
+    /// * `samplerate`: Sample rate of the metered signal
+    ///
+    /// returns: ()
+    pub fn set_release(&mut self, seconds: f32, samplerate: f32) {
+        let n = (seconds * samplerate) as f64;
+        self.release = if n > 0.0 {
+            T::from_f64(Float::exp(-n.recip()))
+        } else {
+            T::zero()
+        };
+    }
+
+    /// Push a new sample into the meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input sample
+    ///
+    /// returns: ()
+    pub fn push(&mut self, x: T) {
+        let level = match &mut self.interpolator {
+            Some(interpolator) => interpolator.push(x),
+            None => x.simd_abs(),
+        };
+        self.peak = level.simd_max(self.peak * self.release);
+    }
+
+    /// Push all the samples in the slice into the meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: Input samples
+    ///
+    /// returns: ()
+    pub fn push_block(&mut self, block: &[T]) {
+        for x in block.iter().copied() {
+            self.push(x);
+        }
+    }
+
+    /// Current peak value, in linear units.
+    pub fn peak(&self) -> T {
+        self.peak
+    }
+
+    /// Reset the meter.
+    pub fn reset(&mut self) {
+        self.peak = T::zero();
+        if let Some(interpolator) = &mut self.interpolator {
+            interpolator.reset();
+        }
+    }
+}
+
+/// Peak meter writing its value into an atomic, allowing it to be read from another thread (ie.
+/// from a UI thread).
+#[derive(Debug, Clone)]
+pub struct AtomicPeakMeter {
+    meter: PeakMeter<f32>,
+    shared: Arc<AtomicF32>,
+}
+
+impl AtomicPeakMeter {
+    /// Create a new atomic peak meter from the provided meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `meter`: Inner peak meter
+    ///
+    /// returns: AtomicPeakMeter
+    pub fn new(meter: PeakMeter<f32>) -> Self {
+        Self {
+            shared: Arc::new(AtomicF32::new(meter.peak())),
+            meter,
+        }
+    }
+
+    /// Returns a handle to the shared peak value, which can be sent to another thread.
+    pub fn shared(&self) -> Arc<AtomicF32> {
+        self.shared.clone()
+    }
+
+    /// Sets the release time of the meter.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds`: Release time constant (s)
+    /// * `samplerate`: Sample rate of the metered signal
+    ///
+    /// returns: ()
+    pub fn set_release(&mut self, seconds: f32, samplerate: f32) {
+        self.meter.set_release(seconds, samplerate);
+    }
+
+    /// Push a new sample into the meter, and publish the new peak value.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input sample
+    ///
+    /// returns: ()
+    pub fn push(&mut self, x: f32) {
+        self.meter.push(x);
+        self.shared.store(self.meter.peak(), Ordering::Relaxed);
+    }
+
+    /// Push all the samples in the slice into the meter, and publish the peak value at the end of
+    /// the block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block`: Input samples
+    ///
+    /// returns: ()
+    pub fn push_block(&mut self, block: &[f32]) {
+        self.meter.push_block(block);
+        self.shared.store(self.meter.peak(), Ordering::Relaxed);
+    }
+
+    /// Current peak value, in linear units.
+    pub fn peak(&self) -> f32 {
+        self.meter.peak()
+    }
+
+    /// Reset the meter.
+    pub fn reset(&mut self) {
+        self.meter.reset();
+        self.shared.store(0.0, Ordering::Relaxed);
+    }
+}
+
+/// Shared handle to the levels of a signal, measured over each processed block by [`MeterTap`].
+///
+/// Handles are cheap to clone and can be sent to another thread (ie. a UI thread) to read the
+/// levels from, without locking.
+#[derive(Debug, Clone, Default)]
+pub struct LevelHandle {
+    peak: Arc<AtomicF32>,
+    rms: Arc<AtomicF32>,
+}
+
+impl LevelHandle {
+    /// Peak level of the last processed block, in linear units.
+    pub fn peak(&self) -> f32 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// RMS level of the last processed block, in linear units.
+    pub fn rms(&self) -> f32 {
+        self.rms.load(Ordering::Relaxed)
+    }
+
+    /// Measure the levels of the block, combining all channels and SIMD lanes, and publish them.
+    fn publish<T: Scalar<Element: Float>, const N: usize>(&self, block: AudioBufferRef<T, N>) {
+        let mut peak = 0f64;
+        let mut sum = 0f64;
+        for x in (0..N).flat_map(|ch| block.get_channel(ch).iter()) {
+            for lane in 0..T::LANES {
+                let x = x.extract(lane).to_f64().unwrap_or(0.0);
+                peak = peak.max(x.abs());
+                sum += x * x;
+            }
+        }
+        let count = block.samples() * N * T::LANES;
+        let rms = if count > 0 {
+            (sum / count as f64).sqrt()
+        } else {
+            0.0
+        };
+        self.peak.store(peak as f32, Ordering::Relaxed);
+        self.rms.store(rms as f32, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.peak.store(0.0, Ordering::Relaxed);
+        self.rms.store(0.0, Ordering::Relaxed);
+    }
+}
+
+/// Processor wrapper measuring the peak and RMS levels of the inputs and outputs of the inner
+/// processor over each block, and publishing them through [`LevelHandle`]s.
+///
+/// Levels combine all channels and all SIMD lanes of the signal. Measuring neither allocates nor
+/// locks, and is therefore safe to do on the audio thread.
+#[derive(Debug, Clone)]
+pub struct MeterTap<P> {
+    /// Inner processor
+    pub inner: P,
+    input: LevelHandle,
+    output: LevelHandle,
+}
+
+impl<P> MeterTap<P> {
+    /// Wrap the processor, metering its inputs and outputs.
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            input: LevelHandle::default(),
+            output: LevelHandle::default(),
+        }
+    }
+
+    /// Returns a handle to the levels of the inputs.
+    pub fn input_level(&self) -> LevelHandle {
+        self.input.clone()
+    }
+
+    /// Returns a handle to the levels of the outputs.
+    pub fn output_level(&self) -> LevelHandle {
+        self.output.clone()
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for MeterTap<P> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.inner.try_set_samplerate(samplerate)
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.input.clear();
+        self.output.clear();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcessBlock<I, O>, const I: usize, const O: usize> DSPProcessBlock<I, O> for MeterTap<P>
+where
+    <P::Sample as SimdValue>::Element: Float,
+{
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, I>,
+        mut outputs: AudioBufferMut<Self::Sample, O>,
+    ) {
+        self.input.publish(inputs);
+        self.inner.process_block(inputs, outputs.as_mut());
+        self.output.publish(outputs.as_ref());
+    }
+
+    fn max_block_size(&self) -> Option<usize> {
+        self.inner.max_block_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peak_of(mut meter: PeakMeter<f32>, input: &[f32]) -> f32 {
+        let mut max = 0.0;
+        for x in input.iter().copied() {
+            meter.push(x);
+            max = f32::max(max, meter.peak());
+        }
+        max
+    }
+
+    #[test]
+    fn true_peak_exceeds_sample_peak() {
+        // Sine at fs/4 sampled at 45° phase offset: every sample sits at ±sqrt(2)/2, while the
+        // underlying signal reaches ±1 between samples.
+        let input = (0..256)
+            .map(|i| f32::sin(std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4))
+            .collect::<Vec<_>>();
+        let sample_peak = peak_of(PeakMeter::new(1.0, 48e3), &input);
+        let true_peak = peak_of(PeakMeter::true_peak(1.0, 48e3), &input);
+
+        assert!((sample_peak - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+        assert!(
+            true_peak > sample_peak + 0.2,
+            "True peak {true_peak} does not exceed sample peak {sample_peak}"
+        );
+    }
+
+    #[test]
+    fn atomic_meter_publishes_peak() {
+        let mut meter = AtomicPeakMeter::new(PeakMeter::new(0.1, 48e3));
+        let shared = meter.shared();
+        meter.push_block(&[0.1, 0.25, -0.5]);
+        assert_eq!(0.5, shared.load(Ordering::Relaxed));
+        meter.reset();
+        assert_eq!(0.0, shared.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn meter_tap_reports_levels() {
+        use crate::dsp::buffer::AudioBuffer;
+        use crate::dsp::{BlockAdapter, DSPProcess};
+        use crate::simd::AutoF32x2;
+
+        struct Gain(f32);
+
+        impl DSPMeta for Gain {
+            type Sample = AutoF32x2;
+        }
+
+        impl DSPProcess<1, 1> for Gain {
+            fn process(&mut self, [x]: [AutoF32x2; 1]) -> [AutoF32x2; 1] {
+                [x * AutoF32x2::splat(self.0)]
+            }
+        }
+
+        let mut tap = MeterTap::new(BlockAdapter(Gain(0.5)));
+        let (input_level, output_level) = (tap.input_level(), tap.output_level());
+        // Full-scale sine over whole periods in the left channel, silence in the right one
+        let input = (0..64)
+            .map(|i| {
+                let x = f32::sin(core::f32::consts::TAU * i as f32 / 16.0 + 0.1);
+                AutoF32x2::new(x, 0.0)
+            })
+            .collect::<Vec<_>>();
+        let mut output = [AutoF32x2::splat(0.0); 64];
+        tap.process_block(
+            AudioBuffer::new([&input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+        );
+
+        let peak = f32::sin(core::f32::consts::TAU * 4.0 / 16.0 + 0.1);
+        assert!((input_level.peak() - peak).abs() < 1e-6);
+        assert!((output_level.peak() - 0.5 * peak).abs() < 1e-6);
+        // Sine RMS is 1/sqrt(2), halved again by the silent channel's share
+        assert!((input_level.rms() - 0.5).abs() < 1e-6);
+        assert!((output_level.rms() - 0.25).abs() < 1e-6);
+
+        tap.reset();
+        assert_eq!(0.0, input_level.peak());
+        assert_eq!(0.0, output_level.rms());
+    }
+}
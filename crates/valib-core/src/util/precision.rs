@@ -0,0 +1,140 @@
+//! # Higher-precision processing
+//!
+//! Provides running processors at `f64` precision from an `f32` signal path. Some algorithms, like
+//! high-resonance or very low frequency feedback filters, are numerically too sensitive for `f32`
+//! coefficients and state, while the rest of the processing chain is fine with it.
+use crate::dsp::{DSPMeta, DSPProcess};
+use crate::{Scalar, SimdCast};
+
+/// Processor wrapper running an `f64` inner processor from an `f32` signal path.
+///
+/// Inputs are cast to the `f64` counterpart of the sample type before being processed by the
+/// inner processor, and its outputs cast back to `f32`. This works for SIMD sample types as well,
+/// where the lane count is preserved.
+#[derive(Debug, Copy, Clone)]
+pub struct HighPrecision<P> {
+    /// Inner processor, running at `f64` precision
+    pub inner: P,
+}
+
+impl<P> HighPrecision<P> {
+    /// Wrap the `f64` processor to be used with the corresponding `f32` sample type.
+    pub fn new(inner: P) -> Self {
+        Self { inner }
+    }
+
+    /// Unwrap this adaptor and give back the inner DSP instance.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: DSPMeta> DSPMeta for HighPrecision<P>
+where
+    P::Sample: SimdCast<f32>,
+    <P::Sample as SimdCast<f32>>::Output: Scalar + SimdCast<f64, Output = P::Sample>,
+{
+    type Sample = <P::Sample as SimdCast<f32>>::Output;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        self.inner.try_set_samplerate(samplerate)
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<I, O>, const I: usize, const O: usize> DSPProcess<I, O> for HighPrecision<P>
+where
+    P::Sample: SimdCast<f32>,
+    <P::Sample as SimdCast<f32>>::Output: Scalar + SimdCast<f64, Output = P::Sample>,
+{
+    fn process(&mut self, x: [Self::Sample; I]) -> [Self::Sample; O] {
+        self.inner
+            .process(x.map(SimdCast::cast))
+            .map(SimdCast::cast)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::{AutoF32x2, AutoF64x2};
+    use simba::simd::SimdValue;
+
+    /// Two-pole resonator in direct form
+    #[derive(Debug, Copy, Clone)]
+    struct Resonator<T> {
+        a: [T; 2],
+        s: [T; 2],
+    }
+
+    impl<T: Scalar> Resonator<T> {
+        /// Resonator at the given frequency and Q, with coefficients rounded to the sample type
+        fn new(samplerate: f64, freq: f64, q: f64) -> Self {
+            let w = core::f64::consts::TAU * freq / samplerate;
+            let r = 1.0 - w / (2.0 * q);
+            Self {
+                a: [T::from_f64(2.0 * r * w.cos()), T::from_f64(r * r)],
+                s: [T::zero(); 2],
+            }
+        }
+    }
+
+    impl<T: Scalar> DSPMeta for Resonator<T> {
+        type Sample = T;
+
+        fn reset(&mut self) {
+            self.s = [T::zero(); 2];
+        }
+    }
+
+    impl<T: Scalar> DSPProcess<1, 1> for Resonator<T> {
+        fn process(&mut self, [x]: [T; 1]) -> [T; 1] {
+            let y = x + self.a[0] * self.s[0] - self.a[1] * self.s[1];
+            self.s = [y, self.s[0]];
+            [y]
+        }
+    }
+
+    /// Peak amplitude of the last second of the response to a short impulse
+    fn tail<P: DSPProcess<1, 1, Sample = f32>>(mut dsp: P) -> f32 {
+        let impulse = |i: usize| if i == 0 { 1e-3 } else { 0.0 };
+        (0..480_000)
+            .map(|i| dsp.process([impulse(i)])[0])
+            .skip(432_000)
+            .fold(0.0, |max, y| y.abs().max(max))
+    }
+
+    #[test]
+    fn high_precision_keeps_resonant_filter_stable() {
+        const SAMPLERATE: f64 = 48e3;
+        // Rounding the coefficients to f32 moves a pole of this resonator outside the unit circle:
+        // instead of decaying, its output keeps growing past the peak of the original response
+        let single = tail(Resonator::<f32>::new(SAMPLERATE, 1.0, 10.0));
+        assert!(single > 20.0, "f32 resonator did not blow up ({single})");
+
+        let double = tail(HighPrecision::new(Resonator::<f64>::new(
+            SAMPLERATE, 1.0, 10.0,
+        )));
+        assert!(double < 0.5, "Tail of {double}");
+    }
+
+    #[test]
+    fn high_precision_preserves_lanes() {
+        let mut dsp = HighPrecision::new(Resonator::<AutoF64x2>::new(48e3, 1e3, 0.7));
+        let [y] = dsp.process([AutoF32x2::from([1.0, 0.5])]);
+        assert_eq!(1.0, y.extract(0));
+        assert_eq!(0.5, y.extract(1));
+    }
+}
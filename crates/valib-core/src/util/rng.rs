@@ -0,0 +1,83 @@
+//! # Random number generation
+//!
+//! Provides a small seedable pseudo-random number generator, for noise sources, dithering and
+//! anything else needing reproducible randomness on the audio thread.
+use crate::Scalar;
+
+/// Small and fast pseudo-random number generator (xorshift64*), seeded through SplitMix64.
+///
+/// Generators created with the same seed output the same sequence of values.
+#[derive(Debug, Copy, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a new random number generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed`: Seed of the generator. Any value is valid, including 0.
+    ///
+    /// returns: Rng
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 scrambling of the seed, which also guarantees a non-zero state
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        Self {
+            state: (z ^ (z >> 31)) | 1,
+        }
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Returns a uniformly distributed value in `[0, 1)`.
+    pub fn next_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a uniformly distributed value in `[-1, 1)`.
+    pub fn next_bipolar(&mut self) -> f64 {
+        2.0 * self.next_unit() - 1.0
+    }
+
+    /// Returns a uniformly distributed index in `0..len`. `len` must not be 0.
+    pub fn next_index(&mut self, len: usize) -> usize {
+        ((self.next_unit() * len as f64) as usize).min(len - 1)
+    }
+
+    /// Returns a scalar where each lane contains a new uniformly distributed value in `[-1, 1)`.
+    pub fn next_scalar<T: Scalar>(&mut self) -> T {
+        let mut value = T::zero();
+        for lane in 0..T::LANES {
+            value.replace(lane, T::from_f64(self.next_bipolar()).extract(0));
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible_and_in_range() {
+        let mut a = Rng::new(0);
+        let mut b = Rng::new(0);
+        for _ in 0..10_000 {
+            let x = a.next_bipolar();
+            assert_eq!(x, b.next_bipolar());
+            assert!((-1.0..1.0).contains(&x));
+            assert!(a.next_index(3) < 3);
+            b.next_index(3);
+        }
+        assert_ne!(Rng::new(0).next_u64(), Rng::new(1).next_u64());
+    }
+}
@@ -0,0 +1,192 @@
+//! # Musical scales
+//!
+//! Provides scale definitions in cents, and quantization of frequencies to the nearest scale
+//! degree. Scales repeat every octave, and can be microtonal.
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use num_traits::{Euclid, Float};
+use numeric_literals::replace_float_literals;
+
+use crate::Scalar;
+
+/// Cents in one octave
+const OCTAVE_CENTS: f64 = 1200.0;
+
+/// Musical scale, defined as a set of cents offsets within an octave, relative to a root
+/// frequency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    root_hz: f64,
+    cents: Box<[f64]>,
+}
+
+impl Scale {
+    /// Create a new scale from its degrees, given in cents relative to the root frequency.
+    ///
+    /// Degrees are wrapped into a single octave, sorted, and deduplicated.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_hz`: Root frequency of the scale (Hz)
+    /// * `cents`: Scale degrees, in cents from the root
+    ///
+    /// returns: Scale
+    ///
+    /// # Panics
+    ///
+    /// Panics if no degrees are given.
+    pub fn new(root_hz: f64, cents: impl IntoIterator<Item = f64>) -> Self {
+        let mut cents = cents
+            .into_iter()
+            .map(|c| Euclid::rem_euclid(&c, &OCTAVE_CENTS))
+            .collect::<Vec<_>>();
+        assert!(!cents.is_empty(), "Scale needs at least one degree");
+        cents.sort_by(f64::total_cmp);
+        cents.dedup();
+        Self {
+            root_hz,
+            cents: cents.into_boxed_slice(),
+        }
+    }
+
+    /// Create a new scale from its degrees, given as frequency ratios relative to the root
+    /// frequency.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_hz`: Root frequency of the scale (Hz)
+    /// * `ratios`: Scale degrees, as ratios of the root frequency
+    ///
+    /// returns: Scale
+    pub fn from_ratios(root_hz: f64, ratios: impl IntoIterator<Item = f64>) -> Self {
+        Self::new(
+            root_hz,
+            ratios.into_iter().map(|r| OCTAVE_CENTS * Float::log2(r)),
+        )
+    }
+
+    /// Create an equal temperament scale, dividing the octave into `divisions` equal steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_hz`: Root frequency of the scale (Hz)
+    /// * `divisions`: Number of steps per octave
+    ///
+    /// returns: Scale
+    pub fn equal_temperament(root_hz: f64, divisions: usize) -> Self {
+        let step = OCTAVE_CENTS / divisions as f64;
+        Self::new(root_hz, (0..divisions).map(|i| i as f64 * step))
+    }
+
+    /// Create the standard 12-TET chromatic scale, with A4 set at the given frequency.
+    ///
+    /// # Arguments
+    ///
+    /// * `a4_hz`: Frequency of A4 (Hz)
+    ///
+    /// returns: Scale
+    pub fn twelve_tet(a4_hz: f64) -> Self {
+        Self::equal_temperament(a4_hz, 12)
+    }
+
+    /// Create a 12-tone, 5-limit just intonation chromatic scale.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_hz`: Root frequency of the scale (Hz)
+    ///
+    /// returns: Scale
+    pub fn just_intonation(root_hz: f64) -> Self {
+        Self::from_ratios(
+            root_hz,
+            [
+                1.0,
+                16.0 / 15.0,
+                9.0 / 8.0,
+                6.0 / 5.0,
+                5.0 / 4.0,
+                4.0 / 3.0,
+                45.0 / 32.0,
+                3.0 / 2.0,
+                8.0 / 5.0,
+                5.0 / 3.0,
+                9.0 / 5.0,
+                15.0 / 8.0,
+            ],
+        )
+    }
+
+    /// Root frequency of the scale (Hz)
+    pub fn root_hz(&self) -> f64 {
+        self.root_hz
+    }
+
+    /// Scale degrees, in cents from the root, sorted in ascending order within a single octave.
+    pub fn cents(&self) -> &[f64] {
+        &self.cents
+    }
+
+    /// Quantize a frequency to the nearest degree of this scale, in any octave. Distances between
+    /// degrees are measured in cents, and each lane of a SIMD frequency is quantized
+    /// independently.
+    ///
+    /// # Arguments
+    ///
+    /// * `freq_hz`: Frequency to quantize (Hz)
+    ///
+    /// returns: T
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn quantize<T: Scalar>(&self, freq_hz: T) -> T {
+        let cents = 1200.0 * (freq_hz / T::from_f64(self.root_hz)).simd_log2();
+        let octave = (cents / 1200.0).simd_floor();
+        let rel = cents - 1200.0 * octave;
+
+        let mut best = T::from_f64(self.cents[0]);
+        let mut best_dist = (rel - best).simd_abs();
+        // Also consider the first degree of the next octave
+        let candidates = self.cents[1..]
+            .iter()
+            .copied()
+            .chain([self.cents[0] + OCTAVE_CENTS]);
+        for degree in candidates {
+            let degree = T::from_f64(degree);
+            let dist = (rel - degree).simd_abs();
+            let closer = dist.simd_lt(best_dist);
+            best = degree.select(closer, best);
+            best_dist = dist.select(closer, best_dist);
+        }
+
+        T::from_f64(self.root_hz) * 2.0.simd_powf(octave + best / 1200.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::AutoF32x4;
+    use crate::util::midi_to_freq;
+
+    #[test]
+    fn quantize_detuned_to_twelve_tet() {
+        let scale = Scale::twelve_tet(440.0);
+        // A4 + 30 cents, C5 - 40 cents
+        let a4 = 440.0 * 2f32.powf(30.0 / 1200.0);
+        let c5 = midi_to_freq::<f32>(72) * 2f32.powf(-40.0 / 1200.0);
+        assert!((scale.quantize(a4) - 440.0).abs() < 1e-3);
+        assert!((scale.quantize(c5) - midi_to_freq::<f32>(72)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn quantize_simd_lanes() {
+        let scale = Scale::just_intonation(100.0);
+        let input = AutoF32x4::new(148.0, 99.0, 412.0, 51.0);
+        let actual = scale.quantize(input);
+        for (actual, expected) in actual.0.into_iter().zip([150.0, 100.0, 400.0, 50.0]) {
+            assert!(
+                (actual - expected).abs() < 1e-3,
+                "Expected {expected}, got {actual}"
+            );
+        }
+    }
+}
@@ -1,9 +1,15 @@
 //! Test utilities. Needs the `test-utils` feature to enable this module.
 use std::{ops::Range, path::Path};
 
+use nalgebra::Complex;
 use plotters::coord::{self, ranged1d::ValueFormatter};
 use plotters::{chart::SeriesAnno, prelude::*};
 
+use crate::dsp::buffer::AudioBufferBox;
+use crate::dsp::DSPProcessBlock;
+use crate::math::fft::Fft;
+use crate::math::window::blackman_harris;
+
 fn assert_ok(res: Result<(), impl std::fmt::Display>) {
     match res {
         Ok(()) => {}
@@ -188,3 +194,83 @@ impl<'a> Plot<'a> {
         self.render_into(&root);
     }
 }
+
+/// Length of the analysis window of [`measure_aliasing`]
+const ALIASING_FFT_SIZE: usize = 1 << 14;
+/// Number of bins on each side of a harmonic attributed to it, covering the main lobe of the
+/// analysis window
+const ALIASING_HARMONIC_BINS: usize = 6;
+
+/// Measure the aliasing produced by a processor.
+///
+/// A sine tone is processed, and the spectrum of the output is computed over a window taken after
+/// the processor has settled. Energy at the fundamental and at the harmonics below Nyquist is the
+/// expected output of a nonlinear processor; everything else (excluding DC) is counted as
+/// aliasing, that is, the harmonics above Nyquist folded back into the audible band.
+///
+/// # Arguments
+///
+/// * `dsp`: Processor to measure, which gets reset beforehand
+/// * `test_freq`: Frequency of the test tone (Hz)
+/// * `samplerate`: Sample rate the processor runs at
+///
+/// returns: f64 Ratio of the alias energy to the energy of the fundamental
+pub fn measure_aliasing<P: DSPProcessBlock<1, 1, Sample = f32>>(
+    dsp: &mut P,
+    test_freq: f32,
+    samplerate: f32,
+) -> f64 {
+    dsp.set_samplerate(samplerate);
+    dsp.reset();
+    let settle = ALIASING_FFT_SIZE / 4 + dsp.latency();
+    let len = settle + ALIASING_FFT_SIZE;
+    let w = std::f64::consts::TAU * test_freq as f64 / samplerate as f64;
+    let input = AudioBufferBox::new([(0..len)
+        .map(|i| f64::sin(w * i as f64) as f32)
+        .collect::<Box<[_]>>()])
+    .unwrap();
+    let mut output = AudioBufferBox::zeroed(len);
+    let block_size = dsp.max_block_size().unwrap_or(len);
+    for start in (0..len).step_by(block_size) {
+        let end = (start + block_size).min(len);
+        dsp.process_block(input.slice(start..end), output.slice_mut(start..end));
+    }
+
+    // Periodic window, from a symmetric window one sample longer
+    let mut window = vec![0.0; ALIASING_FFT_SIZE + 1];
+    blackman_harris(&mut window);
+    let mut spectrum = output.get_channel(0)[settle..]
+        .iter()
+        .zip(&window)
+        .map(|(&y, &w)| Complex::new(y as f64 * w, 0.0))
+        .collect::<Vec<_>>();
+    Fft::new(ALIASING_FFT_SIZE).forward(&mut spectrum);
+    let power = spectrum[..=ALIASING_FFT_SIZE / 2]
+        .iter()
+        .map(|c| c.norm_sqr())
+        .collect::<Vec<_>>();
+
+    let bin_of = |freq: f64| (freq * ALIASING_FFT_SIZE as f64 / samplerate as f64).round() as usize;
+    let lobe = |bin: usize| {
+        bin.saturating_sub(ALIASING_HARMONIC_BINS)
+            ..(bin + ALIASING_HARMONIC_BINS + 1).min(power.len())
+    };
+    let mut is_expected = vec![false; power.len()];
+    // DC and harmonics below Nyquist
+    for harmonic in (0..).map(|k| k as f64 * test_freq as f64) {
+        if harmonic >= samplerate as f64 / 2.0 {
+            break;
+        }
+        for bin in lobe(bin_of(harmonic)) {
+            is_expected[bin] = true;
+        }
+    }
+
+    let fundamental = power[lobe(bin_of(test_freq as f64))].iter().sum::<f64>();
+    let aliases = power
+        .iter()
+        .zip(is_expected)
+        .filter_map(|(p, expected)| (!expected).then_some(p))
+        .sum::<f64>();
+    aliases / fundamental
+}
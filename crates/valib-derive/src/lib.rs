@@ -1,8 +1,8 @@
-use darling::{ast, FromDeriveInput, FromVariant};
+use darling::{ast, FromDeriveInput, FromMeta, FromVariant};
 use proc_macro::TokenStream;
 use quote::{quote, ToTokens};
 
-#[proc_macro_derive(ParamName, attributes(name))]
+#[proc_macro_derive(ParamName, attributes(param_name))]
 pub fn derive_param_name(item: TokenStream) -> TokenStream {
     match DeriveParamName::from_derive_input(&syn::parse_macro_input!(item)) {
         Ok(d) => d.into_token_stream().into(),
@@ -10,20 +10,70 @@ pub fn derive_param_name(item: TokenStream) -> TokenStream {
     }
 }
 
+/// Numeric attribute value, accepting integer and negative literals on top of what darling accepts
+/// for floats.
+#[derive(Debug, Copy, Clone)]
+struct Number(f32);
+
+impl FromMeta for Number {
+    fn from_expr(expr: &syn::Expr) -> darling::Result<Self> {
+        match expr {
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => Self::from_expr(expr).map(|Self(x)| Self(-x)),
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => int.base10_parse().map(Self).map_err(darling::Error::from),
+            syn::Expr::Group(group) => Self::from_expr(&group.expr),
+            _ => f32::from_expr(expr).map(Self),
+        }
+        .map_err(|e| e.with_span(expr))
+    }
+}
+
 #[derive(Debug, FromVariant)]
 #[darling(supports(unit), attributes(param_name))]
 struct Variant {
     ident: syn::Ident,
     #[darling(rename = "display")]
     name: Option<String>,
+    unit: Option<String>,
+    min: Option<Number>,
+    max: Option<Number>,
 }
 
 impl Variant {
+    fn has_metadata(&self) -> bool {
+        self.unit.is_some() || self.min.is_some() || self.max.is_some()
+    }
+
     fn impl_match_name(&self) -> proc_macro2::TokenStream {
-        let Self { ident, name } = self;
+        let Self { ident, name, .. } = self;
         let name = name.clone().unwrap_or(ident.to_string());
         quote! {
-            Self::#ident => std::borrow::Cow::Borrowed(#name)
+            Self::#ident => alloc::borrow::Cow::Borrowed(#name)
+        }
+    }
+
+    fn impl_match_range(&self) -> proc_macro2::TokenStream {
+        let Self {
+            ident, min, max, ..
+        } = self;
+        let min = min.map_or(0.0, |Number(x)| x);
+        let max = max.map_or(1.0, |Number(x)| x);
+        quote! {
+            Self::#ident => (#min, #max)
+        }
+    }
+
+    fn impl_match_unit(&self) -> proc_macro2::TokenStream {
+        let Self { ident, unit, .. } = self;
+        let unit = unit.as_deref().unwrap_or_default();
+        quote! {
+            Self::#ident => #unit
         }
     }
 
@@ -64,13 +114,36 @@ impl quote::ToTokens for DeriveParamName {
         let variants = fields
             .iter()
             .map(|Variant { ident, .. }| quote! { Self::#ident });
+        // Parameter metadata is only generated when given, leaving the defaults of the trait
+        // otherwise
+        let impl_metadata = fields.iter().any(|f| f.has_metadata()).then(|| {
+            let impl_range = fields.iter().map(|f| f.impl_match_range());
+            let impl_unit = fields.iter().map(|f| f.impl_match_unit());
+            quote! {
+                fn range(&self) -> (f32, f32) {
+                    match self {
+                        #(#impl_range),*
+                    }
+                }
+
+                fn unit(&self) -> &'static str {
+                    match self {
+                        #(#impl_unit),*
+                    }
+                }
+            }
+        });
+        // `alloc` is declared locally, so the generated code works in both `std` and `no_std` crates
         stream.extend(quote! {
+            const _: () = {
+            extern crate alloc;
+
             impl ParamName for #ident {
                 fn count() -> usize {
                     #count
                 }
 
-                fn name(&self) -> std::borrow::Cow<'static, str> {
+                fn name(&self) -> alloc::borrow::Cow<'static, str> {
                     match self {
                         #(#impl_name),*
                     }
@@ -92,7 +165,10 @@ impl quote::ToTokens for DeriveParamName {
                 fn iter() -> impl Iterator<Item=Self> {
                     [#(#variants),*].into_iter()
                 }
+
+                #impl_metadata
             }
+            };
         });
     }
 }
@@ -120,4 +196,23 @@ mod tests {
         let output = from_derive_input.into_token_stream().to_string();
         insta::assert_snapshot!(prettyplease::unparse(&syn::parse_file(&output).unwrap()));
     }
+
+    #[test]
+    fn test_metadata_derive() {
+        let input = syn::parse_str(
+            /* rust */
+            r#"enum DspParams {
+                #[param_name(unit = "Hz", min = 20, max = 20e3)]
+                Cutoff,
+                Resonance,
+                #[param_name(display = "Output Gain", unit = "dB", min = -24.0, max = 24.0)]
+                Gain,
+            }"#,
+        )
+        .expect("Parsing valid code");
+        let from_derive_input =
+            DeriveParamName::from_derive_input(&input).expect("Parsing valid code");
+        let output = from_derive_input.into_token_stream().to_string();
+        insta::assert_snapshot!(prettyplease::unparse(&syn::parse_file(&output).unwrap()));
+    }
 }
@@ -0,0 +1,152 @@
+//! # Compressor
+//!
+//! Provides a feed-forward downward compressor, optionally keyed from a sidechain input.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::follower::EnvelopeFollower;
+
+/// Feed-forward downward compressor.
+///
+/// The level of the key signal is tracked with an [`EnvelopeFollower`] using the attack and
+/// release times of the compressor. Levels above the threshold are reduced according to the ratio,
+/// such that a level `ratio` times louder than the threshold comes out only twice as loud (in dB,
+/// the excess level is divided by the ratio).
+///
+/// The compressor is keyed from its input with the [`DSPProcess<1, 1>`] implementation, or from a
+/// sidechain signal given as the second input with the [`DSPProcess<2, 1>`] implementation.
+#[derive(Debug, Copy, Clone)]
+pub struct Compressor<T> {
+    threshold: T,
+    ratio: T,
+    detector: EnvelopeFollower<T>,
+    gain: T,
+}
+
+impl<T: Scalar> Compressor<T> {
+    /// Create a new compressor.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the compressor
+    /// * `threshold`: Level above which the signal is compressed (linear)
+    /// * `ratio`: Compression ratio, 1 meaning no compression
+    /// * `attack`: Attack time constant (s)
+    /// * `release`: Release time constant (s)
+    ///
+    /// returns: Compressor<T>
+    pub fn new(samplerate: f32, threshold: T, ratio: T, attack: f32, release: f32) -> Self {
+        Self {
+            threshold,
+            ratio,
+            detector: EnvelopeFollower::new(samplerate, attack, release),
+            gain: T::one(),
+        }
+    }
+
+    /// Sets the level above which the signal is compressed (linear).
+    pub fn set_threshold(&mut self, threshold: T) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the compression ratio. Values below 1 are clamped to 1.
+    pub fn set_ratio(&mut self, ratio: T) {
+        self.ratio = ratio.simd_max(T::one());
+    }
+
+    /// Sets the attack time constant (s).
+    pub fn set_attack(&mut self, attack: f32) {
+        self.detector.set_attack(attack);
+    }
+
+    /// Sets the release time constant (s).
+    pub fn set_release(&mut self, release: f32) {
+        self.detector.set_release(release);
+    }
+
+    /// Current gain reduction applied by the compressor (linear gain, 1 meaning no reduction).
+    pub fn gain_reduction(&self) -> T {
+        self.gain
+    }
+
+    fn process_keyed(&mut self, x: T, key: T) -> T {
+        let [level] = self.detector.process([key]);
+        let over = (level / self.threshold).simd_max(T::one());
+        self.gain = over.simd_powf(self.ratio.simd_recip() - T::one());
+        x * self.gain
+    }
+}
+
+impl<T: Scalar> DSPMeta for Compressor<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.detector.set_samplerate(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.gain = T::one();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Compressor<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        [self.process_keyed(x, x)]
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for Compressor<T> {
+    fn process(&mut self, [x, sidechain]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        [self.process_keyed(x, sidechain)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 1000.0;
+
+    #[test]
+    fn compressor_reduces_by_ratio() {
+        let mut comp = Compressor::new(SAMPLERATE, 0.25, 4.0, 0.0, 0.0);
+        // 4 times (12 dB) above threshold, compressed down to 3 dB above threshold
+        let [y] = comp.process([1.0]);
+        assert!((y - 0.25 * 4f32.powf(0.25)).abs() < 1e-6, "Got {y}");
+        // Below threshold, left untouched
+        let [y] = comp.process([0.1]);
+        assert_eq!(0.1, y);
+    }
+
+    #[test]
+    fn compressor_sidechain_tracks_key() {
+        let mut comp = Compressor::new(SAMPLERATE, 0.1, 10.0, 0.001, 0.01);
+        // Constant main signal, keyed from bursts of an independent signal
+        let key = |i: usize| if (i / 100) % 2 == 1 { 0.8 } else { 0.0 };
+        let gains = (0..400)
+            .map(|i| {
+                comp.process([0.5, key(i)]);
+                comp.gain_reduction()
+            })
+            .collect::<Vec<_>>();
+
+        for (i, gain) in gains.into_iter().enumerate() {
+            // Leave time for the detector to settle
+            if i % 100 < 50 {
+                continue;
+            }
+            if key(i) > 0.0 {
+                assert!(gain < 0.5, "No gain reduction at {i} while the key is loud");
+            } else {
+                // The main signal is above threshold, but is not the key
+                assert!(
+                    gain > 0.99,
+                    "Gain reduction of {gain} at {i} while the key is quiet"
+                );
+            }
+        }
+    }
+}
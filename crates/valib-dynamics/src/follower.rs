@@ -0,0 +1,93 @@
+//! # Envelope follower
+//!
+//! Provides a peak envelope follower with separate attack and release times, used as the level
+//! detector of dynamics processors.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+/// Compute the one-pole smoothing coefficient for the given time constant.
+pub(crate) fn time_coeff<T: Scalar>(seconds: f32, samplerate: f32) -> T {
+    let n = (seconds * samplerate) as f64;
+    if n > 0.0 {
+        T::from_f64((-n.recip()).exp())
+    } else {
+        T::zero()
+    }
+}
+
+/// Peak envelope follower, tracking the absolute value of its input with separate attack and
+/// release times.
+#[derive(Debug, Copy, Clone)]
+pub struct EnvelopeFollower<T> {
+    attack: f32,
+    release: f32,
+    samplerate: f32,
+    attack_coeff: T,
+    release_coeff: T,
+    envelope: T,
+}
+
+impl<T: Scalar> EnvelopeFollower<T> {
+    /// Create a new envelope follower.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the follower
+    /// * `attack`: Attack time constant (s)
+    /// * `release`: Release time constant (s)
+    ///
+    /// returns: EnvelopeFollower<T>
+    pub fn new(samplerate: f32, attack: f32, release: f32) -> Self {
+        Self {
+            attack,
+            release,
+            samplerate,
+            attack_coeff: time_coeff(attack, samplerate),
+            release_coeff: time_coeff(release, samplerate),
+            envelope: T::zero(),
+        }
+    }
+
+    /// Sets the attack time constant (s).
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack;
+        self.attack_coeff = time_coeff(attack, self.samplerate);
+    }
+
+    /// Sets the release time constant (s).
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release;
+        self.release_coeff = time_coeff(release, self.samplerate);
+    }
+
+    /// Current value of the envelope.
+    pub fn envelope(&self) -> T {
+        self.envelope
+    }
+}
+
+impl<T: Scalar> DSPMeta for EnvelopeFollower<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.attack_coeff = time_coeff(self.attack, samplerate);
+        self.release_coeff = time_coeff(self.release, samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.envelope = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for EnvelopeFollower<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let level = x.simd_abs();
+        let coeff = self
+            .attack_coeff
+            .select(level.simd_gt(self.envelope), self.release_coeff);
+        self.envelope = level + (self.envelope - level) * coeff;
+        [self.envelope]
+    }
+}
@@ -0,0 +1,208 @@
+//! # Noise gate
+//!
+//! Provides a noise gate / downward expander, optionally keyed from a sidechain input.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::follower::{time_coeff, EnvelopeFollower};
+
+/// Attack time constant of the level detector (s)
+const DETECTOR_ATTACK: f32 = 1e-4;
+/// Release time constant of the level detector (s)
+const DETECTOR_RELEASE: f32 = 0.01;
+
+/// Noise gate.
+///
+/// The level of the key signal is tracked with an [`EnvelopeFollower`]; the gate opens when the
+/// level goes above the threshold, and closes once it has stayed below the threshold for the
+/// duration of the hold time. The gain applied to the signal moves smoothly between the open and
+/// closed states with the attack and release times.
+///
+/// When the range is non-zero, the gate acts as a downward expander: signals below the threshold
+/// are attenuated by the range gain instead of being muted.
+///
+/// The gate is keyed from its input with the [`DSPProcess<1, 1>`] implementation, or from a
+/// sidechain signal given as the second input with the [`DSPProcess<2, 1>`] implementation.
+#[derive(Debug, Copy, Clone)]
+pub struct Gate<T> {
+    threshold: T,
+    range: T,
+    attack: f32,
+    hold: f32,
+    release: f32,
+    samplerate: f32,
+    attack_coeff: T,
+    release_coeff: T,
+    hold_samples: T,
+    hold_counter: T,
+    detector: EnvelopeFollower<T>,
+    gain: T,
+}
+
+impl<T: Scalar> Gate<T> {
+    /// Create a new noise gate.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the gate
+    /// * `threshold`: Opening threshold (linear)
+    /// * `attack`: Attack time constant (s)
+    /// * `hold`: Hold time (s)
+    /// * `release`: Release time constant (s)
+    ///
+    /// returns: Gate<T>
+    pub fn new(samplerate: f32, threshold: T, attack: f32, hold: f32, release: f32) -> Self {
+        let mut this = Self {
+            threshold,
+            range: T::zero(),
+            attack,
+            hold,
+            release,
+            samplerate,
+            attack_coeff: T::zero(),
+            release_coeff: T::zero(),
+            hold_samples: T::zero(),
+            hold_counter: T::zero(),
+            detector: EnvelopeFollower::new(samplerate, DETECTOR_ATTACK, DETECTOR_RELEASE),
+            gain: T::zero(),
+        };
+        this.set_samplerate(samplerate);
+        this.gain = this.range;
+        this
+    }
+
+    /// Sets the opening threshold (linear).
+    pub fn set_threshold(&mut self, threshold: T) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the gain applied when the gate is closed (linear). 0 mutes the signal, while greater
+    /// values turn the gate into a downward expander.
+    pub fn set_range(&mut self, range: T) {
+        self.range = range;
+    }
+
+    /// Sets the attack time constant (s).
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack;
+        self.attack_coeff = time_coeff(attack, self.samplerate);
+    }
+
+    /// Sets the hold time (s).
+    pub fn set_hold(&mut self, hold: f32) {
+        self.hold = hold;
+        self.hold_samples = T::from_f32(hold * self.samplerate);
+    }
+
+    /// Sets the release time constant (s).
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release;
+        self.release_coeff = time_coeff(release, self.samplerate);
+    }
+
+    /// Current gain applied by the gate (linear).
+    pub fn gain(&self) -> T {
+        self.gain
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process_keyed(&mut self, x: T, key: T) -> T {
+        let [level] = self.detector.process([key]);
+        let above = level.simd_gt(self.threshold);
+        self.hold_counter = self
+            .hold_samples
+            .select(above, (self.hold_counter - 1.0).simd_max(0.0));
+        let open = above | self.hold_counter.simd_gt(0.0);
+
+        let target = T::one().select(open, self.range);
+        let coeff = self.attack_coeff.select(open, self.release_coeff);
+        self.gain = target + (self.gain - target) * coeff;
+        x * self.gain
+    }
+}
+
+impl<T: Scalar> DSPMeta for Gate<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.detector.set_samplerate(samplerate);
+        self.set_attack(self.attack);
+        self.set_hold(self.hold);
+        self.set_release(self.release);
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.hold_counter = T::zero();
+        self.gain = self.range;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Gate<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        [self.process_keyed(x, x)]
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for Gate<T> {
+    fn process(&mut self, [x, sidechain]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        [self.process_keyed(x, sidechain)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 1000.0;
+
+    #[test]
+    fn gate_opens_and_closes() {
+        const HOLD: f32 = 0.05;
+        let mut gate = Gate::new(SAMPLERATE, 0.1, 0.001, HOLD, 0.005);
+        // Quiet, loud, then quiet again
+        let amplitude = |i: usize| if (200..400).contains(&i) { 0.5 } else { 0.01 };
+        let gains = (0..800)
+            .map(|i| {
+                gate.process([amplitude(i)]);
+                gate.gain()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(gains[..200].iter().all(|&g| g < 1e-3), "Gate opened early");
+        assert!(
+            gains[210..400].iter().all(|&g| g > 0.99),
+            "Gate did not open"
+        );
+        // Detector release and hold keep the gate open after the signal goes below threshold
+        assert!(
+            gains[400..440].iter().all(|&g| g > 0.99),
+            "Gate closed early"
+        );
+        assert!(gains[500..].iter().all(|&g| g < 1e-3), "Gate did not close");
+    }
+
+    #[test]
+    fn gate_sidechain_keys_gain() {
+        let mut gate = Gate::new(SAMPLERATE, 0.1, 0.0, 0.0, 0.0);
+        let [y] = gate.process([0.01, 0.5]);
+        assert_eq!(0.01, y);
+        for _ in 0..100 {
+            gate.process([0.01, 0.0]);
+        }
+        let [y] = gate.process([0.01, 0.0]);
+        assert_eq!(0.0, y);
+    }
+
+    #[test]
+    fn expander_attenuates_by_range() {
+        let mut gate = Gate::new(SAMPLERATE, 0.1, 0.0, 0.0, 0.0);
+        gate.set_range(0.25);
+        let [y]: [f32; 1] = gate.process([0.01]);
+        assert!((y - 0.0025).abs() < 1e-6, "Got {y}");
+    }
+}
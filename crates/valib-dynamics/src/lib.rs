@@ -0,0 +1,10 @@
+#![warn(missing_docs)]
+//! # Dynamics processors
+//!
+//! This crate provides dynamics processors (compressors, limiters, gates, ...) for `valib`.
+
+pub mod compressor;
+pub mod follower;
+pub mod gate;
+pub mod limiter;
+pub mod transient;
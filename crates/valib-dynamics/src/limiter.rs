@@ -0,0 +1,303 @@
+//! # Lookahead limiter
+//!
+//! Provides a brickwall limiter which delays its input to apply gain reduction ahead of the peaks,
+//! guaranteeing that the output never exceeds the threshold. The limiter can optionally be keyed
+//! from a sidechain input.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::blocks::DelayLine;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::simd::SimdBool;
+use valib_core::Scalar;
+
+use crate::follower::EnvelopeFollower;
+
+/// Monotonic queue of positions in the lookahead window, for a single SIMD lane, whose values are
+/// increasing from front to back. The front is the position of the minimum over the window.
+#[derive(Debug, Clone)]
+struct MinQueue {
+    positions: Box<[usize]>,
+    head: usize,
+    len: usize,
+}
+
+impl MinQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            positions: vec![0; capacity].into_boxed_slice(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn front(&self) -> usize {
+        self.positions[self.head]
+    }
+
+    fn back(&self) -> usize {
+        self.positions[(self.head + self.len - 1) % self.positions.len()]
+    }
+
+    /// Push the value at `pos`, which is about to overwrite the oldest value of the window. Values
+    /// are given with the lane of this queue splatted across all lanes.
+    fn push<T: Scalar>(&mut self, pos: usize, value_at: impl Fn(usize) -> T) {
+        if self.len > 0 && self.front() == pos {
+            self.head = (self.head + 1) % self.positions.len();
+            self.len -= 1;
+        }
+        let value = value_at(pos);
+        while self.len > 0 && value_at(self.back()).simd_ge(value).all() {
+            self.len -= 1;
+        }
+        self.positions[(self.head + self.len) % self.positions.len()] = pos;
+        self.len += 1;
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
+
+/// Lookahead brickwall limiter.
+///
+/// The gain reduction required by each input sample is held for the duration of the lookahead,
+/// released exponentially by an [`EnvelopeFollower`], and smoothed with a moving average over the
+/// lookahead window, which gives a smooth gain reduction curve that is fully applied by the time
+/// the peak reaches the output. Both the hold and the moving average run in constant time per
+/// sample.
+///
+/// The limiter is keyed from its input with the [`DSPProcess<1, 1>`] implementation, or from a
+/// sidechain signal given as the second input with the [`DSPProcess<2, 1>`] implementation. When
+/// keyed from a sidechain, the output is only guaranteed to stay below the threshold as long as
+/// the key is at least as loud as the input.
+#[derive(Debug, Clone)]
+pub struct Limiter<T> {
+    threshold: T,
+    lookahead: f32,
+    samplerate: f32,
+    /// Follows the gain reduction (1 - gain), with an instant attack
+    release_env: EnvelopeFollower<T>,
+    delay: DelayLine<T>,
+    required: Box<[T]>,
+    held: Box<[MinQueue]>,
+    released: Box<[T]>,
+    released_sum: T,
+    pos: usize,
+}
+
+impl<T: Scalar> Limiter<T> {
+    /// Create a new lookahead limiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the limiter
+    /// * `lookahead`: Lookahead time (s)
+    /// * `threshold`: Maximum output amplitude (linear)
+    /// * `release`: Release time constant (s)
+    ///
+    /// returns: Limiter<T>
+    pub fn new(samplerate: f32, lookahead: f32, threshold: T, release: f32) -> Self {
+        let mut this = Self {
+            threshold,
+            lookahead,
+            samplerate,
+            release_env: EnvelopeFollower::new(samplerate, 0.0, release),
+            delay: DelayLine::new(0),
+            required: Box::new([]),
+            held: Box::new([]),
+            released: Box::new([]),
+            released_sum: T::zero(),
+            pos: 0,
+        };
+        this.set_samplerate(samplerate);
+        this
+    }
+
+    /// Maximum output amplitude (linear).
+    pub fn threshold(&self) -> T {
+        self.threshold
+    }
+
+    /// Sets the maximum output amplitude (linear).
+    pub fn set_threshold(&mut self, threshold: T) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the release time constant (s).
+    pub fn set_release(&mut self, release: f32) {
+        self.release_env.set_release(release);
+    }
+
+    /// Sets the lookahead time (s). This changes the latency of the limiter, and reallocates its
+    /// internal buffers, and therefore should not be called from a real-time context.
+    pub fn set_lookahead(&mut self, lookahead: f32) {
+        self.lookahead = lookahead;
+        self.allocate();
+    }
+
+    /// Current gain reduction applied by the limiter (linear gain, 1 meaning no reduction).
+    pub fn gain_reduction(&self) -> T {
+        self.average_gain()
+    }
+
+    fn allocate(&mut self) {
+        let window = (self.lookahead * self.samplerate).round().max(0.0) as usize + 1;
+        self.delay = DelayLine::new(window - 1);
+        self.required = vec![T::one(); window].into_boxed_slice();
+        self.held = (0..T::LANES).map(|_| MinQueue::new(window)).collect();
+        self.released = vec![T::one(); window].into_boxed_slice();
+        self.reset();
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process_keyed(&mut self, x: T, key: T) -> T {
+        let len = self.required.len();
+        let [delayed] = self.delay.process([x]);
+        let peak = key.simd_abs().simd_max(1e-30);
+        self.required[self.pos] = (self.threshold / peak).simd_min(1.0);
+
+        // Hold the required gain reduction over the lookahead window
+        let mut held = T::one();
+        for (lane, queue) in self.held.iter_mut().enumerate() {
+            let required = &self.required;
+            queue.push(self.pos, |pos| T::splat(required[pos].extract(lane)));
+            held.replace(lane, required[queue.front()].extract(lane));
+        }
+        let [reduction] = self.release_env.process([1.0 - held]);
+        let released = 1.0 - reduction;
+        self.released_sum += released - self.released[self.pos];
+        self.released[self.pos] = released;
+
+        let gain = self.average_gain();
+        self.pos = (self.pos + 1) % len;
+        if self.pos == 0 {
+            // Recompute the sum once per window to avoid accumulating rounding errors
+            self.released_sum = self.released.iter().copied().fold(0.0, |acc, g| acc + g);
+        }
+        delayed * gain
+    }
+
+    fn average_gain(&self) -> T {
+        self.released_sum / T::from_f64(self.released.len() as _)
+    }
+}
+
+impl<T: Scalar> DSPMeta for Limiter<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.release_env.set_samplerate(samplerate);
+        self.allocate();
+    }
+
+    fn latency(&self) -> usize {
+        self.delay.latency()
+    }
+
+    fn reset(&mut self) {
+        self.delay.reset();
+        self.required.fill(T::one());
+        for queue in &mut self.held {
+            queue.clear();
+        }
+        self.released.fill(T::one());
+        self.released_sum = T::from_f64(self.released.len() as _);
+        self.release_env.reset();
+        self.pos = 0;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Limiter<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let y = self.process_keyed(x, x);
+        // Guard against rounding errors in the gain computation
+        [y.simd_clamp(-self.threshold, self.threshold)]
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for Limiter<T> {
+    fn process(&mut self, [x, sidechain]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        [self.process_keyed(x, sidechain)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    #[test]
+    fn limiter_output_below_threshold() {
+        const THRESHOLD: f32 = 0.5;
+        let mut limiter = Limiter::new(SAMPLERATE, 0.005, THRESHOLD, 0.05);
+        let input = (0..4800)
+            .map(|i| {
+                let amplitude = if (1000..3000).contains(&i) { 2.0 } else { 0.25 };
+                amplitude * f32::sin(std::f32::consts::TAU * 440.0 * i as f32 / SAMPLERATE)
+            })
+            .collect::<Vec<_>>();
+        let output = input
+            .iter()
+            .map(|&x| limiter.process([x])[0])
+            .collect::<Vec<_>>();
+
+        let peak = output.iter().copied().map(f32::abs).fold(0.0, f32::max);
+        assert!(peak <= THRESHOLD, "Output peak {peak} exceeds threshold");
+
+        // Gain reduction is applied smoothly rather than by clipping: the limited section should
+        // still be a scaled sine wave, reaching close to the threshold
+        assert!(peak > 0.9 * THRESHOLD, "Output peak {peak} too low");
+    }
+
+    #[test]
+    fn limiter_latency_matches_lookahead() {
+        let mut limiter = Limiter::new(SAMPLERATE, 0.001, 1.0, 0.05);
+        assert_eq!(48, limiter.latency());
+
+        let output = (0..100)
+            .map(|i| limiter.process([if i == 0 { 0.5 } else { 0.0 }])[0])
+            .collect::<Vec<_>>();
+        let delay = output.iter().position(|&x| x != 0.0).unwrap();
+        assert_eq!(limiter.latency(), delay);
+        assert_eq!(0.5, output[delay]);
+    }
+
+    #[test]
+    fn limiter_sidechain_keys_gain() {
+        let mut limiter = Limiter::new(SAMPLERATE, 0.0, 0.5, 0.0);
+        // Quiet input, loud key
+        let [y]: [f32; 1] = limiter.process([0.1, 1.0]);
+        assert!((y - 0.05).abs() < 1e-6, "Got {y}");
+        // Loud input, quiet key
+        let [y] = limiter.process([1.0, 0.1]);
+        assert_eq!(1.0, y);
+    }
+
+    #[test]
+    fn limiter_simd_lanes_match_scalar() {
+        use valib_core::simd::{AutoF32x2, SimdValue};
+
+        let signal = |lane: usize, i: usize| {
+            let amplitude = [2.0, 0.8][lane] * if (i / 500) % 2 == 0 { 1.0 } else { 0.3 };
+            amplitude
+                * f32::sin(std::f32::consts::TAU * [440.0, 97.0][lane] * i as f32 / SAMPLERATE)
+        };
+        let mut simd = Limiter::new(SAMPLERATE, 0.002, AutoF32x2::splat(0.5), 0.02);
+        let mut scalar = [0, 1].map(|_| Limiter::new(SAMPLERATE, 0.002, 0.5, 0.02));
+        for i in 0..4800 {
+            let [y] = simd.process([AutoF32x2::new(signal(0, i), signal(1, i))]);
+            for (lane, limiter) in scalar.iter_mut().enumerate() {
+                let [expected] = limiter.process([signal(lane, i)]);
+                let actual = y.extract(lane);
+                assert!(
+                    (expected - actual).abs() < 1e-5,
+                    "Lane {lane} at sample {i}: expected {expected}, got {actual}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! # Transient shaper
+//!
+//! Provides a transient shaper, boosting or attenuating the attack and sustain portions of a
+//! signal independently of its level.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::follower::EnvelopeFollower;
+
+/// Attack time constant of the fast envelope follower (s)
+const FAST_ATTACK: f32 = 5e-4;
+/// Release time constant of the fast envelope follower (s)
+const FAST_RELEASE: f32 = 0.1;
+/// Attack time constant of the slow envelope follower (s)
+const SLOW_ATTACK: f32 = 0.02;
+
+/// Transient shaper.
+///
+/// A fast envelope follower tracks the input, and a slow envelope follower smooths rises of the
+/// fast envelope while following its decays immediately. At the onset of a transient, the fast
+/// follower reacts right away while the slow one lags behind. Their relative difference is used
+/// as the transient detection signal, going from 1 at the onset of a transient down to 0 once the
+/// slow follower caught up, during the sustain part of the sound. The applied
+/// gain is interpolated between the attack and sustain gains in the decibel domain according to
+/// this signal.
+///
+/// As the detection only depends on the relative difference between the envelopes, the shaper
+/// behaves the same regardless of the level of the input.
+#[derive(Debug, Copy, Clone)]
+pub struct TransientShaper<T> {
+    fast: EnvelopeFollower<T>,
+    slow: EnvelopeFollower<T>,
+    attack_gain_ln: T,
+    sustain_gain_ln: T,
+}
+
+impl<T: Scalar> TransientShaper<T> {
+    /// Create a new transient shaper, with neutral attack and sustain gains.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the transient shaper
+    ///
+    /// returns: TransientShaper<T>
+    pub fn new(samplerate: f32) -> Self {
+        Self {
+            fast: EnvelopeFollower::new(samplerate, FAST_ATTACK, FAST_RELEASE),
+            slow: EnvelopeFollower::new(samplerate, SLOW_ATTACK, 0.0),
+            attack_gain_ln: T::zero(),
+            sustain_gain_ln: T::zero(),
+        }
+    }
+
+    /// Sets the gain applied to transients (dB). Positive values emphasize them, negative values
+    /// soften them.
+    pub fn set_attack_gain(&mut self, db: T) {
+        self.attack_gain_ln = db_to_ln_gain(db);
+    }
+
+    /// Sets the gain applied to the sustain part of the signal (dB).
+    pub fn set_sustain_gain(&mut self, db: T) {
+        self.sustain_gain_ln = db_to_ln_gain(db);
+    }
+
+    /// Current transient detection signal, going from 0 (sustain) to 1 (transient onset).
+    pub fn transient(&self) -> T {
+        let fast = self.fast.envelope();
+        let slow = self.slow.envelope();
+        ((fast - slow) / fast.simd_max(T::from_f64(1e-12))).simd_clamp(T::zero(), T::one())
+    }
+}
+
+/// Converts a gain in decibels into the natural logarithm of the linear gain.
+fn db_to_ln_gain<T: Scalar>(db: T) -> T {
+    db * T::from_f64(core::f64::consts::LN_10 / 20.0)
+}
+
+impl<T: Scalar> DSPMeta for TransientShaper<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.fast.set_samplerate(samplerate);
+        self.slow.set_samplerate(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for TransientShaper<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let fast = self.fast.process([x]);
+        self.slow.process(fast);
+        let t = self.transient();
+        let gain_ln = self.sustain_gain_ln + (self.attack_gain_ln - self.sustain_gain_ln) * t;
+        [x * gain_ln.simd_exp()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    /// Decaying sine burst
+    fn input() -> Vec<f32> {
+        (0..SAMPLERATE as usize / 2)
+            .map(|i| {
+                let t = i as f32 / SAMPLERATE;
+                f32::sin(std::f32::consts::TAU * 1e3 * t) * f32::exp(-t / 0.1)
+            })
+            .collect()
+    }
+
+    fn burst(attack_db: f32, sustain_db: f32) -> Vec<f32> {
+        let mut shaper = TransientShaper::new(SAMPLERATE);
+        shaper.set_attack_gain(attack_db);
+        shaper.set_sustain_gain(sustain_db);
+        input()
+            .into_iter()
+            .map(|x| shaper.process([x])[0])
+            .collect()
+    }
+
+    fn peak(signal: &[f32]) -> f32 {
+        signal.iter().copied().map(f32::abs).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn neutral_is_transparent() {
+        assert_eq!(input(), burst(0.0, 0.0));
+    }
+
+    #[test]
+    fn attack_gain_boosts_initial_peak() {
+        let neutral = burst(0.0, 0.0);
+        let boosted = burst(6.0, 0.0);
+        let softened = burst(-6.0, 0.0);
+
+        // Onset (first 5 ms)
+        let onset = ..240;
+        assert!(
+            peak(&boosted[onset]) > 1.5 * peak(&neutral[onset]),
+            "Boosted onset peak: {}",
+            peak(&boosted[onset])
+        );
+        assert!(peak(&softened[onset]) < 0.75 * peak(&neutral[onset]));
+
+        // Sustain is left untouched
+        let sustain = 9600..;
+        let ratio = peak(&boosted[sustain.clone()]) / peak(&neutral[sustain]);
+        assert!((ratio - 1.0).abs() < 0.05, "Sustain ratio: {ratio}");
+    }
+
+    #[test]
+    fn sustain_gain_affects_tail() {
+        let neutral = burst(0.0, 0.0);
+        let reduced = burst(0.0, -6.0);
+        let sustain = 9600..;
+        let ratio = peak(&reduced[sustain.clone()]) / peak(&neutral[sustain]);
+        assert!((ratio - 0.5).abs() < 0.05, "Sustain ratio: {ratio}");
+    }
+}
@@ -0,0 +1,154 @@
+//! # Bitcrusher
+//!
+//! Provides bit depth and sample rate reduction, for lo-fi effects or emulating fixed-point
+//! converters.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::util::dither::{Dither, DitherMode};
+use valib_core::Scalar;
+
+/// Bit depth and sample rate reducer.
+///
+/// The input is held for a number of samples given by the sample rate reduction factor, without
+/// any filtering, and quantized to the given bit depth, where the full scale is `[-1, 1)`. The
+/// output takes exactly `2^bits` distinct levels, as a signed fixed-point signal would, and is
+/// clipped to those levels.
+///
+/// Quantization is done by a [`Dither`] quantizer, without dither noise by default. With noise
+/// shaping enabled, the quantization error is fed back with a first-order highpass response, which
+/// moves the quantization noise towards high frequencies.
+#[derive(Debug, Copy, Clone)]
+pub struct BitCrusher<T> {
+    /// Quantizer, which can also be set to dither the signal
+    pub quantizer: Dither<T>,
+    reduction: f32,
+    /// Samples left until the input is sampled again
+    countdown: f32,
+    held: T,
+}
+
+impl<T: Scalar> BitCrusher<T> {
+    /// Create a new bitcrusher, without sample rate reduction, dither or noise shaping.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits`: Bit depth to quantize to
+    ///
+    /// returns: BitCrusher<T>
+    pub fn new(bits: u32) -> Self {
+        Self {
+            quantizer: Dither::new(0, bits, DitherMode::None),
+            reduction: 1.0,
+            countdown: 0.0,
+            held: T::zero(),
+        }
+    }
+
+    /// Enables or disables first-order noise shaping, in a builder fashion.
+    pub fn with_noise_shaping(mut self, noise_shaping: bool) -> Self {
+        self.quantizer.set_noise_shaping(noise_shaping);
+        self
+    }
+
+    /// Sets the sample rate reduction factor, that is the number of samples each input sample is
+    /// held for. Fractional factors are supported, and alternate between shorter and longer holds.
+    pub fn set_sample_rate_reduction(&mut self, factor: f32) {
+        self.reduction = factor.max(1.0);
+        self.countdown = self.countdown.min(self.reduction);
+    }
+
+    fn quantize(&mut self, x: T) -> T {
+        let max = T::from_f64((1u64 << (self.quantizer.bits() - 1)) as f64);
+        let step = self.quantizer.step();
+        let [y] = self.quantizer.process([x]);
+        // Clipping after the quantizer keeps the clipping error out of the noise shaping loop
+        y.simd_clamp(-max * step, (max - T::one()) * step)
+    }
+}
+
+impl<T: Scalar> DSPMeta for BitCrusher<T> {
+    type Sample = T;
+
+    fn reset(&mut self) {
+        self.quantizer.reset();
+        self.countdown = 0.0;
+        self.held = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for BitCrusher<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        if self.countdown <= 0.0 {
+            self.held = self.quantize(x);
+            self.countdown += self.reduction;
+        }
+        self.countdown -= 1.0;
+        [self.held]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn bitcrusher_has_2_pow_bits_levels() {
+        for bits in [1, 3, 8] {
+            let mut crusher = BitCrusher::<f32>::new(bits);
+            // Sweep past the full scale to also hit the clipped levels
+            let levels = (-2000..=2000)
+                .map(|i| {
+                    let [y] = crusher.process([i as f32 / 1000.0]);
+                    (y / crusher.quantizer.step()) as i32
+                })
+                .collect::<BTreeSet<_>>();
+            assert_eq!(1 << bits, levels.len(), "{bits} bits");
+        }
+    }
+
+    #[test]
+    fn sample_rate_reduction_holds_input() {
+        let mut crusher = BitCrusher::<f32>::new(16);
+        crusher.set_sample_rate_reduction(4.0);
+        let output = (0..16)
+            .map(|i| crusher.process([i as f32 / 16.0])[0])
+            .collect::<Vec<_>>();
+        for (i, chunk) in output.chunks(4).enumerate() {
+            let expected = (4 * i) as f32 / 16.0;
+            assert!(chunk.iter().all(|&y| y == expected), "{chunk:?}");
+        }
+    }
+
+    #[test]
+    fn noise_shaping_moves_error_up() {
+        // Energy of the quantization error of a sine, at low and high frequencies
+        let error_energy = |noise_shaping: bool| {
+            let mut crusher = BitCrusher::<f64>::new(8).with_noise_shaping(noise_shaping);
+            let error = (0..48_000)
+                .map(|i| {
+                    let x = 0.7 * f64::sin(i as f64 * 0.0123);
+                    crusher.process([x])[0] - x
+                })
+                .collect::<Vec<_>>();
+            // Moving sums only keep the lowest frequencies, and differences emphasize the highest
+            let low = error
+                .windows(16)
+                .map(|w| w.iter().sum::<f64>().powi(2))
+                .sum::<f64>();
+            let high = error.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum::<f64>();
+            (low, high)
+        };
+
+        let (plain_low, plain_high) = error_energy(false);
+        let (shaped_low, shaped_high) = error_energy(true);
+        assert!(
+            shaped_low < 0.5 * plain_low,
+            "Low frequency error: {plain_low} -> {shaped_low}"
+        );
+        assert!(
+            shaped_high > plain_high,
+            "High frequency error: {plain_high} -> {shaped_high}"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! # Frequency shifter
+//!
+//! Provides a single-sideband frequency shifter, which moves every frequency of the input by the
+//! same amount. Unlike pitch shifting, this does not preserve harmonic relationships.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_filters::hilbert::Hilbert;
+use valib_oscillators::Phasor;
+
+/// Single-sideband frequency shifter.
+///
+/// The analytic signal of the input is computed with a [`Hilbert`] transformer, and multiplied
+/// with a complex oscillator at the shift frequency. The real part of the product is the input
+/// shifted by that frequency, without the mirrored sideband that ring modulation produces.
+#[derive(Debug, Copy, Clone)]
+pub struct FrequencyShifter<T> {
+    samplerate: T,
+    shift: T,
+    hilbert: Hilbert<T>,
+    phasor: Phasor<T>,
+}
+
+impl<T: Scalar> FrequencyShifter<T> {
+    /// Create a new frequency shifter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the frequency shifter
+    /// * `shift`: Frequency shift (Hz), which can be negative to shift frequencies down
+    ///
+    /// returns: FrequencyShifter<T>
+    pub fn new(samplerate: T, shift: T) -> Self {
+        Self {
+            samplerate,
+            shift,
+            hilbert: Hilbert::new(),
+            phasor: Phasor::new(samplerate, shift.simd_abs()),
+        }
+    }
+
+    /// Current frequency shift (Hz)
+    pub fn shift(&self) -> T {
+        self.shift
+    }
+
+    /// Sets the frequency shift (Hz), which can be negative to shift frequencies down. The phase of
+    /// the oscillator is kept, so that changing the shift does not produce clicks.
+    pub fn set_shift(&mut self, shift: T) {
+        self.shift = shift;
+        self.phasor.set_frequency(self.samplerate, shift.simd_abs());
+    }
+}
+
+impl<T: Scalar> DSPMeta for FrequencyShifter<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f32(samplerate);
+        self.phasor
+            .set_frequency(self.samplerate, self.shift.simd_abs());
+    }
+
+    fn reset(&mut self) {
+        self.hilbert.reset();
+        self.phasor.set_phase(T::zero());
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for FrequencyShifter<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [re, im] = self.hilbert.process([x]);
+        let [phase] = self.phasor.process([]);
+        let (sin, cos) = (T::simd_two_pi() * phase).simd_sin_cos();
+        // The oscillator runs at the absolute shift; a negative shift rotates the other way
+        let sin = sin.select(self.shift.simd_ge(T::zero()), -sin);
+        [re * cos - im * sin]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const SAMPLERATE: f64 = 48e3;
+
+    /// Frequency of the signal, estimated from its zero crossings
+    fn frequency(signal: &[f64]) -> f64 {
+        let crossings = signal
+            .windows(2)
+            .filter(|w| w[0].signum() != w[1].signum())
+            .count();
+        crossings as f64 / 2.0 * SAMPLERATE / signal.len() as f64
+    }
+
+    #[rstest]
+    #[case(1000.0, 100.0)]
+    #[case(1000.0, -250.0)]
+    #[case(440.0, 1000.0)]
+    fn frequency_shifter_moves_tone(#[case] freq: f64, #[case] shift: f64) {
+        let mut shifter = FrequencyShifter::new(SAMPLERATE, shift);
+        let output = (0..(1.1 * SAMPLERATE) as usize)
+            .map(|i| f64::sin(std::f64::consts::TAU * freq * i as f64 / SAMPLERATE))
+            .map(|x| shifter.process([x])[0])
+            .collect::<Vec<_>>();
+
+        // One second of output, after the filters have settled
+        let steady = &output[(0.1 * SAMPLERATE) as usize..];
+        let actual = frequency(steady);
+        assert!(
+            (actual - (freq + shift)).abs() <= 1.0,
+            "Expected {} Hz, got {actual} Hz",
+            freq + shift
+        );
+        // Single sideband: the amplitude stays constant
+        let peak = steady.iter().fold(0.0, |max: f64, y| max.max(y.abs()));
+        assert!((peak - 1.0).abs() < 0.01, "Peak amplitude of {peak}");
+    }
+}
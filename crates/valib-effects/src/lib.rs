@@ -0,0 +1,12 @@
+#![warn(missing_docs)]
+//! # Effects
+//!
+//! This crate provides audio effects (stereo processing, delays, modulation effects, ...) for
+//! `valib`.
+
+pub mod bitcrusher;
+pub mod frequency_shifter;
+pub mod modulation;
+pub mod multiband;
+pub mod phaser;
+pub mod stereo;
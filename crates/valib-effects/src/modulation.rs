@@ -0,0 +1,202 @@
+//! # Ring and amplitude modulation
+//!
+//! Provides ring modulation, which multiplies two signals together, and amplitude modulation,
+//! which varies the gain of a signal by a modulator shifted into a positive range.
+//!
+//! Both processors either take the modulator as their second input, or generate it from an
+//! internal sine carrier.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_oscillators::lfo::{Lfo, LfoShape};
+
+/// Ring modulator.
+///
+/// The output is the product of the input and the modulator, which replaces each frequency of
+/// the input with the sum and difference of it with the modulator frequencies.
+///
+/// As a `DSPProcess<2, 1>`, the modulator is the second input. As a `DSPProcess<1, 1>`, the
+/// modulator is the internal sine carrier.
+#[derive(Debug, Copy, Clone)]
+pub struct RingMod<T> {
+    /// Internal carrier oscillator, used when no modulator input is given
+    pub carrier: Lfo<T>,
+}
+
+impl<T: Scalar> RingMod<T> {
+    /// Create a new ring modulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the modulator
+    /// * `frequency`: Frequency of the internal sine carrier (Hz)
+    ///
+    /// returns: RingMod<T>
+    pub fn new(samplerate: f32, frequency: T) -> Self {
+        Self {
+            carrier: Lfo::new(T::from_f64(samplerate as _), frequency, LfoShape::Sine),
+        }
+    }
+
+    /// Sets the frequency of the internal carrier (Hz).
+    pub fn set_frequency(&mut self, frequency: T) {
+        self.carrier.set_frequency(frequency);
+    }
+}
+
+impl<T: Scalar> DSPMeta for RingMod<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.carrier.set_samplerate(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.carrier.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for RingMod<T> {
+    fn process(&mut self, [x, m]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        [x * m]
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for RingMod<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [m] = self.carrier.process([]);
+        [x * m]
+    }
+}
+
+/// Amplitude modulator.
+///
+/// The bipolar modulator is shifted into the 0..1 range, and scaled by the depth, before being
+/// multiplied with the input. Unlike ring modulation, the input frequencies are kept, with the
+/// sum and difference frequencies added as sidebands.
+///
+/// As a `DSPProcess<2, 1>`, the modulator is the second input. As a `DSPProcess<1, 1>`, the
+/// modulator is the internal sine carrier.
+#[derive(Debug, Copy, Clone)]
+pub struct AmpMod<T> {
+    /// Internal carrier oscillator, used when no modulator input is given
+    pub carrier: Lfo<T>,
+    depth: T,
+}
+
+impl<T: Scalar> AmpMod<T> {
+    /// Create a new amplitude modulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the modulator
+    /// * `frequency`: Frequency of the internal sine carrier (Hz)
+    /// * `depth`: Modulation depth, in the 0..1 range
+    ///
+    /// returns: AmpMod<T>
+    pub fn new(samplerate: f32, frequency: T, depth: T) -> Self {
+        let mut this = Self {
+            carrier: Lfo::new(T::from_f64(samplerate as _), frequency, LfoShape::Sine),
+            depth: T::zero(),
+        };
+        this.set_depth(depth);
+        this
+    }
+
+    /// Sets the frequency of the internal carrier (Hz).
+    pub fn set_frequency(&mut self, frequency: T) {
+        self.carrier.set_frequency(frequency);
+    }
+
+    /// Sets the modulation depth, in the 0..1 range. At full depth, the gain goes all the way down
+    /// to zero at the troughs of the modulator.
+    pub fn set_depth(&mut self, depth: T) {
+        self.depth = depth.simd_clamp(T::zero(), T::one());
+    }
+
+    /// Gain applied to the input for the given bipolar modulator value
+    fn gain(&self, m: T) -> T {
+        let half_depth = self.depth * T::from_f64(0.5);
+        T::one() - half_depth + half_depth * m
+    }
+}
+
+impl<T: Scalar> DSPMeta for AmpMod<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.carrier.set_samplerate(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.carrier.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for AmpMod<T> {
+    fn process(&mut self, [x, m]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        [x * self.gain(m)]
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for AmpMod<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [m] = self.carrier.process([]);
+        [x * self.gain(m)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::util::goertzel::Goertzel;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    /// Amplitudes of the output at the given frequencies, modulating a 1 kHz sine by a 300 Hz one
+    fn spectrum(mut process: impl FnMut(f32, f32) -> f32, freqs: [f32; 4]) -> [f32; 4] {
+        let sine = |f: f32, i: usize| f32::sin(std::f32::consts::TAU * f * i as f32 / SAMPLERATE);
+        // 0.1 s holds a whole number of periods of all the frequencies involved
+        let output = (0..(0.1 * SAMPLERATE) as usize)
+            .map(|i| process(sine(1000.0, i), sine(300.0, i)))
+            .collect::<Vec<_>>();
+        freqs.map(|f| Goertzel::new(f, SAMPLERATE).process_block(&output))
+    }
+
+    #[test]
+    fn ring_mod_has_sum_and_difference() {
+        let mut ring_mod = RingMod::<f32>::new(SAMPLERATE, 300.0);
+        let [input, modulator, diff, sum] = spectrum(
+            |x, m| ring_mod.process([x, m])[0],
+            [1000.0, 300.0, 700.0, 1300.0],
+        );
+        assert!(input < 1e-3, "Input amplitude of {input}");
+        assert!(modulator < 1e-3, "Modulator amplitude of {modulator}");
+        assert!((diff - 0.5).abs() < 1e-3, "Difference amplitude of {diff}");
+        assert!((sum - 0.5).abs() < 1e-3, "Sum amplitude of {sum}");
+
+        // The internal carrier gives the same result
+        let [_, _, diff, sum] = spectrum(
+            |x, _| ring_mod.process([x])[0],
+            [1000.0, 300.0, 700.0, 1300.0],
+        );
+        assert!((diff - 0.5).abs() < 1e-3, "Difference amplitude of {diff}");
+        assert!((sum - 0.5).abs() < 1e-3, "Sum amplitude of {sum}");
+    }
+
+    #[test]
+    fn amp_mod_keeps_input() {
+        let mut amp_mod = AmpMod::<f32>::new(SAMPLERATE, 300.0, 1.0);
+        let [input, modulator, diff, sum] = spectrum(
+            |x, m| amp_mod.process([x, m])[0],
+            [1000.0, 300.0, 700.0, 1300.0],
+        );
+        assert!((input - 0.5).abs() < 1e-3, "Input amplitude of {input}");
+        assert!(modulator < 1e-3, "Modulator amplitude of {modulator}");
+        assert!((diff - 0.25).abs() < 1e-3, "Difference amplitude of {diff}");
+        assert!((sum - 0.25).abs() < 1e-3, "Sum amplitude of {sum}");
+    }
+}
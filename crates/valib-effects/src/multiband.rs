@@ -0,0 +1,243 @@
+//! # Multiband processing
+//!
+//! Provides a framework to split a signal into frequency bands, process each band independently,
+//! and sum them back, as used by multiband compressors and saturators.
+use num_traits::Zero;
+
+use valib_core::dsp::blocks::DelayLine;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_filters::biquad::Biquad;
+use valib_filters::crossover::LinkwitzRiley;
+use valib_saturators::Linear;
+
+/// Allpass filters compensating for the phase shift of the crossovers a band isn't split by
+type PhaseCompensation<T> = Box<[Biquad<T, Linear>]>;
+
+/// Multiband processor, splitting the input into `BANDS` frequency bands with Linkwitz-Riley
+/// crossovers, processing each band with its own instance of `P`, and summing the bands back.
+///
+/// Band `i` spans from crossover frequency `i - 1` to crossover frequency `i`, the first band
+/// starting at DC and the last one ending at Nyquist. The lower bands are run through allpass
+/// filters matching the phase shift of the crossovers they are not split by, so that all bands
+/// stay in phase. When each processor is the identity, the output is therefore flat in magnitude,
+/// with an allpass phase response.
+///
+/// Bands whose processor has less latency than the others are delayed to stay aligned, and the
+/// latency of the multiband processor is the highest latency of the band processors, as the
+/// crossovers themselves have none.
+#[derive(Debug, Clone)]
+pub struct Multiband<P: DSPMeta, const BANDS: usize> {
+    /// Band processors, from the lowest to the highest band
+    pub bands: [P; BANDS],
+    crossovers: Box<[LinkwitzRiley<P::Sample>]>,
+    /// Phase compensation of each band, one allpass per crossover above the band's own
+    compensation: [PhaseCompensation<P::Sample>; BANDS],
+    /// Delays compensating for the latency difference between bands
+    delays: [DelayLine<P::Sample>; BANDS],
+}
+
+impl<P: DSPProcess<1, 1>, const BANDS: usize> Multiband<P, BANDS> {
+    /// Create a new multiband processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the processor
+    /// * `frequencies`: Crossover frequencies (Hz), in increasing order. There must be one less
+    ///     crossover frequency than there are bands.
+    /// * `bands`: Band processors, from the lowest to the highest band
+    ///
+    /// returns: Multiband<P, { BANDS }>
+    pub fn new(samplerate: f32, frequencies: &[P::Sample], mut bands: [P; BANDS]) -> Self {
+        assert!(BANDS > 0, "Multiband processors need at least one band");
+        assert_eq!(
+            BANDS - 1,
+            frequencies.len(),
+            "There must be one crossover frequency between each band"
+        );
+        let samplerate_t = P::Sample::from_f64(samplerate as _);
+        let crossovers = frequencies
+            .iter()
+            .map(|&fc| LinkwitzRiley::new(samplerate_t, fc))
+            .collect::<Box<[_]>>();
+        for band in &mut bands {
+            band.set_samplerate(samplerate);
+        }
+        let mut this = Self {
+            bands,
+            compensation: std::array::from_fn(|i| {
+                crossovers[(i + 1).min(BANDS - 1)..]
+                    .iter()
+                    .map(|c| c.allpass())
+                    .collect()
+            }),
+            crossovers,
+            delays: std::array::from_fn(|_| DelayLine::new(0)),
+        };
+        this.update_latency();
+        this
+    }
+
+    /// Sets the crossover frequency between bands `i` and `i + 1` (Hz). Crossover frequencies
+    /// must stay in increasing order.
+    ///
+    /// # Arguments
+    ///
+    /// * `i`: Index of the crossover, which must be less than `BANDS - 1`
+    /// * `fc`: New crossover frequency (Hz)
+    ///
+    /// returns: ()
+    pub fn set_crossover(&mut self, i: usize, fc: P::Sample) {
+        self.crossovers[i].set_cutoff(fc);
+        self.update_compensation(i);
+    }
+
+    /// Update the band delays after the latency of the band processors has changed.
+    ///
+    /// This reallocates when the latency changes, and therefore should not be called from the
+    /// audio thread.
+    pub fn update_latency(&mut self) {
+        let latency = self.latency();
+        for (band, delay) in self.bands.iter().zip(&mut self.delays) {
+            let len = latency - band.latency();
+            if len != delay.max_delay() {
+                *delay = DelayLine::new(len);
+            }
+        }
+    }
+
+    /// Update the allpass filters compensating for the phase shift of the given crossover
+    fn update_compensation(&mut self, crossover: usize) {
+        let allpass = self.crossovers[crossover].allpass();
+        // Bands below the crossover, except the one it splits off, have one allpass per crossover
+        // above them, in order
+        for band in 0..crossover {
+            let index = crossover - band - 1;
+            self.compensation[band][index].update_coefficients(&allpass);
+        }
+    }
+}
+
+impl<P: DSPProcess<1, 1>, const BANDS: usize> DSPMeta for Multiband<P, BANDS> {
+    type Sample = P::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        for crossover in &mut self.crossovers {
+            crossover.set_samplerate(samplerate);
+        }
+        for i in 0..self.crossovers.len() {
+            self.update_compensation(i);
+        }
+        for band in &mut self.bands {
+            band.set_samplerate(samplerate);
+        }
+        self.update_latency();
+    }
+
+    fn latency(&self) -> usize {
+        self.bands.iter().map(|b| b.latency()).max().unwrap_or(0)
+    }
+
+    fn reset(&mut self) {
+        for crossover in &mut self.crossovers {
+            crossover.reset();
+        }
+        for allpass in self.compensation.iter_mut().flat_map(|c| c.iter_mut()) {
+            allpass.reset();
+        }
+        for band in &mut self.bands {
+            band.reset();
+        }
+        for delay in &mut self.delays {
+            delay.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<P: DSPProcess<1, 1>, const BANDS: usize> DSPProcess<1, 1> for Multiband<P, BANDS> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let mut rest = x;
+        let mut y = P::Sample::zero();
+        for i in 0..BANDS {
+            let band = if let Some(crossover) = self.crossovers.get_mut(i) {
+                let [low, high] = crossover.process([rest]);
+                rest = high;
+                low
+            } else {
+                rest
+            };
+            let band = self.compensation[i]
+                .iter_mut()
+                .fold(band, |x, allpass| allpass.process([x])[0]);
+            let [band] = self.bands[i].process([band]);
+            let [band] = self.delays[i].process([band]);
+            y += band;
+        }
+        [y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Delays the input by a fixed number of samples, reported as its latency
+    #[derive(Debug, Clone)]
+    struct Delay(Vec<f64>);
+
+    impl DSPMeta for Delay {
+        type Sample = f64;
+
+        fn latency(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl DSPProcess<1, 1> for Delay {
+        fn process(&mut self, [x]: [f64; 1]) -> [f64; 1] {
+            self.0.insert(0, x);
+            [self.0.pop().unwrap()]
+        }
+    }
+
+    #[test]
+    fn multiband_identity_reconstructs_input() {
+        const SAMPLERATE: f32 = 48e3;
+        const LEN: usize = 4096;
+        let bands = [0, 7, 0, 3].map(|n| Delay(vec![0.0; n]));
+        let mut multiband = Multiband::new(SAMPLERATE, &[200.0, 1000.0, 5000.0], bands);
+        assert_eq!(7, multiband.latency());
+
+        let impulse_response = (0..LEN)
+            .map(|i| multiband.process([if i == 0 { 1.0 } else { 0.0 }])[0])
+            .collect::<Vec<_>>();
+        // Nothing comes out before the latency of the slowest band
+        assert!(impulse_response[..7].iter().all(|&y| y == 0.0));
+
+        // Flat magnitude response
+        for k in [1, 10, 17, 50, 85, 300, 427, 1000, 2047] {
+            let (re, im) =
+                impulse_response
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (n, y)| {
+                        let phase = std::f64::consts::TAU * (k * n) as f64 / LEN as f64;
+                        (re + y * phase.cos(), im - y * phase.sin())
+                    });
+            let magnitude = f64::hypot(re, im);
+            assert!(
+                (magnitude - 1.0).abs() < 1e-3,
+                "Magnitude of {magnitude} at bin {k}"
+            );
+        }
+
+        // Moving a crossover keeps the response flat
+        multiband.set_crossover(1, 2000.0);
+        multiband.reset();
+        let energy = (0..LEN)
+            .map(|i| multiband.process([if i == 0 { 1.0 } else { 0.0 }])[0].powi(2))
+            .sum::<f64>();
+        assert!((energy - 1.0).abs() < 1e-3, "Energy of {energy}");
+    }
+}
@@ -0,0 +1,206 @@
+//! # Phaser
+//!
+//! Provides a phaser, made of a cascade of first-order allpass filters whose cutoff is swept by an
+//! LFO, mixed back with the dry signal.
+use valib_core::dsp::blocks::P1;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_oscillators::lfo::{Lfo, LfoShape};
+
+/// Maximum number of allpass stages of the [`Phaser`]
+pub const MAX_STAGES: usize = 12;
+/// Range of the cutoff sweep at full depth, in octaves on each side of the center frequency
+const SWEEP_OCTAVES: f64 = 3.0;
+/// Maximum feedback amount, keeping the feedback loop stable
+const MAX_FEEDBACK: f64 = 0.95;
+
+/// Phaser effect.
+///
+/// The input is run through a cascade of first-order allpass filters sharing the same cutoff,
+/// and summed with the dry signal. Each pair of stages produces one notch in the spectrum, where
+/// the allpass cascade is out of phase with the dry signal. The cutoff is swept around the center
+/// frequency by a sine LFO, which moves the notches up and down the spectrum. Feeding the output
+/// of the cascade back into its input sharpens the notches, and adds resonant peaks in between.
+#[derive(Debug, Copy, Clone)]
+pub struct Phaser<T> {
+    samplerate: f32,
+    num_stages: usize,
+    center: T,
+    depth: T,
+    feedback: T,
+    lfo: Lfo<T>,
+    stages: [P1<T>; MAX_STAGES],
+    last_out: T,
+}
+
+impl<T: Scalar> Phaser<T> {
+    /// Create a new phaser with 4 stages, centered around 1 kHz, with a rate of 0.5 Hz, half depth
+    /// and no feedback.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the phaser
+    ///
+    /// returns: Phaser<T>
+    pub fn new(samplerate: f32) -> Self {
+        let sr = T::from_f32(samplerate);
+        let center = T::from_f64(1e3);
+        Self {
+            samplerate,
+            num_stages: 4,
+            center,
+            depth: T::from_f64(0.5),
+            feedback: T::zero(),
+            lfo: Lfo::new(sr, T::from_f64(0.5), LfoShape::Sine),
+            stages: [P1::new(sr, center); MAX_STAGES],
+            last_out: T::zero(),
+        }
+    }
+
+    /// Sets the number of allpass stages, clamped between 1 and [`MAX_STAGES`]. There is one notch
+    /// for each pair of stages.
+    pub fn set_stages(&mut self, num_stages: usize) {
+        let num_stages = num_stages.clamp(1, MAX_STAGES);
+        // Newly enabled stages start from a clean state
+        for stage in &mut self.stages[self.num_stages.min(num_stages)..num_stages] {
+            stage.reset();
+        }
+        self.num_stages = num_stages;
+    }
+
+    /// Sets the rate of the LFO sweeping the cutoff (Hz).
+    pub fn set_rate(&mut self, rate: T) {
+        self.lfo.set_frequency(rate);
+    }
+
+    /// Sets the depth of the sweep, in the 0..1 range. At full depth, the cutoff sweeps 3 octaves
+    /// on each side of the center frequency.
+    pub fn set_depth(&mut self, depth: T) {
+        self.depth = depth.simd_clamp(T::zero(), T::one());
+    }
+
+    /// Sets the center frequency of the sweep (Hz).
+    pub fn set_center(&mut self, center: T) {
+        self.center = center;
+    }
+
+    /// Sets the feedback amount, clamped to the -0.95..0.95 range. Negative values move the
+    /// resonant peaks in between the notches.
+    pub fn set_feedback(&mut self, feedback: T) {
+        let max = T::from_f64(MAX_FEEDBACK);
+        self.feedback = feedback.simd_clamp(-max, max);
+    }
+
+    /// Current cutoff of the allpass stages (Hz)
+    pub fn cutoff(&self) -> T {
+        self.stages[0].fc
+    }
+}
+
+impl<T: Scalar> DSPMeta for Phaser<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.lfo.set_samplerate(samplerate);
+        for stage in &mut self.stages {
+            stage.set_samplerate(samplerate);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lfo.reset();
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+        self.last_out = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Phaser<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let [lfo] = self.lfo.process([]);
+        let octaves = T::from_f64(SWEEP_OCTAVES) * self.depth * lfo;
+        let fc = (self.center * T::from_f64(2.0).simd_powf(octaves))
+            .simd_min(T::from_f32(0.45 * self.samplerate));
+
+        let mut y = x + self.feedback * self.last_out;
+        for stage in &mut self.stages[..self.num_stages] {
+            stage.set_fc(fc);
+            let [_, _, ap] = stage.process([y]);
+            y = ap;
+        }
+        self.last_out = y;
+        [(x + y) * T::from_f64(0.5)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    /// Magnitude response of the phaser at the given frequencies, measured over a window
+    /// around each of the given times (s).
+    fn magnitude_over_time<const N: usize>(freqs: &[f32], times: [f32; N]) -> Vec<[f32; N]> {
+        let window = (0.02 * SAMPLERATE) as usize;
+        let len = (times.iter().copied().fold(0.0, f32::max) * SAMPLERATE) as usize + window;
+        freqs
+            .iter()
+            .map(|&f| {
+                let mut phaser = Phaser::new(SAMPLERATE);
+                phaser.set_rate(1.0);
+                let output = (0..len)
+                    .map(|i| {
+                        let x = f32::sin(std::f32::consts::TAU * f * i as f32 / SAMPLERATE);
+                        phaser.process([x])[0]
+                    })
+                    .collect::<Vec<_>>();
+                times.map(|t| {
+                    let start = (t * SAMPLERATE) as usize - window / 2;
+                    let block = &output[start..start + window];
+                    let power = block.iter().map(|y| y * y).sum::<f32>() / window as f32;
+                    (2.0 * power).sqrt()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn phaser_notches_move() {
+        let freqs = (0..64)
+            .map(|i| 100.0 * 100f32.powf(i as f32 / 63.0))
+            .collect::<Vec<_>>();
+        // LFO at its maximum, then its minimum
+        let response = magnitude_over_time(&freqs, [0.25, 0.75]);
+
+        let notch = |k: usize| {
+            (0..freqs.len())
+                .min_by(|&a, &b| response[a][k].total_cmp(&response[b][k]))
+                .unwrap()
+        };
+        let [high, low] = [notch(0), notch(1)];
+        assert!(response[high][0] < 0.1, "No notch when swept up");
+        assert!(response[low][1] < 0.1, "No notch when swept down");
+        assert!(
+            freqs[high] > 2.0 * freqs[low],
+            "Notch at {} Hz when swept up, {} Hz when swept down",
+            freqs[high],
+            freqs[low]
+        );
+        // Each notch is gone at the other point in time
+        assert!(response[high][1] > 0.5);
+        assert!(response[low][0] > 0.5);
+    }
+
+    #[test]
+    fn phaser_clamps_stages() {
+        let mut phaser = Phaser::<f32>::new(SAMPLERATE);
+        phaser.set_stages(100);
+        assert_eq!(MAX_STAGES, phaser.num_stages);
+        phaser.set_stages(0);
+        assert_eq!(1, phaser.num_stages);
+    }
+}
@@ -0,0 +1,495 @@
+//! # Stereo processing
+//!
+//! Provides processors acting on the stereo image of a signal.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::blocks::{DelayLine, MixCurve};
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+/// Maximum Haas delay supported by [`StereoWidener`] (ms)
+pub const MAX_HAAS_MS: f32 = 50.0;
+/// Time constant of the correlation estimate of [`StereoWidener::mono_compatibility`] (s)
+const CORRELATION_TIME: f32 = 0.3;
+
+/// Mid/side width processor, scaling the side channel of a stereo signal.
+///
+/// A width of 0 collapses the signal to mono, 1 leaves it untouched, and values above 1 increase
+/// the level of the side channel.
+#[derive(Debug, Copy, Clone)]
+pub struct MidSide<T> {
+    width: T,
+}
+
+impl<T: Scalar> Default for MidSide<T> {
+    fn default() -> Self {
+        Self::new(T::one())
+    }
+}
+
+impl<T: Scalar> MidSide<T> {
+    /// Create a new mid/side width processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `width`: Stereo width. 0 is mono, 1 is the original image.
+    ///
+    /// returns: MidSide<T>
+    pub fn new(width: T) -> Self {
+        Self { width }
+    }
+
+    /// Stereo width. 0 is mono, 1 is the original image.
+    pub fn width(&self) -> T {
+        self.width
+    }
+
+    /// Sets the stereo width. 0 is mono, 1 is the original image.
+    pub fn set_width(&mut self, width: T) {
+        self.width = width;
+    }
+
+    /// Convert a left/right pair into a mid/side pair.
+    pub fn encode([l, r]: [T; 2]) -> [T; 2] {
+        let half = T::from_f64(0.5);
+        [half * (l + r), half * (l - r)]
+    }
+
+    /// Convert a mid/side pair back into a left/right pair.
+    pub fn decode([m, s]: [T; 2]) -> [T; 2] {
+        [m + s, m - s]
+    }
+}
+
+impl<T: Scalar> DSPMeta for MidSide<T> {
+    type Sample = T;
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 2> for MidSide<T> {
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process(&mut self, [l, r]: [Self::Sample; 2]) -> [Self::Sample; 2] {
+        // Equivalent to decode([m, width * s]) after encoding, written such that a width of 1 is
+        // exactly transparent
+        let direct = 0.5 * (1.0 + self.width);
+        let cross = 0.5 * (1.0 - self.width);
+        [direct * l + cross * r, direct * r + cross * l]
+    }
+}
+
+/// Stereo widener, combining [`MidSide`] width scaling with a Haas delay on the right channel.
+///
+/// A width of 0 collapses the signal to mono, 1 leaves it untouched, and values above 1 increase
+/// the level of the side channel. The Haas delay delays the right channel by a few milliseconds,
+/// which widens the perceived image at the cost of comb filtering when summed to mono; use
+/// [`Self::mono_compatibility`] to monitor it.
+#[derive(Debug, Clone)]
+pub struct StereoWidener<T> {
+    mid_side: MidSide<T>,
+    haas_ms: f32,
+    samplerate: f32,
+    delay: DelayLine<T>,
+    correlation_coeff: T,
+    power_lr: T,
+    power_l: T,
+    power_r: T,
+}
+
+impl<T: Scalar> StereoWidener<T> {
+    /// Create a new stereo widener, with a width of 1 and no Haas delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the widener
+    ///
+    /// returns: StereoWidener<T>
+    pub fn new(samplerate: f32) -> Self {
+        let mut this = Self {
+            mid_side: MidSide::default(),
+            haas_ms: 0.0,
+            samplerate,
+            delay: DelayLine::new(0),
+            correlation_coeff: T::zero(),
+            power_lr: T::zero(),
+            power_l: T::zero(),
+            power_r: T::zero(),
+        };
+        this.set_samplerate(samplerate);
+        this
+    }
+
+    /// Stereo width. 0 is mono, 1 is the original image.
+    pub fn width(&self) -> T {
+        self.mid_side.width()
+    }
+
+    /// Sets the stereo width. 0 is mono, 1 is the original image.
+    pub fn set_width(&mut self, width: T) {
+        self.mid_side.set_width(width);
+    }
+
+    /// Delay applied to the right channel (ms).
+    pub fn haas_ms(&self) -> f32 {
+        self.haas_ms
+    }
+
+    /// Sets the delay applied to the right channel (ms). The delay is rounded to the nearest sample,
+    /// and clamped to [`MAX_HAAS_MS`].
+    pub fn set_haas_ms(&mut self, haas_ms: f32) {
+        self.haas_ms = haas_ms.clamp(0.0, MAX_HAAS_MS);
+        self.delay
+            .set_delay((self.haas_ms * 1e-3 * self.samplerate).round() as usize);
+    }
+
+    /// Estimate of the correlation between the output channels, in the -1..=1 range. Values close
+    /// to 1 mean the output sums to mono without loss, while negative values indicate phase
+    /// cancellation when summed to mono.
+    pub fn mono_compatibility(&self) -> T {
+        let norm = (self.power_l * self.power_r).simd_sqrt();
+        let correlation = self.power_lr / norm.simd_max(T::from_f64(1e-12));
+        // Report full compatibility on silence
+        T::one().select(norm.simd_le(T::from_f64(1e-12)), correlation)
+    }
+}
+
+impl<T: Scalar> DSPMeta for StereoWidener<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        let max_samples = (MAX_HAAS_MS * 1e-3 * samplerate).ceil() as usize;
+        self.delay = DelayLine::new(max_samples);
+        self.correlation_coeff = T::from_f64((-1.0 / (CORRELATION_TIME * samplerate) as f64).exp());
+        self.set_haas_ms(self.haas_ms);
+    }
+
+    fn reset(&mut self) {
+        self.delay.reset();
+        self.power_lr = T::zero();
+        self.power_l = T::zero();
+        self.power_r = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 2> for StereoWidener<T> {
+    fn process(&mut self, [l, r]: [Self::Sample; 2]) -> [Self::Sample; 2] {
+        let [l, r] = self.mid_side.process([l, r]);
+        let [r] = self.delay.process([r]);
+
+        let a = self.correlation_coeff;
+        self.power_lr = l * r + (self.power_lr - l * r) * a;
+        self.power_l = l * l + (self.power_l - l * l) * a;
+        self.power_r = r * r + (self.power_r - r * r) * a;
+        [l, r]
+    }
+}
+
+/// Pan law, setting how the gains of both channels change with the pan position, and how much
+/// the signal is attenuated at the center.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PanLaw {
+    /// -3 dB at the center. The gains follow a quarter of a sine wave, such that the total power
+    /// stays constant across pan positions.
+    #[default]
+    ConstantPower,
+    /// -4.5 dB at the center, in between the constant power and linear laws.
+    Compromise,
+    /// -6 dB at the center. The gains follow a squared sine wave, such that they sum to 1 across
+    /// pan positions, with a smoother change than the linear law near the sides.
+    SineSquared,
+    /// -6 dB at the center. The gains change linearly, such that they sum to 1 across pan
+    /// positions.
+    Linear,
+}
+
+impl PanLaw {
+    /// Compute the gains of both channels for the given pan position.
+    ///
+    /// # Arguments
+    ///
+    /// * `pan`: Pan position, -1 being fully left and 1 fully right
+    ///
+    /// returns: [T; 2]
+    pub fn gains<T: Scalar>(&self, pan: T) -> [T; 2] {
+        // Panning is a crossfade from the left channel to the right one
+        let t = T::from_f64(0.5) * (pan + T::one());
+        let linear = MixCurve::Linear.gains(t);
+        let sine = MixCurve::EqualPower.gains(t);
+        match self {
+            Self::ConstantPower => sine,
+            Self::Compromise => [
+                (sine[0] * linear[0]).simd_sqrt(),
+                (sine[1] * linear[1]).simd_sqrt(),
+            ],
+            Self::SineSquared => sine.map(|g| g * g),
+            Self::Linear => linear,
+        }
+    }
+}
+
+/// Mono to stereo panner.
+///
+/// The channel gains are computed when the pan position or the pan law change, and not on every
+/// sample.
+#[derive(Debug, Copy, Clone)]
+pub struct Panner<T> {
+    pan: T,
+    law: PanLaw,
+    gains: [T; 2],
+}
+
+impl<T: Scalar> Panner<T> {
+    /// Create a new panner.
+    ///
+    /// # Arguments
+    ///
+    /// * `pan`: Pan position, -1 being fully left and 1 fully right
+    /// * `law`: Pan law
+    ///
+    /// returns: Panner<T>
+    pub fn new(pan: T, law: PanLaw) -> Self {
+        Self {
+            pan,
+            law,
+            gains: law.gains(pan),
+        }
+    }
+
+    /// Current pan position
+    pub fn pan(&self) -> T {
+        self.pan
+    }
+
+    /// Sets the pan position, -1 being fully left and 1 fully right.
+    pub fn set_pan(&mut self, pan: T) {
+        self.pan = pan;
+        self.gains = self.law.gains(pan);
+    }
+
+    /// Sets the pan law.
+    pub fn set_law(&mut self, law: PanLaw) {
+        self.law = law;
+        self.gains = law.gains(self.pan);
+    }
+
+    /// Current gains of the left and right channels
+    pub fn gains(&self) -> [T; 2] {
+        self.gains
+    }
+}
+
+impl<T: Scalar> Default for Panner<T> {
+    fn default() -> Self {
+        Self::new(T::zero(), PanLaw::default())
+    }
+}
+
+impl<T: Scalar> DSPMeta for Panner<T> {
+    type Sample = T;
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 2> for Panner<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 2] {
+        self.gains.map(|g| g * x)
+    }
+}
+
+/// Stereo balance control.
+///
+/// Unlike a panner, the signal is left untouched at the center, and moving the balance towards
+/// one side attenuates the other side, following the pan law. The channel gains are computed when
+/// the balance or the pan law change, and not on every sample.
+#[derive(Debug, Copy, Clone)]
+pub struct StereoBalance<T> {
+    balance: T,
+    law: PanLaw,
+    gains: [T; 2],
+}
+
+impl<T: Scalar> StereoBalance<T> {
+    /// Create a new stereo balance control.
+    ///
+    /// # Arguments
+    ///
+    /// * `balance`: Balance position, -1 being fully left and 1 fully right
+    /// * `law`: Pan law, used for the attenuation of the opposite side
+    ///
+    /// returns: StereoBalance<T>
+    pub fn new(balance: T, law: PanLaw) -> Self {
+        Self {
+            balance,
+            law,
+            gains: Self::compute_gains(balance, law),
+        }
+    }
+
+    /// Current balance position
+    pub fn balance(&self) -> T {
+        self.balance
+    }
+
+    /// Sets the balance position, -1 being fully left and 1 fully right.
+    pub fn set_balance(&mut self, balance: T) {
+        self.balance = balance;
+        self.gains = Self::compute_gains(balance, self.law);
+    }
+
+    /// Sets the pan law.
+    pub fn set_law(&mut self, law: PanLaw) {
+        self.law = law;
+        self.gains = Self::compute_gains(self.balance, law);
+    }
+
+    /// Current gains of the left and right channels
+    pub fn gains(&self) -> [T; 2] {
+        self.gains
+    }
+
+    /// Pan law gains normalized to unity at the center
+    fn compute_gains(balance: T, law: PanLaw) -> [T; 2] {
+        let [center, _] = law.gains(T::zero());
+        law.gains(balance).map(|g| (g / center).simd_min(T::one()))
+    }
+}
+
+impl<T: Scalar> Default for StereoBalance<T> {
+    fn default() -> Self {
+        Self::new(T::zero(), PanLaw::default())
+    }
+}
+
+impl<T: Scalar> DSPMeta for StereoBalance<T> {
+    type Sample = T;
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 2> for StereoBalance<T> {
+    fn process(&mut self, [l, r]: [Self::Sample; 2]) -> [Self::Sample; 2] {
+        let [gl, gr] = self.gains;
+        [l * gl, r * gr]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 1000.0;
+
+    fn input(i: usize) -> [f32; 2] {
+        let t = i as f32 / SAMPLERATE;
+        [
+            f32::sin(std::f32::consts::TAU * 10.0 * t),
+            0.3 * f32::sin(std::f32::consts::TAU * 27.0 * t),
+        ]
+    }
+
+    #[test]
+    fn mid_side_scales_side() {
+        let [m, s] = MidSide::encode([0.75f64, -0.25]);
+        assert_eq!([0.25, 0.5], [m, s]);
+        assert_eq!([0.75, -0.25], MidSide::decode([m, s]));
+
+        let mut mid_side = MidSide::new(1.5);
+        let [l, r] = mid_side.process([0.75, -0.25]);
+        let [el, er] = MidSide::decode([m, 1.5 * s]);
+        assert!((l - el).abs() < 1e-12 && (r - er).abs() < 1e-12);
+    }
+
+    #[test]
+    fn widener_unit_width_is_transparent() {
+        let mut widener = StereoWidener::new(SAMPLERATE);
+        for i in 0..1000 {
+            assert_eq!(input(i), widener.process(input(i)));
+        }
+    }
+
+    #[test]
+    fn widener_zero_width_is_mono() {
+        let mut widener = StereoWidener::new(SAMPLERATE);
+        widener.set_width(0.0);
+        for i in 0..1000 {
+            let [l, r] = widener.process(input(i));
+            assert_eq!(l, r);
+        }
+        let correlation = widener.mono_compatibility();
+        assert!((correlation - 1.0).abs() < 1e-4, "Got {correlation}");
+    }
+
+    #[test]
+    fn widener_haas_delay() {
+        let mut widener = StereoWidener::new(SAMPLERATE);
+        widener.set_haas_ms(12.0);
+        let output = (0..50)
+            .map(|i| {
+                let x = if i == 0 { 1.0 } else { 0.0 };
+                widener.process([x, x])
+            })
+            .collect::<Vec<_>>();
+        let left = output.iter().position(|[l, _]| *l != 0.0).unwrap();
+        let right = output.iter().position(|[_, r]| *r != 0.0).unwrap();
+        assert_eq!(0, left);
+        assert_eq!(12, right);
+    }
+
+    #[test]
+    fn widener_mono_compatibility_detects_phase_inversion() {
+        let mut widener = StereoWidener::new(SAMPLERATE);
+        for i in 0..1000 {
+            let [x, _] = input(i);
+            widener.process([x, -x]);
+        }
+        let correlation = widener.mono_compatibility();
+        assert!((correlation + 1.0).abs() < 1e-4, "Got {correlation}");
+    }
+
+    #[test]
+    fn pan_laws_center_attenuation() {
+        let center_db = |law: PanLaw| {
+            let [l, r] = Panner::<f64>::new(0.0, law).process([1.0]);
+            assert_eq!(l, r);
+            20.0 * l.log10()
+        };
+        assert!((center_db(PanLaw::ConstantPower) + 3.01).abs() < 0.01);
+        assert!((center_db(PanLaw::Compromise) + 4.5).abs() < 0.05);
+        assert!((center_db(PanLaw::SineSquared) + 6.02).abs() < 0.01);
+        assert!((center_db(PanLaw::Linear) + 6.02).abs() < 0.01);
+    }
+
+    #[test]
+    fn constant_power_preserves_power() {
+        let mut panner = Panner::<f64>::default();
+        for i in -10..=10 {
+            panner.set_pan(i as f64 / 10.0);
+            let [l, r] = panner.process([0.5]);
+            let power = l * l + r * r;
+            assert!((power - 0.25).abs() < 1e-12, "Pan {i}: power of {power}");
+        }
+        panner.set_pan(-1.0);
+        assert_eq!([0.5, 0.0], panner.process([0.5]));
+
+        // Amplitude-preserving laws sum to the input instead
+        for law in [PanLaw::SineSquared, PanLaw::Linear] {
+            panner.set_law(law);
+            for i in -10..=10 {
+                panner.set_pan(i as f64 / 10.0);
+                let [l, r] = panner.process([0.5]);
+                assert!((l + r - 0.5).abs() < 1e-12, "{law:?}, pan {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn balance_is_transparent_at_center() {
+        let mut balance = StereoBalance::<f32>::default();
+        assert_eq!([0.3, -0.7], balance.process([0.3, -0.7]));
+
+        balance.set_balance(1.0);
+        assert_eq!([0.0, -0.7], balance.process([0.3, -0.7]));
+        balance.set_balance(-0.5);
+        let [l, r] = balance.process([1.0, 1.0]);
+        assert_eq!(1.0, l);
+        assert!(r > 0.0 && r < 1.0);
+    }
+}
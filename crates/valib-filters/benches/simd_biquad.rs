@@ -0,0 +1,36 @@
+//! Compares the throughput of the biquad filter between scalar and SIMD sample types.
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion, Throughput};
+use valib_core::benchmarking::benchmark_dsp;
+use valib_core::dsp::DSPProcess;
+use valib_core::simd::{AutoF32x2, AutoF32x4};
+use valib_core::Scalar;
+use valib_filters::biquad::Biquad;
+use valib_saturators::Linear;
+
+/// Number of samples processed per iteration
+const AMOUNT: usize = 1 << 12;
+
+fn lowpass<T: Scalar>() -> Biquad<T, Linear> {
+    Biquad::lowpass(T::from_f64(0.1), T::from_f64(0.707))
+}
+
+fn bench_sample_type<T: Scalar>(group: &mut BenchmarkGroup<WallTime>, name: &str)
+where
+    Biquad<T, Linear>: DSPProcess<1, 1, Sample = T>,
+{
+    // Each lane counts as one channel, such that the throughput compares directly between types
+    group.throughput(Throughput::Elements((AMOUNT * T::LANES) as u64));
+    group.bench_function(name, |b| b.iter(|| benchmark_dsp(AMOUNT, lowpass::<T>())));
+}
+
+fn simd_biquad(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simd_biquad");
+    bench_sample_type::<f32>(&mut group, "f32");
+    bench_sample_type::<AutoF32x2>(&mut group, "AutoF32x2");
+    bench_sample_type::<AutoF32x4>(&mut group, "AutoF32x4");
+    group.finish();
+}
+
+criterion_group!(benches, simd_biquad);
+criterion_main!(benches);
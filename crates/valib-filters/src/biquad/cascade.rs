@@ -0,0 +1,182 @@
+//! Cascades of biquad sections, for higher-order filters.
+use nalgebra::Complex;
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::analysis::DspAnalysis;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_saturators::{Linear, Saturator};
+
+use super::Biquad;
+
+/// Cascade of `N` biquad sections, processed in series. Filters up to order `2 * N` can be
+/// represented; unused sections are set to pass their input through unchanged.
+#[derive(Debug, Copy, Clone)]
+pub struct BiquadCascade<T, S, const N: usize> {
+    /// Biquad sections of the cascade, in processing order
+    pub sections: [Biquad<T, S>; N],
+}
+
+impl<T: Scalar, const N: usize> BiquadCascade<T, Linear, N> {
+    /// Create a new Butterworth lowpass filter of the given order.
+    ///
+    /// # Arguments
+    ///
+    /// * `order`: Order of the filter. Must be less than or equal to `2 * N`.
+    /// * `fc`: Cutoff frequency coefficient (normalized where 1 == samplerate)
+    ///
+    /// returns: BiquadCascade<T, Linear, { N }>
+    pub fn butterworth_lowpass(order: usize, fc: T) -> Self {
+        Self::lowpass_from_prototype(order, fc, T::one(), T::one(), T::one())
+    }
+
+    /// Create a new Chebyshev type I lowpass filter of the given order. The passband has an
+    /// equiripple response between `-ripple_db` and 0 dB, and the response at the cutoff frequency
+    /// is `-ripple_db`.
+    ///
+    /// # Arguments
+    ///
+    /// * `order`: Order of the filter. Must be less than or equal to `2 * N`.
+    /// * `fc`: Cutoff frequency coefficient (normalized where 1 == samplerate)
+    /// * `ripple_db`: Passband ripple (dB)
+    ///
+    /// returns: BiquadCascade<T, Linear, { N }>
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn chebyshev1_lowpass(order: usize, fc: T, ripple_db: T) -> Self {
+        let eps2 = 10.0.simd_powf(ripple_db / 10.0) - 1.0;
+        let v0 = eps2.simd_sqrt().simd_recip().simd_asinh() / T::from_f64(order as _);
+        // Even-order Chebyshev filters start at the bottom of the ripple
+        let gain = if order % 2 == 0 {
+            (1.0 + eps2).simd_sqrt().simd_recip()
+        } else {
+            1.0
+        };
+        Self::lowpass_from_prototype(order, fc, v0.simd_sinh(), v0.simd_cosh(), gain)
+    }
+
+    /// Build a lowpass filter from the analog prototype whose poles are located at
+    /// `-a sin(theta) + j b cos(theta)`, as is the case for Butterworth (`a = b = 1`) and Chebyshev
+    /// filters.
+    #[replace_float_literals(T::from_f64(literal))]
+    fn lowpass_from_prototype(order: usize, fc: T, a: T, b: T, gain: T) -> Self {
+        assert!(order >= 1, "Filter order must be at least 1");
+        assert!(
+            order.div_ceil(2) <= N,
+            "Filter of order {order} needs more than the {N} available sections"
+        );
+        let wc = (T::simd_pi() * fc).simd_tan();
+        let mut sections = core::array::from_fn(|_| Biquad::new([1.0, 0.0, 0.0], [0.0, 0.0]));
+
+        for (k, section) in sections.iter_mut().take(order / 2).enumerate() {
+            let theta =
+                T::from_f64(core::f64::consts::PI * (2 * k + 1) as f64 / (2 * order) as f64);
+            let sigma = a * theta.simd_sin();
+            let omega = b * theta.simd_cos();
+            let w0 = (sigma * sigma + omega * omega).simd_sqrt();
+            let q = w0 / (2.0 * sigma);
+            let f0 = (w0 * wc).simd_atan() / T::simd_pi();
+            *section = Biquad::lowpass(f0, q);
+        }
+
+        if order % 2 == 1 {
+            // Real pole at -a, implemented as a bilinear-transformed one-pole section
+            let w = a * wc;
+            let b0 = w / (1.0 + w);
+            sections[order / 2] = Biquad::new([b0, b0, 0.0], [(w - 1.0) / (w + 1.0), 0.0]);
+        }
+
+        for b in &mut sections[0].b {
+            *b *= gain;
+        }
+        Self { sections }
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>, const N: usize> DSPMeta for BiquadCascade<T, S, N> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        for section in &mut self.sections {
+            section.set_samplerate(samplerate);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.sections.iter().map(|s| s.latency()).sum()
+    }
+
+    fn reset(&mut self) {
+        for section in &mut self.sections {
+            section.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, S: Saturator<T>, const N: usize> DSPProcess<1, 1> for BiquadCascade<T, S, N> {
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        self.sections
+            .iter_mut()
+            .fold(x, |x, section| section.process(x))
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>, const N: usize> DspAnalysis<1, 1> for BiquadCascade<T, S, N> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let h = self
+            .sections
+            .iter()
+            .map(|s| s.h_z(z)[0][0])
+            .fold(Complex::from(T::one()), |acc, h| acc * h);
+        [[h]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::simd::SimdComplexField;
+
+    const SAMPLERATE: f64 = 48e3;
+
+    fn gain_db<const N: usize>(filter: &BiquadCascade<f64, Linear, N>, freq: f64) -> f64 {
+        let [[h]] = filter.freq_response(SAMPLERATE, freq);
+        20.0 * h.simd_modulus().log10()
+    }
+
+    #[test]
+    fn butterworth_4th_order_rolloff() {
+        let filter = BiquadCascade::<_, _, 2>::butterworth_lowpass(4, 100.0 / SAMPLERATE);
+        assert!((gain_db(&filter, 100.0) + 3.0103).abs() < 1e-3);
+        assert!(gain_db(&filter, 10.0).abs() < 1e-3);
+
+        let slope = gain_db(&filter, 3200.0) - gain_db(&filter, 1600.0);
+        assert!((slope + 24.0).abs() < 0.5, "Slope is {slope} dB/oct");
+    }
+
+    #[test]
+    fn butterworth_odd_order_uses_extra_section() {
+        let filter = BiquadCascade::<_, _, 3>::butterworth_lowpass(3, 100.0 / SAMPLERATE);
+        assert!((gain_db(&filter, 100.0) + 3.0103).abs() < 1e-3);
+
+        let slope = gain_db(&filter, 3200.0) - gain_db(&filter, 1600.0);
+        assert!((slope + 18.0).abs() < 0.5, "Slope is {slope} dB/oct");
+    }
+
+    #[test]
+    fn chebyshev1_passband_ripple() {
+        const RIPPLE: f64 = 1.0;
+        let filter = BiquadCascade::<_, _, 2>::chebyshev1_lowpass(4, 1000.0 / SAMPLERATE, RIPPLE);
+        let (min, max) = (1..100)
+            .map(|i| gain_db(&filter, i as f64 * 10.0))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), g| {
+                (min.min(g), max.max(g))
+            });
+        assert!(max < 1e-3, "Passband exceeds unity gain: {max} dB");
+        assert!((min + RIPPLE).abs() < 1e-2, "Passband minimum: {min} dB");
+        assert!((gain_db(&filter, 1000.0) + RIPPLE).abs() < 1e-3);
+
+        // Steeper than the Butterworth filter of the same order right after the cutoff
+        let butterworth = BiquadCascade::<_, _, 2>::butterworth_lowpass(4, 1000.0 / SAMPLERATE);
+        assert!(gain_db(&filter, 2000.0) < gain_db(&butterworth, 2000.0));
+    }
+}
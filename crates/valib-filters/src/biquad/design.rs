@@ -1,5 +1,5 @@
-use std::f64::consts::PI;
-use std::{fmt, ops};
+use core::f64::consts::PI;
+use core::{fmt, ops};
 
 use nalgebra::Complex;
 use num_traits::{NumOps, One, Zero};
@@ -87,7 +87,7 @@ where
         let final_degree = res.poles.len().max(res.zeros.len());
         let to_add = final_degree - res.zeros.len();
         res.zeros
-            .extend(std::iter::repeat(-Complex::one()).take(to_add));
+            .extend(core::iter::repeat(-Complex::one()).take(to_add));
         res
     }
 
@@ -120,8 +120,8 @@ pub fn biquad<T: Scalar>(transfer_function: Rational<Polynom<T>>) -> Biquad<T, L
 
     let a0 = transfer_function.1.get(2);
     Biquad::new(
-        std::array::from_fn(|i| transfer_function.0.get(2 - i)),
-        std::array::from_fn(|i| transfer_function.1.get(1 - i) / a0),
+        core::array::from_fn(|i| transfer_function.0.get(2 - i)),
+        core::array::from_fn(|i| transfer_function.1.get(1 - i) / a0),
     )
 }
 
@@ -15,16 +15,22 @@
 use nalgebra::Complex;
 use numeric_literals::replace_float_literals;
 use valib_core::dsp::analysis::DspAnalysis;
+use valib_core::dsp::state::DspState;
 use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::math::bilinear_transform;
 use valib_core::Scalar;
 use valib_saturators::{Linear, Saturator};
 
+mod cascade;
 #[cfg(never)]
 pub mod design;
 
+pub use cascade::BiquadCascade;
+
 /// Biquad struct in Transposed Direct Form II. Optionally, a [`Saturator`] instance can be used
 /// to apply waveshaping to the internal states.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Biquad<T, S> {
     na: [T; 2],
     b: [T; 3],
@@ -64,6 +70,14 @@ impl<T: Copy, S> Biquad<T, S> {
     }
 }
 
+impl<T: Scalar, S> Biquad<T, S> {
+    /// Returns the coefficients of the filter, as the numerator `b` and denominator `a` coefficients
+    /// of its transfer function (the leading denominator coefficient is always 1).
+    pub fn coefficients(&self) -> ([T; 3], [T; 2]) {
+        (self.b, self.na.map(T::neg))
+    }
+}
+
 #[profiling::all_functions]
 impl<T: Scalar> Biquad<T, Linear> {
     /// Create a new instance of a Biquad with the provided poles and zeros coefficients.
@@ -77,6 +91,26 @@ impl<T: Scalar> Biquad<T, Linear> {
         }
     }
 
+    /// Create a new Biquad from an analog prototype, using the bilinear transform.
+    ///
+    /// The analog transfer function is given in ascending powers of `s`:
+    /// `H(s) = (b[0] + b[1] s + b[2] s^2) / (a[0] + a[1] s + a[2] s^2)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `b`: Numerator coefficients of the analog transfer function
+    /// * `a`: Denominator coefficients of the analog transfer function
+    /// * `samplerate`: Sample rate of the resulting filter
+    /// * `prewarp`: Frequency (in Hz) at which the digital response exactly matches the analog one.
+    ///     When [`None`], no pre-warping is performed and frequencies get compressed towards
+    ///     Nyquist.
+    ///
+    /// returns: Biquad<T, Linear>
+    pub fn from_analog(b: [T; 3], a: [T; 3], samplerate: T, prewarp: Option<T>) -> Self {
+        let (b, [_, a1, a2]) = bilinear_transform(b, a, samplerate, prewarp);
+        Self::new(b, [a1, a2])
+    }
+
     /// Create a lowpass with the provided frequency cutoff coefficient (normalized where 1 == samplerate) and resonance factor.
     #[replace_float_literals(T::from_f64(literal))]
     pub fn lowpass(fc: T, q: T) -> Self {
@@ -216,7 +250,7 @@ impl<T: Scalar> Biquad<T, Linear> {
         let alpha = sw0 / (2. * q);
 
         let b0 = amp * ((amp + 1.) + (amp - 1.) * cw0 + 2. * amp.simd_sqrt() * alpha);
-        let b1 = -2. * amp * ((amp + 1.) + (amp - 1.) * cw0);
+        let b1 = -2. * amp * ((amp - 1.) + (amp + 1.) * cw0);
         let b2 = amp * ((amp + 1.) + (amp - 1.) * cw0 - (2. * amp.simd_sqrt() * alpha));
 
         let a0 = (amp + 1.) - (amp - 1.) * cw0 + 2. * amp.simd_sqrt() * alpha;
@@ -229,6 +263,24 @@ impl<T: Scalar> Biquad<T, Linear> {
 
 impl<T: Scalar, S: Saturator<T>> DSPMeta for Biquad<T, S> {
     type Sample = T;
+
+    fn reset(&mut self) {
+        self.s = [T::zero(); 2];
+    }
+}
+
+/// The state is made of the internal filter memories, along with the saturators.
+impl<T: Scalar, S: Clone + Saturator<T>> DspState for Biquad<T, S> {
+    type State = ([T; 2], [S; 2]);
+
+    fn snapshot(&self) -> Self::State {
+        (self.s, self.sats.clone())
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.s = state.0;
+        self.sats.clone_from(&state.1);
+    }
 }
 
 #[profiling::all_functions]
@@ -238,7 +290,7 @@ impl<T: Scalar, S: Saturator<T>> DSPProcess<1, 1> for Biquad<T, S> {
     fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
         let x = x[0];
         let in0 = x * self.b[0] + self.s[0];
-        let s_out: [_; 2] = std::array::from_fn(|i| self.sats[i].saturate(in0 / 10.));
+        let s_out: [_; 2] = core::array::from_fn(|i| self.sats[i].saturate(in0 / 10.));
         let in1 = x * self.b[1] + self.s[1] + self.sats[0].saturate(in0 / 10.) * 10. * self.na[0];
         let in2 = x * self.b[2] + self.sats[1].saturate(in0 / 10.) * 10. * self.na[1];
         self.s = [in1, in2];
@@ -270,14 +322,14 @@ mod tests {
         DSPProcessBlock,
     };
     use valib_saturators::clippers::DiodeClipperModel;
-    use valib_saturators::Dynamic;
+    use valib_saturators::{Dynamic, DynamicKind};
 
     #[test]
     fn test_lp_diode_clipper() {
         let samplerate = 1000.0;
         let sat = DiodeClipperModel::new_led(2, 3);
-        let biquad = Biquad::lowpass(10.0 / samplerate, 20.0)
-            .with_saturators(Dynamic::DiodeClipper(sat), Dynamic::DiodeClipper(sat));
+        let sat = Dynamic::new(DynamicKind::DiodeClipper).with_diode_clipper(sat);
+        let biquad = Biquad::lowpass(10.0 / samplerate, 20.0).with_saturators(sat, sat);
         let mut biquad = BlockAdapter(biquad);
 
         let input: [_; 512] =
@@ -287,4 +339,89 @@ mod tests {
 
         insta::assert_csv_snapshot!(output.get_channel(0), { "[]" => insta::rounded_redaction(4) });
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut biquad = Biquad::<f64, _>::bandpass_peak0(0.1, 2.0).with_saturators(
+            Dynamic::new(DynamicKind::Tanh),
+            Dynamic::new(DynamicKind::Tanh),
+        );
+        // Get some internal state to serialize as well
+        for i in 0..16 {
+            biquad.process([i as f64 / 16.0]);
+        }
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        writer.serialize(biquad).unwrap();
+        let data = writer.into_inner().unwrap();
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(&*data);
+        let mut deserialized: Biquad<f64, Dynamic<f64>> =
+            reader.deserialize().next().unwrap().unwrap();
+
+        for i in 0..16 {
+            let x = [(i as f64 / 4.0).sin()];
+            assert_eq!(biquad.process(x), deserialized.process(x));
+        }
+    }
+
+    #[test]
+    fn from_analog_matches_rbj_lowpass() {
+        let samplerate = 48e3;
+        let fc = 2e3;
+        let q = 2.0;
+        let wc = std::f64::consts::TAU * fc;
+
+        // H(s) = wc^2 / (s^2 + wc/Q s + wc^2)
+        let analog = Biquad::from_analog(
+            [wc * wc, 0.0, 0.0],
+            [wc * wc, wc / q, 1.0],
+            samplerate,
+            Some(fc),
+        );
+        let rbj = Biquad::<f64, _>::lowpass(fc / samplerate, q);
+        for f in [20.0, 500.0, 2e3, 8e3, 20e3] {
+            let [[expected]] = rbj.freq_response(samplerate, f);
+            let [[actual]] = analog.freq_response(samplerate, f);
+            assert!(
+                (expected - actual).norm() < 1e-9,
+                "At {f} Hz: expected {expected}, got {actual}"
+            );
+        }
+
+        // Without pre-warping, the cutoff moves below its analog frequency
+        let unwarped = Biquad::from_analog(
+            [wc * wc, 0.0, 0.0],
+            [wc * wc, wc / q, 1.0],
+            samplerate,
+            None,
+        );
+        let [[at_fc]] = unwarped.freq_response(samplerate, fc);
+        assert!(at_fc.norm() < q);
+    }
+
+    #[test]
+    fn snapshot_restores_decay() {
+        let mut biquad = Biquad::<f64, Linear>::lowpass(0.05, 4.0);
+        biquad.process([1.0]);
+        for _ in 0..32 {
+            biquad.process([0.0]);
+        }
+        let state = biquad.snapshot();
+        let expected = (0..64)
+            .map(|_| biquad.process([0.0])[0])
+            .collect::<Vec<_>>();
+        // Ringing must still be going on for the test to be meaningful
+        assert!(expected[0].abs() > 1e-3);
+
+        biquad.restore(&state);
+        let actual = (0..64)
+            .map(|_| biquad.process([0.0])[0])
+            .collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
 }
@@ -0,0 +1,106 @@
+//! # Cached coefficients
+//!
+//! Provides a memoization helper to avoid recomputing filter coefficients when the parameters they
+//! are computed from did not change.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use valib_filters::biquad::Biquad;
+//! use valib_filters::cached::Cached;
+//! let mut filter = Biquad::<f32, _>::lowpass(0.25, 0.707);
+//! let mut coefficients = Cached::new(filter);
+//! // Only computes the coefficients when the cutoff or resonance change
+//! let new_coefficients = coefficients.get_or_update((0.1, 0.707), |&(fc, q)| Biquad::lowpass(fc, q));
+//! filter.update_coefficients(new_coefficients);
+//! ```
+
+/// Memoized value, recomputed only when the key it depends on changes.
+#[derive(Debug, Copy, Clone)]
+pub struct Cached<K, V> {
+    key: Option<K>,
+    value: V,
+}
+
+impl<K, V> Cached<K, V> {
+    /// Create a new cached value, which will be recomputed on the first call to
+    /// [`Self::get_or_update`].
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: Initial value
+    ///
+    /// returns: Cached<K, V>
+    pub fn new(value: V) -> Self {
+        Self { key: None, value }
+    }
+
+    /// Create a new cached value, computed from the given key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Key the value was computed from
+    /// * `value`: Initial value
+    ///
+    /// returns: Cached<K, V>
+    pub fn with_key(key: K, value: V) -> Self {
+        Self {
+            key: Some(key),
+            value,
+        }
+    }
+
+    /// Returns the cached value, without checking if it is up-to-date.
+    pub fn get(&self) -> &V {
+        &self.value
+    }
+
+    /// Forces the next call to [`Self::get_or_update`] to recompute the value.
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+}
+
+impl<K: PartialEq, V> Cached<K, V> {
+    /// Returns the cached value, recomputing it first if the key changed since it was last
+    /// computed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Key the value depends on
+    /// * `compute`: Function computing the value from the key
+    ///
+    /// returns: &V
+    pub fn get_or_update(&mut self, key: K, compute: impl FnOnce(&K) -> V) -> &V {
+        if self.key.as_ref() != Some(&key) {
+            self.value = compute(&key);
+            self.key = Some(key);
+        }
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::biquad::Biquad;
+    use valib_saturators::Linear;
+
+    #[test]
+    fn recomputes_only_on_key_change() {
+        let mut computations = 0;
+        let mut compute = |&(fc, q): &(f32, f32)| {
+            computations += 1;
+            Biquad::<f32, Linear>::lowpass(fc, q)
+        };
+        let mut cached = Cached::new(Biquad::lowpass(0.25, 0.707));
+
+        for _ in 0..64 {
+            cached.get_or_update((0.1, 0.707), &mut compute);
+        }
+        cached.get_or_update((0.2, 0.707), &mut compute);
+        cached.invalidate();
+        cached.get_or_update((0.2, 0.707), &mut compute);
+        assert_eq!(3, computations);
+    }
+}
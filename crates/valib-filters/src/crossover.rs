@@ -0,0 +1,134 @@
+//! # Crossovers
+//!
+//! Provides Linkwitz-Riley crossovers, splitting a signal into frequency bands which sum back to an
+//! allpass response, for multiband processing.
+use numeric_literals::replace_float_literals;
+
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_saturators::Linear;
+
+use crate::biquad::Biquad;
+
+/// 4th-order Linkwitz-Riley crossover, splitting the input into a low and a high band.
+///
+/// Each band is made of two cascaded 2nd-order Butterworth filters, such that both bands are
+/// -6 dB at the crossover frequency and in phase with each other. Their sum is therefore flat in
+/// magnitude, and has the phase response of the 2nd-order allpass given by [`Self::allpass`].
+#[derive(Debug, Copy, Clone)]
+pub struct LinkwitzRiley<T> {
+    samplerate: T,
+    fc: T,
+    lowpass: [Biquad<T, Linear>; 2],
+    highpass: [Biquad<T, Linear>; 2],
+}
+
+impl<T: Scalar> LinkwitzRiley<T> {
+    /// Create a new Linkwitz-Riley crossover.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the crossover
+    /// * `fc`: Crossover frequency (Hz)
+    ///
+    /// returns: LinkwitzRiley<T>
+    pub fn new(samplerate: T, fc: T) -> Self {
+        let mut this = Self {
+            samplerate,
+            fc,
+            lowpass: [Biquad::new([T::zero(); 3], [T::zero(); 2]); 2],
+            highpass: [Biquad::new([T::zero(); 3], [T::zero(); 2]); 2],
+        };
+        this.update_coefficients();
+        this
+    }
+
+    /// Crossover frequency (Hz)
+    pub fn cutoff(&self) -> T {
+        self.fc
+    }
+
+    /// Sets the crossover frequency (Hz). The state of the filters is kept.
+    pub fn set_cutoff(&mut self, fc: T) {
+        self.fc = fc;
+        self.update_coefficients();
+    }
+
+    /// Allpass filter with the same phase response as the sum of both bands, used to keep other
+    /// signals in phase with the output of this crossover.
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn allpass(&self) -> Biquad<T, Linear> {
+        Biquad::allpass(self.fc / self.samplerate, 0.5.simd_sqrt())
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn update_coefficients(&mut self) {
+        let fc = self.fc / self.samplerate;
+        let q = 0.5.simd_sqrt();
+        let lowpass = Biquad::lowpass(fc, q);
+        let highpass = Biquad::highpass(fc, q);
+        for biquad in &mut self.lowpass {
+            biquad.update_coefficients(&lowpass);
+        }
+        for biquad in &mut self.highpass {
+            biquad.update_coefficients(&highpass);
+        }
+    }
+}
+
+impl<T: Scalar> DSPMeta for LinkwitzRiley<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.update_coefficients();
+    }
+
+    fn reset(&mut self) {
+        for biquad in self.lowpass.iter_mut().chain(&mut self.highpass) {
+            biquad.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 2> for LinkwitzRiley<T> {
+    /// Split the input, returning the low band and the high band.
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 2] {
+        let low = self.lowpass.iter_mut().fold(x, |x, f| f.process(x));
+        let high = self.highpass.iter_mut().fold(x, |x, f| f.process(x));
+        [low[0], high[0]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linkwitz_riley_bands_sum_to_allpass() {
+        const SAMPLERATE: f64 = 48e3;
+        let mut crossover = LinkwitzRiley::new(SAMPLERATE, 1000.0);
+        let mut allpass = crossover.allpass();
+        for i in 0..1000 {
+            let x = if i == 0 { 1.0 } else { 0.0 };
+            let [low, high] = crossover.process([x]);
+            let [expected] = allpass.process([x]);
+            assert!((low + high - expected).abs() < 1e-9, "Sample {i}");
+        }
+
+        // Both bands are -6 dB at the crossover frequency
+        crossover.reset();
+        let (low, high) = (0..9600).fold((0f64, 0f64), |(low, high), i| {
+            let x = f64::sin(std::f64::consts::TAU * 1000.0 * i as f64 / SAMPLERATE);
+            let [l, h] = crossover.process([x]);
+            if i < 4800 {
+                (low, high)
+            } else {
+                (low.max(l.abs()), high.max(h.abs()))
+            }
+        });
+        assert!((low - 0.5).abs() < 1e-3, "Low band amplitude of {low}");
+        assert!((high - 0.5).abs() < 1e-3, "High band amplitude of {high}");
+    }
+}
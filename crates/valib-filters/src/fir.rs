@@ -1,158 +1,173 @@
-//! Module implementing FIR filters by way of convolution.
-use std::{collections::VecDeque, ops};
+//! # FIR filters
+//!
+//! Provides FIR filters with arbitrary taps, computed by direct convolution in the time domain.
+//! This has no latency of its own and is cheap for short impulse responses; longer ones are better
+//! served by FFT convolution.
+use alloc::boxed::Box;
+use alloc::vec;
 
-use crate::dsp::DSPMeta;
-use crate::dsp::DSPProcess;
-use numeric_literals::replace_float_literals;
+use nalgebra::Complex;
+use nalgebra::SimdBool;
+use num_traits::Zero;
+
+use valib_core::dsp::analysis::DspAnalysis;
+use valib_core::dsp::{DSPMeta, DSPProcess};
 use valib_core::Scalar;
 
-fn slice_add<T: Copy + ops::Add<T, Output = T>>(in1: &[T], in2: &[T], out: &mut [T]) {
-    let len = in1.len().min(in2.len()).min(out.len());
-    for i in 0..len {
-        out[i] = in1[i] + in2[i];
-    }
-}
+/// Number of independent partial sums of the dot product of [`FirFilter`]
+const ACCUMULATORS: usize = 4;
 
-fn slice_add_to<T: Copy + ops::AddAssign<T>>(out: &mut [T], inp: &[T]) {
-    for (o, i) in out.iter_mut().zip(inp.iter().copied()) {
-        *o += i;
-    }
+/// FIR filter with an arbitrary set of taps.
+///
+/// The input history is stored twice in a row, such that the last `N` samples are always
+/// contiguous in memory. The convolution is then a dot product, summed into several independent
+/// accumulators which break the dependency chain between additions, and let the compiler vectorize
+/// the inner loop.
+///
+/// Filters with symmetric or anti-symmetric taps are linear-phase, and report their group delay of
+/// `(N - 1) / 2` samples as latency. For an even number of taps, the group delay falls between two
+/// samples, and is rounded up. Other filters report no latency.
+#[derive(Debug, Clone)]
+pub struct FirFilter<T> {
+    /// Taps, in reverse order to line up with the history
+    coeffs: Box<[T]>,
+    history: Box<[T]>,
+    pos: usize,
+    latency: usize,
 }
 
-fn slice_sub_to<T: Copy + ops::SubAssign<T>>(out: &mut [T], inp: &[T]) {
-    for (o, i) in out.iter_mut().zip(inp.iter().copied()) {
-        *o -= i;
+impl<T: Scalar> FirFilter<T> {
+    /// Create a new FIR filter from its taps, that is its impulse response.
+    ///
+    /// # Arguments
+    ///
+    /// * `coeffs`: Taps of the filter, which must not be empty
+    ///
+    /// returns: FirFilter<T>
+    pub fn new(coeffs: &[T]) -> Self {
+        assert!(!coeffs.is_empty(), "FIR filters need at least one tap");
+        let len = coeffs.len();
+        let linear_phase = (0..len / 2).all(|i| {
+            let (a, b) = (coeffs[i], coeffs[len - 1 - i]);
+            a.simd_eq(b).all() || a.simd_eq(-b).all()
+        });
+        Self {
+            coeffs: coeffs.iter().rev().copied().collect(),
+            history: vec![T::zero(); 2 * len].into_boxed_slice(),
+            pos: 0,
+            latency: if linear_phase { len / 2 } else { 0 },
+        }
     }
-}
 
-/// Perform arbitrary-length convolution with an O(n^log2(3)) complexity
-/// Translated from Taken from https://www.musicdsp.org/en/latest/Filters/65-time-domain-convolution-with-o-n-log2-3.html
-/// in1, in2: input signals (of size n)
-/// out: output signal (of size 2n)
-/// buffer: staging buffer (of size 2n)
-#[replace_float_literals(T::from_f64(literal))]
-#[allow(unused)]
-fn convolution<T: Scalar>(in1: &[T], in2: &[T], out: &mut [T], buffer: &mut [T]) {
-    let size = in1.len();
-    debug_assert_eq!(size, in2.len());
-    debug_assert_eq!(out.len(), 2 * size);
-    debug_assert_eq!(buffer.len(), 2 * size);
-
-    out.fill(0.0);
-
-    if size == 1 {
-        out[0] = in1[0] * in2[0];
-        return;
+    /// Create a new linear-phase lowpass FIR filter using the windowed-sinc method.
+    ///
+    /// # Arguments
+    ///
+    /// * `fc`: Cutoff frequency, normalized to the sample rate (0.5 being the Nyquist frequency)
+    /// * `bandwidth`: Width of the transition band, normalized to the sample rate
+    ///
+    /// returns: FirFilter<T>
+    pub fn lowpass(fc: T, bandwidth: f64) -> Self {
+        Self::new(&kernels::windowed_sinc(fc, bandwidth))
     }
 
-    // first calculate (A1 + A2 * z^-n)*(B1 + B2 * z^-n)*z^n
-    {
-        let (in11, in12) = in1.split_at(size / 2);
-        slice_add(in11, in12, buffer);
-    }
-    {
-        let (in21, in22) = in2.split_at(size / 2);
-        slice_add(in21, in22, &mut buffer[size / 2..]);
-    }
-    {
-        let (tmp1, tmp2) = buffer.split_at_mut(size / 2);
-        let (tmp2, tmp3) = tmp2.split_at_mut(size);
-        convolution(tmp1, tmp2, &mut out[size / 2..], tmp3);
+    /// Number of taps of the filter
+    pub fn len(&self) -> usize {
+        self.coeffs.len()
     }
 
-    // then add A1*B1 and substract A1*B1*z^n
-    {
-        let (tmp1, tmp2) = buffer.split_at_mut(size);
-        convolution(
-            &in1[..size / 2],
-            &in2[..size / 2],
-            &mut tmp1[..size / 2],
-            &mut tmp2[..size / 2],
-        );
-        slice_add_to(out, tmp1);
-        slice_sub_to(&mut out[size / 2..], &tmp1[..size / 2]);
-    }
-
-    // then add A2*B2 and substract A2*B2*z^-n
-    {
-        let (tmp1, tmp2) = buffer.split_at_mut(size);
-        convolution(
-            &in1[size / 2..],
-            &in2[size / 2..],
-            &mut tmp1[..size / 2],
-            &mut tmp2[..size / 2],
-        );
-        slice_add_to(&mut out[size..], tmp1);
-        slice_sub_to(&mut out[size / 2..], tmp1);
+    /// Returns true if the filter has no taps. This is never the case, as filters are constructed
+    /// with at least one tap.
+    pub fn is_empty(&self) -> bool {
+        self.coeffs.is_empty()
     }
 }
 
-pub struct Fir<T> {
-    kernel: Box<[T]>,
-    memory: VecDeque<T>,
-    kernel_latency: usize,
-}
+impl<T: Scalar> DSPMeta for FirFilter<T> {
+    type Sample = T;
 
-impl<T: Scalar> Fir<T> {
-    pub fn lowpass(fc: T, bandwidth: f64) -> Self {
-        let kernel = Vec::from(kernels::windowed_sinc(fc, bandwidth));
-        let len = kernel.len();
-        Self::new(kernel, len / 2)
+    fn latency(&self) -> usize {
+        self.latency
     }
 
-    pub fn new(kernel: impl IntoIterator<Item = T>, kernel_latency: usize) -> Self {
-        let kernel = Box::from_iter(kernel);
-        let memory = VecDeque::from(vec![T::from_f64(0.0); kernel.len()]);
-        Self {
-            kernel,
-            memory,
-            kernel_latency,
-        }
+    fn reset(&mut self) {
+        self.history.fill(T::zero());
+        self.pos = 0;
     }
 }
 
-impl<T: Scalar> DSPMeta for Fir<T> {
-    type Sample = T;
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for FirFilter<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let len = self.coeffs.len();
+        self.history[self.pos] = x;
+        self.history[self.pos + len] = x;
+        self.pos = (self.pos + 1) % len;
 
-    fn latency(&self) -> usize {
-        self.kernel_latency
-    }
-
-    fn reset(&mut self) {
-        self.memory = VecDeque::from(vec![T::from_f64(0.0); self.kernel.len()]);
+        // From oldest to newest sample
+        let window = &self.history[self.pos..self.pos + len];
+        let coeffs = self.coeffs.chunks_exact(ACCUMULATORS);
+        let samples = window.chunks_exact(ACCUMULATORS);
+        let mut acc = [T::zero(); ACCUMULATORS];
+        for (&c, &x) in coeffs.remainder().iter().zip(samples.remainder()) {
+            acc[0] += c * x;
+        }
+        for (c, x) in coeffs.zip(samples) {
+            for i in 0..ACCUMULATORS {
+                acc[i] += c[i] * x[i];
+            }
+        }
+        [acc.into_iter().fold(T::zero(), |sum, a| sum + a)]
     }
 }
 
-impl<T: Scalar> DSPProcess<1, 1> for Fir<T> {
-    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
-        let [x] = x;
-        self.memory.pop_front();
-        self.memory.push_back(x);
-        let y = self
-            .kernel
-            .iter()
-            .copied()
-            .zip(self.memory.iter().copied())
-            .fold(T::from_f64(0.0), |acc, (a, b)| acc + a * b);
-        [y]
+impl<T: Scalar> DspAnalysis<1, 1> for FirFilter<T> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let zinv = z.powi(-1);
+        let (y, _) = self.coeffs.iter().rev().fold(
+            (Complex::zero(), Complex::new(T::one(), T::zero())),
+            |(y, zk), &c| (y + zk.scale(c), zk * zinv),
+        );
+        [[y]]
     }
 }
 
+/// FIR filter kernel design.
 pub mod kernels {
+    use alloc::boxed::Box;
+    use alloc::vec;
+
     use numeric_literals::replace_float_literals;
 
     use valib_core::Scalar;
 
+    /// Fill the slice with a lowpass kernel using the windowed-sinc method, with a Blackman window.
+    /// The kernel is normalized for unity gain at DC.
+    ///
+    /// # Arguments
+    ///
+    /// * `fc`: Cutoff frequency, normalized to the sample rate
+    /// * `slice`: Kernel to fill, which must have an odd length
+    ///
+    /// returns: ()
     #[replace_float_literals(T::from_f64(literal))]
     pub fn windowed_sinc_in_place<T: Scalar>(fc: T, slice: &mut [T]) {
         debug_assert_eq!(slice.len() % 2, 1);
-        let width = T::from_f64(slice.len() as _);
-        let half_width = 0.5 * width;
-        for (i, s) in slice.iter_mut().enumerate() {
-            let i = T::from_f64(i as _);
-            *s = T::simd_sin(T::simd_two_pi() * fc * (i - half_width)) / (i - half_width);
-            *s *= 0.42 - 0.5 * T::simd_cos(T::simd_two_pi() * i / width)
-                + 0.08 * T::simd_cos(2.0 * T::simd_two_pi() * i / width);
+        let width = T::from_f64((slice.len() - 1) as _);
+        let center = (slice.len() - 1) / 2;
+        // Only compute one half and mirror it, so that the kernel is exactly symmetric
+        for i in 0..=center {
+            let mut s = if i == center {
+                T::simd_two_pi() * fc
+            } else {
+                let t = T::from_f64(i as f64 - center as f64);
+                T::simd_sin(T::simd_two_pi() * fc * t) / t
+            };
+            let x = T::from_f64(i as _);
+            s *= 0.42 - 0.5 * T::simd_cos(T::simd_two_pi() * x / width)
+                + 0.08 * T::simd_cos(2.0 * T::simd_two_pi() * x / width);
+            slice[i] = s;
+            slice[2 * center - i] = s;
         }
         // Normalization
         let magnitude = slice.iter().copied().fold(0.0, |a, b| a + b);
@@ -161,6 +176,14 @@ pub mod kernels {
         }
     }
 
+    /// Create a lowpass kernel using the windowed-sinc method, with a Blackman window.
+    ///
+    /// # Arguments
+    ///
+    /// * `fc`: Cutoff frequency, normalized to the sample rate
+    /// * `bandwidth`: Width of the transition band, normalized to the sample rate
+    ///
+    /// returns: Box<[T]>
     pub fn windowed_sinc<T: Scalar>(fc: T, bandwidth: f64) -> Box<[T]> {
         let mut length = (4.0 / bandwidth) as usize;
         if length % 2 == 0 {
@@ -175,25 +198,85 @@ pub mod kernels {
 
 #[cfg(test)]
 mod tests {
-    use crate::dsp::buffer::AudioBuffer;
-    use crate::dsp::{BlockAdapter, DSPProcessBlock};
-
     use super::*;
 
     #[test]
-    fn test_fir_direct() {
-        let input = Box::from_iter([1.0, 0.0, 0.0, 0.0].into_iter().cycle().take(16));
-        let input = AudioBuffer::new([input]).unwrap();
-        let mut output = input.clone();
-        let mut fir = BlockAdapter(Fir::new([0.25, 0.5, 0.25], 1));
-
-        output.fill(0.0);
-        fir.process_block(input.as_ref(), output.as_mut());
-        insta::assert_csv_snapshot!(output.get_channel(0), { "[]" => insta::rounded_redaction(4) })
+    fn fir_direct() {
+        let mut fir = FirFilter::new(&[0.25, 0.5, 0.25]);
+        assert_eq!(1, fir.latency());
+        let output = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0].map(|x| fir.process([x])[0]);
+        assert_eq!([0.25, 0.5, 0.25, 0.0, 0.25, 0.5], output);
+
+        let fir = FirFilter::new(&[1.0, 0.5, 0.25]);
+        assert_eq!(0, fir.latency());
+    }
+
+    #[test]
+    fn fir_even_length_latency() {
+        // Group delay of 0.5 sample
+        let mut fir = FirFilter::new(&[0.5, -0.5]);
+        assert_eq!(1, fir.latency());
+        let output = [1.0, 0.0, 0.0].map(|x| fir.process([x])[0]);
+        assert_eq!([0.5, -0.5, 0.0], output);
+
+        // Group delay of 2.5 samples
+        let fir = FirFilter::new(&[0.1, 0.2, 0.3, 0.3, 0.2, 0.1]);
+        assert_eq!(3, fir.latency());
+    }
+
+    #[test]
+    fn fir_matches_direct_convolution() {
+        // Longer than the accumulators, with a remainder
+        let taps = (0..11).map(|i| (i as f64 * 0.7).sin()).collect::<Vec<_>>();
+        let input = (0..40).map(|i| (i as f64 * 1.3).cos()).collect::<Vec<_>>();
+        let mut fir = FirFilter::new(&taps);
+        for (n, &x) in input.iter().enumerate() {
+            let [y] = fir.process([x]);
+            let expected = (0..=n.min(taps.len() - 1))
+                .map(|k| taps[k] * input[n - k])
+                .sum::<f64>();
+            assert!(
+                (y - expected).abs() < 1e-12,
+                "Sample {n}: {y} != {expected}"
+            );
+        }
     }
 
     #[test]
-    fn test_kernel_lowpass() {
-        insta::assert_csv_snapshot!(&kernels::windowed_sinc(0.1, 0.1), { "[]" => insta::rounded_redaction(5) });
+    fn windowed_sinc_lowpass_response() {
+        const SAMPLERATE: f32 = 48e3;
+        let mut fir = FirFilter::<f32>::lowpass(0.05, 0.02);
+        assert_eq!(201, fir.len());
+        assert_eq!(100, fir.latency());
+
+        // Peak amplitude of the steady-state response to a sine at the given frequency
+        let mut amplitude = |f: f32| {
+            fir.reset();
+            let output = (0..2000)
+                .map(|i| {
+                    let x = f32::sin(std::f32::consts::TAU * f * i as f32 / SAMPLERATE);
+                    fir.process([x])[0]
+                })
+                .collect::<Vec<_>>();
+            output[fir.len()..]
+                .iter()
+                .fold(0.0, |max, y| y.abs().max(max))
+        };
+
+        // Cutoff at 2.4 kHz, transition band of 960 Hz
+        for f in [100.0, 500.0, 1000.0, 1500.0] {
+            let a = amplitude(f);
+            assert!((a - 1.0).abs() < 0.01, "Passband gain of {a} at {f} Hz");
+        }
+        for f in [4000.0, 8000.0, 15000.0] {
+            let a = amplitude(f);
+            assert!(a < 1e-3, "Stopband gain of {a} at {f} Hz");
+        }
+        let a = amplitude(2400.0);
+        assert!((a - 0.5).abs() < 0.05, "Cutoff gain of {a}");
+
+        // The response matches the analysis
+        let [[h]] = fir.freq_response(SAMPLERATE, 2400.0);
+        assert!((h.norm() - a).abs() < 0.01);
     }
 }
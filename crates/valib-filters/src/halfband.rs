@@ -1,11 +1,17 @@
-//! # Polyphase filter
+//! # Half-band filters
 //!
-//! Port of <https://www.musicdsp.org/en/latest/Filters/39-polyphase-filters.html>.
+//! The polyphase IIR filters are a port of
+//! <https://www.musicdsp.org/en/latest/Filters/39-polyphase-filters.html>. Linear-phase FIR
+//! half-band filters are also provided, at the cost of higher latency.
 
-use num_traits::Zero;
+use nalgebra::Complex;
+use num_traits::{Float, One, Zero};
 
+use valib_core::dsp::analysis::DspAnalysis;
 use valib_core::dsp::blocks::Series;
+use valib_core::dsp::state::DspState;
 use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::math::window;
 use valib_core::Scalar;
 
 /// Specialized 2nd-order allpass filter.
@@ -37,6 +43,18 @@ impl<T: Scalar> DSPMeta for Allpass<T> {
     }
 }
 
+impl<T: Scalar> DspState for Allpass<T> {
+    type State = ([T; 3], [T; 3]);
+
+    fn snapshot(&self) -> Self::State {
+        (self.x, self.y)
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        (self.x, self.y) = *state;
+    }
+}
+
 #[profiling::all_functions]
 impl<T: Scalar> DSPProcess<1, 1> for Allpass<T> {
     fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
@@ -47,12 +65,19 @@ impl<T: Scalar> DSPProcess<1, 1> for Allpass<T> {
     }
 }
 
+impl<T: Scalar> DspAnalysis<1, 1> for Allpass<T> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let z2 = z.powi(-2);
+        [[(z2 + self.a) / (z2.scale(self.a) + T::one())]]
+    }
+}
+
 impl<T: Zero> Allpass<T> {
     pub fn new(a: T) -> Self {
         Self {
             a,
-            x: std::array::from_fn(|_| T::zero()),
-            y: std::array::from_fn(|_| T::zero()),
+            x: core::array::from_fn(|_| T::zero()),
+            y: core::array::from_fn(|_| T::zero()),
         }
     }
 }
@@ -63,13 +88,22 @@ pub struct HalfbandFilter<T, const ORDER: usize> {
     filter_a: Series<[Allpass<T>; ORDER]>,
     filter_b: Series<[Allpass<T>; ORDER]>,
     y0: T,
+    group_delay: f64,
 }
 
 impl<T: Scalar, const ORDER: usize> DSPMeta for HalfbandFilter<T, ORDER> {
     type Sample = T;
 
+    /// Group delay of the filter at DC, rounded to the nearest sample. The filter is not
+    /// linear-phase, and therefore the group delay increases when approaching the cutoff.
     fn latency(&self) -> usize {
-        self.filter_a.latency() + self.filter_b.latency()
+        Float::round(self.group_delay) as usize
+    }
+
+    fn reset(&mut self) {
+        self.filter_a.reset();
+        self.filter_b.reset();
+        self.y0.set_zero();
     }
 }
 
@@ -82,14 +116,62 @@ impl<T: Scalar, const ORDER: usize> DSPProcess<1, 1> for HalfbandFilter<T, ORDER
     }
 }
 
+/// The two allpass paths are combined as `(A(z) + z^-1 B(z)) / 2`. As the filter is
+/// power-complementary, the magnitude response at the half-band cutoff (a quarter of the sample rate)
+/// is -3 dB.
+impl<T: Scalar, const ORDER: usize> DspAnalysis<1, 1> for HalfbandFilter<T, ORDER> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let [[a]] = self.filter_a.h_z(z);
+        let [[b]] = self.filter_b.h_z(z);
+        [[(a + b / z).scale(T::from_f64(0.5))]]
+    }
+}
+
+/// Memories of a [`HalfbandFilter`], as captured by [`DspState::snapshot`]
+pub type HalfbandState<T, const ORDER: usize> =
+    ([([T; 3], [T; 3]); ORDER], [([T; 3], [T; 3]); ORDER], T);
+
+/// The state is made of the memories of the allpass sections; the coefficients are kept.
+impl<T: Scalar, const ORDER: usize> DspState for HalfbandFilter<T, ORDER> {
+    type State = HalfbandState<T, ORDER>;
+
+    fn snapshot(&self) -> Self::State {
+        (
+            self.filter_a.0.each_ref().map(Allpass::snapshot),
+            self.filter_b.0.each_ref().map(Allpass::snapshot),
+            self.y0,
+        )
+    }
+
+    fn restore(&mut self, (a, b, y0): &Self::State) {
+        for (filter, state) in self.filter_a.0.iter_mut().zip(a) {
+            filter.restore(state);
+        }
+        for (filter, state) in self.filter_b.0.iter_mut().zip(b) {
+            filter.restore(state);
+        }
+        self.y0 = *y0;
+    }
+}
+
 impl<T: Scalar, const ORDER: usize> HalfbandFilter<T, ORDER> {
-    fn from_coeffs(k_a: [T; ORDER], k_b: [T; ORDER]) -> Self {
+    fn from_coeffs(k_a: [f64; ORDER], k_b: [f64; ORDER]) -> Self {
+        // Each allpass section H(z) = (a + z^-2) / (1 + a z^-2) has a group delay of
+        // 2 (1 - a) / (1 + a) samples at DC; both paths are summed, so only the first one is needed
+        let group_delay = k_a.iter().map(|a| 2.0 * (1.0 - a) / (1.0 + a)).sum::<f64>();
         Self {
-            filter_a: Series(std::array::from_fn(|i| Allpass::new(k_a[i]))),
-            filter_b: Series(std::array::from_fn(|i| Allpass::new(k_b[i]))),
+            filter_a: Series(k_a.map(|a| Allpass::new(T::from_f64(a)))),
+            filter_b: Series(k_b.map(|a| Allpass::new(T::from_f64(a)))),
             y0: T::zero(),
+            group_delay,
         }
     }
+
+    /// Group delay of the filter at DC, in samples. Unlike [`DSPMeta::latency`], this is not
+    /// rounded to a whole number of samples.
+    pub fn group_delay(&self) -> f64 {
+        self.group_delay
+    }
 }
 
 /// Construct a steep half-band filter of order 12
@@ -102,14 +184,14 @@ pub fn steep_order12<T: Scalar>() -> HalfbandFilter<T, 6> {
         , 0.769741833862266
         , 0.8922608180038789
         , 0.962094548378084
-        ].map(T::from_f64),
+        ],
         [ 0.13654762463195771
         , 0.42313861743656667
         , 0.6775400499741616
         , 0.839889624849638
         , 0.9315419599631839
         , 0.9878163707328971
-        ].map(T::from_f64),
+        ],
     )
 }
 
@@ -122,12 +204,220 @@ pub fn steep_order10<T: Scalar>() -> HalfbandFilter<T, 5> {
 		, 0.6725475931034693
 		, 0.8590884928249939
 		, 0.9540209867860787
-		].map(T::from_f64),
+		],
         [ 0.18621906251989334
 		, 0.529951372847964
 		, 0.7810257527489514
 		, 0.9141815687605308
 		, 0.985475023014907
-        ].map(T::from_f64),
+        ],
     )
 }
+
+/// Linear-phase FIR half-band filter with `N` taps.
+///
+/// Linear phase comes at the cost of latency, which is always `(N - 1) / 2` samples.
+#[derive(Debug, Clone, Copy)]
+pub struct FirHalfbandFilter<T, const N: usize> {
+    coeffs: [T; N],
+    history: [T; N],
+    pos: usize,
+}
+
+impl<T: Scalar, const N: usize> DSPMeta for FirHalfbandFilter<T, N> {
+    type Sample = T;
+
+    fn latency(&self) -> usize {
+        (N - 1) / 2
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(T::zero());
+        self.pos = 0;
+    }
+}
+
+/// Memories of a [`FirHalfbandFilter`], as captured by [`DspState::snapshot`]
+pub type FirHalfbandState<T, const N: usize> = ([T; N], usize);
+
+/// The state is made of the delay line memory; the coefficients are kept.
+impl<T: Scalar, const N: usize> DspState for FirHalfbandFilter<T, N> {
+    type State = FirHalfbandState<T, N>;
+
+    fn snapshot(&self) -> Self::State {
+        (self.history, self.pos)
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        (self.history, self.pos) = *state;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, const N: usize> DSPProcess<1, 1> for FirHalfbandFilter<T, N> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        self.pos = (self.pos + 1) % N;
+        self.history[self.pos] = x;
+        let mut y = T::zero();
+        for (k, c) in self.coeffs.iter().copied().enumerate() {
+            y += c * self.history[(self.pos + N - k) % N];
+        }
+        [y]
+    }
+}
+
+/// As the filter is amplitude-complementary, the magnitude response at the half-band cutoff (a
+/// quarter of the sample rate) is -6 dB.
+impl<T: Scalar, const N: usize> DspAnalysis<1, 1> for FirHalfbandFilter<T, N> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let zinv = z.powi(-1);
+        let (y, _) = self
+            .coeffs
+            .iter()
+            .fold((Complex::zero(), Complex::one()), |(y, zk), &c| {
+                (y + zk.scale(c), zk * zinv)
+            });
+        [[y]]
+    }
+}
+
+impl<T: Scalar, const N: usize> FirHalfbandFilter<T, N> {
+    /// Group delay of the filter, in samples. As the filter is linear-phase, it is the same at all
+    /// frequencies, and always a whole number of samples.
+    pub fn group_delay(&self) -> f64 {
+        (N - 1) as f64 / 2.0
+    }
+
+    /// Design a new half-band FIR filter using the windowed-sinc method and a Kaiser window.
+    ///
+    /// # Arguments
+    ///
+    /// * `beta`: Kaiser window shape parameter, trading stopband attenuation for transition
+    ///     width.
+    ///
+    /// returns: FirHalfbandFilter<T, { N }>
+    pub fn kaiser(beta: f64) -> Self {
+        assert!(
+            N % 2 == 1,
+            "Half-band FIR filters need an odd number of taps"
+        );
+        let center = (N - 1) as f64 / 2.0;
+        let mut window = [0.0; N];
+        window::kaiser(&mut window, beta);
+        let coeffs = core::array::from_fn::<_, N, _>(|i| {
+            let t = i as f64 - center;
+            let sinc = if t == 0.0 {
+                0.5
+            } else {
+                Float::sin(core::f64::consts::FRAC_PI_2 * t) / (core::f64::consts::PI * t)
+            };
+            sinc * window[i]
+        });
+        let sum = coeffs.iter().sum::<f64>();
+        Self {
+            coeffs: coeffs.map(|c| T::from_f64(c / sum)),
+            history: [T::zero(); N],
+            pos: 0,
+        }
+    }
+}
+
+/// Construct a linear-phase FIR half-band filter of order 62 (63 taps), with around 80 dB of
+/// stopband attenuation.
+pub fn linear_phase_order62<T: Scalar>() -> FirHalfbandFilter<T, 63> {
+    FirHalfbandFilter::kaiser(8.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Magnitude response of the filter (dB), with the sample rate normalized to 1
+    fn magnitude_db(filter: &impl DspAnalysis<1, 1, Sample = f64>, f: f64) -> f64 {
+        let [[h]] = filter.freq_response(1.0, f);
+        20.0 * h.norm().log10()
+    }
+
+    /// Maximum magnitude over the given normalized frequency range (dB)
+    fn max_magnitude_db(
+        filter: &impl DspAnalysis<1, 1, Sample = f64>,
+        start: f64,
+        end: f64,
+    ) -> f64 {
+        (0..=200)
+            .map(|i| magnitude_db(filter, start + (end - start) * i as f64 / 200.0))
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    #[test]
+    fn iir_halfband_response() {
+        let filter = steep_order12::<f64>();
+        assert!(magnitude_db(&filter, 0.01).abs() < 1e-6);
+        let cutoff = magnitude_db(&filter, 0.25);
+        assert!((cutoff + 3.01).abs() < 0.01, "Cutoff at {cutoff} dB");
+        let stopband = max_magnitude_db(&filter, 0.3, 0.5);
+        assert!(stopband < -100.0, "Stopband at {stopband} dB");
+    }
+
+    #[test]
+    fn fir_halfband_response() {
+        let filter = linear_phase_order62::<f64>();
+        assert!(magnitude_db(&filter, 0.01).abs() < 1e-3);
+        let cutoff = magnitude_db(&filter, 0.25);
+        assert!((cutoff + 6.02).abs() < 0.01, "Cutoff at {cutoff} dB");
+        let stopband = max_magnitude_db(&filter, 0.3, 0.5);
+        assert!(stopband < -80.0, "Stopband at {stopband} dB");
+    }
+
+    fn check_snapshot_round_trip<P: DspState + DSPProcess<1, 1, Sample = f64>>(mut filter: P) {
+        filter.process([1.0]);
+        let state = filter.snapshot();
+        let expected = (0..64)
+            .map(|_| filter.process([0.0])[0])
+            .collect::<Vec<_>>();
+        assert!(expected.iter().any(|y| y.abs() > 1e-3));
+
+        filter.process([-1.0]);
+        filter.restore(&state);
+        let actual = (0..64)
+            .map(|_| filter.process([0.0])[0])
+            .collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        check_snapshot_round_trip(steep_order12::<f64>());
+        check_snapshot_round_trip(linear_phase_order62::<f64>());
+    }
+
+    /// Index of the peak of the impulse response of the filter
+    fn impulse_peak(mut filter: impl DSPProcess<1, 1, Sample = f64>) -> usize {
+        (0..256)
+            .map(|i| filter.process([if i == 0 { 1.0 } else { 0.0 }])[0].abs())
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn latency_matches_impulse_peak() {
+        let check = |latency: usize, peak: usize| {
+            assert!(
+                latency.abs_diff(peak) <= 1,
+                "Latency {latency}, peak at {peak}"
+            );
+        };
+        check(
+            steep_order10::<f64>().latency(),
+            impulse_peak(steep_order10()),
+        );
+        check(
+            steep_order12::<f64>().latency(),
+            impulse_peak(steep_order12()),
+        );
+        let fir = linear_phase_order62::<f64>();
+        assert_eq!(fir.latency(), impulse_peak(fir));
+    }
+}
@@ -0,0 +1,148 @@
+//! # Hilbert transformer
+//!
+//! Provides an IIR Hilbert transformer, turning a real signal into its analytic signal. The
+//! design is a port of Olli Niemitalo's allpass pair, from
+//! <https://yehar.com/blog/?p=368>.
+use nalgebra::Complex;
+
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+/// Squared coefficients of the allpass filters of the real part
+const COEFFS_RE: [f64; 4] = [
+    0.4021921162426,
+    0.8561710882420,
+    0.9722909545651,
+    0.9952884791278,
+];
+/// Squared coefficients of the allpass filters of the imaginary part
+const COEFFS_IM: [f64; 4] = [0.6923878, 0.9360654322959, 0.9882295226860, 0.9987488452737];
+
+/// Allpass filter in `z^-2`, with transfer function `(a - z^-2) / (1 - a z^-2)`.
+#[derive(Debug, Copy, Clone)]
+struct Allpass2<T> {
+    a: T,
+    /// Last two inputs, most recent first
+    x: [T; 2],
+    /// Last two outputs, most recent first
+    y: [T; 2],
+}
+
+impl<T: Scalar> Allpass2<T> {
+    fn new(coeff: f64) -> Self {
+        Self {
+            a: T::from_f64(coeff * coeff),
+            x: [T::zero(); 2],
+            y: [T::zero(); 2],
+        }
+    }
+
+    fn process(&mut self, x: T) -> T {
+        let y = self.a * (x + self.y[1]) - self.x[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+
+    fn reset(&mut self) {
+        self.x = [T::zero(); 2];
+        self.y = [T::zero(); 2];
+    }
+}
+
+/// Hilbert transformer, producing the analytic signal of its input as a pair of real and
+/// imaginary parts.
+///
+/// Both outputs are phase-shifted versions of the input, with the imaginary part lagging the real
+/// part by 90 degrees. The phase difference is within 0.7 degrees of 90 degrees from 0.1% of the
+/// sample rate up to the Nyquist frequency, which makes it suitable for frequency shifting or
+/// envelope detection. The real part is not the input itself, as it is also phase-shifted.
+///
+/// The outputs are `[real, imaginary]`.
+#[derive(Debug, Copy, Clone)]
+pub struct Hilbert<T> {
+    path_re: [Allpass2<T>; 4],
+    path_im: [Allpass2<T>; 4],
+    delay_im: T,
+}
+
+impl<T: Scalar> Default for Hilbert<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Scalar> Hilbert<T> {
+    /// Create a new Hilbert transformer. The design is normalized to the sample rate, and does not
+    /// need to know it.
+    pub fn new() -> Self {
+        Self {
+            path_re: COEFFS_RE.map(Allpass2::new),
+            path_im: COEFFS_IM.map(Allpass2::new),
+            delay_im: T::zero(),
+        }
+    }
+
+    /// Process a single sample, returning the analytic signal as a complex value.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: Input sample
+    ///
+    /// returns: Complex<T>
+    pub fn process_analytic(&mut self, x: T) -> Complex<T> {
+        let [re, im] = self.process([x]);
+        Complex::new(re, im)
+    }
+}
+
+impl<T: Scalar> DSPMeta for Hilbert<T> {
+    type Sample = T;
+
+    fn reset(&mut self) {
+        for allpass in self.path_re.iter_mut().chain(&mut self.path_im) {
+            allpass.reset();
+        }
+        self.delay_im = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 2> for Hilbert<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 2] {
+        let re = self.path_re.iter_mut().fold(x, |x, ap| ap.process(x));
+        let im = self.path_im.iter_mut().fold(x, |x, ap| ap.process(x));
+        let im = core::mem::replace(&mut self.delay_im, im);
+        [re, im]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(50.0)]
+    #[case(500.0)]
+    #[case(5000.0)]
+    #[case(15000.0)]
+    fn hilbert_produces_analytic_signal(#[case] freq: f64) {
+        const SAMPLERATE: f64 = 48e3;
+        let mut hilbert = Hilbert::<f64>::new();
+        let output = (0..20000)
+            .map(|i| f64::sin(std::f64::consts::TAU * freq * i as f64 / SAMPLERATE))
+            .map(|x| hilbert.process_analytic(x))
+            .collect::<Vec<_>>();
+
+        let steady = &output[10000..];
+        let step = std::f64::consts::TAU * freq / SAMPLERATE;
+        for (z0, z1) in steady.iter().zip(&steady[1..]) {
+            // Constant envelope
+            assert!((z0.norm() - 1.0).abs() < 0.01, "Envelope of {}", z0.norm());
+            // Rotating at the positive input frequency
+            let rotation = (z1 * z0.conj()).arg();
+            assert!((rotation - step).abs() < 0.015, "Rotation of {rotation}");
+        }
+    }
+}
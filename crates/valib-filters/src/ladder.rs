@@ -12,7 +12,7 @@
 //! let output = filter.process([0.0]);
 //! ```
 
-use std::fmt;
+use core::fmt;
 
 use nalgebra::{Complex, SVector};
 use numeric_literals::replace_float_literals;
@@ -125,8 +125,8 @@ impl<T: Scalar, Topo: LadderTopology<T>> HasParameters for Ladder<T, Topo> {
 
     fn set_parameter(&mut self, param: Self::Name, value: f32) {
         match param {
-            LadderParams::Cutoff => self.set_cutoff(T::from_f64(value as _)),
-            LadderParams::Resonance => self.set_resonance(T::from_f64(value as _)),
+            LadderParams::Cutoff => self.set_cutoff(T::from_f32(value)),
+            LadderParams::Resonance => self.set_resonance(T::from_f32(value)),
         }
     }
 }
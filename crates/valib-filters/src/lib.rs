@@ -1,11 +1,21 @@
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 //! # Filters for `valib`
 //!
 //! This module provides various filter implementations using `valib` process definitions.
 
+extern crate alloc;
+
 pub mod biquad;
+pub mod cached;
+pub mod crossover;
+pub mod fir;
 pub mod halfband;
+pub mod hilbert;
 pub mod ladder;
+pub mod lpg;
+pub mod modal;
+pub mod smoothed;
 pub mod specialized;
 pub mod statespace;
 pub mod svf;
@@ -0,0 +1,192 @@
+//! # Low-pass gate
+//!
+//! Provides a Buchla-style low-pass gate, where a single control input drives both the level and
+//! the cutoff of a lowpass filter, through a lag emulating the slow response of vactrols.
+use valib_core::dsp::blocks::P1;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_saturators::Slew;
+
+/// Low-pass gate, combining a VCA and a one-pole lowpass filter driven by the same control.
+///
+/// The control input, in the 0..1 range, goes through a slew limiter emulating the lag of the
+/// vactrol. The lagged control sets the gain of the VCA linearly, and the cutoff of the filter
+/// exponentially between the minimum and maximum cutoff frequencies, such that the sound gets
+/// darker as it gets quieter.
+///
+/// Implements [`DSPProcess<2, 1>`], with the audio signal as first input and the control signal as
+/// second input.
+#[derive(Debug, Copy, Clone)]
+pub struct LowpassGate<T> {
+    samplerate: f32,
+    lag: f32,
+    min_fc: T,
+    max_fc: T,
+    /// Natural log of the ratio of the maximum and minimum cutoffs
+    log_ratio: T,
+    slew: Slew<T>,
+    filter: P1<T>,
+}
+
+impl<T: Scalar> LowpassGate<T> {
+    /// Create a new low-pass gate, with a cutoff range of 20 Hz to 20 kHz (limited by the sample
+    /// rate).
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the low-pass gate
+    /// * `lag`: Time for the gate to fully open or close (s)
+    ///
+    /// returns: LowpassGate<T>
+    pub fn new(samplerate: f32, lag: f32) -> Self {
+        let min_fc = T::from_f64(20.0);
+        let mut this = Self {
+            samplerate,
+            lag,
+            min_fc,
+            max_fc: T::from_f64(20e3),
+            log_ratio: T::zero(),
+            slew: Slew::default(),
+            filter: P1::new(T::from_f32(samplerate), min_fc),
+        };
+        this.set_samplerate(samplerate);
+        this
+    }
+
+    /// Sets the time for the gate to fully open or close (s).
+    pub fn set_lag(&mut self, lag: f32) {
+        self.lag = lag;
+        // The slew limiter runs at least at one sample per step, which also covers a lag of 0
+        let max_diff = (self.lag * self.samplerate).max(1.0).recip();
+        self.slew.max_diff = T::from_f32(max_diff);
+    }
+
+    /// Sets the range of the filter cutoff, reached when the gate is fully closed and fully open
+    /// respectively (Hz). Cutoffs are limited to the 1 Hz to Nyquist range.
+    pub fn set_cutoff_range(&mut self, min_fc: T, max_fc: T) {
+        let nyquist = T::from_f32(0.45 * self.samplerate);
+        let clamp = |fc: T| fc.simd_clamp(T::one(), nyquist);
+        self.min_fc = clamp(min_fc);
+        self.max_fc = clamp(max_fc);
+        self.log_ratio = (self.max_fc / self.min_fc).simd_ln();
+    }
+
+    /// Current control value, after the vactrol lag.
+    pub fn control(&self) -> T {
+        self.slew.current_value()
+    }
+
+    /// Current filter cutoff (Hz)
+    pub fn cutoff(&self) -> T {
+        self.filter.fc
+    }
+}
+
+impl<T: Scalar> DSPMeta for LowpassGate<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.filter.set_samplerate(samplerate);
+        self.set_lag(self.lag);
+        self.set_cutoff_range(self.min_fc, self.max_fc);
+    }
+
+    fn latency(&self) -> usize {
+        self.filter.latency()
+    }
+
+    fn reset(&mut self) {
+        self.slew.reset();
+        self.filter.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 1> for LowpassGate<T> {
+    fn process(&mut self, [x, control]: [Self::Sample; 2]) -> [Self::Sample; 1] {
+        let [c] = self.slew.process([control.simd_clamp(T::zero(), T::one())]);
+        self.filter
+            .set_fc(self.min_fc * (self.log_ratio * c).simd_exp());
+        self.filter.process([x * c])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+    const LAG: f32 = 0.01;
+
+    #[test]
+    fn lpg_tracks_control_with_lag() {
+        let lag_samples = (LAG * SAMPLERATE) as usize;
+        let mut lpg = LowpassGate::<f32>::new(SAMPLERATE, LAG);
+        lpg.set_cutoff_range(100.0, 10e3);
+
+        // Gate is closed at rest
+        let [y] = lpg.process([1.0, 0.0]);
+        assert_eq!(0.0, y);
+        assert_eq!(100.0, lpg.cutoff());
+
+        // Halfway through opening
+        for _ in 0..lag_samples / 2 {
+            lpg.process([1.0, 1.0]);
+        }
+        assert!((lpg.control() - 0.5).abs() < 1e-3);
+        assert!((lpg.cutoff() - 1e3).abs() < 10.0, "{}", lpg.cutoff());
+
+        // Fully open
+        for _ in 0..lag_samples / 2 {
+            lpg.process([1.0, 1.0]);
+        }
+        assert!((lpg.control() - 1.0).abs() < 1e-3);
+        assert!((lpg.cutoff() - 10e3).abs() < 10.0, "{}", lpg.cutoff());
+        let [y] = lpg.process([1.0, 1.0]);
+        assert!((y - 1.0).abs() < 1e-2, "Gain of {y} when open");
+
+        // Closing back takes the lag time as well
+        for _ in 0..lag_samples / 2 {
+            lpg.process([1.0, 0.0]);
+        }
+        assert!((lpg.control() - 0.5).abs() < 1e-3);
+        let [y] = lpg.process([1.0, 0.0]);
+        assert!((y - 0.5).abs() < 0.05, "Gain of {y} when half-closed");
+    }
+
+    #[test]
+    fn lpg_darkens_when_closing() {
+        let amplitude = |control: f32| {
+            let mut lpg = LowpassGate::<f32>::new(SAMPLERATE, 0.0);
+            lpg.set_cutoff_range(100.0, 10e3);
+            (0..4800)
+                .map(|i| {
+                    let x = f32::sin(std::f32::consts::TAU * 5e3 * i as f32 / SAMPLERATE);
+                    lpg.process([x, control])[0].abs()
+                })
+                .skip(2400)
+                .fold(0.0, f32::max)
+        };
+        let open = amplitude(1.0);
+        let half = amplitude(0.5);
+        // A VCA alone would only halve the amplitude
+        assert!(half < 0.25 * open, "Open: {open}, half-closed: {half}");
+    }
+
+    #[test]
+    fn lpg_clamps_cutoff_range() {
+        let mut lpg = LowpassGate::<f32>::new(SAMPLERATE, 0.0);
+        lpg.set_cutoff_range(0.0, 1e6);
+        assert_eq!(1.0, lpg.min_fc);
+        for control in [0.0, 0.5, 1.0] {
+            let [y] = lpg.process([1.0, control]);
+            assert!(y.is_finite() && lpg.cutoff().is_finite());
+        }
+        assert!(
+            (lpg.cutoff() - 0.45 * SAMPLERATE).abs() < 1.0,
+            "{}",
+            lpg.cutoff()
+        );
+    }
+}
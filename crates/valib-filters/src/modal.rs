@@ -0,0 +1,178 @@
+//! # Modal synthesis
+//!
+//! Provides a bank of tuned resonators, for physical modeling of struck or plucked objects such as
+//! bells, bars or drums, where each resonator models one mode of vibration of the object.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_saturators::Linear;
+
+use crate::biquad::Biquad;
+
+/// Parameters of a single mode of a [`ModalBank`].
+#[derive(Debug, Copy, Clone)]
+struct Mode<T> {
+    freq: T,
+    decay: T,
+    gain: T,
+}
+
+/// Bank of `N` resonators, excited by a common input.
+///
+/// Each mode is a two-pole resonator whose impulse response is a decaying sine at the mode
+/// frequency, starting at the mode gain. Exciting the bank with an impulse therefore produces the
+/// sum of the modes ringing, while exciting it with noise produces their filtered sum. All modes
+/// start silent, with a gain of zero.
+#[derive(Debug, Copy, Clone)]
+pub struct ModalBank<T, const N: usize> {
+    samplerate: T,
+    modes: [Mode<T>; N],
+    resonators: [Biquad<T, Linear>; N],
+}
+
+impl<T: Scalar, const N: usize> ModalBank<T, N> {
+    /// Create a new modal bank, with all modes silent.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the modal bank
+    ///
+    /// returns: ModalBank<T, { N }>
+    pub fn new(samplerate: T) -> Self {
+        let mode = Mode {
+            freq: T::from_f64(440.0),
+            decay: T::one(),
+            gain: T::zero(),
+        };
+        let mut this = Self {
+            samplerate,
+            modes: [mode; N],
+            resonators: [Biquad::new([T::zero(); 3], [T::zero(); 2]); N],
+        };
+        for i in 0..N {
+            this.update_resonator(i);
+        }
+        this
+    }
+
+    /// Sets the parameters of the mode at the given index. The state of the resonator is kept, so
+    /// that ringing modes can be retuned.
+    ///
+    /// # Arguments
+    ///
+    /// * `i`: Index of the mode, which must be less than `N`
+    /// * `freq`: Frequency of the mode (Hz), clamped below Nyquist
+    /// * `decay`: Time it takes for the mode to decay by 60 dB (s)
+    /// * `gain`: Amplitude of the mode when excited by a unit impulse
+    ///
+    /// returns: ()
+    pub fn set_mode(&mut self, i: usize, freq: T, decay: T, gain: T) {
+        self.modes[i] = Mode { freq, decay, gain };
+        self.update_resonator(i);
+    }
+
+    fn update_resonator(&mut self, i: usize) {
+        let Mode { freq, decay, gain } = self.modes[i];
+        let freq = freq.simd_min(T::from_f64(0.49) * self.samplerate);
+        let w = T::simd_two_pi() * freq / self.samplerate;
+        // Pole radius such that the amplitude drops by 60 dB after the decay time
+        let r = T::from_f64(1e-3).simd_powf((decay * self.samplerate).simd_recip());
+        let (sin, cos) = w.simd_sin_cos();
+        // Impulse response of gain * r^n * sin(w n)
+        let resonator = Biquad::new(
+            [T::zero(), gain * r * sin, T::zero()],
+            [-T::from_f64(2.0) * r * cos, r * r],
+        );
+        self.resonators[i].update_coefficients(&resonator);
+    }
+}
+
+impl<T: Scalar, const N: usize> DSPMeta for ModalBank<T, N> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f32(samplerate);
+        for i in 0..N {
+            self.update_resonator(i);
+        }
+    }
+
+    fn reset(&mut self) {
+        for resonator in &mut self.resonators {
+            resonator.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, const N: usize> DSPProcess<1, 1> for ModalBank<T, N> {
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let y = self
+            .resonators
+            .iter_mut()
+            .fold(T::zero(), |acc, resonator| acc + resonator.process(x)[0]);
+        [y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f64 = 48e3;
+
+    #[test]
+    fn modal_bank_single_mode_rings() {
+        const FREQ: f64 = 1e3;
+        const DECAY: f64 = 0.5;
+        let mut bank = ModalBank::<f64, 4>::new(SAMPLERATE);
+        bank.set_mode(2, FREQ, DECAY, 0.8);
+
+        let output = (0..SAMPLERATE as usize)
+            .map(|i| bank.process([if i == 0 { 1.0 } else { 0.0 }])[0])
+            .collect::<Vec<_>>();
+
+        // Decaying sine at the mode frequency
+        let r = 1e-3f64.powf(1.0 / (DECAY * SAMPLERATE));
+        for (n, y) in output.iter().enumerate() {
+            let expected = 0.8
+                * r.powi(n as i32)
+                * (std::f64::consts::TAU * FREQ * n as f64 / SAMPLERATE).sin();
+            assert!(
+                (y - expected).abs() < 1e-9,
+                "Sample {n}: expected {expected}, got {y}"
+            );
+        }
+
+        // Peak amplitude over one period around the given time (s)
+        let peak = |t: f64| {
+            let start = (t * SAMPLERATE) as usize;
+            output[start..start + (SAMPLERATE / FREQ) as usize]
+                .iter()
+                .fold(0.0, |max: f64, y| max.max(y.abs()))
+        };
+        assert!((peak(0.0) - 0.8).abs() < 0.01);
+        // -60 dB after the decay time
+        let ratio = peak(DECAY) / peak(0.0);
+        assert!((ratio - 1e-3).abs() < 1e-4, "Decayed to {ratio}");
+    }
+
+    #[test]
+    fn modal_bank_sums_modes() {
+        let mut bank = ModalBank::<f64, 2>::new(SAMPLERATE);
+        bank.set_mode(0, 200.0, 1.0, 1.0);
+        bank.set_mode(1, 3000.0, 0.1, 0.5);
+        let mut single = ModalBank::<f64, 1>::new(SAMPLERATE);
+        single.set_mode(0, 3000.0, 0.1, 0.5);
+        let mut other = ModalBank::<f64, 1>::new(SAMPLERATE);
+        other.set_mode(0, 200.0, 1.0, 1.0);
+
+        for i in 0..1000 {
+            let x = if i % 300 == 0 { 1.0 } else { 0.0 };
+            let expected = single.process([x])[0] + other.process([x])[0];
+            assert!((bank.process([x])[0] - expected).abs() < 1e-12);
+        }
+
+        bank.reset();
+        assert_eq!([0.0], bank.process([0.0]));
+    }
+}
@@ -0,0 +1,465 @@
+//! # Smoothed filters
+//!
+//! Provides wrappers around [`Biquad`] and [`Svf`] filters which smooth their cutoff and resonance
+//! controls, and recompute the filter coefficients at a reduced rate. This avoids both the cost of
+//! recomputing coefficients every sample, and the zipper noise of large jumps in the controls.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use valib_core::dsp::DSPProcess;
+//! use valib_filters::biquad::Biquad;
+//! use valib_filters::smoothed::SmoothedBiquad;
+//! let mut filter = SmoothedBiquad::new(48e3, Biquad::lowpass, 1e3, 0.707);
+//! filter.set_target_cutoff(5e3);
+//! // The cutoff glides from 1 kHz to 5 kHz as samples are processed
+//! let output = filter.process([0.0]);
+//! ```
+use valib_core::dsp::state::DspState;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+use valib_saturators::{Linear, Saturator};
+
+use crate::biquad::Biquad;
+use crate::specialized::OnePole;
+use crate::svf::Svf;
+
+/// Default number of samples between two coefficient updates
+pub const DEFAULT_UPDATE_INTERVAL: usize = 16;
+/// Default smoothing time constant of the controls (s)
+const DEFAULT_SMOOTHING_TIME: f64 = 0.01;
+
+/// Pair of one-pole smoothed controls, ticking at a reduced control rate. The first control is the
+/// cutoff, which is smoothed in the logarithmic domain.
+#[derive(Debug, Copy, Clone)]
+struct SmoothedControls<T> {
+    smoothers: [OnePole<T>; 2],
+    targets: [T; 2],
+    values: [T; 2],
+    samplerate: f32,
+    smoothing_time: T,
+    interval: usize,
+    counter: usize,
+    reset_on_samplerate_change: bool,
+}
+
+impl<T: Scalar> SmoothedControls<T> {
+    fn new(samplerate: f32, values: [T; 2]) -> Self {
+        let mut this = Self {
+            smoothers: [OnePole::lowpass(T::from_f64(samplerate as _), T::one()); 2],
+            targets: values,
+            values,
+            samplerate,
+            smoothing_time: T::from_f64(DEFAULT_SMOOTHING_TIME),
+            interval: DEFAULT_UPDATE_INTERVAL,
+            counter: 0,
+            reset_on_samplerate_change: false,
+        };
+        this.update_smoothers();
+        this.jump_to_targets();
+        this
+    }
+
+    fn update_smoothers(&mut self) {
+        let control_rate = self.samplerate / self.interval as f32;
+        for smoother in &mut self.smoothers {
+            smoother.set_samplerate(control_rate);
+            smoother.set_time_constant(self.smoothing_time);
+        }
+    }
+
+    /// Values of the controls in the domain they are smoothed in
+    fn smoothed_targets(&self) -> [T; 2] {
+        let [fc, q] = self.targets;
+        [fc.simd_ln(), q]
+    }
+
+    fn jump_to_targets(&mut self) {
+        let targets = self.smoothed_targets();
+        for (smoother, target) in self.smoothers.iter_mut().zip(targets) {
+            smoother.restore(&target);
+        }
+        self.values = self.targets;
+        self.counter = 0;
+    }
+
+    /// Advance the controls by one sample, returning the new values when the coefficients need
+    /// to be recomputed.
+    fn tick(&mut self) -> Option<[T; 2]> {
+        if self.counter > 0 {
+            self.counter -= 1;
+            return None;
+        }
+        self.counter = self.interval - 1;
+        let [log_fc, q] = self.smoothed_targets();
+        let [[log_fc], [q]] = [
+            self.smoothers[0].process([log_fc]),
+            self.smoothers[1].process([q]),
+        ];
+        self.values = [log_fc.simd_exp(), q];
+        Some(self.values)
+    }
+}
+
+macro_rules! smoothed_controls_methods {
+    () => {
+        /// Sets the number of samples between two coefficient updates, in a builder fashion.
+        pub fn with_update_interval(mut self, interval: usize) -> Self {
+            self.set_update_interval(interval);
+            self
+        }
+
+        /// Sets the number of samples between two coefficient updates. Lower values follow the
+        /// controls more closely, at the expense of more computations.
+        pub fn set_update_interval(&mut self, interval: usize) {
+            self.controls.interval = interval.max(1);
+            self.controls.counter = self.controls.counter.min(self.controls.interval - 1);
+            self.controls.update_smoothers();
+        }
+
+        /// Sets the time constant of the control smoothing (s).
+        pub fn set_smoothing_time(&mut self, seconds: T) {
+            self.controls.smoothing_time = seconds;
+            self.controls.update_smoothers();
+        }
+
+        /// Sets whether the filter is reset when the sample rate changes, in a builder fashion. See
+        /// [`Self::set_reset_on_samplerate_change`].
+        pub fn with_reset_on_samplerate_change(mut self, reset: bool) -> Self {
+            self.set_reset_on_samplerate_change(reset);
+            self
+        }
+
+        /// Sets whether the filter is reset when the sample rate changes. Otherwise, the filter
+        /// state carries over to the new sample rate, and the filter is smoothly retuned.
+        ///
+        /// This is off by default.
+        pub fn set_reset_on_samplerate_change(&mut self, reset: bool) {
+            self.controls.reset_on_samplerate_change = reset;
+        }
+
+        /// Sets the cutoff frequency (Hz) the filter smoothly moves towards.
+        pub fn set_target_cutoff(&mut self, fc: T) {
+            self.controls.targets[0] = fc;
+        }
+
+        /// Current smoothed cutoff frequency (Hz)
+        pub fn cutoff(&self) -> T {
+            self.controls.values[0]
+        }
+    };
+}
+
+/// [`Biquad`] filter with smoothed cutoff and Q controls.
+///
+/// The controls are smoothed by one-pole lowpass filters running at the control rate, and the
+/// coefficients are recomputed from the smoothed values with the given design function every
+/// [`DEFAULT_UPDATE_INTERVAL`] samples by default. The cutoff is smoothed logarithmically, so that
+/// it glides at an even rate in octaves.
+#[derive(Debug, Copy, Clone)]
+pub struct SmoothedBiquad<T, S> {
+    biquad: Biquad<T, S>,
+    design: fn(T, T) -> Biquad<T, Linear>,
+    controls: SmoothedControls<T>,
+}
+
+impl<T: Scalar> SmoothedBiquad<T, Linear> {
+    /// Create a new smoothed biquad filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `design`: Function computing the biquad from its normalized cutoff and Q, for example
+    ///     [`Biquad::lowpass`]. Non-capturing closures can be used to set other parameters, such as
+    ///     the gain of peaking filters.
+    /// * `fc`: Initial cutoff frequency (Hz)
+    /// * `q`: Initial Q factor
+    ///
+    /// returns: SmoothedBiquad<T, Linear>
+    pub fn new(samplerate: f32, design: fn(T, T) -> Biquad<T, Linear>, fc: T, q: T) -> Self {
+        let controls = SmoothedControls::new(samplerate, [fc, q]);
+        Self {
+            biquad: design(fc / T::from_f64(samplerate as _), q),
+            design,
+            controls,
+        }
+    }
+}
+
+impl<T: Scalar, S> SmoothedBiquad<T, S> {
+    smoothed_controls_methods!();
+
+    /// Apply these new saturators to the inner biquad, returning a new instance of it.
+    pub fn with_saturators<S2>(self, s0: S2, s1: S2) -> SmoothedBiquad<T, S2> {
+        SmoothedBiquad {
+            biquad: self.biquad.with_saturators(s0, s1),
+            design: self.design,
+            controls: self.controls,
+        }
+    }
+
+    /// Sets the Q factor the filter smoothly moves towards.
+    pub fn set_target_q(&mut self, q: T) {
+        self.controls.targets[1] = q;
+    }
+
+    /// Current smoothed Q factor
+    pub fn q(&self) -> T {
+        self.controls.values[1]
+    }
+
+    /// Inner biquad filter
+    pub fn inner(&self) -> &Biquad<T, S> {
+        &self.biquad
+    }
+
+    fn update_coefficients(&mut self, [fc, q]: [T; 2]) {
+        let fc = fc / T::from_f64(self.controls.samplerate as _);
+        self.biquad.update_coefficients(&(self.design)(fc, q));
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> DSPMeta for SmoothedBiquad<T, S> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        let changed = samplerate != self.controls.samplerate;
+        self.biquad.set_samplerate(samplerate);
+        self.controls.samplerate = samplerate;
+        self.controls.update_smoothers();
+        self.update_coefficients(self.controls.values);
+        if changed && self.controls.reset_on_samplerate_change {
+            self.reset();
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.biquad.latency()
+    }
+
+    fn reset(&mut self) {
+        self.biquad.reset();
+        self.controls.jump_to_targets();
+        self.update_coefficients(self.controls.values);
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, S: Saturator<T>> DSPProcess<1, 1> for SmoothedBiquad<T, S> {
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        if let Some(values) = self.controls.tick() {
+            self.update_coefficients(values);
+        }
+        self.biquad.process(x)
+    }
+}
+
+/// [`Svf`] filter with smoothed cutoff and resonance controls.
+///
+/// The controls are smoothed by one-pole lowpass filters running at the control rate, and the
+/// coefficients are recomputed from the smoothed values every [`DEFAULT_UPDATE_INTERVAL`] samples
+/// by default. The cutoff is smoothed logarithmically, so that it glides at an even rate in
+/// octaves.
+#[derive(Debug, Copy, Clone)]
+pub struct SmoothedSvf<T, S = Linear> {
+    svf: Svf<T, S>,
+    controls: SmoothedControls<T>,
+}
+
+impl<T: Scalar> SmoothedSvf<T, Linear> {
+    /// Create a new smoothed SVF filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `fc`: Initial cutoff frequency (Hz)
+    /// * `r`: Initial resonance amount (in 0..1 for stable filters)
+    ///
+    /// returns: SmoothedSvf<T, Linear>
+    pub fn new(samplerate: f32, fc: T, r: T) -> Self {
+        let mut this = Self {
+            svf: Svf::new(T::from_f64(samplerate as _), fc, r),
+            controls: SmoothedControls::new(samplerate, [fc, r]),
+        };
+        // Set the resonance the same way as later updates do
+        this.update_coefficients([fc, r]);
+        this
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> SmoothedSvf<T, S> {
+    smoothed_controls_methods!();
+
+    /// Apply this new saturator to the inner SVF, returning a new instance of it.
+    pub fn with_saturator<S2: Saturator<T>>(self, saturator: S2) -> SmoothedSvf<T, S2> {
+        SmoothedSvf {
+            svf: self.svf.with_saturator(saturator),
+            controls: self.controls,
+        }
+    }
+
+    /// Sets the resonance amount the filter smoothly moves towards.
+    pub fn set_target_resonance(&mut self, r: T) {
+        self.controls.targets[1] = r;
+    }
+
+    /// Current smoothed resonance amount
+    pub fn resonance(&self) -> T {
+        self.controls.values[1]
+    }
+
+    /// Inner SVF filter
+    pub fn inner(&self) -> &Svf<T, S> {
+        &self.svf
+    }
+
+    fn update_coefficients(&mut self, [fc, r]: [T; 2]) {
+        self.svf.set_cutoff(fc);
+        self.svf.set_r(r);
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> DSPMeta for SmoothedSvf<T, S> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        let changed = samplerate != self.controls.samplerate;
+        self.svf.set_samplerate(samplerate);
+        self.controls.samplerate = samplerate;
+        self.controls.update_smoothers();
+        self.update_coefficients(self.controls.values);
+        if changed && self.controls.reset_on_samplerate_change {
+            self.reset();
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.svf.latency()
+    }
+
+    fn reset(&mut self) {
+        self.svf.reset();
+        self.controls.jump_to_targets();
+        self.update_coefficients(self.controls.values);
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, S: Saturator<T>> DSPProcess<1, 3> for SmoothedSvf<T, S> {
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 3] {
+        if let Some(values) = self.controls.tick() {
+            self.update_coefficients(values);
+        }
+        self.svf.process(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    /// Largest change of any coefficient between the two biquads
+    fn max_coefficient_change(a: &Biquad<f64, Linear>, b: &Biquad<f64, Linear>) -> f64 {
+        let ((ab, aa), (bb, ba)) = (a.coefficients(), b.coefficients());
+        ab.iter()
+            .chain(&aa)
+            .zip(bb.iter().chain(&ba))
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn smoothed_biquad_has_no_coefficient_jumps() {
+        let mut filter = SmoothedBiquad::<f64, _>::new(SAMPLERATE, Biquad::lowpass, 100.0, 0.707);
+        let start = *filter.inner();
+        filter.set_target_cutoff(10e3);
+
+        let target = Biquad::lowpass(10e3 / SAMPLERATE as f64, 0.707);
+        // Changing the cutoff directly jumps by a large amount
+        assert!(max_coefficient_change(&start, &target) > 0.5);
+
+        let mut previous = start;
+        let mut max_change = 0.0f64;
+        for _ in 0..(0.1 * SAMPLERATE) as usize {
+            filter.process([0.0]);
+            let current = *filter.inner();
+            max_change = max_change.max(max_coefficient_change(&previous, &current));
+            previous = current;
+        }
+        assert!(max_change < 0.05, "Coefficients jumped by {max_change}");
+        // The cutoff has settled on the target after 10 time constants
+        assert!((filter.cutoff() / 10e3 - 1.0).abs() < 1e-3);
+        assert!(max_coefficient_change(&previous, &target) < 1e-3);
+    }
+
+    #[test]
+    fn smoothed_svf_glides_to_target() {
+        let mut filter = SmoothedSvf::<f64>::new(SAMPLERATE, 200.0, 0.5);
+        filter.set_target_cutoff(2000.0);
+        filter.set_target_resonance(0.1);
+        let cutoffs = (0..(0.1 * SAMPLERATE) as usize)
+            .map(|_| {
+                filter.process([0.0]);
+                filter.cutoff()
+            })
+            .collect::<Vec<_>>();
+        // Monotonic glide, with no big steps
+        for w in cutoffs.windows(2) {
+            assert!(w[1] >= w[0] && w[1] / w[0] < 1.1, "{} -> {}", w[0], w[1]);
+        }
+        assert!((filter.cutoff() / 2000.0 - 1.0).abs() < 1e-3);
+        assert!((filter.resonance() - 0.1).abs() < 1e-3);
+
+        // Reset jumps straight to the targets
+        filter.set_target_cutoff(500.0);
+        filter.reset();
+        assert_eq!(500.0, filter.cutoff());
+    }
+
+    #[test]
+    fn smoothed_svf_set_samplerate_updates_coefficients() {
+        let mut filter = SmoothedSvf::<f64>::new(SAMPLERATE, 1000.0, 0.5);
+        filter.set_samplerate(96e3);
+        let mut expected = SmoothedSvf::<f64>::new(96e3, 1000.0, 0.5);
+        for i in 0..256 {
+            let x = if i == 0 { 1.0 } else { 0.0 };
+            assert_eq!(expected.process([x]), filter.process([x]), "Sample {i}");
+        }
+    }
+
+    #[test]
+    fn reset_on_samplerate_change() {
+        let mut biquad = SmoothedBiquad::<f64, _>::new(SAMPLERATE, Biquad::lowpass, 1000.0, 0.707);
+        let mut svf = SmoothedSvf::<f64>::new(SAMPLERATE, 1000.0, 0.5);
+        let settle = |biquad: &mut SmoothedBiquad<f64, Linear>, svf: &mut SmoothedSvf<f64>| {
+            for _ in 0..100 {
+                biquad.process([1.0]);
+                svf.process([1.0]);
+            }
+        };
+        let is_cleared = |biquad: &SmoothedBiquad<f64, Linear>, svf: &SmoothedSvf<f64>| {
+            let ([b0, b1], _) = biquad.inner().snapshot();
+            let ([s0, s1], _) = svf.inner().snapshot();
+            [b0, b1, s0, s1].map(|s| s == 0.0)
+        };
+
+        // Off by default, the filter state carries over
+        settle(&mut biquad, &mut svf);
+        biquad.set_samplerate(44.1e3);
+        svf.set_samplerate(44.1e3);
+        assert_eq!([false; 4], is_cleared(&biquad, &svf));
+
+        biquad.set_reset_on_samplerate_change(true);
+        svf.set_reset_on_samplerate_change(true);
+        // Setting the same sample rate is not a change
+        biquad.set_samplerate(44.1e3);
+        svf.set_samplerate(44.1e3);
+        assert_eq!([false; 4], is_cleared(&biquad, &svf));
+
+        biquad.set_samplerate(SAMPLERATE);
+        svf.set_samplerate(SAMPLERATE);
+        assert_eq!([true; 4], is_cleared(&biquad, &svf));
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! Provides specialized filters for specific use-cases.
 use crate::biquad::Biquad;
+use nalgebra::Complex;
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::analysis::DspAnalysis;
+use valib_core::dsp::state::DspState;
 use valib_core::dsp::{DSPMeta, DSPProcess};
 use valib_core::Scalar;
 use valib_saturators::Linear;
@@ -56,3 +60,328 @@ impl<T: Scalar> DSPProcess<1, 1> for DcBlocker<T> {
         self.0.process(x)
     }
 }
+
+/// Output mode of the [`OnePole`] filter.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum OnePoleMode {
+    /// Lowpass output
+    #[default]
+    Lowpass,
+    /// Highpass output
+    Highpass,
+}
+
+/// Topology-preserving one-pole filter, configurable either by cutoff frequency or by time
+/// constant. Useful as a parameter smoother or for simple tone controls.
+#[derive(Debug, Copy, Clone)]
+pub struct OnePole<T> {
+    /// Output mode of the filter
+    pub mode: OnePoleMode,
+    fc: T,
+    samplerate: T,
+    g: T,
+    s: T,
+}
+
+impl<T: Scalar> OnePole<T> {
+    /// Create a new one-pole filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `fc`: Cutoff frequency (Hz)
+    /// * `mode`: Output mode of the filter
+    ///
+    /// returns: OnePole<T>
+    pub fn new(samplerate: T, fc: T, mode: OnePoleMode) -> Self {
+        let mut this = Self {
+            mode,
+            fc,
+            samplerate,
+            g: T::zero(),
+            s: T::zero(),
+        };
+        this.update_coefficients();
+        this
+    }
+
+    /// Create a new lowpass one-pole filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `fc`: Cutoff frequency (Hz)
+    ///
+    /// returns: OnePole<T>
+    pub fn lowpass(samplerate: T, fc: T) -> Self {
+        Self::new(samplerate, fc, OnePoleMode::Lowpass)
+    }
+
+    /// Create a new highpass one-pole filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `fc`: Cutoff frequency (Hz)
+    ///
+    /// returns: OnePole<T>
+    pub fn highpass(samplerate: T, fc: T) -> Self {
+        Self::new(samplerate, fc, OnePoleMode::Highpass)
+    }
+
+    /// Cutoff frequency of the filter (Hz)
+    pub fn cutoff(&self) -> T {
+        self.fc
+    }
+
+    /// Sets the cutoff frequency of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `fc`: Cutoff frequency (Hz)
+    ///
+    /// returns: ()
+    pub fn set_cutoff(&mut self, fc: T) {
+        self.fc = fc;
+        self.update_coefficients();
+    }
+
+    /// Sets the time constant of the filter, that is the time it takes for the lowpass output to
+    /// reach `1 - 1/e` (about 63%) of a step input.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds`: Time constant (s)
+    ///
+    /// returns: ()
+    pub fn set_time_constant(&mut self, seconds: T) {
+        self.set_cutoff((T::simd_two_pi() * seconds).simd_recip());
+    }
+
+    fn update_coefficients(&mut self) {
+        let fc = self.fc.simd_min(self.samplerate * T::from_f64(0.499));
+        self.g = (T::simd_pi() * fc / self.samplerate).simd_tan();
+    }
+}
+
+impl<T: Scalar> DSPMeta for OnePole<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.update_coefficients();
+    }
+
+    fn reset(&mut self) {
+        self.s = T::zero();
+    }
+}
+
+/// The state is the memory of the integrator, which is also the steady-state output of the lowpass
+/// for a constant input.
+impl<T: Scalar> DspState for OnePole<T> {
+    type State = T;
+
+    fn snapshot(&self) -> Self::State {
+        self.s
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.s = *state;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for OnePole<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let v = (x - self.s) * self.g / (T::one() + self.g);
+        let lp = v + self.s;
+        self.s = lp + v;
+        match self.mode {
+            OnePoleMode::Lowpass => [lp],
+            OnePoleMode::Highpass => [x - lp],
+        }
+    }
+}
+
+impl<T: Scalar> DspAnalysis<1, 1> for OnePole<T> {
+    #[replace_float_literals(Complex::from(T::from_f64(literal)))]
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let g = Complex::from(self.g);
+        let den = z * (1.0 + g) + g - 1.0;
+        let num = match self.mode {
+            OnePoleMode::Lowpass => g * (z + 1.0),
+            OnePoleMode::Highpass => z - 1.0,
+        };
+        [[num / den]]
+    }
+}
+
+/// Tilt EQ, boosting high frequencies while cutting low frequencies (or vice-versa) around a pivot
+/// frequency.
+///
+/// Built from a low shelf and a high shelf sharing the same pivot frequency, with opposite gains.
+#[derive(Debug, Copy, Clone)]
+pub struct TiltFilter<T> {
+    low: Biquad<T, Linear>,
+    high: Biquad<T, Linear>,
+    samplerate: T,
+    pivot: T,
+    tilt: T,
+}
+
+impl<T: Scalar> TiltFilter<T> {
+    /// Create a new tilt filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the filter is going to run
+    /// * `pivot`: Pivot frequency (Hz)
+    /// * `tilt`: Tilt amount (dB). Positive values boost the high end and cut the low end of the
+    ///     spectrum by half this amount each.
+    ///
+    /// returns: TiltFilter<T>
+    pub fn new(samplerate: T, pivot: T, tilt: T) -> Self {
+        let mut this = Self {
+            low: Biquad::new([T::one(), T::zero(), T::zero()], [T::zero(); 2]),
+            high: Biquad::new([T::one(), T::zero(), T::zero()], [T::zero(); 2]),
+            samplerate,
+            pivot,
+            tilt,
+        };
+        this.update_coefficients();
+        this
+    }
+
+    /// Sets the pivot frequency of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `pivot`: Pivot frequency (Hz)
+    ///
+    /// returns: ()
+    pub fn set_pivot(&mut self, pivot: T) {
+        self.pivot = pivot;
+        self.update_coefficients();
+    }
+
+    /// Sets the tilt amount of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `tilt`: Tilt amount (dB)
+    ///
+    /// returns: ()
+    pub fn set_tilt(&mut self, tilt: T) {
+        self.tilt = tilt;
+        self.update_coefficients();
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn update_coefficients(&mut self) {
+        let q = T::from_f64(core::f64::consts::FRAC_1_SQRT_2);
+        let fc = self.pivot / self.samplerate;
+        // Each shelf gets half of the tilt; the RBJ amplitude parameter is 10^(dB/40)
+        let amp = 10.0.simd_powf(self.tilt / 80.0);
+        self.low
+            .update_coefficients(&Biquad::lowshelf(fc, q, amp.simd_recip()));
+        self.high
+            .update_coefficients(&Biquad::highshelf(fc, q, amp));
+    }
+}
+
+impl<T: Scalar> DSPMeta for TiltFilter<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.update_coefficients();
+    }
+
+    fn reset(&mut self) {
+        self.low.reset();
+        self.high.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for TiltFilter<T> {
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        self.high.process(self.low.process(x))
+    }
+}
+
+impl<T: Scalar> DspAnalysis<1, 1> for TiltFilter<T> {
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 1]; 1] {
+        let [[low]] = self.low.h_z(z);
+        let [[high]] = self.high.h_z(z);
+        [[low * high]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::simd::SimdComplexField;
+
+    #[rstest::rstest]
+    fn one_pole_cutoff(
+        #[values(OnePoleMode::Lowpass, OnePoleMode::Highpass)] mode: OnePoleMode,
+        #[values(100.0, 1000.0, 10000.0)] fc: f64,
+    ) {
+        const SAMPLERATE: f64 = 44.1e3;
+        let filter = OnePole::new(SAMPLERATE, fc, mode);
+        let [[h]] = filter.freq_response(SAMPLERATE, fc);
+        let db = 20.0 * h.simd_modulus().log10();
+        assert!((db + 3.0103).abs() < 1e-3, "Gain at cutoff: {db} dB");
+
+        // Same result from the time constant
+        let mut filter = OnePole::new(SAMPLERATE, 0.0, mode);
+        filter.set_time_constant(f64::recip(std::f64::consts::TAU * fc));
+        let [[h]] = filter.freq_response(SAMPLERATE, fc);
+        let db = 20.0 * h.simd_modulus().log10();
+        assert!((db + 3.0103).abs() < 1e-3, "Gain at cutoff: {db} dB");
+    }
+
+    #[rstest::rstest]
+    fn tilt_symmetric_around_pivot(
+        #[values(-6.0, 3.0, 12.0)] tilt: f64,
+        #[values(1.5, 2.0, 4.0)] ratio: f64,
+    ) {
+        const SAMPLERATE: f64 = 48e3;
+        const PIVOT: f64 = 1000.0;
+        let filter = TiltFilter::new(SAMPLERATE, PIVOT, tilt);
+        let gain_db = |f: f64| {
+            let [[h]] = filter.freq_response(SAMPLERATE, f);
+            20.0 * h.simd_modulus().log10()
+        };
+
+        assert!(gain_db(PIVOT).abs() < 1e-3);
+        let low = gain_db(PIVOT / ratio);
+        let high = gain_db(PIVOT * ratio);
+        assert!(
+            (low + high).abs() < 0.05,
+            "Asymmetric gains: {low} dB below pivot, {high} dB above"
+        );
+        assert!(low.signum() == -tilt.signum() && high.signum() == tilt.signum());
+        // Asymptotic gains are half of the tilt amount
+        assert!((gain_db(10.0) + tilt / 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn one_pole_snapshot_round_trip() {
+        let mut filter = OnePole::new(1000.0, 10.0, OnePoleMode::Lowpass);
+        filter.process([1.0]);
+        let state = filter.snapshot();
+        let expected = (0..32)
+            .map(|_| filter.process([0.0])[0])
+            .collect::<Vec<_>>();
+
+        filter.process([-1.0]);
+        filter.restore(&state);
+        let actual = (0..32)
+            .map(|_| filter.process([0.0])[0])
+            .collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
+}
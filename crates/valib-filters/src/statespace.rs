@@ -50,6 +50,8 @@ pub struct StateSpace<
     saturators: S,
 }
 
+/// Transfer function of the linear state-space system, computed as `C (zI - A)^-1 B + D`. When the
+/// `zI - A` matrix is singular (i.e. `z` is a pole of the system), the response is `NaN`.
 impl<
         T: Copy + Scalar + nalgebra::RealField,
         const IN: usize,
@@ -242,4 +244,30 @@ mod tests {
             .map(|f| filter.0.freq_response(1024.0, f)[0][0].abs());
         insta::assert_csv_snapshot!(&freq_response as &[_], { "[]" => insta::rounded_redaction(3)})
     }
+
+    #[test]
+    fn test_rc_filter_hz_matches_measured() {
+        const SAMPLERATE: f64 = 1024.0;
+        const SETTLE: usize = 1024;
+        const WINDOW: usize = 1024;
+        for f in [4.0, 16.0, 64.0, 128.0, 256.0, 400.0] {
+            let mut filter = RC::<f64>::new(0.25);
+            let analytic = filter.0.freq_response(SAMPLERATE, f)[0][0];
+
+            // Correlate the steady-state output against the input sine over whole periods
+            let w = std::f64::consts::TAU * f / SAMPLERATE;
+            let measured = (0..SETTLE + WINDOW)
+                .map(|n| (n, filter.process([f64::sin(w * n as f64)])[0]))
+                .skip(SETTLE)
+                .map(|(n, y)| Complex::from_polar(y, -w * n as f64))
+                .sum::<Complex<f64>>()
+                * Complex::i()
+                * (2.0 / WINDOW as f64);
+
+            assert!(
+                (analytic - measured).norm() < 1e-6,
+                "Response at {f} Hz: analytic {analytic}, measured {measured}"
+            );
+        }
+    }
 }
@@ -9,6 +9,7 @@ use numeric_literals::replace_float_literals;
 use valib_core::dsp::{
     analysis::DspAnalysis,
     parameter::{HasParameters, ParamId, ParamName},
+    state::DspState,
     DSPMeta, DSPProcess,
 };
 use valib_core::Scalar;
@@ -25,6 +26,7 @@ pub enum SvfParams {
 
 /// SVF topology filter, with optional non-linearities.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Svf<T, Mode = Linear> {
     s: [T; 2],
     r: T,
@@ -41,7 +43,7 @@ impl<T: Scalar, Mode: Saturator<T>> HasParameters for Svf<T, Mode> {
     type Name = SvfParams;
 
     fn set_parameter(&mut self, param: Self::Name, value: f32) {
-        let value = T::from_f64(value as _);
+        let value = T::from_f32(value);
         match param {
             SvfParams::Cutoff => self.set_cutoff(value),
             SvfParams::Resonance => self.set_r(value),
@@ -66,6 +68,20 @@ impl<T: Scalar, Mode: Saturator<T>> DSPMeta for Svf<T, Mode> {
     }
 }
 
+/// The state is made of the internal filter memories, along with the saturator.
+impl<T: Scalar, Mode: Clone + Saturator<T>> DspState for Svf<T, Mode> {
+    type State = ([T; 2], Mode);
+
+    fn snapshot(&self) -> Self::State {
+        (self.s, self.saturator.clone())
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.s = state.0;
+        self.saturator.clone_from(&state.1);
+    }
+}
+
 #[profiling::all_functions]
 impl<T: Scalar, S: Saturator<T>> DSPProcess<1, 3> for Svf<T, S> {
     #[inline(always)]
@@ -182,12 +198,149 @@ impl<T: Scalar, S: Saturator<T>> Svf<T, S> {
     }
 }
 
+/// Number of Newton-Rhapson iterations performed per sample in [`NonlinearSvf`].
+const NEWTON_ITERATIONS: usize = 4;
+
+/// Nonlinear SVF topology filter, where the saturator is applied to the output of the bandpass
+/// integrator (section 6.6).
+///
+/// Contrary to [`Svf`], which evaluates the saturator on the previous integrator state, the
+/// nonlinearity is here part of the zero-delay feedback loop, and the resulting implicit equation
+/// is solved with Newton-Rhapson iterations. This keeps the filter bounded when driven into
+/// self-oscillation with negative damping values.
+#[derive(Debug, Copy, Clone)]
+pub struct NonlinearSvf<T, S> {
+    s: [T; 2],
+    r: T,
+    fc: T,
+    g: T,
+    samplerate: T,
+    saturator: S,
+}
+
+impl<T: Scalar, S: Saturator<T>> HasParameters for NonlinearSvf<T, S> {
+    type Name = SvfParams;
+
+    fn set_parameter(&mut self, param: Self::Name, value: f32) {
+        let value = T::from_f32(value);
+        match param {
+            SvfParams::Cutoff => self.set_cutoff(value),
+            SvfParams::Resonance => self.set_r(value),
+        }
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> DSPMeta for NonlinearSvf<T, S> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.update_coefficients();
+    }
+
+    fn reset(&mut self) {
+        self.s.fill(T::zero());
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, S: Saturator<T>> DSPProcess<1, 3> for NonlinearSvf<T, S> {
+    #[inline(always)]
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 3] {
+        let [s1, s2] = self.s;
+        let g = self.g;
+        let g1 = 2. * self.r + g;
+
+        // Solve y + k * sat(y) = u for y, the input of the saturator
+        let k = g * g1;
+        let u = g * (x[0] - s2) + s1;
+        let mut y = u / (1. + k);
+        for _ in 0..NEWTON_ITERATIONS {
+            let f = y + k * self.saturator.saturate(y) - u;
+            let df = 1. + k * self.saturator.sat_diff(y);
+            y -= f / df;
+        }
+        let bp = self.saturator.saturate(y);
+        self.saturator.update_state(y, bp);
+
+        let hp = x[0] - g1 * bp - s2;
+        let v1 = g * hp;
+        let s1 = bp + v1;
+
+        let v2 = g * bp;
+        let lp = v2 + s2;
+        let s2 = lp + v2;
+
+        self.s = [s1, s2];
+        [lp, bp, hp]
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> DspAnalysis<1, 3> for NonlinearSvf<T, S> {
+    /// Small-signal response of the filter, assuming the saturator has unity slope at the origin.
+    #[replace_float_literals(Complex::from(T::from_f64(literal)))]
+    fn h_z(&self, z: Complex<Self::Sample>) -> [[Complex<Self::Sample>; 3]; 1] {
+        let s = (z - 1.0) / ((z + 1.0) * self.g);
+        let den = Complex::<T>::one() / (s * s + s * Complex::from(self.r) * 2.0 + 1.0);
+        [[den, s * den, s * s * den]]
+    }
+}
+
+impl<T: Scalar, S: Saturator<T>> NonlinearSvf<T, S> {
+    /// Create a new nonlinear SVF filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the filter
+    /// * `fc`: Cutoff frequency (Hz)
+    /// * `r`: Damping amount, the filter having a Q of `1 / (2 r)`. Set slightly below zero to make
+    ///     the filter self-oscillate.
+    /// * `saturator`: Saturator applied to the output of the bandpass integrator
+    ///
+    /// returns: NonlinearSvf<T, S>
+    pub fn new(samplerate: T, fc: T, r: T, saturator: S) -> Self {
+        let mut this = Self {
+            s: [T::zero(); 2],
+            r,
+            fc,
+            g: T::zero(),
+            samplerate,
+            saturator,
+        };
+        this.update_coefficients();
+        this
+    }
+
+    /// Set the new filter cutoff frequency (in Hz).
+    pub fn set_cutoff(&mut self, freq: T) {
+        self.fc = freq;
+        self.update_coefficients();
+    }
+
+    /// Set the damping amount, the filter having a Q of `1 / (2 r)`. Negative values make the
+    /// filter self-oscillate.
+    pub fn set_r(&mut self, r: T) {
+        self.r = r;
+    }
+
+    /// Replace the saturator of this filter.
+    pub fn set_saturator(&mut self, saturator: S) {
+        self.saturator = saturator;
+    }
+
+    fn update_coefficients(&mut self) {
+        self.g = (T::simd_pi() * self.fc / self.samplerate).simd_tan();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use nalgebra::ComplexField;
     use plotters::prelude::*;
     use valib_core::util::tests::*;
+    use valib_saturators::Tanh;
 
     #[test]
     fn test_svf_hz() {
@@ -226,4 +379,67 @@ mod tests {
         .create_svg("plots/svf/freq_response_hz.svg");
         insta::assert_csv_snapshot!(&hz as &[_], { "[][]" => insta::rounded_redaction(3)})
     }
+
+    #[test]
+    fn test_nonlinear_svf_self_oscillation() {
+        const SAMPLERATE: f64 = 4096.0;
+        const FC: f64 = 100.0;
+        const R: f64 = -0.05;
+
+        fn run<S: Saturator<f64>>(saturator: S) -> Box<[f32]> {
+            let mut filter = NonlinearSvf::new(SAMPLERATE, FC, R, saturator);
+            (0..1024)
+                .map(|i| {
+                    let x = if i == 0 { 0.1 } else { 0.0 };
+                    let [lp, _, _] = filter.process([x]);
+                    lp as f32
+                })
+                .collect()
+        }
+
+        let linear = run(Linear);
+        let saturated = run(Tanh);
+        let peak = |out: &[f32]| out.iter().copied().map(f32::abs).fold(0.0, f32::max);
+        assert!(peak(&linear) > 10.0, "Linear SVF should blow up");
+        assert!(peak(&saturated) < 10.0, "Saturated SVF should stay bounded");
+        assert!(
+            peak(&saturated[768..]) > 0.1,
+            "Saturated SVF should sustain its oscillation"
+        );
+
+        Plot {
+            title: "Nonlinear SVF self-oscillation",
+            bode: false,
+            series: &[
+                Series {
+                    label: "Linear",
+                    samplerate: SAMPLERATE as _,
+                    series: &linear[..256],
+                    color: &BLUE,
+                },
+                Series {
+                    label: "Tanh",
+                    samplerate: SAMPLERATE as _,
+                    series: &saturated,
+                    color: &RED,
+                },
+            ],
+        }
+        .create_svg("plots/svf/nonlinear_self_oscillation.svg");
+        insta::assert_csv_snapshot!(&saturated as &[_], { "[]" => insta::rounded_redaction(3) })
+    }
+
+    #[test]
+    fn svf_snapshot_round_trip() {
+        let mut svf = Svf::<f64>::new(1024.0, 50.0, 0.1);
+        svf.process([1.0]);
+        let state = svf.snapshot();
+        let expected = (0..64).map(|_| svf.process([0.0])).collect::<Vec<_>>();
+        assert!(expected.iter().any(|[lp, ..]| lp.abs() > 1e-3));
+
+        svf.process([-3.0]);
+        svf.restore(&state);
+        let actual = (0..64).map(|_| svf.process([0.0])).collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
 }
@@ -70,6 +70,51 @@ impl<E: 'static + PartialEq + Enum, P: 'static + Send + Sync + ParamName> BindTo
     }
 }
 
+/// Construct a nih-plug [`Params`] struct, binding each of its parameters to a variant of a
+/// [`ParamName`] enum, instead of calling [`BindToParameter::bind_to_parameter`] on each one by
+/// hand.
+///
+/// Each field is written as `Variant => field: param`, where `param` is the nih-plug parameter
+/// stored in `field`, bound to `Variant` of the parameter name enum.
+///
+/// # Example
+///
+/// ```ignore
+/// use nih_plug::prelude::*;
+/// use valib_core::dsp::parameter::{ParamId, ParamName, RemoteControl};
+/// use valib_nih_plug::bind_all;
+///
+/// #[derive(Debug, Copy, Clone, PartialEq, Eq, ParamName)]
+/// enum DspParams {
+///     Cutoff,
+///     Resonance,
+/// }
+///
+/// #[derive(Params)]
+/// struct PluginParams {
+///     #[id = "fc"]
+///     cutoff: FloatParam,
+///     #[id = "res"]
+///     resonance: FloatParam,
+/// }
+///
+/// fn create_params(remote: &RemoteControl<DspParams>) -> PluginParams {
+///     bind_all!(remote, DspParams => PluginParams {
+///         Cutoff => cutoff: FloatParam::new("Cutoff", 3000.0, FloatRange::Linear { min: 20.0, max: 20e3 }),
+///         Resonance => resonance: FloatParam::new("Resonance", 0.5, FloatRange::Linear { min: 0.0, max: 1.0 }),
+///     })
+/// }
+/// ```
+#[macro_export]
+macro_rules! bind_all {
+    ($remote:expr, $name:ty => $params:path { $($variant:ident => $field:ident: $param:expr),* $(,)? }) => {{
+        let remote = $remote;
+        $params {
+            $($field: $crate::BindToParameter::bind_to_parameter($param, remote, <$name>::$variant),)*
+        }
+    }};
+}
+
 /// Extension trait for casting the output of a `value()` method through the [`Scalar`] trait.
 pub trait ValueAs {
     /// Get the current value, cast to `T`.
@@ -79,7 +124,7 @@ pub trait ValueAs {
 impl ValueAs for FloatParam {
     #[inline]
     fn value_as<T: Scalar>(&self) -> T {
-        T::from_f64(self.value() as _)
+        T::from_f32(self.value())
     }
 }
 
@@ -193,6 +238,82 @@ pub fn process_buffer_simd<
     }
 }
 
+/// Processes a [`nih-plug`] buffer in its entirety with a sidechained [`DSPBlock`] instance,
+/// mapping channels to lanes in the scalar type.
+///
+/// The first input of the dsp instance receives the main buffer, and the second input receives the
+/// sidechain key, typically taken from the auxiliary input buffers given to the plugin
+/// (`aux.inputs`). When no sidechain is given, the processor is keyed from the main buffer. When the
+/// sidechain has fewer channels than the main buffer, its last channel is used for the remaining
+/// lanes, such that a mono key can drive all channels.
+///
+/// This function automatically respects the value reported by [`DSPBlock::max_buffer_size`]. Up to
+/// [`MAX_BUF_SIZE`] samples will be processed at once.
+///
+/// # Arguments
+///
+/// * `dsp`: [`DSPBlock`] instance to process the buffer with
+/// * `buffer`: Buffer to process
+/// * `sidechain`: Optional sidechain buffer keying the processor
+///
+/// panics if the scalar type has more channels than the buffer holds, or if the sidechain buffer
+/// is shorter than the main buffer.
+#[profiling::function]
+pub fn process_buffer_simd_sidechain<
+    T: Scalar<Element = f32>,
+    Dsp: DSPProcessBlock<2, 1, Sample = T>,
+    const MAX_BUF_SIZE: usize,
+>(
+    dsp: &mut Dsp,
+    buffer: &mut Buffer,
+    sidechain: Option<&Buffer>,
+) {
+    let channels = buffer.channels();
+    assert!(T::LANES <= channels);
+    let sidechain = sidechain
+        .map(|sc| sc.as_slice_immutable())
+        .filter(|sc| !sc.is_empty());
+    if let Some(sc) = sidechain {
+        assert!(
+            sc[0].len() >= buffer.samples(),
+            "Sidechain buffer is shorter than the main buffer"
+        );
+    }
+    let mut input = AudioBuffer::const_new([[T::from_f64(0.0); MAX_BUF_SIZE]; 2]);
+    let mut output = AudioBuffer::const_new([[T::from_f64(0.0); MAX_BUF_SIZE]]);
+    let max_buffer_size = dsp.max_block_size().unwrap_or(MAX_BUF_SIZE);
+    nih_debug_assert!(max_buffer_size <= MAX_BUF_SIZE);
+    for (start, mut block) in buffer.iter_blocks(max_buffer_size) {
+        let mut input = input.array_slice_mut(..block.samples());
+        let mut output = output.array_slice_mut(..block.samples());
+        for (i, mut c) in block.iter_samples().enumerate() {
+            let mut frame = T::zero();
+            for (ch, s) in c.iter_mut().enumerate() {
+                frame.replace(ch, *s);
+            }
+            let key = match sidechain {
+                Some(sc) => {
+                    let mut key = T::zero();
+                    for ch in 0..channels {
+                        key.replace(ch, sc[ch.min(sc.len() - 1)][start + i]);
+                    }
+                    key
+                }
+                None => frame,
+            };
+            input.set_frame(i, [frame, key]);
+        }
+
+        dsp.process_block(input.as_ref(), output.as_mut());
+
+        for (i, mut c) in block.iter_samples().enumerate() {
+            for (ch, s) in c.iter_mut().enumerate() {
+                *s = output.get_frame(i)[0].extract(ch);
+            }
+        }
+    }
+}
+
 /// Processes a [`nih-plug`] buffer in its entirety with a [`DSPBlock`] instance, mapping channels
 /// to lanes in the scalar type.
 ///
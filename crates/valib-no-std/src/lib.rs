@@ -0,0 +1,31 @@
+#![warn(missing_docs)]
+#![no_std]
+//! # `no_std` build check
+//!
+//! This crate builds `valib` DSP code without `std`, checking that `valib-core`,
+//! `valib-saturators` and `valib-filters` stay usable on embedded targets.
+//!
+//! Cargo unifies features across the packages being built, so this check is only meaningful
+//! when the crate is built on its own: `cargo test -p valib-no-std`.
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_filters::biquad::Biquad;
+use valib_saturators::Tanh;
+
+/// Filter a buffer in-place with a saturated lowpass biquad.
+///
+/// # Arguments
+///
+/// * `samplerate`: Sample rate of the signal
+/// * `cutoff`: Cutoff frequency of the filter (Hz)
+/// * `samples`: Signal to filter
+///
+/// returns: ()
+pub fn lowpass_in_place(samplerate: f32, cutoff: f32, samples: &mut [f32]) {
+    let mut biquad =
+        Biquad::<f32, _>::lowpass(cutoff / samplerate, core::f32::consts::FRAC_1_SQRT_2)
+            .with_saturators(Tanh, Tanh);
+    biquad.reset();
+    for x in samples {
+        [*x] = biquad.process([*x]);
+    }
+}
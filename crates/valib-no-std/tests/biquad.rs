@@ -0,0 +1,20 @@
+use valib_no_std::lowpass_in_place;
+
+const SAMPLERATE: f32 = 48e3;
+
+#[test]
+fn lowpass_passes_dc() {
+    // Small enough for the saturators to be transparent
+    let mut samples = [0.01; 4096];
+    lowpass_in_place(SAMPLERATE, 1e3, &mut samples);
+    let last = samples[samples.len() - 1];
+    assert!((last - 0.01).abs() < 1e-4, "Got {last}");
+}
+
+#[test]
+fn lowpass_attenuates_nyquist() {
+    let mut samples: [f32; 4096] = core::array::from_fn(|i| if i % 2 == 0 { 0.5 } else { -0.5 });
+    lowpass_in_place(SAMPLERATE, 1e3, &mut samples);
+    let peak = samples[2048..].iter().fold(0f32, |acc, x| acc.max(x.abs()));
+    assert!(peak < 1e-2, "Got {peak}");
+}
@@ -0,0 +1,286 @@
+//! # Low-frequency oscillators
+//!
+//! Provides an LFO with several waveshapes, to be used as a modulation source.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::noise::WhiteNoise;
+use crate::Phasor;
+
+/// Waveshape of the LFO. All shapes output bipolar values in the -1..1 range.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum LfoShape {
+    /// Sine wave
+    #[default]
+    Sine,
+    /// Triangle wave, starting at -1
+    Triangle,
+    /// Rising sawtooth wave
+    Saw,
+    /// Square wave, starting at 1
+    Square,
+    /// Random value held for each period
+    SampleAndHold,
+}
+
+/// Low-frequency oscillator, driven by a [`Phasor`].
+#[derive(Debug, Copy, Clone)]
+pub struct Lfo<T> {
+    /// Waveshape of the oscillator
+    pub shape: LfoShape,
+    /// Apply PolyBLEP corrections on the discontinuities of the saw and square shapes. Useful when
+    /// the LFO is run at audio rates.
+    pub band_limited: bool,
+    phasor: Phasor<T>,
+    samplerate: T,
+    frequency: T,
+    last_phase: T,
+    noise: WhiteNoise<T>,
+    held: T,
+}
+
+impl<T: Scalar> Lfo<T> {
+    /// Create a new LFO.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the LFO
+    /// * `frequency`: Frequency of the LFO (Hz)
+    /// * `shape`: Waveshape of the LFO
+    ///
+    /// returns: Lfo<T>
+    pub fn new(samplerate: T, frequency: T, shape: LfoShape) -> Self {
+        Self {
+            shape,
+            band_limited: false,
+            phasor: Phasor::new(samplerate, frequency),
+            samplerate,
+            frequency,
+            last_phase: T::one(),
+            noise: WhiteNoise::new(0),
+            held: T::zero(),
+        }
+    }
+
+    /// Sets the seed of the random values of the sample-and-hold shape, in a builder fashion.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.set_seed(seed);
+        self
+    }
+
+    /// Sets the seed of the random values of the sample-and-hold shape. LFOs with the same seed
+    /// output the same sequence of values. The sequence restarts from the beginning.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.noise = WhiteNoise::new(seed);
+    }
+
+    /// Sets the frequency of the LFO. The phase remains continuous.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency`: New frequency (Hz)
+    ///
+    /// returns: ()
+    pub fn set_frequency(&mut self, frequency: T) {
+        self.frequency = frequency;
+        self.phasor.set_frequency(self.samplerate, frequency);
+    }
+
+    /// Sets the frequency of the LFO in sync with the tempo, such that a period lasts the given
+    /// number of beats.
+    ///
+    /// # Arguments
+    ///
+    /// * `beats`: Length of one period, in beats
+    /// * `bpm`: Tempo, in beats per minute
+    ///
+    /// returns: ()
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn set_tempo_sync(&mut self, beats: T, bpm: T) {
+        self.set_frequency(bpm / (60.0 * beats));
+    }
+
+    /// Frequency of the LFO (Hz).
+    pub fn frequency(&self) -> T {
+        self.frequency
+    }
+
+    /// Sets the phase of the LFO, for example to retrigger it on a new note. In sample-and-hold
+    /// mode, a new value is also drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase`: New phase, in the 0..1 range
+    ///
+    /// returns: ()
+    pub fn set_phase(&mut self, phase: T) {
+        self.phasor.set_phase(phase);
+        self.last_phase = T::one();
+    }
+}
+
+impl<T: Scalar> DSPMeta for Lfo<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.phasor.set_frequency(self.samplerate, self.frequency);
+    }
+
+    fn reset(&mut self) {
+        self.set_phase(T::zero());
+        self.noise.reset();
+        self.held = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<0, 1> for Lfo<T> {
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+        let dt = self.phasor.step();
+        let [p] = self.phasor.process([]);
+        // The phasor only wraps past 1, so a phase of exactly 1 is the start of the next cycle
+        let p = (p - 1.0).select(p.simd_ge(1.0), p);
+        let wrapped = p.simd_lt(self.last_phase);
+        self.last_phase = p;
+
+        let y = match self.shape {
+            LfoShape::Sine => (T::simd_two_pi() * p).simd_sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (p - 0.5).simd_abs(),
+            LfoShape::Saw => {
+                let saw = 2.0 * p - 1.0;
+                if self.band_limited {
+                    saw - poly_blep(p, dt)
+                } else {
+                    saw
+                }
+            }
+            LfoShape::Square => {
+                let square = T::one().select(p.simd_lt(0.5), -T::one());
+                if self.band_limited {
+                    square + poly_blep(p, dt) - poly_blep((p + 0.5).simd_fract(), dt)
+                } else {
+                    square
+                }
+            }
+            LfoShape::SampleAndHold => {
+                let [x] = self.noise.process([]);
+                self.held = x.select(wrapped, self.held);
+                self.held
+            }
+        };
+        [y]
+    }
+}
+
+/// Polynomial approximation of the band-limited step residual, to be applied on a unit
+/// discontinuity at phase 0.
+#[replace_float_literals(T::from_f64(literal))]
+fn poly_blep<T: Scalar>(p: T, dt: T) -> T {
+    let t = p / dt;
+    let before = (p - 1.0) / dt;
+    let after_step = 2.0 * t - t * t - 1.0;
+    let before_step = before * before + 2.0 * before + 1.0;
+    let y = after_step.select(p.simd_lt(dt), T::zero());
+    before_step.select(p.simd_gt(1.0 - dt), y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const SAMPLERATE: f64 = 64.0;
+    const PERIOD: usize = 64;
+
+    #[rstest]
+    fn lfo_range_and_period(
+        #[values(
+            LfoShape::Sine,
+            LfoShape::Triangle,
+            LfoShape::Saw,
+            LfoShape::Square,
+            LfoShape::SampleAndHold
+        )]
+        shape: LfoShape,
+    ) {
+        let mut lfo = Lfo::new(SAMPLERATE, 1.0, shape);
+        let output = (0..4 * PERIOD)
+            .map(|_| lfo.process([])[0])
+            .collect::<Vec<_>>();
+
+        let (min, max) = output
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| {
+                (min.min(x), max.max(x))
+            });
+        assert!(min >= -1.0 && max <= 1.0, "Out of range: {min}..{max}");
+
+        if shape == LfoShape::SampleAndHold {
+            for period in output.chunks_exact(PERIOD) {
+                assert!(period.iter().all(|&x| x == period[0]));
+            }
+            assert_ne!(output[0], output[PERIOD]);
+        } else {
+            assert!(
+                max - min > 1.9,
+                "Does not span the full range: {min}..{max}"
+            );
+            for i in 0..3 * PERIOD {
+                assert!(
+                    (output[i] - output[i + PERIOD]).abs() < 1e-9,
+                    "Not periodic at sample {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn band_limited_saw_smooths_discontinuity() {
+        let max_jump = |band_limited| {
+            let mut lfo = Lfo::new(SAMPLERATE, 4.0, LfoShape::Saw);
+            lfo.band_limited = band_limited;
+            let output = (0..PERIOD).map(|_| lfo.process([])[0]).collect::<Vec<_>>();
+            output
+                .windows(2)
+                .map(|w| (w[1] - w[0]).abs())
+                .fold(0.0, f64::max)
+        };
+        assert!(max_jump(true) < max_jump(false));
+    }
+
+    #[test]
+    fn lfo_tempo_sync() {
+        let mut lfo = Lfo::new(SAMPLERATE, 1.0, LfoShape::Saw);
+        // Two beats at 120 BPM last one second
+        lfo.set_tempo_sync(2.0, 120.0);
+        assert_eq!(1.0, lfo.frequency());
+        lfo.set_tempo_sync(0.25, 120.0);
+        assert_eq!(8.0, lfo.frequency());
+    }
+
+    #[test]
+    fn sample_and_hold_seed() {
+        let run = |seed| {
+            let mut lfo = Lfo::new(SAMPLERATE, 1.0, LfoShape::SampleAndHold).with_seed(seed);
+            (0..4 * PERIOD)
+                .map(|_| lfo.process([])[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn lfo_retrigger() {
+        let mut lfo = Lfo::new(SAMPLERATE, 1.0, LfoShape::Saw);
+        let [first] = lfo.process([]);
+        for _ in 0..10 {
+            lfo.process([]);
+        }
+        lfo.set_phase(0.0);
+        assert_eq!([first], lfo.process([]));
+    }
+}
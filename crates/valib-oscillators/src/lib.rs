@@ -1,13 +1,20 @@
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 //! # Oscillators
 //!
 //! This module provides oscillators for `valib`.
+
+extern crate alloc;
+
 use numeric_literals::replace_float_literals;
 use valib_core::dsp::DSPMeta;
 use valib_core::dsp::DSPProcess;
 use valib_core::Scalar;
 
 pub mod blit;
+pub mod lfo;
+pub mod noise;
+pub mod sweep;
 pub mod wavetable;
 
 /// Tracks normalized phase for a given frequency. Phase is smooth even when frequency changes, so
@@ -61,4 +68,25 @@ impl<T: Scalar> Phasor<T> {
     pub fn set_frequency(&mut self, samplerate: T, freq: T) {
         self.step = freq / samplerate;
     }
+
+    /// Current phase of the phasor, in the 0..1 range.
+    pub fn phase(&self) -> T {
+        self.phase
+    }
+
+    /// Sets the phase of this phasor, for example to retrigger an oscillator.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase`: New phase, in the 0..1 range
+    ///
+    /// returns: ()
+    pub fn set_phase(&mut self, phase: T) {
+        self.phase = phase.simd_fract();
+    }
+
+    /// Phase increment per sample.
+    pub fn step(&self) -> T {
+        self.step
+    }
 }
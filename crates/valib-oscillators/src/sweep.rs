@@ -0,0 +1,222 @@
+//! # Sine sweeps
+//!
+//! Provides an exponential sine sweep generator, along with its inverse filter, for measuring the
+//! impulse response of systems with the method of Farina.
+use alloc::vec::Vec;
+
+use num_traits::Float;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+/// Number of frequencies at which the gain of the inverse filter is measured for normalization
+const GAIN_PROBES: usize = 16;
+
+/// Exponential (logarithmic) sine sweep.
+///
+/// The instantaneous frequency rises exponentially from the start frequency to the end frequency
+/// over the duration of the sweep, spending the same time in each octave. Once the sweep is
+/// finished, the generator outputs silence.
+///
+/// The phase is computed in double precision regardless of the sample type, so that long sweeps
+/// stay accurate.
+#[derive(Debug, Copy, Clone)]
+pub struct ExpSweep<T> {
+    samplerate: f32,
+    start: f64,
+    end: f64,
+    duration: f64,
+    index: usize,
+    len: usize,
+    __sample: core::marker::PhantomData<T>,
+}
+
+impl<T: Scalar> ExpSweep<T> {
+    /// Create a new exponential sine sweep.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the sweep
+    /// * `start`: Frequency at the start of the sweep (Hz)
+    /// * `end`: Frequency at the end of the sweep (Hz)
+    /// * `duration`: Duration of the sweep (s)
+    ///
+    /// returns: ExpSweep<T>
+    pub fn new(samplerate: f32, start: f64, end: f64, duration: f64) -> Self {
+        assert!(
+            start > 0.0 && end > start,
+            "Sweep frequencies must be positive and increasing"
+        );
+        Self {
+            samplerate,
+            start,
+            end,
+            duration,
+            index: 0,
+            len: (duration * samplerate as f64).round() as usize,
+            __sample: core::marker::PhantomData,
+        }
+    }
+
+    /// Length of the sweep, in samples.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the sweep has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true when all the samples of the sweep have been output.
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.len
+    }
+
+    /// Sweep rate constant, that is the time it takes for the frequency to be multiplied by e (s).
+    fn rate(&self) -> f64 {
+        self.duration / Float::ln(self.end / self.start)
+    }
+
+    /// Value of the sweep at the given sample index, without checking the bounds.
+    fn value_at(&self, index: usize) -> f64 {
+        let l = self.rate();
+        let t = index as f64 / self.samplerate as f64;
+        let phase = core::f64::consts::TAU * self.start * l * (Float::exp(t / l) - 1.0);
+        Float::sin(phase)
+    }
+
+    /// Compute the inverse filter of the sweep.
+    ///
+    /// This is the time-reversed sweep, with an amplitude envelope decreasing by 6 dB per octave
+    /// to compensate for the pink spectrum of the sweep. Convolving the response of a linear
+    /// system to the sweep with the inverse filter gives its impulse response, starting at index
+    /// `len() - 1`. Harmonic distortion products of nonlinear systems end up before that index,
+    /// and can be separated out.
+    ///
+    /// The inverse filter is normalized such that the deconvolved response has unity gain within the
+    /// range of the sweep.
+    pub fn inverse_filter(&self) -> Vec<T> {
+        let l = self.rate();
+        let sweep = (0..self.len).map(|i| self.value_at(i)).collect::<Vec<_>>();
+        let inverse = (0..self.len)
+            .map(|i| {
+                let t = i as f64 / self.samplerate as f64;
+                sweep[self.len - 1 - i] * Float::exp(-t / l)
+            })
+            .collect::<Vec<_>>();
+
+        // Magnitude of the convolution of the sweep with its inverse filter, which is the product
+        // of their spectra. It is averaged over frequencies spread within the sweep, as the
+        // spectrum ripples around the ideal flat response.
+        let magnitude = |signal: &[f64], w: f64| {
+            let (re, im) = signal
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (i, &x)| {
+                    let (sin, cos) = Float::sin_cos(w * i as f64);
+                    (re + x * cos, im - x * sin)
+                });
+            Float::hypot(re, im)
+        };
+        let gain = (0..GAIN_PROBES)
+            .map(|k| {
+                // Stay away from the edges of the sweep, where the spectrum rolls off
+                let position = (k as f64 + 0.5) / GAIN_PROBES as f64;
+                let f = self.start * Float::powf(self.end / self.start, 0.1 + 0.8 * position);
+                let w = core::f64::consts::TAU * f / self.samplerate as f64;
+                magnitude(&sweep, w) * magnitude(&inverse, w)
+            })
+            .sum::<f64>()
+            / GAIN_PROBES as f64;
+
+        inverse.into_iter().map(|x| T::from_f64(x / gain)).collect()
+    }
+}
+
+impl<T: Scalar> DSPMeta for ExpSweep<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.len = (self.duration * samplerate as f64).round() as usize;
+    }
+
+    fn reset(&mut self) {
+        self.index = 0;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<0, 1> for ExpSweep<T> {
+    fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+        if self.is_finished() {
+            return [T::zero()];
+        }
+        let y = self.value_at(self.index);
+        self.index += 1;
+        [T::from_f64(y)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLERATE: f32 = 8000.0;
+
+    #[test]
+    fn sweep_is_bounded_and_stops() {
+        let mut sweep = ExpSweep::<f32>::new(SAMPLERATE, 20.0, 4000.0, 0.5);
+        assert_eq!(4000, sweep.len());
+        let output = (0..5000).map(|_| sweep.process([])[0]).collect::<Vec<_>>();
+        assert!(output.iter().all(|x| x.abs() <= 1.0));
+        assert!(sweep.is_finished());
+        assert!(output[4000..].iter().all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn sweep_recovers_impulse_response() {
+        // Delayed FIR system with a known impulse response
+        const DELAY: usize = 5;
+        let expected = [0.8, -0.4, 0.25, 0.1];
+        let mut sweep = ExpSweep::<f64>::new(SAMPLERATE, 20.0, 4000.0, 0.5);
+        let len = sweep.len();
+        let excitation = (0..len + 64)
+            .map(|_| sweep.process([])[0])
+            .collect::<Vec<_>>();
+        let response = (0..excitation.len())
+            .map(|i| {
+                expected
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, h)| Some(h * excitation.get(i.checked_sub(DELAY + k)?)?))
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        // Only compute the part of the convolution where the linear response lands
+        let inverse = sweep.inverse_filter();
+        let ir = (0..16)
+            .map(|lag| {
+                let n = len - 1 + lag;
+                inverse
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, g)| Some(g * response.get(n.checked_sub(k)?)?))
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        for (i, actual) in ir.into_iter().enumerate() {
+            let expected = i
+                .checked_sub(DELAY)
+                .and_then(|k| expected.get(k))
+                .copied()
+                .unwrap_or(0.0);
+            assert!(
+                (actual - expected).abs() < 0.02,
+                "Sample {i}: expected {expected}, got {actual}"
+            );
+        }
+    }
+}
@@ -2,9 +2,14 @@
 //!
 //! Provides oscillators which work by interpolating a single-cycle waveform defined as a set of
 //! samples.
-use std::ops::Range;
+use alloc::boxed::Box;
+use alloc::vec;
+use core::ops::Range;
+
 use valib_core::dsp::DSPMeta;
 use valib_core::math::interpolation::{SimdIndex, SimdInterpolatable};
+use valib_core::simd::SimdValue;
+use valib_core::util::simd_index_simd;
 use valib_core::{
     dsp::DSPProcess,
     math::interpolation::{Interpolate, Linear},
@@ -30,9 +35,13 @@ where
     <T as SimdCast<usize>>::Output: SimdIndex,
 {
     fn process(&mut self, [phase]: [Self::Sample; 1]) -> [Self::Sample; 1] {
-        let y = self
-            .interpolation
-            .interpolate_on_slice(phase.simd_fract(), &self.array);
+        let y = interpolate_cycle(
+            &self.interpolation,
+            phase,
+            &self.array,
+            T::index_from_usize(0),
+            N,
+        );
         [y]
     }
 }
@@ -67,11 +76,11 @@ impl<T: Scalar, const N: usize, Interp> Wavetable<T, N, Interp> {
     /// returns: Wavetable<T, { N }, Interp, 2>
     pub fn from_fn(interpolation: Interp, range: Range<T>, f: impl Fn(T) -> T) -> Self {
         let r = range.end - range.start;
-        let step = T::from_f64(N as f64) / r;
+        let step = r / T::from_f64(N as f64);
         Self::new(
             interpolation,
-            std::array::from_fn(|i| {
-                let x = T::from_f64(i as f64) * step;
+            core::array::from_fn(|i| {
+                let x = range.start + T::from_f64(i as f64) * step;
                 f(x)
             }),
         )
@@ -88,3 +97,247 @@ impl<T: Scalar, const N: usize, Interp> Wavetable<T, N, Interp> {
         Self::from_fn(interpolation, T::zero()..T::simd_two_pi(), |x| x.simd_sin())
     }
 }
+
+impl<T: Scalar + SimdInterpolatable, const N: usize, Interp> Wavetable<T, N, Interp>
+where
+    <T as SimdCast<usize>>::Output: SimdIndex,
+{
+    /// Create a new wavetable oscillator from the provided single-cycle waveform. The waveform is
+    /// resampled to fit the table size, and normalized to a peak value of 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpolation`: Interpolation method
+    /// * `samples`: Samples of a single cycle of the waveform
+    ///
+    /// returns: Wavetable<T, { N }, Interp, 2>
+    pub fn from_samples(interpolation: Interp, samples: &[T]) -> Self {
+        let mut array = [T::zero(); N];
+        resample_cycle(&mut array, samples);
+        normalize(&mut array);
+        Self::new(interpolation, array)
+    }
+}
+
+/// Wavetable oscillator morphing between several single-cycle waveforms (or frames), with a
+/// customizable interpolation method. Its DSP implementation expects a phasor signal as its first
+/// input.
+pub struct MorphingWavetable<T, const N: usize, Interp = Linear, const I: usize = 2> {
+    frames: Box<[T]>,
+    num_frames: usize,
+    position: T,
+    interpolation: Interp,
+}
+
+impl<T: Scalar, const N: usize, Interp, const I: usize> DSPMeta
+    for MorphingWavetable<T, N, Interp, I>
+{
+    type Sample = T;
+}
+
+#[profiling::all_functions]
+impl<T, const N: usize, const I: usize, Interp: Interpolate<T, I>> DSPProcess<1, 1>
+    for MorphingWavetable<T, N, Interp, I>
+where
+    T: Scalar + SimdInterpolatable,
+    <T as SimdCast<usize>>::Output: SimdIndex,
+{
+    fn process(&mut self, [phase]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let last = self.num_frames - 1;
+        let pos = self.position.simd_clamp(T::zero(), T::one()) * T::from_f64(last as f64);
+        let frac = pos.simd_fract();
+        let index = pos.simd_floor().cast();
+        let mut next = index;
+        for j in 0..<T as SimdCast<usize>>::Output::LANES {
+            next.replace(j, usize::min(index.extract(j) + 1, last));
+        }
+
+        let frame_len = T::index_from_usize(N);
+        let a = interpolate_cycle(
+            &self.interpolation,
+            phase,
+            &self.frames,
+            index * frame_len,
+            N,
+        );
+        let b = interpolate_cycle(
+            &self.interpolation,
+            phase,
+            &self.frames,
+            next * frame_len,
+            N,
+        );
+        [a + (b - a) * frac]
+    }
+}
+
+impl<T: Scalar + SimdInterpolatable, const N: usize, Interp> MorphingWavetable<T, N, Interp>
+where
+    <T as SimdCast<usize>>::Output: SimdIndex,
+{
+    /// Create a new morphing wavetable oscillator from consecutive single-cycle waveforms. Each
+    /// cycle is resampled to fit the table size, and the whole wavetable is normalized to a peak
+    /// value of 1, preserving the relative levels of the cycles.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpolation`: Interpolation method
+    /// * `samples`: Samples of all the cycles, one after the other
+    /// * `cycle_len`: Length of a single cycle in `samples`
+    ///
+    /// returns: MorphingWavetable<T, { N }, Interp, 2>
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` does not contain at least one cycle, or its length is not a multiple of
+    /// `cycle_len`.
+    pub fn from_cycles(interpolation: Interp, samples: &[T], cycle_len: usize) -> Self {
+        assert!(
+            cycle_len > 0 && !samples.is_empty() && samples.len() % cycle_len == 0,
+            "Wavetable of length {} does not contain whole cycles of length {cycle_len}",
+            samples.len()
+        );
+        let mut frames = vec![T::zero(); samples.len() / cycle_len * N].into_boxed_slice();
+        for (frame, cycle) in frames
+            .chunks_exact_mut(N)
+            .zip(samples.chunks_exact(cycle_len))
+        {
+            resample_cycle(frame, cycle);
+        }
+        normalize(&mut frames);
+        Self {
+            num_frames: frames.len() / N,
+            frames,
+            position: T::zero(),
+            interpolation,
+        }
+    }
+}
+
+impl<T: Scalar, const N: usize, Interp, const I: usize> MorphingWavetable<T, N, Interp, I> {
+    /// Number of frames in this wavetable.
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    /// Sets the morph position within the wavetable, where 0 is the first frame and 1 is the last
+    /// frame. Intermediate positions crossfade between the two nearest frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `t`: Morph position (0..=1)
+    ///
+    /// returns: ()
+    pub fn set_position(&mut self, t: T) {
+        self.position = t;
+    }
+}
+
+/// Interpolate within the single-cycle waveform of length `len` starting at `offset` in `values`,
+/// wrapping around the edges of the cycle.
+fn interpolate_cycle<T, Interp: Interpolate<T, I>, const I: usize>(
+    interpolation: &Interp,
+    phase: T,
+    values: &[T],
+    offset: <T as SimdCast<usize>>::Output,
+    len: usize,
+) -> T
+where
+    T: Scalar + SimdInterpolatable,
+    <T as SimdCast<usize>>::Output: SimdIndex,
+{
+    let pos = phase.simd_fract() * T::from_f64(len as f64);
+    let frac = pos.simd_fract();
+    let index = pos.simd_floor().cast();
+    let taps = core::array::from_fn(|i| {
+        let mut tap = index;
+        for j in 0..<T as SimdCast<usize>>::Output::LANES {
+            // Offsetting by a whole cycle prevents indices before the start from saturating
+            let ix = Interp::indices(index.extract(j) + len)[i] % len;
+            tap.replace(j, offset.extract(j) + ix);
+        }
+        simd_index_simd(values, tap)
+    });
+    interpolation.interpolate(frac, taps)
+}
+
+/// Resample a single cycle into the output slice with linear interpolation.
+fn resample_cycle<T>(output: &mut [T], cycle: &[T])
+where
+    T: Scalar + SimdInterpolatable,
+    <T as SimdCast<usize>>::Output: SimdIndex,
+{
+    let step = T::from_f64(output.len() as f64).simd_recip();
+    for (i, out) in output.iter_mut().enumerate() {
+        let phase = T::from_f64(i as f64) * step;
+        *out = interpolate_cycle(&Linear, phase, cycle, T::index_from_usize(0), cycle.len());
+    }
+}
+
+/// Normalize the values in place to a peak value of 1. Silent lanes are left untouched.
+fn normalize<T: Scalar>(values: &mut [T]) {
+    let peak = values
+        .iter()
+        .fold(T::zero(), |acc, x| acc.simd_max(x.simd_abs()));
+    let gain = peak.simd_recip().select(peak.simd_gt(T::zero()), T::one());
+    for x in values {
+        *x *= gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Phasor;
+
+    const SAMPLERATE: f64 = 48e3;
+
+    fn sine_cycle(len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|i| f64::sin(std::f64::consts::TAU * i as f64 / len as f64))
+            .collect()
+    }
+
+    #[test]
+    fn from_samples_matches_sine() {
+        let mut osc = Wavetable::<_, 256, _>::from_samples(Linear, &sine_cycle(100));
+        let mut phasor = Phasor::new(SAMPLERATE, 440.0);
+        for _ in 0..1024 {
+            let [phase] = phasor.process([]);
+            let [y] = osc.process([phase]);
+            let expected = f64::sin(std::f64::consts::TAU * phase);
+            assert!(
+                (y - expected).abs() < 2e-3,
+                "Expected {expected}, got {y} at phase {phase}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_samples_normalizes() {
+        let table = sine_cycle(64)
+            .into_iter()
+            .map(|x| 0.25 * x)
+            .collect::<Vec<_>>();
+        let mut osc = Wavetable::<_, 64, _>::from_samples(Linear, &table);
+        let [y] = osc.process([0.25]);
+        assert!((y - 1.0).abs() < 1e-6, "Table not normalized: peak is {y}");
+    }
+
+    #[test]
+    fn morphing_wavetable_position() {
+        let mut cycles = sine_cycle(128);
+        cycles.extend(sine_cycle(128).into_iter().map(|x| -0.5 * x));
+        let mut osc = MorphingWavetable::<_, 128, _>::from_cycles(Linear, &cycles, 128);
+        assert_eq!(2, osc.num_frames());
+
+        for (position, expected) in [(0.0, 1.0), (1.0, -0.5), (0.5, 0.25)] {
+            osc.set_position(position);
+            let [y] = osc.process([0.25]);
+            assert!(
+                (y - expected).abs() < 1e-6,
+                "Expected {expected} at position {position}, got {y}"
+            );
+        }
+    }
+}
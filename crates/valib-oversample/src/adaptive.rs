@@ -0,0 +1,430 @@
+//! # Adaptive oversampling
+//!
+//! Provides an oversampling wrapper which only oversamples when the inner processor needs it.
+
+use valib_core::dsp::blocks::DelayLine;
+use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use valib_core::dsp::{BlockAdapter, DSPMeta, DSPProcess, DSPProcessBlock};
+use valib_core::simd::SimdBool;
+use valib_core::Scalar;
+
+use crate::{Oversample, OversampleMode, Oversampled};
+
+/// Processors which can tell whether they currently behave nonlinearly, which
+/// [`AdaptiveOversample`] can use to decide when to oversample, see
+/// [`AdaptiveOversample::with_nonlinearity_hint`].
+pub trait NonlinearityHint {
+    /// Returns whether the processor is currently nonlinear, for example because its drive is
+    /// high, or [`None`] if it cannot tell.
+    fn is_nonlinear(&self) -> Option<bool> {
+        None
+    }
+}
+
+impl<P: NonlinearityHint> NonlinearityHint for BlockAdapter<P> {
+    fn is_nonlinear(&self) -> Option<bool> {
+        self.0.is_nonlinear()
+    }
+}
+
+/// Ongoing switch between the base rate and the oversampled processors.
+#[derive(Debug, Copy, Clone)]
+struct Transition {
+    to_oversampled: bool,
+    /// Samples left before starting the crossfade, letting the incoming processor settle
+    warmup: usize,
+    /// Position within the crossfade
+    pos: usize,
+}
+
+/// Oversampling wrapper which only oversamples when the inner processor is nonlinear.
+///
+/// Two instances of the inner processor are kept, one running at the base sample rate and the
+/// other oversampled, and only one of them runs at a time outside of transitions. The oversampled
+/// processor is used when the input level rises above a threshold, or, when enabled with
+/// [`Self::with_nonlinearity_hint`], when the inner processor reports being nonlinear through
+/// [`NonlinearityHint`].
+///
+/// The oversampled path uses linear-phase filters, and the faster of both paths is delayed to match
+/// the latency of the other, so that they stay aligned (to the nearest sample) during crossfades and
+/// the reported latency does not change when switching. When switching, the incoming processor is
+/// reset and run alongside the outgoing one until it has settled, and the output is then crossfaded
+/// between both.
+pub struct AdaptiveOversample<T, P> {
+    base: P,
+    oversampled: Oversampled<T, P>,
+    /// Delays aligning the paths with each other
+    base_delay: DelayLine<T>,
+    oversampled_delay: DelayLine<T>,
+    samplerate: f32,
+    hint: fn(&P) -> Option<bool>,
+    threshold: T,
+    envelope: T,
+    is_oversampled: bool,
+    transition: Option<Transition>,
+    fade_len: usize,
+    buffer_current: Box<[T]>,
+    buffer_next: Box<[T]>,
+}
+
+impl<T: Scalar, P: Clone + DSPProcessBlock<1, 1, Sample = T>> AdaptiveOversample<T, P> {
+    /// Create a new adaptive oversampling wrapper, starting at the base sample rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Base sample rate
+    /// * `os_factor`: Oversampling factor used when the inner processor is nonlinear
+    /// * `max_block_size`: Maximum block size that will be expected to be processed
+    /// * `inner`: Inner processor, which is cloned for the oversampled path
+    ///
+    /// returns: AdaptiveOversample<T, P>
+    pub fn new(samplerate: f32, os_factor: usize, max_block_size: usize, inner: P) -> Self {
+        let mut base = inner.clone();
+        base.set_samplerate(samplerate);
+        let oversampled =
+            Oversample::new_with_mode(os_factor, max_block_size, OversampleMode::LinearPhase)
+                .with_dsp(samplerate, inner);
+        let max_block_size = oversampled.max_block_size().unwrap_or(max_block_size);
+        let mut this = Self {
+            base_delay: DelayLine::new(0),
+            oversampled_delay: DelayLine::new(0),
+            base,
+            oversampled,
+            samplerate,
+            hint: |_| None,
+            threshold: T::from_f64(0.1),
+            envelope: T::zero(),
+            is_oversampled: false,
+            transition: None,
+            fade_len: (0.01 * samplerate) as usize,
+            buffer_current: vec![T::zero(); max_block_size].into_boxed_slice(),
+            buffer_next: vec![T::zero(); max_block_size].into_boxed_slice(),
+        };
+        this.update_delays();
+        this
+    }
+}
+
+impl<T: Scalar, P: DSPProcessBlock<1, 1, Sample = T>> AdaptiveOversample<T, P> {
+    /// Uses the [`NonlinearityHint`] of the inner processor to decide when to oversample, in a
+    /// builder fashion. The input level threshold is only used when the hint returns [`None`].
+    pub fn with_nonlinearity_hint(mut self) -> Self
+    where
+        P: NonlinearityHint,
+    {
+        self.hint = P::is_nonlinear;
+        self
+    }
+
+    /// Sets the input level (linear amplitude) above which the oversampled processor is used, when
+    /// no [`NonlinearityHint`] tells whether the inner processor is nonlinear. The base rate processor is used
+    /// again when the level drops below half the threshold. Defaults to 0.1 (-20 dB).
+    pub fn set_threshold(&mut self, threshold: T) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the duration of the crossfade between processors (s). Defaults to 10 ms.
+    pub fn set_fade_time(&mut self, fade_time: f32) {
+        self.fade_len = ((fade_time * self.samplerate) as usize).max(1);
+    }
+
+    /// Returns the oversampling factor currently in use. During transitions, this is the factor
+    /// of the processor being faded out.
+    pub fn os_factor(&self) -> usize {
+        if self.is_oversampled {
+            self.oversampled.os_factor()
+        } else {
+            1
+        }
+    }
+
+    /// Returns true while switching between the base rate and the oversampled processors.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Inner processor running at the base sample rate
+    pub fn base(&self) -> &P {
+        &self.base
+    }
+
+    /// Inner processor running oversampled
+    pub fn oversampled(&self) -> &Oversampled<T, P> {
+        &self.oversampled
+    }
+
+    /// Recompute the path delays after the latency of either processor changed
+    fn update_delays(&mut self) {
+        let latency = self.latency();
+        self.base_delay = DelayLine::new(latency - self.base.latency());
+        self.oversampled_delay = DelayLine::new(latency - self.oversampled.latency());
+    }
+
+    /// Update the envelope with the input, and start a transition if needed
+    fn update_mode(&mut self, input: &[T]) {
+        let peak = input
+            .iter()
+            .fold(T::zero(), |peak, x| peak.simd_max(x.simd_abs()));
+        // 100 ms release
+        let release = T::from_f64(f64::exp(
+            -(input.len() as f64) / (0.1 * self.samplerate as f64),
+        ));
+        self.envelope = peak.simd_max(self.envelope * release);
+        if self.transition.is_some() {
+            return;
+        }
+
+        let wants_oversampling = (self.hint)(&self.base).unwrap_or_else(|| {
+            if self.is_oversampled {
+                self.envelope
+                    .simd_gt(self.threshold * T::from_f64(0.5))
+                    .any()
+            } else {
+                self.envelope.simd_gt(self.threshold).any()
+            }
+        });
+        if wants_oversampling != self.is_oversampled {
+            if wants_oversampling {
+                self.oversampled.reset();
+                self.oversampled_delay.reset();
+            } else {
+                self.base.reset();
+                self.base_delay.reset();
+            }
+            self.transition = Some(Transition {
+                to_oversampled: wants_oversampling,
+                warmup: 2 * self.latency() + 32,
+                pos: 0,
+            });
+        }
+    }
+
+    fn process_path(&mut self, oversampled: bool, input: &[T], next: bool) {
+        let buffer = if next {
+            &mut self.buffer_next[..input.len()]
+        } else {
+            &mut self.buffer_current[..input.len()]
+        };
+        let inputs = AudioBufferRef::new([input]).unwrap();
+        let outputs = AudioBufferMut::new([&mut *buffer]).unwrap();
+        let delay = if oversampled {
+            self.oversampled.process_block(inputs, outputs);
+            &mut self.oversampled_delay
+        } else {
+            self.base.process_block(inputs, outputs);
+            &mut self.base_delay
+        };
+        for x in buffer {
+            [*x] = delay.process([*x]);
+        }
+    }
+
+    fn process_chunk(&mut self, input: &[T], output: &mut [T]) {
+        self.update_mode(input);
+        self.process_path(self.is_oversampled, input, false);
+        let Some(mut transition) = self.transition else {
+            output.copy_from_slice(&self.buffer_current[..input.len()]);
+            return;
+        };
+
+        self.process_path(transition.to_oversampled, input, true);
+        let fade_len = T::from_f64(self.fade_len as _);
+        for ((y, &current), &next) in output
+            .iter_mut()
+            .zip(&*self.buffer_current)
+            .zip(&*self.buffer_next)
+        {
+            if transition.warmup > 0 {
+                transition.warmup -= 1;
+                *y = current;
+            } else if transition.pos < self.fade_len {
+                let gain = T::from_f64(transition.pos as _) / fade_len;
+                *y = current + (next - current) * gain;
+                transition.pos += 1;
+            } else {
+                *y = next;
+            }
+        }
+
+        if transition.warmup == 0 && transition.pos >= self.fade_len {
+            self.is_oversampled = transition.to_oversampled;
+            self.transition = None;
+        } else {
+            self.transition = Some(transition);
+        }
+    }
+}
+
+impl<T: Scalar, P: DSPProcessBlock<1, 1, Sample = T>> DSPMeta for AdaptiveOversample<T, P> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.fade_len = self.fade_len * samplerate as usize / self.samplerate as usize;
+        self.samplerate = samplerate;
+        self.base.set_samplerate(samplerate);
+        self.oversampled.set_samplerate(samplerate);
+        self.update_delays();
+    }
+
+    fn latency(&self) -> usize {
+        self.base.latency().max(self.oversampled.latency())
+    }
+
+    fn reset(&mut self) {
+        self.base.reset();
+        self.oversampled.reset();
+        self.base_delay.reset();
+        self.oversampled_delay.reset();
+        self.envelope = T::zero();
+        self.transition = None;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar, P: DSPProcessBlock<1, 1, Sample = T>> DSPProcessBlock<1, 1>
+    for AdaptiveOversample<T, P>
+{
+    fn process_block(&mut self, inputs: AudioBufferRef<T, 1>, mut outputs: AudioBufferMut<T, 1>) {
+        let max_block_size = self.buffer_current.len();
+        for (input, output) in inputs
+            .get_channel(0)
+            .chunks(max_block_size)
+            .zip(outputs.get_channel_mut(0).chunks_mut(max_block_size))
+        {
+            self.process_chunk(input, output);
+        }
+    }
+
+    fn max_block_size(&self) -> Option<usize> {
+        Some(self.buffer_current.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::dsp::buffer::AudioBuffer;
+
+    const SAMPLERATE: f32 = 48e3;
+
+    #[derive(Debug, Copy, Clone)]
+    struct Tanh;
+
+    impl DSPMeta for Tanh {
+        type Sample = f32;
+    }
+
+    impl DSPProcess<1, 1> for Tanh {
+        fn process(&mut self, [x]: [f32; 1]) -> [f32; 1] {
+            [x.tanh()]
+        }
+    }
+
+    #[test]
+    fn adaptive_oversample_follows_level() {
+        let mut adaptive = AdaptiveOversample::new(SAMPLERATE, 4, 64, BlockAdapter(Tanh));
+        let mut reference = Oversample::new_with_mode(4, 64, OversampleMode::LinearPhase)
+            .with_dsp(SAMPLERATE, BlockAdapter(Tanh));
+        let latency = adaptive.latency();
+        assert_eq!(reference.latency(), latency);
+        // Near the top of the band, where misaligned paths would cancel out during crossfades
+        let freq = 15e3;
+        // Quiet, then rising to loud over 100 ms, then quiet again
+        let amplitude = |t: f32| match t {
+            ..0.2 => 0.01,
+            ..0.3 => 0.01 + (t - 0.2) * 10.0,
+            ..0.6 => 1.0,
+            ..0.7 => 1.0 - (t - 0.6) * 9.9,
+            _ => 0.01,
+        };
+        let input = (0..SAMPLERATE as usize)
+            .map(|i| i as f32 / SAMPLERATE)
+            .map(|t| amplitude(t) * f32::sin(std::f32::consts::TAU * freq * t))
+            .collect::<Vec<_>>();
+        let mut output = vec![0.0; input.len()];
+        let mut expected = vec![0.0; input.len()];
+        let mut factors = vec![];
+        for ((x, y), e) in input
+            .chunks(64)
+            .zip(output.chunks_mut(64))
+            .zip(expected.chunks_mut(64))
+        {
+            adaptive.process_block(
+                AudioBuffer::new([x]).unwrap(),
+                AudioBuffer::new([y]).unwrap(),
+            );
+            reference.process_block(
+                AudioBuffer::new([x]).unwrap(),
+                AudioBuffer::new([e]).unwrap(),
+            );
+            assert_eq!(latency, adaptive.latency());
+            factors.push(adaptive.os_factor());
+        }
+
+        let factor_at = |t: f32| factors[(t * SAMPLERATE) as usize / 64];
+        assert_eq!(1, factor_at(0.1));
+        assert_eq!(4, factor_at(0.5));
+        assert_eq!(1, factor_at(0.99));
+
+        // No dips at the transitions: the level follows the one of the always oversampled
+        // processor, up to the sub-sample misalignment left between both paths
+        let rms = |s: &[f32]| (s.iter().map(|x| x * x).sum::<f32>() / s.len() as f32).sqrt();
+        for (i, (y, e)) in output
+            .chunks(64)
+            .zip(expected.chunks(64))
+            .enumerate()
+            .skip(1)
+        {
+            let (y, e) = (rms(y), rms(e));
+            assert!(
+                (y - e).abs() <= 0.15 * e + 1e-3,
+                "Level of {y} instead of {e} at block {i}"
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_oversample_uses_hint() {
+        #[derive(Debug, Copy, Clone)]
+        struct AlwaysNonlinear;
+
+        impl DSPMeta for AlwaysNonlinear {
+            type Sample = f32;
+        }
+
+        impl DSPProcess<1, 1> for AlwaysNonlinear {
+            fn process(&mut self, x: [f32; 1]) -> [f32; 1] {
+                x
+            }
+        }
+
+        impl NonlinearityHint for AlwaysNonlinear {
+            fn is_nonlinear(&self) -> Option<bool> {
+                Some(true)
+            }
+        }
+
+        let process = |adaptive: &mut AdaptiveOversample<f32, BlockAdapter<AlwaysNonlinear>>| {
+            let input = [0.0; 64];
+            let mut output = [0.0; 64];
+            for _ in 0..100 {
+                adaptive.process_block(
+                    AudioBuffer::new([&input[..]]).unwrap(),
+                    AudioBuffer::new([&mut output[..]]).unwrap(),
+                );
+            }
+        };
+
+        // Without the hint, the quiet input keeps the base rate
+        let mut adaptive =
+            AdaptiveOversample::new(SAMPLERATE, 2, 64, BlockAdapter(AlwaysNonlinear));
+        process(&mut adaptive);
+        assert_eq!(1, adaptive.os_factor());
+
+        let mut adaptive =
+            AdaptiveOversample::new(SAMPLERATE, 2, 64, BlockAdapter(AlwaysNonlinear))
+                .with_nonlinearity_hint();
+        process(&mut adaptive);
+        assert_eq!(2, adaptive.os_factor());
+    }
+}
@@ -5,24 +5,35 @@
 //!
 //! Available here is a polyphase-based oversampling method, with more to come in the future.
 
-use num_complex::Complex;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ops::DerefMut;
 
 use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
 use valib_core::dsp::parameter::HasParameters;
-use valib_core::dsp::DSPProcessBlock;
+use valib_core::dsp::state::DspState;
 use valib_core::dsp::{DSPMeta, DSPProcess};
-use valib_core::simd::SimdComplexField;
+use valib_core::dsp::{DSPProcessBlock, DSPProcessBlockBorrowed};
 use valib_core::Scalar;
 use valib_filters::halfband;
-use valib_filters::halfband::HalfbandFilter;
+use valib_filters::halfband::{FirHalfbandFilter, FirHalfbandState, HalfbandFilter, HalfbandState};
+
+pub mod adaptive;
+pub mod resampler;
+
+pub use resampler::{Decimator, Interpolator};
 
 /// Ping-pong buffer. Allows processing of effect chains operating on buffers, by allowing the input
 /// and output buffers be swapped after each effect.
+///
+/// The buffers are owned by default, but any container dereferencing to a slice can be used, for
+/// example to borrow external memory.
 #[derive(Debug, Clone)]
-pub struct PingPongBuffer<T> {
-    left: Box<[T]>,
-    right: Box<[T]>,
+pub struct PingPongBuffer<T, C = Box<[T]>> {
+    left: C,
+    right: C,
     input_is_left: bool,
+    __element: PhantomData<T>,
 }
 
 impl<T> PingPongBuffer<T> {
@@ -38,10 +49,48 @@ impl<T> PingPongBuffer<T> {
         I::IntoIter: Clone,
     {
         let it = contents.into_iter();
+        Self::from_buffers(it.clone().collect(), it.collect())
+    }
+
+    /// Resize both buffers to the given length. Existing contents are kept, and new elements are
+    /// set to the given value.
+    ///
+    /// This reallocates the buffers, and therefore must not be called from the audio thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_len`: New length of the buffers
+    /// * `fill`: Value to set new elements to
+    ///
+    /// returns: ()
+    pub fn resize(&mut self, new_len: usize, fill: T)
+    where
+        T: Clone,
+    {
+        for buffer in [&mut self.left, &mut self.right] {
+            let mut vec = std::mem::take(buffer).into_vec();
+            vec.resize(new_len, fill.clone());
+            *buffer = vec.into_boxed_slice();
+        }
+    }
+}
+
+impl<T, C: DerefMut<Target = [T]>> PingPongBuffer<T, C> {
+    /// Create a new ping-pong buffer from the two given buffers.
+    ///
+    /// # Arguments
+    ///
+    /// * `left`: First buffer, initially used as the input
+    /// * `right`: Second buffer, initially used as the output
+    ///
+    /// returns: PingPongBuffer<T, C>
+    pub fn from_buffers(left: C, right: C) -> Self {
+        assert_eq!(left.len(), right.len());
         Self {
-            left: it.clone().collect(),
-            right: it.collect(),
+            left,
+            right,
             input_is_left: true,
+            __element: PhantomData,
         }
     }
 
@@ -142,26 +191,127 @@ impl<T> PingPongBuffer<T> {
     }
 }
 
+/// Filter design used by the resampling stages.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum OversampleMode {
+    /// Linear-phase FIR half-band filters. The phase response of the signal is preserved, at the
+    /// cost of latency.
+    LinearPhase,
+    /// Polyphase IIR (allpass) half-band filters. The phase response is not linear, but the group
+    /// delay is much lower.
+    #[default]
+    MinimumPhase,
+}
+
+/// Memories of a [`StageFilter`]
+#[derive(Debug, Clone, Copy)]
+enum StageFilterState<T> {
+    LinearPhase(FirHalfbandState<T, 63>),
+    MinimumPhase(HalfbandState<T, 6>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum StageFilter<T> {
+    LinearPhase(FirHalfbandFilter<T, 63>),
+    MinimumPhase(HalfbandFilter<T, 6>),
+}
+
+impl<T: Scalar> StageFilter<T> {
+    fn new(mode: OversampleMode) -> Self {
+        match mode {
+            OversampleMode::LinearPhase => Self::LinearPhase(halfband::linear_phase_order62()),
+            OversampleMode::MinimumPhase => Self::MinimumPhase(halfband::steep_order12()),
+        }
+    }
+
+    fn latency(&self) -> usize {
+        match self {
+            Self::LinearPhase(filter) => filter.latency(),
+            Self::MinimumPhase(filter) => filter.latency(),
+        }
+    }
+
+    fn group_delay(&self) -> f64 {
+        match self {
+            Self::LinearPhase(filter) => filter.group_delay(),
+            Self::MinimumPhase(filter) => filter.group_delay(),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Self::LinearPhase(filter) => filter.reset(),
+            Self::MinimumPhase(filter) => filter.reset(),
+        }
+    }
+
+    fn snapshot(&self) -> StageFilterState<T> {
+        match self {
+            Self::LinearPhase(filter) => StageFilterState::LinearPhase(filter.snapshot()),
+            Self::MinimumPhase(filter) => StageFilterState::MinimumPhase(filter.snapshot()),
+        }
+    }
+
+    /// Restore the filter memories. A state captured with another filter design does not apply,
+    /// and resets the filter instead.
+    fn restore(&mut self, state: &StageFilterState<T>) {
+        match (self, state) {
+            (Self::LinearPhase(filter), StageFilterState::LinearPhase(state)) => {
+                filter.restore(state)
+            }
+            (Self::MinimumPhase(filter), StageFilterState::MinimumPhase(state)) => {
+                filter.restore(state)
+            }
+            (filter, _) => filter.reset(),
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, x: T) -> T {
+        match self {
+            Self::LinearPhase(filter) => filter.process([x])[0],
+            Self::MinimumPhase(filter) => filter.process([x])[0],
+        }
+    }
+}
+
 /// Single resample stage.
 #[derive(Debug, Clone, Copy)]
 pub struct ResampleStage<T, const UPSAMPLE: bool> {
-    filter: HalfbandFilter<T, 6>,
+    filter: StageFilter<T>,
 }
 
 impl<T: Scalar, const UPSAMPLE: bool> Default for ResampleStage<T, UPSAMPLE> {
     fn default() -> Self {
-        Self {
-            filter: halfband::steep_order12(),
-        }
+        Self::new(OversampleMode::default())
     }
 }
 
 impl<T: Scalar, const UPSAMPLE: bool> ResampleStage<T, UPSAMPLE> {
-    /// Latency of the resample stage
+    /// Create a new resample stage using the filter design of the given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`: Filter design of the stage
+    ///
+    /// returns: ResampleStage<T, { UPSAMPLE }>
+    pub fn new(mode: OversampleMode) -> Self {
+        Self {
+            filter: StageFilter::new(mode),
+        }
+    }
+
+    /// Latency of the resample stage, in samples at the oversampled rate.
     pub fn latency(&self) -> usize {
         self.filter.latency()
     }
 
+    /// Group delay of the resample stage, in samples at the oversampled rate. For
+    /// [`OversampleMode::MinimumPhase`], this is the group delay at DC.
+    pub fn group_delay(&self) -> f64 {
+        self.filter.group_delay()
+    }
+
     /// Reset the resample stage
     pub fn reset(&mut self) {
         self.filter.reset();
@@ -176,8 +326,8 @@ impl<T: Scalar> ResampleStage<T, true> {
     pub fn process_block(&mut self, input: &[T], output: &mut [T]) {
         assert_eq!(input.len() * 2, output.len());
         for (i, s) in input.iter().copied().enumerate() {
-            let [x0] = self.filter.process([s + s]);
-            let [x1] = self.filter.process([T::zero()]);
+            let x0 = self.filter.process(s + s);
+            let x1 = self.filter.process(T::zero());
             output[2 * i + 0] = x0;
             output[2 * i + 1] = x1;
         }
@@ -192,27 +342,232 @@ impl<T: Scalar> ResampleStage<T, false> {
     pub fn process_block(&mut self, input: &[T], output: &mut [T]) {
         assert_eq!(input.len(), 2 * output.len());
         for i in 0..output.len() {
-            let [y] = self.filter.process([input[2 * i + 0]]);
-            let [_] = self.filter.process([input[2 * i + 1]]);
+            let y = self.filter.process(input[2 * i + 0]);
+            self.filter.process(input[2 * i + 1]);
             output[i] = y;
         }
     }
 }
 
+/// Latency of a chain of resample stages, in samples at the output sample rate of the chain.
+fn chain_latency<T: Scalar, const UPSAMPLE: bool>(stages: &[ResampleStage<T, UPSAMPLE>]) -> usize {
+    let num_stages = stages.len() as i32;
+    stages
+        .iter()
+        .enumerate()
+        .map(|(i, stage)| {
+            let i = i as i32;
+            // Scale each stage's latency from its own sample rate to the output sample rate
+            let scale = if UPSAMPLE {
+                2f32.powi(num_stages - 1 - i)
+            } else {
+                2f32.powi(i - num_stages)
+            };
+            stage.latency() as f32 * scale
+        })
+        .sum::<f32>()
+        .round() as usize
+}
+
+/// Estimate the oversampling factor needed to run a waveshaper without aliasing.
+///
+/// A polynomial nonlinearity of order `n` generates harmonics up to `n` times the frequencies of
+/// its input. The recommended factor is the smallest power of two for which the oversampled
+/// Nyquist frequency is above the highest generated harmonic, that is `n * max_input_freq_hz`,
+/// such that no harmonic folds back into the spectrum before downsampling.
+///
+/// Saturators which aren't polynomials, such as `tanh`, generate an infinite series of harmonics;
+/// their amplitude decreases with the order however, and a "practical" order, beyond which the
+/// harmonics are considered inaudible, can be used instead (for example 5 to 9 for soft
+/// clippers driven moderately). Hard clippers have slowly decreasing harmonics, and need
+/// higher orders.
+///
+/// # Arguments
+///
+/// * `saturator_order`: Order of the nonlinearity, that is the highest generated harmonic
+/// * `max_input_freq_hz`: Highest frequency present in the input (Hz)
+/// * `samplerate`: Sample rate before oversampling (Hz)
+///
+/// returns: usize
+///
+/// # Example
+///
+/// ```rust
+/// use valib_oversample::{recommended_oversample_factor, Oversample};
+/// // Cubic waveshaper over the full audible range
+/// let factor = recommended_oversample_factor(3, 20e3, 48e3);
+/// assert_eq!(4, factor);
+/// let os = Oversample::<f32>::new(factor, 512);
+/// assert_eq!(4, os.oversampling_amount());
+/// ```
+pub fn recommended_oversample_factor(
+    saturator_order: usize,
+    max_input_freq_hz: f32,
+    samplerate: f32,
+) -> usize {
+    let highest_harmonic = saturator_order.max(1) as f32 * max_input_freq_hz;
+    let factor = (2.0 * highest_harmonic / samplerate).ceil() as usize;
+    factor.max(1).next_power_of_two()
+}
+
+/// Streaming resampler, changing the sample rate by a power of two one sample at a time.
+///
+/// Samples are pushed into the stream with [`Self::push`], and resampled samples become available
+/// to pull out with [`Self::try_pull`]. This is useful when the input and output rates don't align
+/// to block boundaries.
+#[derive(Debug, Clone)]
+pub struct ResampleStream<T, const UPSAMPLE: bool> {
+    stages: Box<[ResampleStage<T, UPSAMPLE>]>,
+    buffer: PingPongBuffer<T>,
+    num_pending: usize,
+    queue: VecDeque<T>,
+}
+
+impl<T: Scalar, const UPSAMPLE: bool> ResampleStream<T, UPSAMPLE> {
+    /// Create a new resampling stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: Resampling factor. The next power of two will be used if it is not one already.
+    /// * `capacity`: Number of output samples that can be waiting to be pulled out without
+    ///     allocating.
+    ///
+    /// returns: ResampleStream<T, { UPSAMPLE }>
+    pub fn new(factor: usize, capacity: usize) -> Self {
+        assert!(factor >= 1);
+        let factor = factor.next_power_of_two();
+        let num_stages = factor.ilog2() as usize;
+        Self {
+            stages: (0..num_stages).map(|_| ResampleStage::default()).collect(),
+            buffer: PingPongBuffer::new(vec![T::zero(); factor]),
+            num_pending: 0,
+            queue: VecDeque::with_capacity(capacity.max(factor)),
+        }
+    }
+
+    /// Resampling factor of this stream.
+    pub fn factor(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the latency of the stream, in output samples.
+    pub fn latency(&self) -> usize {
+        chain_latency(&self.stages)
+    }
+
+    /// Returns the number of samples available to pull out of the stream.
+    pub fn available(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Pull the next resampled sample out of the stream, if available.
+    pub fn try_pull(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    /// Reset the state of this stream, discarding all pending samples.
+    pub fn reset(&mut self) {
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+        self.buffer.fill(T::zero());
+        self.num_pending = 0;
+        self.queue.clear();
+    }
+}
+
+impl<T: Scalar> ResampleStream<T, true> {
+    /// Push a new sample into the stream. This makes [`Self::factor`] new samples available to
+    /// pull out of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample`: Input sample
+    ///
+    /// returns: ()
+    pub fn push(&mut self, sample: T) {
+        let mut len = 1;
+        let (_, output) = self.buffer.get_io_buffers(..len);
+        output[0] = sample;
+        for stage in &mut self.stages {
+            self.buffer.switch();
+            let (input, output) = self.buffer.get_io_buffers(..2 * len);
+            stage.process_block(&input[..len], output);
+            len *= 2;
+        }
+        self.queue
+            .extend(self.buffer.get_output_ref(..len).iter().copied());
+    }
+}
+
+impl<T: Scalar> ResampleStream<T, false> {
+    /// Push a new sample into the stream. A new sample is available to pull out of the stream
+    /// every [`Self::factor`] pushed samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample`: Input sample
+    ///
+    /// returns: ()
+    pub fn push(&mut self, sample: T) {
+        let factor = self.factor();
+        let (_, output) = self.buffer.get_io_buffers(..);
+        output[self.num_pending] = sample;
+        self.num_pending += 1;
+        if self.num_pending < factor {
+            return;
+        }
+
+        self.num_pending = 0;
+        let mut len = factor;
+        for stage in &mut self.stages {
+            self.buffer.switch();
+            let (input, output) = self.buffer.get_io_buffers(..len);
+            len /= 2;
+            stage.process_block(input, &mut output[..len]);
+        }
+        self.queue.push_back(self.buffer.get_output_ref(..1)[0]);
+    }
+}
+
 /// Raw oversampling type. Works by taking a block of audio, processing it and returning a slice to
 /// an internal buffer containing the upsampled audio data you should process in place. Once done,
 /// call `.finish(output)` on the slice to downsample the internal buffer again, and output it to
 /// `output`.
+///
+/// The scratch buffer holding the oversampled audio is owned by default; see [`OversampleRef`] to
+/// use an externally provided scratch buffer instead.
 #[derive(Debug, Clone)]
-pub struct Oversample<T> {
+pub struct Oversample<T, C = Box<[T]>> {
+    mode: OversampleMode,
     max_factor: usize,
     num_stages_active: usize,
-    os_buffer: PingPongBuffer<T>,
+    os_buffer: PingPongBuffer<T, C>,
     upsample: Box<[ResampleStage<T, true>]>,
     downsample: Box<[ResampleStage<T, false>]>,
 }
 
-impl<T> Oversample<T> {
+/// Captured memories of the resampling filters of an [`Oversample`] instance, as returned by
+/// [`Oversample::snapshot`].
+#[derive(Debug, Clone)]
+pub struct OversampleState<T> {
+    upsample: Box<[StageFilterState<T>]>,
+    downsample: Box<[StageFilterState<T>]>,
+}
+
+/// Oversampling filter using an externally provided scratch buffer, instead of owning it. Only the
+/// (small) state of the resampling filters is allocated on construction.
+///
+/// This allows sharing a single large scratch buffer between oversamplers which are not processing
+/// at the same time.
+pub type OversampleRef<'a, T> = Oversample<T, &'a mut [T]>;
+
+impl<T, C: DerefMut<Target = [T]>> Oversample<T, C> {
+    /// Returns the filter design used by this oversampling filter.
+    pub fn mode(&self) -> OversampleMode {
+        self.mode
+    }
+
     /// Returns the current oversampling amount.
     pub fn oversampling_amount(&self) -> usize {
         usize::pow(2, self.num_stages_active as _)
@@ -254,18 +609,112 @@ impl<T: Scalar> Oversample<T> {
     /// * `max_block_size`: Maximum block size that will be expected to be processed.
     ///
     /// returns: Oversample<T>
-    pub fn new(max_os_factor: usize, max_block_size: usize) -> Self
-    where
-        Complex<T>: SimdComplexField,
-    {
+    pub fn new(max_os_factor: usize, max_block_size: usize) -> Self {
+        Self::new_with_mode(max_os_factor, max_block_size, OversampleMode::default())
+    }
+
+    /// Create a new oversampling filter, using the filter design of the given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_os_factor`: Maximum oversampling factor supported by this instance. The actual
+    ///     oversampling can be changed after creation, but will need to always be less than or equal
+    ///     to this factor.
+    /// * `max_block_size`: Maximum block size that will be expected to be processed.
+    /// * `mode`: Filter design used by the resampling stages.
+    ///
+    /// returns: Oversample<T>
+    pub fn new_with_mode(
+        max_os_factor: usize,
+        max_block_size: usize,
+        mode: OversampleMode,
+    ) -> Self {
         assert!(max_os_factor >= 1);
         let max_os_factor = max_os_factor.next_power_of_two();
-        let num_stages = max_os_factor.ilog2() as usize;
         let os_buffer = vec![T::zero(); max_block_size * max_os_factor];
-        let os_buffer = PingPongBuffer::new(os_buffer);
-        let upsample = (0..num_stages).map(|_| ResampleStage::default()).collect();
-        let downsample = (0..num_stages).map(|_| ResampleStage::default()).collect();
+        Self::from_buffer(max_os_factor, PingPongBuffer::new(os_buffer), mode)
+    }
+
+    /// Sets the maximum block size that will be expected to be processed, at the maximum
+    /// oversampling factor.
+    ///
+    /// This reallocates the internal buffers, and therefore must not be called from the audio
+    /// thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_block_size`: New maximum block size
+    ///
+    /// returns: ()
+    pub fn set_max_block_size(&mut self, max_block_size: usize) {
+        self.os_buffer
+            .resize(max_block_size * self.max_factor, T::zero());
+    }
+
+    /// Construct an [`Oversampled`] given this oversample instance and a block processor to wrap.
+    pub fn with_dsp<P: DSPProcessBlock<1, 1>>(
+        self,
+        samplerate: f32,
+        mut dsp: P,
+    ) -> Oversampled<T, P> {
+        let max_block_size = dsp.max_block_size().unwrap_or(self.os_buffer.len());
+        // Verify that we satisfy the inner DSPBlock instance's requirement on maximum block size
+        assert!(self.os_buffer.len() <= max_block_size);
+        let staging_buffer = vec![T::zero(); max_block_size].into_boxed_slice();
+        // The base rate block size is largest without oversampling
+        let output_buffer = vec![T::zero(); self.os_buffer.len()].into_boxed_slice();
+        dsp.set_samplerate(samplerate * self.oversampling_amount() as f32);
+        dsp.set_oversampling_context(self.oversampling_amount(), samplerate);
+        Oversampled {
+            oversampling: self,
+            staging_buffer,
+            output_buffer,
+            os_len: 0,
+            inner: dsp,
+            base_samplerate: samplerate,
+            reset_on_samplerate_change: false,
+        }
+    }
+}
+
+impl<'a, T: Scalar> OversampleRef<'a, T> {
+    /// Create a new oversampling filter, using the given scratch buffer to hold the oversampled
+    /// audio.
+    ///
+    /// The scratch buffer is split in two halves used as ping-pong buffers; the maximum block size
+    /// at the maximum oversampling factor is therefore `scratch.len() / (2 * max_os_factor)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_os_factor`: Maximum oversampling factor supported by this instance.
+    /// * `scratch`: Scratch buffer
+    /// * `mode`: Filter design used by the resampling stages.
+    ///
+    /// returns: OversampleRef<T>
+    pub fn new_in(max_os_factor: usize, scratch: &'a mut [T], mode: OversampleMode) -> Self {
+        assert!(max_os_factor >= 1);
+        let max_os_factor = max_os_factor.next_power_of_two();
+        let half = scratch.len() / 2;
+        let (left, right) = scratch[..2 * half].split_at_mut(half);
+        Self::from_buffer(
+            max_os_factor,
+            PingPongBuffer::from_buffers(left, right),
+            mode,
+        )
+    }
+}
+
+impl<T: Scalar, C: DerefMut<Target = [T]>> Oversample<T, C> {
+    fn from_buffer(
+        max_os_factor: usize,
+        os_buffer: PingPongBuffer<T, C>,
+        mode: OversampleMode,
+    ) -> Self {
+        let num_stages = max_os_factor.ilog2() as usize;
+        let upsample = (0..num_stages).map(|_| ResampleStage::new(mode)).collect();
+        let downsample = (0..num_stages).map(|_| ResampleStage::new(mode)).collect();
         Self {
+            mode,
             max_factor: max_os_factor,
             num_stages_active: num_stages,
             os_buffer,
@@ -274,20 +723,34 @@ impl<T: Scalar> Oversample<T> {
         }
     }
 
-    /// Returns the latency of the filter. This includes both upsampling and downsampling.
+    /// Returns the latency of the filter, in samples at the base sample rate. This includes both
+    /// upsampling and downsampling, and is [`Self::group_delay_fractional`] rounded to the nearest
+    /// sample.
     pub fn latency(&self) -> usize {
-        let upsample_latency = self
-            .upsample
+        self.group_delay_fractional().round() as usize
+    }
+
+    /// Returns the group delay of the filter, in samples at the base sample rate, including both
+    /// upsampling and downsampling. Unlike [`Self::latency`], the delay is not rounded to a whole
+    /// number of samples, which allows precisely aligning a dry signal with the oversampled one.
+    ///
+    /// For [`OversampleMode::MinimumPhase`], this is the group delay at DC, as it increases with
+    /// frequency.
+    pub fn group_delay_fractional(&self) -> f64 {
+        let n = self.num_stages_active;
+        // Upsampling stage i runs at 2^(i+1) times the base sample rate
+        let upsample_delay = self.upsample[..n]
             .iter()
-            .map(|p| p.latency())
-            .rev()
-            .fold(0.0, |acc, l| acc / 2.0 + l as f32) as usize;
-        let downsample_latency = self
-            .downsample
+            .enumerate()
+            .map(|(i, p)| p.group_delay() / 2f64.powi(i as i32 + 1))
+            .sum::<f64>();
+        // Downsampling stage i runs at 2^(n-i) times the base sample rate
+        let downsample_delay = self.downsample[..n]
             .iter()
-            .map(|p| p.latency())
-            .fold(0.0, |acc, l| acc / 2.0 + l as f32) as usize;
-        2 * self.num_stages_active + upsample_latency + downsample_latency
+            .enumerate()
+            .map(|(i, p)| p.group_delay() / 2f64.powi((n - i) as i32))
+            .sum::<f64>();
+        upsample_delay + downsample_delay
     }
 
     /// Reset the state of this oversampling filter.
@@ -301,22 +764,69 @@ impl<T: Scalar> Oversample<T> {
         }
     }
 
-    /// Construct an [`Oversampled`] given this oversample instance and a block processor to wrap.
-    pub fn with_dsp<P: DSPProcessBlock<1, 1>>(
-        self,
-        samplerate: f32,
-        mut dsp: P,
-    ) -> Oversampled<T, P> {
-        let max_block_size = dsp.max_block_size().unwrap_or(self.os_buffer.len());
-        // Verify that we satisfy the inner DSPBlock instance's requirement on maximum block size
-        assert!(self.os_buffer.len() <= max_block_size);
-        let staging_buffer = vec![T::zero(); max_block_size].into_boxed_slice();
-        dsp.set_samplerate(samplerate * self.num_stages_active as f32);
-        Oversampled {
-            oversampling: self,
-            staging_buffer,
-            inner: dsp,
-            base_samplerate: samplerate,
+    /// Capture the memories of the resampling filters. This allocates, and therefore should not
+    /// be called from the audio thread.
+    pub fn snapshot(&self) -> OversampleState<T> {
+        OversampleState {
+            upsample: self.upsample.iter().map(|s| s.filter.snapshot()).collect(),
+            downsample: self
+                .downsample
+                .iter()
+                .map(|s| s.filter.snapshot())
+                .collect(),
+        }
+    }
+
+    /// Restore the memories of the resampling filters from a snapshot taken with
+    /// [`Self::snapshot`]. This does not allocate.
+    ///
+    /// Only the filter memories are restored; the filter design and the oversampling amount are
+    /// kept. Stages whose filter design differs from the one the snapshot was taken with are reset
+    /// instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: Captured state of the resampling filters
+    ///
+    /// panics if the snapshot was taken from an oversampling filter with a different maximum
+    /// oversampling factor.
+    pub fn restore(&mut self, state: &OversampleState<T>) {
+        assert_eq!(
+            self.upsample.len(),
+            state.upsample.len(),
+            "Snapshot taken with a different maximum oversampling factor"
+        );
+        for (stage, state) in self.upsample.iter_mut().zip(&state.upsample) {
+            stage.filter.restore(state);
+        }
+        for (stage, state) in self.downsample.iter_mut().zip(&state.downsample) {
+            stage.filter.restore(state);
+        }
+    }
+
+    /// Oversample the input, process it with the given callback, and downsample it back into the
+    /// output.
+    ///
+    /// The input can be longer than [`Self::max_block_size`]; it is then split into chunks that
+    /// are processed one after the other.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: Input buffer, at the base sample rate
+    /// * `output`: Output buffer, at the base sample rate. Must be the same length as the input.
+    /// * `f`: Callback processing the oversampled chunks in place
+    ///
+    /// returns: ()
+    #[profiling::function]
+    pub fn process_with(&mut self, input: &[T], output: &mut [T], mut f: impl FnMut(&mut [T])) {
+        assert_eq!(input.len(), output.len());
+        let max_block_size = self.max_block_size();
+        for (input, output) in input
+            .chunks(max_block_size)
+            .zip(output.chunks_mut(max_block_size))
+        {
+            f(self.upsample(input));
+            self.downsample(output);
         }
     }
 
@@ -369,21 +879,74 @@ impl<T: Scalar> Oversample<T> {
 pub struct Oversampled<T, P> {
     oversampling: Oversample<T>,
     staging_buffer: Box<[T]>,
+    output_buffer: Box<[T]>,
+    os_len: usize,
     /// Inner processor
     pub inner: P,
     base_samplerate: f32,
+    reset_on_samplerate_change: bool,
 }
 
-impl<T, P> Oversampled<T, P> {
+impl<T, P: DSPMeta> Oversampled<T, P> {
     /// Return the current oversampling factor
     pub fn os_factor(&self) -> usize {
         self.oversampling.oversampling_amount()
     }
 
+    /// Sets whether the resampling filters and the inner processor are reset when the sample rate
+    /// changes, in a builder fashion. See [`Self::set_reset_on_samplerate_change`].
+    pub fn with_reset_on_samplerate_change(mut self, reset: bool) -> Self {
+        self.reset_on_samplerate_change = reset;
+        self
+    }
+
+    /// Sets whether the resampling filters and the inner processor are reset when the sample rate
+    /// changes. The state left over from the previous sample rate otherwise carries over, which
+    /// can produce transients, but keeps the signal continuous.
+    ///
+    /// This is off by default.
+    pub fn set_reset_on_samplerate_change(&mut self, reset: bool) {
+        self.reset_on_samplerate_change = reset;
+    }
+
+    /// Informs the inner processor of the current oversampling factor and base sample rate.
+    fn update_inner_context(&mut self) {
+        let factor = self.os_factor();
+        self.inner
+            .set_oversampling_context(factor, self.base_samplerate);
+    }
+
     /// Drops the oversampling filter, returning the inner processor.
     pub fn into_inner(self) -> P {
         self.inner
     }
+
+    /// Returns the output of the inner processor from the latest processed block, at the
+    /// oversampled rate, before it is downsampled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use valib_core::dsp::blocks::Bypass;
+    /// use valib_core::dsp::buffer::AudioBufferBox;
+    /// use valib_core::dsp::{BlockAdapter, DSPProcessBlock};
+    /// use valib_oversample::Oversample;
+    /// let mut os = Oversample::<f32>::new(4, 64).with_dsp(44100.0, BlockAdapter(Bypass::default()));
+    /// let input = AudioBufferBox::zeroed(64);
+    /// let mut output = AudioBufferBox::zeroed(64);
+    /// os.process_block(input.as_ref(), output.as_mut());
+    /// assert_eq!(256, os.current_os_len());
+    /// assert_eq!(os.current_os_len(), os.oversampled_output().len());
+    /// ```
+    pub fn oversampled_output(&self) -> &[T] {
+        &self.staging_buffer[..self.os_len]
+    }
+
+    /// Returns the length of the latest oversampled block, that is, the length of the slice
+    /// returned by [`Self::oversampled_output`].
+    pub fn current_os_len(&self) -> usize {
+        self.os_len
+    }
 }
 
 impl<T, P> Oversampled<T, P>
@@ -402,14 +965,48 @@ where
     pub fn inner_samplerate(&self) -> f32 {
         self.base_samplerate * self.oversampling.oversampling_amount() as f32
     }
+
+    /// Upsample the input and process it with the inner processor, leaving the result in the
+    /// oversampling buffer, ready to be downsampled.
+    fn process_oversampled(&mut self, input: &[T]) {
+        let os_block = self.oversampling.upsample(input);
+        self.os_len = os_block.len();
+
+        // Process into the staging buffer, so that the oversampled output stays available after
+        // downsampling
+        let inner_input = AudioBufferRef::new([&*os_block]).unwrap();
+        let inner_output = AudioBufferMut::new([&mut self.staging_buffer[..self.os_len]]).unwrap();
+        self.inner.process_block(inner_input, inner_output);
+        os_block.copy_from_slice(&self.staging_buffer[..self.os_len]);
+    }
 }
 
 impl<T: Scalar, P: DSPMeta<Sample = T>> DSPMeta for Oversampled<T, P> {
     type Sample = T;
 
     fn set_samplerate(&mut self, samplerate: f32) {
+        let changed = samplerate != self.base_samplerate;
+        self.base_samplerate = samplerate;
         self.inner
             .set_samplerate(self.os_factor() as f32 * samplerate);
+        self.update_inner_context();
+        if changed && self.reset_on_samplerate_change {
+            self.reset();
+        }
+    }
+
+    fn try_set_samplerate(&mut self, samplerate: f32) -> bool {
+        if samplerate == self.base_samplerate {
+            return false;
+        }
+        self.base_samplerate = samplerate;
+        self.inner
+            .try_set_samplerate(self.os_factor() as f32 * samplerate);
+        self.update_inner_context();
+        if self.reset_on_samplerate_change {
+            self.reset();
+        }
+        true
     }
 
     fn latency(&self) -> usize {
@@ -422,6 +1019,21 @@ impl<T: Scalar, P: DSPMeta<Sample = T>> DSPMeta for Oversampled<T, P> {
     }
 }
 
+/// The state is made of the state of the resampling filters, along with the state of the inner
+/// processor. Snapshotting allocates, and therefore should not be done from the audio thread.
+impl<T: Scalar, P: DspState<Sample = T>> DspState for Oversampled<T, P> {
+    type State = (OversampleState<T>, P::State);
+
+    fn snapshot(&self) -> Self::State {
+        (self.oversampling.snapshot(), self.inner.snapshot())
+    }
+
+    fn restore(&mut self, (oversampling, inner): &Self::State) {
+        self.oversampling.restore(oversampling);
+        self.inner.restore(inner);
+    }
+}
+
 #[profiling::all_functions]
 impl<T, P> DSPProcessBlock<1, 1> for Oversampled<T, P>
 where
@@ -430,15 +1042,7 @@ where
     P: DSPProcessBlock<1, 1, Sample = T>,
 {
     fn process_block(&mut self, inputs: AudioBufferRef<T, 1>, mut outputs: AudioBufferMut<T, 1>) {
-        let os_block = self.oversampling.upsample(inputs.get_channel(0));
-
-        let mut inner_input =
-            AudioBufferMut::new([&mut self.staging_buffer[..os_block.len()]]).unwrap();
-        inner_input.copy_from_slice(0, os_block);
-        let inner_output = AudioBufferMut::new([os_block]).unwrap();
-
-        self.inner.process_block(inner_input.as_ref(), inner_output);
-
+        self.process_oversampled(inputs.get_channel(0));
         self.oversampling.downsample(outputs.get_channel_mut(0));
     }
 
@@ -447,6 +1051,21 @@ where
     }
 }
 
+#[profiling::all_functions]
+impl<T, P> DSPProcessBlockBorrowed<1, 1> for Oversampled<T, P>
+where
+    Self: DSPMeta<Sample = T>,
+    T: Scalar,
+    P: DSPProcessBlock<1, 1, Sample = T>,
+{
+    fn process_block_borrowed(&mut self, inputs: AudioBufferRef<T, 1>) -> AudioBufferRef<T, 1> {
+        let len = inputs.samples();
+        self.process_oversampled(inputs.get_channel(0));
+        self.oversampling.downsample(&mut self.output_buffer[..len]);
+        AudioBufferRef::new([&self.output_buffer[..len]]).unwrap()
+    }
+}
+
 impl<S, P: HasParameters> HasParameters for Oversampled<S, P> {
     type Name = P::Name;
 
@@ -458,6 +1077,8 @@ impl<S, P: HasParameters> HasParameters for Oversampled<S, P> {
 #[cfg(test)]
 mod tests {
     use numeric_literals::replace_float_literals;
+    use valib_core::dsp::blocks::Integrator;
+    use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
     use valib_core::dsp::{buffer::AudioBufferBox, DSPProcess, DSPProcessBlock as _};
     use valib_core::Scalar;
     use valib_core::{
@@ -465,7 +1086,10 @@ mod tests {
         util::tests::{Plot, Series},
     };
 
-    use super::{Oversample, PingPongBuffer};
+    use super::{
+        recommended_oversample_factor, Oversample, OversampleMode, OversampleRef, Oversampled,
+        PingPongBuffer, ResampleStream,
+    };
 
     #[test]
     fn ping_pong_works() {
@@ -482,6 +1106,277 @@ mod tests {
         assert_eq!(0, output[0]);
     }
 
+    #[test]
+    fn ping_pong_resize() {
+        let mut pingpong = PingPongBuffer::new([1; 4]);
+        pingpong.resize(8, 0);
+        assert_eq!(8, pingpong.len());
+        let (input, output) = pingpong.get_io_buffers(..);
+        assert_eq!([1, 1, 1, 1, 0, 0, 0, 0], input);
+        assert_eq!([1, 1, 1, 1, 0, 0, 0, 0], output);
+    }
+
+    #[test]
+    fn set_max_block_size_allows_larger_blocks() {
+        let input = (0..256)
+            .map(|i| f32::sin(i as f32 / 5.0))
+            .collect::<Vec<_>>();
+
+        let mut expected = vec![0.0; input.len()];
+        let mut os = Oversample::<f32>::new(4, 256);
+        os.upsample(&input);
+        os.downsample(&mut expected);
+
+        let mut os = Oversample::<f32>::new(4, 16);
+        os.set_max_block_size(256);
+        assert_eq!(256, os.max_block_size());
+        let mut output = vec![0.0; input.len()];
+        os.upsample(&input);
+        os.downsample(&mut output);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn oversample_wide_matches_scalar_lanes() {
+        use valib_core::simd::{SimdPartialOrd, SimdValue, WideF32x4, WideF64x4};
+
+        let lanes = [0.05, 0.2, 0.7, 1.3].map(|w: f32| {
+            (0..64)
+                .map(|i| 2.0 * f32::sin(i as f32 * w))
+                .collect::<Vec<_>>()
+        });
+        let input = (0..64)
+            .map(|i| WideF32x4::from(lanes.each_ref().map(|lane| lane[i])))
+            .collect::<Vec<_>>();
+
+        let mut os = Oversample::<WideF32x4>::new(4, 64);
+        for x in os.upsample(&input) {
+            *x = x.simd_clamp(WideF32x4::splat(-1.0), WideF32x4::splat(1.0));
+        }
+        let mut output = vec![WideF32x4::splat(0.0); input.len()];
+        os.downsample(&mut output);
+
+        for (lane, input) in lanes.iter().enumerate() {
+            let mut os = Oversample::<f32>::new(4, 64);
+            for x in os.upsample(input) {
+                *x = x.clamp(-1.0, 1.0);
+            }
+            let mut expected = vec![0.0; input.len()];
+            os.downsample(&mut expected);
+            for (y, expected) in output.iter().zip(expected) {
+                assert!((y.extract(lane) - expected).abs() < 1e-6);
+            }
+        }
+
+        let mut os = Oversample::<WideF64x4>::new(4, 64);
+        assert_eq!(4, os.upsample(&[WideF64x4::splat(1.0); 64]).len() / 64);
+    }
+
+    #[rstest::rstest]
+    fn oversample_ref_matches_owned(
+        #[values(OversampleMode::LinearPhase, OversampleMode::MinimumPhase)] mode: OversampleMode,
+    ) {
+        let saturate = |x: &mut [f32]| x.iter_mut().for_each(|x| *x = (2.0 * *x).tanh());
+        let input = (0..200)
+            .map(|i| f32::sin(i as f32 / 5.0))
+            .collect::<Vec<_>>();
+
+        let mut os = Oversample::<f32>::new_with_mode(4, 64, mode);
+        let mut expected = vec![0.0; input.len()];
+        os.process_with(&input, &mut expected, saturate);
+
+        let mut scratch = vec![0.0; 2 * 4 * 64];
+        let mut os = OversampleRef::new_in(4, &mut scratch, mode);
+        assert_eq!(64, os.max_block_size());
+        let mut output = vec![0.0; input.len()];
+        os.process_with(&input, &mut output, saturate);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn resample_stream_output_count() {
+        const CHUNKS: [usize; 6] = [1, 7, 3, 16, 5, 32];
+        let total: usize = CHUNKS.iter().sum();
+        let mut upsample = ResampleStream::<f32, true>::new(4, 16);
+        let mut downsample = ResampleStream::<f32, false>::new(4, 16);
+
+        let mut i = 0;
+        let mut num_up = 0;
+        let mut num_down = 0;
+        for chunk in CHUNKS {
+            for _ in 0..chunk {
+                let x = f32::sin(i as f32 / 10.0);
+                upsample.push(x);
+                downsample.push(x);
+                i += 1;
+            }
+            while let Some(y) = upsample.try_pull() {
+                assert!(y.is_finite());
+                num_up += 1;
+            }
+            while let Some(y) = downsample.try_pull() {
+                assert!(y.is_finite());
+                num_down += 1;
+            }
+        }
+
+        assert_eq!(4 * total, num_up);
+        assert_eq!(total / 4, num_down);
+    }
+
+    #[test]
+    fn process_with_chunks_long_input() {
+        let saturate = |x: &mut [f32]| x.iter_mut().for_each(|x| *x = (2.0 * *x).tanh());
+        let input = (0..300)
+            .map(|i| f32::sin(i as f32 / 5.0))
+            .collect::<Vec<_>>();
+
+        let mut os = Oversample::<f32>::new(4, 64);
+        let mut expected = vec![0.0; input.len()];
+        for (input, output) in input.chunks(64).zip(expected.chunks_mut(64)) {
+            saturate(os.upsample(input));
+            os.downsample(output);
+        }
+
+        let mut os = Oversample::<f32>::new(4, 64);
+        let mut output = vec![0.0; input.len()];
+        os.process_with(&input, &mut output, saturate);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn oversampled_samplerate_change() {
+        struct Samplerate(f32);
+        impl DSPMeta for Samplerate {
+            type Sample = f32;
+
+            fn set_samplerate(&mut self, samplerate: f32) {
+                self.0 = samplerate;
+            }
+        }
+        impl DSPProcess<1, 1> for Samplerate {
+            fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+                x
+            }
+        }
+
+        let mut os = Oversample::<f32>::new(4, 64).with_dsp(44.1e3, BlockAdapter(Samplerate(0.0)));
+        assert_eq!(4.0 * 44.1e3, os.inner.0 .0);
+        assert!(!os.try_set_samplerate(44.1e3));
+        assert!(os.try_set_samplerate(48e3));
+        assert_eq!(4.0 * 48e3, os.inner.0 .0);
+        assert!(!os.try_set_samplerate(48e3));
+    }
+
+    #[test]
+    fn recommended_factor_increases_with_order() {
+        let factors =
+            [1, 2, 3, 5, 7, 9, 15].map(|order| recommended_oversample_factor(order, 20e3, 48e3));
+        assert_eq!([1, 2, 4, 8, 8, 8, 16], factors);
+        assert!(factors.windows(2).all(|w| w[0] <= w[1]));
+
+        // Band-limited inputs need less oversampling
+        assert_eq!(1, recommended_oversample_factor(3, 5e3, 48e3));
+        // Linear processing needs no oversampling
+        assert_eq!(1, recommended_oversample_factor(1, 20e3, 44.1e3));
+    }
+
+    #[test]
+    fn oversampled_reset_on_samplerate_change() {
+        let process = |os: &mut Oversampled<f32, _>| {
+            let input = [1.0; 64];
+            let mut output = [0.0; 64];
+            os.process_block(
+                AudioBufferRef::new([&input[..]]).unwrap(),
+                AudioBufferMut::new([&mut output[..]]).unwrap(),
+            );
+        };
+
+        let mut os =
+            Oversample::<f32>::new(4, 64).with_dsp(44.1e3, BlockAdapter(Integrator::default()));
+        process(&mut os);
+        assert!(os.inner.0 .0 > 0.0);
+        // Off by default, the state carries over
+        os.set_samplerate(48e3);
+        assert!(os.inner.0 .0 > 0.0);
+
+        os.set_reset_on_samplerate_change(true);
+        // Setting the same sample rate is not a change
+        os.set_samplerate(48e3);
+        assert!(os.inner.0 .0 > 0.0);
+        os.set_samplerate(44.1e3);
+        assert_eq!(0.0, os.inner.0 .0);
+
+        process(&mut os);
+        assert!(os.try_set_samplerate(48e3));
+        assert_eq!(0.0, os.inner.0 .0);
+    }
+
+    #[test]
+    fn oversampled_inner_knows_factor() {
+        #[derive(Default)]
+        struct Recorder {
+            factor: usize,
+            base_samplerate: f32,
+        }
+        impl DSPMeta for Recorder {
+            type Sample = f32;
+
+            fn set_oversampling_context(&mut self, factor: usize, base_samplerate: f32) {
+                self.factor = factor;
+                self.base_samplerate = base_samplerate;
+            }
+        }
+        impl DSPProcess<1, 1> for Recorder {
+            fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
+                x
+            }
+        }
+
+        let mut os =
+            Oversample::<f32>::new(8, 64).with_dsp(44.1e3, BlockAdapter(Recorder::default()));
+        assert_eq!(8, os.inner.0.factor);
+        assert_eq!(44.1e3, os.inner.0.base_samplerate);
+
+        os.set_oversampling_amount(2);
+        assert_eq!(2, os.inner.0.factor);
+        os.set_samplerate(48e3);
+        assert_eq!(2, os.inner.0.factor);
+        assert_eq!(48e3, os.inner.0.base_samplerate);
+    }
+
+    #[rstest::rstest]
+    fn oversample_mode_latency(#[values(2, 4, 8)] factor: usize) {
+        let linear = Oversample::<f32>::new_with_mode(factor, 256, OversampleMode::LinearPhase);
+        let minimum = Oversample::<f32>::new_with_mode(factor, 256, OversampleMode::MinimumPhase);
+        assert!(
+            minimum.latency() < linear.latency(),
+            "Minimum phase latency {} >= linear phase latency {}",
+            minimum.latency(),
+            linear.latency()
+        );
+
+        // The impulse response of the linear-phase filters peaks at the reported latency
+        let mut os = linear;
+        let mut input = [0.0; 256];
+        input[0] = 1.0;
+        let mut output = [0.0; 256];
+        os.upsample(&input);
+        os.downsample(&mut output);
+        let peak = output
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .unwrap()
+            .0;
+        assert!(
+            peak.abs_diff(os.latency()) <= 1,
+            "Impulse peak at {peak}, reported latency {}",
+            os.latency()
+        );
+    }
+
     #[test]
     fn oversampled_dsp_block() {
         use plotters::prelude::*;
@@ -525,7 +1420,7 @@ mod tests {
                 Series {
                     label: "Oversampled",
                     samplerate: 4. * samplerate,
-                    series: os.oversampling.os_buffer.get_output_ref(..),
+                    series: os.oversampled_output(),
                     color: &YELLOW,
                 },
                 Series {
@@ -539,4 +1434,158 @@ mod tests {
         .create_svg("plots/oversample/dsp_block.svg");
         insta::assert_csv_snapshot!(output.get_channel(0), { "[]" => insta::rounded_redaction(3) });
     }
+
+    #[test]
+    fn oversampled_borrowed_chain() {
+        use valib_core::dsp::blocks::P1;
+        use valib_core::dsp::DSPProcessBlockBorrowed;
+
+        const BLOCK: usize = 64;
+        let make = |fc| {
+            Oversample::<f32>::new(4, BLOCK).with_dsp(44100.0, BlockAdapter(P1::new(44100.0, fc)))
+        };
+        let mut expected = [make(1000.0), make(5000.0)];
+        let mut borrowed = [make(1000.0), make(5000.0)];
+        let mut intermediate = AudioBufferBox::zeroed(BLOCK);
+        let mut output = AudioBufferBox::zeroed(BLOCK);
+
+        for block in 0..8 {
+            let input = AudioBufferBox::new([(0..BLOCK)
+                .map(|i| f32::sin((block * BLOCK + i) as f32 / 5.0))
+                .collect()])
+            .unwrap();
+            expected[0].process_block(input.as_ref(), intermediate.as_mut());
+            expected[1].process_block(intermediate.as_ref(), output.as_mut());
+
+            let [first, second] = &mut borrowed;
+            let chained =
+                second.process_block_borrowed(first.process_block_borrowed(input.as_ref()));
+            assert_eq!(&output.get_channel(0)[..], *chained.get_channel(0));
+        }
+    }
+
+    #[test]
+    fn group_delay_fractional_null_test() {
+        const SAMPLERATE: f64 = 44100.0;
+        let w = std::f64::consts::TAU * 1000.0 / SAMPLERATE;
+        let input = (0..2048)
+            .map(|i| f64::sin(w * i as f64))
+            .collect::<Vec<_>>();
+
+        let mut os = Oversample::<f64>::new_with_mode(4, 256, OversampleMode::LinearPhase);
+        let delay = os.group_delay_fractional();
+        assert_eq!(46.5, delay);
+        let mut output = vec![0.0; input.len()];
+        os.process_with(&input, &mut output, |_| {});
+
+        let residual = |delay: f64| {
+            output
+                .iter()
+                .enumerate()
+                .skip(256)
+                .map(|(i, y)| (y - f64::sin(w * (i as f64 - delay))).abs())
+                .fold(0.0, f64::max)
+        };
+        let fractional = residual(delay);
+        let rounded = residual(os.latency() as f64);
+        assert!(fractional < 1e-3, "Residual of {fractional}");
+        assert!(
+            rounded > 10.0 * fractional,
+            "Rounded residual of {rounded} vs. fractional residual of {fractional}"
+        );
+    }
+
+    #[test]
+    fn oversampled_aliasing_rejection() {
+        use valib_core::util::tests::measure_aliasing;
+
+        struct Drive;
+        impl DSPMeta for Drive {
+            type Sample = f32;
+        }
+        impl DSPProcess<1, 1> for Drive {
+            fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+                [f32::tanh(4.0 * x)]
+            }
+        }
+
+        const SAMPLERATE: f32 = 44100.0;
+        const FREQ: f32 = 5000.0;
+        let aliasing_db = |factor| {
+            let mut os =
+                Oversample::<f32>::new(factor, 256).with_dsp(SAMPLERATE, BlockAdapter(Drive));
+            10.0 * measure_aliasing(&mut os, FREQ, SAMPLERATE).log10()
+        };
+        let naive = aliasing_db(1);
+        let oversampled = aliasing_db(4);
+        assert!(oversampled < -70.0, "Aliasing at {oversampled} dB");
+        assert!(
+            oversampled < naive - 30.0,
+            "Aliasing at {oversampled} dB, vs. {naive} dB without oversampling"
+        );
+    }
+
+    #[test]
+    fn oversampled_snapshot_restore() {
+        use valib_core::dsp::state::DspState;
+        use valib_filters::biquad::Biquad;
+        use valib_saturators::Linear;
+
+        const BLOCK: usize = 64;
+        let mut os = Oversample::<f32>::new(4, BLOCK).with_dsp(
+            44100.0,
+            BlockAdapter(Biquad::<f32, Linear>::lowpass(0.02, 4.0)),
+        );
+        let mut impulse = AudioBufferBox::zeroed(BLOCK);
+        impulse.get_channel_mut(0)[0] = 1.0;
+        let silence = AudioBufferBox::zeroed(BLOCK);
+        let mut output = AudioBufferBox::zeroed(BLOCK);
+        os.process_block(impulse.as_ref(), output.as_mut());
+
+        let state = os.snapshot();
+        let mut expected = AudioBufferBox::zeroed(BLOCK);
+        os.process_block(silence.as_ref(), expected.as_mut());
+        assert!(expected.get_channel(0).iter().any(|y| y.abs() > 1e-3));
+        // Processing something else in between
+        os.process_block(impulse.as_ref(), output.as_mut());
+
+        os.restore(&state);
+        os.process_block(silence.as_ref(), output.as_mut());
+        assert_eq!(expected.get_channel(0), output.get_channel(0));
+    }
+
+    #[test]
+    fn restore_keeps_filter_design() {
+        let linear = Oversample::<f32>::new_with_mode(4, 64, OversampleMode::LinearPhase);
+        let mut minimum = Oversample::<f32>::new_with_mode(4, 64, OversampleMode::MinimumPhase);
+        let latency = minimum.latency();
+        minimum.restore(&linear.snapshot());
+        assert_eq!(OversampleMode::MinimumPhase, minimum.mode());
+        assert_eq!(latency, minimum.latency());
+    }
+
+    #[rstest::rstest]
+    fn latency_matches_impulse_peak(
+        #[values(OversampleMode::LinearPhase, OversampleMode::MinimumPhase)] mode: OversampleMode,
+        #[values(1, 2, 4, 8)] factor: usize,
+    ) {
+        let mut os = Oversample::<f64>::new_with_mode(8, 256, mode);
+        os.set_oversampling_amount(factor);
+        let mut input = vec![0.0; 256];
+        input[0] = 1.0;
+        let mut output = vec![0.0; 256];
+        os.process_with(&input, &mut output, |_| {});
+
+        let (peak, _) = output
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .unwrap();
+        let latency = os.latency();
+        // The minimum-phase filters peak slightly after their group delay at DC
+        assert!(
+            latency.abs_diff(peak) <= 1,
+            "Latency {latency}, impulse peak at {peak}"
+        );
+    }
 }
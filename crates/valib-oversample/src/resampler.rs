@@ -0,0 +1,247 @@
+//! # One-way resampling
+//!
+//! Provides block processors only going one way, either up or down in sample rate, for pipelines
+//! which do not come back to the original sample rate, like analysis or multirate chains.
+use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use valib_core::dsp::{DSPMeta, DSPProcessBlock};
+use valib_core::Scalar;
+
+use crate::{chain_latency, OversampleMode, PingPongBuffer, ResampleStage};
+
+/// Block resampler changing the sample rate by a power of two, in one direction only.
+///
+/// Unlike other block processors, the output of a resampler does not have the same length as its
+/// input: [`Interpolator`] outputs [`Self::factor`] times more samples than it is given, and
+/// [`Decimator`] that many times fewer. Inputs of any length can be processed, as they are split
+/// into chunks internally; decimator inputs must have a length multiple of the factor.
+#[derive(Debug, Clone)]
+pub struct Resampler<T, const UPSAMPLE: bool> {
+    factor: usize,
+    stages: Box<[ResampleStage<T, UPSAMPLE>]>,
+    buffer: PingPongBuffer<T>,
+}
+
+/// Block upsampler, outputting [`Resampler::factor`] samples for each input sample.
+pub type Interpolator<T> = Resampler<T, true>;
+
+/// Block downsampler, outputting one sample for each [`Resampler::factor`] input samples.
+pub type Decimator<T> = Resampler<T, false>;
+
+impl<T: Scalar, const UPSAMPLE: bool> Resampler<T, UPSAMPLE> {
+    /// Create a new resampler.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: Resampling factor. The next power of two will be used if it is not one already.
+    /// * `max_block_size`: Maximum number of samples processed at once at the lower sample rate.
+    ///     Longer buffers are split into chunks of this size.
+    ///
+    /// returns: Resampler<T, { UPSAMPLE }>
+    pub fn new(factor: usize, max_block_size: usize) -> Self {
+        Self::new_with_mode(factor, max_block_size, OversampleMode::default())
+    }
+
+    /// Create a new resampler, using the filter design of the given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: Resampling factor. The next power of two will be used if it is not one already.
+    /// * `max_block_size`: Maximum number of samples processed at once at the lower sample rate.
+    ///     Longer buffers are split into chunks of this size.
+    /// * `mode`: Filter design used by the resampling stages.
+    ///
+    /// returns: Resampler<T, { UPSAMPLE }>
+    pub fn new_with_mode(factor: usize, max_block_size: usize, mode: OversampleMode) -> Self {
+        assert!(factor >= 1);
+        assert!(max_block_size >= 1);
+        let factor = factor.next_power_of_two();
+        let num_stages = factor.ilog2() as usize;
+        Self {
+            factor,
+            stages: (0..num_stages).map(|_| ResampleStage::new(mode)).collect(),
+            buffer: PingPongBuffer::new(vec![T::zero(); max_block_size * factor]),
+        }
+    }
+
+    /// Resampling factor of this resampler.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Maximum number of samples processed at once at the lower sample rate.
+    fn chunk_size(&self) -> usize {
+        self.buffer.len() / self.factor
+    }
+}
+
+impl<T: Scalar> Resampler<T, true> {
+    fn upsample(&mut self, input: &[T], output: &mut [T]) {
+        let mut len = input.len();
+        let (_, buffer) = self.buffer.get_io_buffers(..len);
+        buffer.copy_from_slice(input);
+        for stage in &mut self.stages {
+            self.buffer.switch();
+            let (input, output) = self.buffer.get_io_buffers(..2 * len);
+            stage.process_block(&input[..len], output);
+            len *= 2;
+        }
+        self.buffer.copy_into(output);
+    }
+}
+
+impl<T: Scalar> Resampler<T, false> {
+    fn downsample(&mut self, input: &[T], output: &mut [T]) {
+        let mut len = input.len();
+        let (_, buffer) = self.buffer.get_io_buffers(..len);
+        buffer.copy_from_slice(input);
+        for stage in &mut self.stages {
+            self.buffer.switch();
+            let (input, output) = self.buffer.get_io_buffers(..len);
+            len /= 2;
+            stage.process_block(input, &mut output[..len]);
+        }
+        self.buffer.copy_into(output);
+    }
+}
+
+impl<T: Scalar, const UPSAMPLE: bool> DSPMeta for Resampler<T, UPSAMPLE> {
+    type Sample = T;
+
+    /// Latency of the resampler, in samples at the output sample rate.
+    fn latency(&self) -> usize {
+        chain_latency(&self.stages)
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(T::zero());
+        for stage in &mut self.stages {
+            stage.reset();
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcessBlock<1, 1> for Resampler<T, true> {
+    /// Upsample the input. The output buffer must be [`Self::factor`] times longer than the
+    /// input buffer.
+    fn process_block(&mut self, inputs: AudioBufferRef<T, 1>, mut outputs: AudioBufferMut<T, 1>) {
+        assert_eq!(inputs.samples() * self.factor, outputs.samples());
+        let chunk_size = self.chunk_size();
+        for (input, output) in inputs.get_channel(0).chunks(chunk_size).zip(
+            outputs
+                .get_channel_mut(0)
+                .chunks_mut(chunk_size * self.factor),
+        ) {
+            self.upsample(input, output);
+        }
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcessBlock<1, 1> for Resampler<T, false> {
+    /// Downsample the input. The input buffer must be [`Self::factor`] times longer than the
+    /// output buffer.
+    fn process_block(&mut self, inputs: AudioBufferRef<T, 1>, mut outputs: AudioBufferMut<T, 1>) {
+        assert_eq!(inputs.samples(), outputs.samples() * self.factor);
+        let chunk_size = self.chunk_size();
+        for (input, output) in inputs
+            .get_channel(0)
+            .chunks(chunk_size * self.factor)
+            .zip(outputs.get_channel_mut(0).chunks_mut(chunk_size))
+        {
+            self.downsample(input, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::dsp::buffer::AudioBuffer;
+
+    /// Amplitude of the given normalized frequency in the signal, skipping the first samples to
+    /// let the filters settle
+    fn amplitude_at(signal: &[f64], freq: f64) -> f64 {
+        let signal = &signal[signal.len() / 4..];
+        let (re, im) = signal
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (i, x)| {
+                let phase = std::f64::consts::TAU * freq * i as f64;
+                (re + x * phase.cos(), im - x * phase.sin())
+            });
+        2.0 * f64::hypot(re, im) / signal.len() as f64
+    }
+
+    fn sine(freq: f64, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|i| f64::sin(std::f64::consts::TAU * freq * i as f64))
+            .collect()
+    }
+
+    #[test]
+    fn interpolator_rejects_images() {
+        let mut interpolator = Interpolator::<f64>::new(4, 64);
+        assert_eq!(4, interpolator.factor());
+        let input = sine(0.1, 1000);
+        let mut output = vec![0.0; 4 * input.len()];
+        interpolator.process_block(
+            AudioBuffer::new([&input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+        );
+
+        // Frequencies normalized to the output sample rate
+        let signal = amplitude_at(&output, 0.1 / 4.0);
+        assert!((signal - 1.0).abs() < 0.01, "Signal amplitude of {signal}");
+        for image in [0.9 / 4.0, 1.1 / 4.0, 1.9 / 4.0] {
+            let image = amplitude_at(&output, image);
+            assert!(image < 1e-3, "Image amplitude of {image}");
+        }
+    }
+
+    #[test]
+    fn decimator_rejects_aliases() {
+        let mut decimator = Decimator::<f64>::new(4, 64);
+        let mut run = |freq: f64| {
+            decimator.reset();
+            let input = sine(freq, 4000);
+            let mut output = vec![0.0; input.len() / 4];
+            decimator.process_block(
+                AudioBuffer::new([&input[..]]).unwrap(),
+                AudioBuffer::new([&mut output[..]]).unwrap(),
+            );
+            output
+        };
+
+        // Frequencies normalized to the input sample rate
+        let output = run(0.05);
+        assert_eq!(1000, output.len());
+        let signal = amplitude_at(&output, 0.2);
+        assert!((signal - 1.0).abs() < 0.01, "Signal amplitude of {signal}");
+
+        // Would alias down to 0.2 of the output sample rate
+        let output = run(0.2);
+        let alias = amplitude_at(&output, 0.2);
+        assert!(alias < 1e-3, "Alias amplitude of {alias}");
+    }
+
+    #[test]
+    fn resampler_latency_matches_impulse_response() {
+        let mut interpolator =
+            Interpolator::<f64>::new_with_mode(2, 64, OversampleMode::LinearPhase);
+        let mut input = vec![0.0; 64];
+        input[0] = 1.0;
+        let mut output = vec![0.0; 128];
+        interpolator.process_block(
+            AudioBuffer::new([&input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+        );
+        let peak = output
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .unwrap()
+            .0;
+        assert_eq!(interpolator.latency(), peak);
+    }
+}
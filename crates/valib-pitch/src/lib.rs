@@ -0,0 +1,345 @@
+#![warn(missing_docs)]
+//! # Pitch shifting
+//!
+//! This crate provides pitch shifting for `valib`, changing the pitch of the input without changing
+//! its duration.
+use nalgebra::Complex;
+use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use valib_core::dsp::{DSPMeta, DSPProcessBlock};
+use valib_core::math::fft::RealFft;
+use valib_core::math::window;
+use valib_core::Scalar;
+
+/// Number of analysis frames overlapping each sample
+const OVERLAP: usize = 4;
+
+/// Phase vocoder pitch shifter.
+///
+/// The input is analyzed in overlapping windowed frames. The true frequency of each bin is
+/// estimated from its phase difference between consecutive frames, and the bins are moved to
+/// their shifted frequency before being resynthesized. This keeps the duration of the input, at
+/// the cost of some smearing of transients, which gets worse with larger frames.
+///
+/// The output is delayed by three quarters of the frame size, which is reported as the latency of
+/// the processor. The processor can be called with blocks of any size regardless of its frame
+/// size.
+pub struct PitchShifter<T: Scalar> {
+    frame_size: usize,
+    hop_size: usize,
+    semitones: T,
+    ratio: T,
+    fft: RealFft<T>,
+    window: Box<[T]>,
+    input: Box<[T]>,
+    output: Box<[T]>,
+    accumulator: Box<[T]>,
+    pos: usize,
+    time: Box<[T]>,
+    spectrum: Box<[Complex<T>]>,
+    last_phase: Box<[T]>,
+    sum_phase: Box<[T]>,
+    synth_magnitude: Box<[T]>,
+    synth_frequency: Box<[T]>,
+}
+
+impl<T: Scalar> PitchShifter<T> {
+    /// Create a new pitch shifter, with no shift.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_size`: Size of the analysis frames, rounded up to the next power of two (and at
+    ///     least 16). Larger frames resolve low frequencies better, but smear transients and add
+    ///     latency.
+    ///
+    /// returns: PitchShifter<T>
+    pub fn new(frame_size: usize) -> Self {
+        let frame_size = frame_size.max(16).next_power_of_two();
+        let bins = frame_size / 2 + 1;
+        // Periodic Hann window, for perfect overlap
+        let mut window = vec![T::zero(); frame_size + 1];
+        window::hann(&mut window);
+        window.pop();
+        Self {
+            frame_size,
+            hop_size: frame_size / OVERLAP,
+            semitones: T::zero(),
+            ratio: T::one(),
+            fft: RealFft::new(frame_size),
+            window: window.into_boxed_slice(),
+            input: vec![T::zero(); frame_size].into_boxed_slice(),
+            output: vec![T::zero(); frame_size].into_boxed_slice(),
+            accumulator: vec![T::zero(); frame_size].into_boxed_slice(),
+            pos: 0,
+            time: vec![T::zero(); frame_size].into_boxed_slice(),
+            spectrum: vec![Complex::new(T::zero(), T::zero()); bins].into_boxed_slice(),
+            last_phase: vec![T::zero(); bins].into_boxed_slice(),
+            sum_phase: vec![T::zero(); bins].into_boxed_slice(),
+            synth_magnitude: vec![T::zero(); bins].into_boxed_slice(),
+            synth_frequency: vec![T::zero(); bins].into_boxed_slice(),
+        }
+    }
+
+    /// Size of the analysis frames
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Current pitch shift, in semitones
+    pub fn semitones(&self) -> T {
+        self.semitones
+    }
+
+    /// Sets the pitch shift, in semitones. Negative values shift the pitch down.
+    pub fn set_semitones(&mut self, semitones: T) {
+        self.semitones = semitones;
+        self.ratio = T::from_f64(2.0).simd_powf(semitones / T::from_f64(12.0));
+    }
+
+    /// Wrap the phase into the -pi..pi range
+    fn wrap_phase(phase: T) -> T {
+        let two_pi = T::simd_two_pi();
+        phase - two_pi * (phase / two_pi).simd_round()
+    }
+
+    /// Analyze the full input frame, and add its resynthesis to the output accumulator
+    fn process_frame(&mut self) {
+        let bins = self.spectrum.len();
+        let two_pi = T::simd_two_pi();
+        let overlap = T::from_f64(OVERLAP as _);
+        // Phase advance of each bin between frames, per bin index
+        let expected = two_pi / overlap;
+
+        for ((t, &x), &w) in self.time.iter_mut().zip(&self.input).zip(&self.window) {
+            *t = x * w;
+        }
+        self.fft.forward(&self.time, &mut self.spectrum);
+
+        // Analysis, moving each bin to its shifted position
+        self.synth_magnitude.fill(T::zero());
+        self.synth_frequency.fill(T::zero());
+        for (k, (x, last_phase)) in self.spectrum.iter().zip(&mut *self.last_phase).enumerate() {
+            let magnitude = x.re.simd_hypot(x.im);
+            let phase = x.im.simd_atan2(x.re);
+            let delta = Self::wrap_phase(phase - *last_phase - T::from_f64(k as _) * expected);
+            *last_phase = phase;
+            // True frequency of the bin, in bins
+            let frequency = T::from_f64(k as _) + delta * overlap / two_pi;
+
+            // Lanes can shift by different ratios, and are therefore moved separately
+            let target = (T::from_f64(k as _) * self.ratio).simd_round();
+            let shifted = frequency * self.ratio;
+            for lane in 0..T::LANES {
+                // Splatting the lane makes the conversion exact, as all lanes are equal
+                let Some(target): Option<f64> = T::splat(target.extract(lane)).to_subset() else {
+                    continue;
+                };
+                // Non-finite targets come from a non-finite ratio, and have nowhere to go
+                if !target.is_finite() || target >= bins as f64 {
+                    continue;
+                }
+                let target = target as usize;
+                let bin = &mut self.synth_magnitude[target];
+                let sum = *bin + T::splat(magnitude.extract(lane));
+                bin.replace(lane, sum.extract(lane));
+                self.synth_frequency[target].replace(lane, shifted.extract(lane));
+            }
+        }
+
+        // Synthesis, accumulating the phase of each bin at its shifted frequency
+        for (k, ((x, sum_phase), (&magnitude, &frequency))) in self
+            .spectrum
+            .iter_mut()
+            .zip(&mut *self.sum_phase)
+            .zip(self.synth_magnitude.iter().zip(&*self.synth_frequency))
+            .enumerate()
+        {
+            let delta = (frequency - T::from_f64(k as _)) * two_pi / overlap
+                + T::from_f64(k as _) * expected;
+            *sum_phase = Self::wrap_phase(*sum_phase + delta);
+            let (sin, cos) = sum_phase.simd_sin_cos();
+            *x = Complex::new(magnitude * cos, magnitude * sin);
+        }
+        self.fft.inverse(&self.spectrum, &mut self.time);
+
+        // Overlap-add, normalizing both the inverse FFT and the sum of the squared windows
+        let scale = T::from_f64(8.0 / (3.0 * OVERLAP as f64 * self.frame_size as f64));
+        for ((acc, &t), &w) in self
+            .accumulator
+            .iter_mut()
+            .zip(&*self.time)
+            .zip(&*self.window)
+        {
+            *acc += t * w * scale;
+        }
+        let hop = self.hop_size;
+        self.output[..hop].copy_from_slice(&self.accumulator[..hop]);
+        self.accumulator.copy_within(hop.., 0);
+        let len = self.accumulator.len();
+        self.accumulator[len - hop..].fill(T::zero());
+        self.input.copy_within(hop.., 0);
+    }
+}
+
+impl<T: Scalar> DSPMeta for PitchShifter<T> {
+    type Sample = T;
+
+    fn latency(&self) -> usize {
+        self.frame_size - self.hop_size
+    }
+
+    fn reset(&mut self) {
+        self.input.fill(T::zero());
+        self.output.fill(T::zero());
+        self.accumulator.fill(T::zero());
+        self.pos = 0;
+        self.last_phase.fill(T::zero());
+        self.sum_phase.fill(T::zero());
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcessBlock<1, 1> for PitchShifter<T> {
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, 1>,
+        mut outputs: AudioBufferMut<Self::Sample, 1>,
+    ) {
+        let latency = self.latency();
+        for (x, y) in inputs
+            .get_channel(0)
+            .iter()
+            .zip(outputs.get_channel_mut(0).iter_mut())
+        {
+            // The input fills the end of the frame, while the output of the last frame is read
+            self.input[latency + self.pos] = *x;
+            *y = self.output[self.pos];
+            self.pos += 1;
+            if self.pos == self.hop_size {
+                self.process_frame();
+                self.pos = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use valib_core::dsp::buffer::AudioBuffer;
+
+    const SAMPLERATE: f64 = 48e3;
+
+    /// Run the pitch shifter over the input, in blocks of the given size
+    fn run(shifter: &mut PitchShifter<f64>, input: &[f64], block: usize) -> Vec<f64> {
+        let mut output = vec![0.0; input.len()];
+        for (x, y) in input.chunks(block).zip(output.chunks_mut(block)) {
+            let x = AudioBuffer::new([x]).unwrap();
+            let y = AudioBuffer::new([y]).unwrap();
+            shifter.process_block(x, y);
+        }
+        output
+    }
+
+    /// Frequency with the most energy in the signal, searched with a 1 Hz resolution
+    fn dominant_frequency(signal: &[f64]) -> f64 {
+        let power = |f: f64| {
+            let w = std::f64::consts::TAU * f / SAMPLERATE;
+            let (re, im) = signal
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (i, &x)| {
+                    let (sin, cos) = (w * i as f64).sin_cos();
+                    (re + x * cos, im - x * sin)
+                });
+            re * re + im * im
+        };
+        (50..2000)
+            .map(|f| f as f64)
+            .max_by(|&a, &b| power(a).total_cmp(&power(b)))
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case(12.0, 880.0)]
+    #[case(-12.0, 220.0)]
+    #[case(7.0, 659.26)]
+    fn pitch_shifter_shifts_sine(#[case] semitones: f64, #[case] expected: f64) {
+        let mut shifter = PitchShifter::new(2048);
+        assert_eq!(1536, shifter.latency());
+        shifter.set_semitones(semitones);
+
+        let input = (0..24000)
+            .map(|i| f64::sin(std::f64::consts::TAU * 440.0 * i as f64 / SAMPLERATE))
+            .collect::<Vec<_>>();
+        let output = run(&mut shifter, &input, 100);
+
+        let steady = &output[8192..16384];
+        let actual = dominant_frequency(steady);
+        assert!(
+            (actual - expected).abs() <= 2.0,
+            "Expected {expected} Hz, got {actual} Hz"
+        );
+        // Bins moved up leave gaps in the spectrum, so the level is only roughly preserved
+        let peak = steady.iter().fold(0.0, |max: f64, y| max.max(y.abs()));
+        assert!((0.5..1.5).contains(&peak), "Peak amplitude of {peak}");
+    }
+
+    #[test]
+    fn pitch_shifter_simd_lanes_match_scalar() {
+        use valib_core::simd::{AutoF32x2, SimdValue};
+
+        let input = (0..4096)
+            .map(|i| f32::sin(i as f32 * 0.05))
+            .collect::<Vec<_>>();
+        let run_scalar = |semitones: f32| {
+            let mut shifter = PitchShifter::<f32>::new(256);
+            shifter.set_semitones(semitones);
+            let mut output = vec![0.0; input.len()];
+            shifter.process_block(
+                AudioBuffer::new([&input[..]]).unwrap(),
+                AudioBuffer::new([&mut output[..]]).unwrap(),
+            );
+            output
+        };
+
+        let mut shifter = PitchShifter::<AutoF32x2>::new(256);
+        shifter.set_semitones(AutoF32x2::new(5.0, -7.0));
+        let simd_input = input
+            .iter()
+            .map(|&x| AutoF32x2::splat(x))
+            .collect::<Vec<_>>();
+        let mut output = vec![AutoF32x2::splat(0.0); input.len()];
+        shifter.process_block(
+            AudioBuffer::new([&simd_input[..]]).unwrap(),
+            AudioBuffer::new([&mut output[..]]).unwrap(),
+        );
+
+        for (lane, semitones) in [5.0, -7.0].into_iter().enumerate() {
+            let expected = run_scalar(semitones);
+            assert!(expected.iter().any(|y| y.abs() > 0.1));
+            for (i, (y, expected)) in output.iter().zip(&expected).enumerate() {
+                let y = y.extract(lane);
+                assert!(
+                    (y - expected).abs() < 1e-4,
+                    "Lane {lane}, sample {i}: {y} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pitch_shifter_reset_clears_output() {
+        let mut shifter = PitchShifter::new(256);
+        shifter.set_semitones(5.0);
+        let input = (0..4096)
+            .map(|i| f64::sin(i as f64 * 0.05))
+            .collect::<Vec<_>>();
+        let output = run(&mut shifter, &input, 37);
+        assert!(output.iter().any(|y| y.abs() > 0.5));
+
+        shifter.reset();
+        let output = run(&mut shifter, &[0.0; 4096], 37);
+        assert!(output.iter().all(|&y| y == 0.0));
+    }
+}
@@ -0,0 +1,76 @@
+//! # Allpass delays
+//!
+//! Provides the Schroeder allpass delay, used as a diffuser in reverbs.
+use valib_core::dsp::blocks::DelayLine;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+/// Schroeder allpass delay.
+///
+/// Has a flat magnitude response, but smears transients over time, which makes it useful to
+/// increase the echo density of a reverb.
+#[derive(Debug, Clone)]
+pub struct Allpass<T> {
+    line: DelayLine<T>,
+    gain: T,
+}
+
+impl<T: Scalar> Allpass<T> {
+    /// Create a new allpass delay, set to its maximum delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_delay`: Maximum delay (samples)
+    /// * `gain`: Allpass gain, which should be within the -1..1 range
+    ///
+    /// returns: Allpass<T>
+    pub fn new(max_delay: usize, gain: T) -> Self {
+        Self {
+            line: DelayLine::new(max_delay),
+            gain,
+        }
+    }
+
+    /// Sets the delay (samples), clamped between 1 and the maximum delay given at construction.
+    pub fn set_delay(&mut self, delay: usize) {
+        self.line.set_delay(delay.max(1));
+    }
+
+    /// Sets the allpass gain, which should be within the -1..1 range.
+    pub fn set_gain(&mut self, gain: T) {
+        self.gain = gain;
+    }
+}
+
+impl<T: Scalar> DSPMeta for Allpass<T> {
+    type Sample = T;
+
+    fn reset(&mut self) {
+        self.line.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Allpass<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let delayed = self.line.read();
+        let w = x + self.gain * delayed;
+        self.line.push(w);
+        [delayed - self.gain * w]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allpass_preserves_energy() {
+        let mut allpass = Allpass::new(37, 0.7);
+        let energy = (0..10000)
+            .map(|i| allpass.process([if i == 0 { 1.0 } else { 0.0 }])[0])
+            .map(|y: f64| y * y)
+            .sum::<f64>();
+        assert!((energy - 1.0).abs() < 1e-6, "Got {energy}");
+    }
+}
@@ -0,0 +1,240 @@
+//! # Feedback delay network reverb
+//!
+//! Provides a stereo reverb built from a feedback delay network (FDN): a set of delay lines of
+//! mutually prime lengths, recirculated through an orthogonal feedback matrix, with input
+//! diffusion through allpass delays.
+use valib_core::dsp::blocks::DelayLine;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::allpass::Allpass;
+
+/// Number of delay lines in the feedback network
+const NUM_LINES: usize = 8;
+/// Lengths of the delay lines at a size of 1 (ms)
+const LINE_MS: [f32; NUM_LINES] = [29.7, 37.1, 41.1, 43.7, 53.3, 59.9, 67.3, 73.1];
+/// Lengths of the input diffusers at a size of 1 (ms)
+const DIFFUSER_MS: [f32; 2] = [4.77, 1.73];
+/// Gain of the input diffusers
+const DIFFUSER_GAIN: f64 = 0.6;
+/// Maximum size of the reverb
+pub const MAX_SIZE: f32 = 2.0;
+/// Minimum size of the reverb
+pub const MIN_SIZE: f32 = 0.1;
+
+/// Stereo feedback delay network reverb.
+///
+/// The left and right inputs are diffused through allpass delays, then injected into the
+/// alternating lines of an 8-line feedback delay network, mixed by a Householder matrix. Each
+/// line is attenuated such that the reverb decays by 60 dB over the configured decay time, and
+/// an optional one-pole lowpass in the loop makes high frequencies decay faster.
+///
+/// The output is fully wet.
+#[derive(Debug, Clone)]
+pub struct Reverb<T> {
+    samplerate: f32,
+    decay_time: f32,
+    size: f32,
+    damping: T,
+    lines: [DelayLine<T>; NUM_LINES],
+    gains: [T; NUM_LINES],
+    damping_state: [T; NUM_LINES],
+    diffusers: [[Allpass<T>; DIFFUSER_MS.len()]; 2],
+}
+
+impl<T: Scalar> Reverb<T> {
+    /// Create a new reverb, with a size of 1 and no damping.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the reverb
+    /// * `decay_time`: Time for the reverb to decay by 60 dB (s)
+    ///
+    /// returns: Reverb<T>
+    pub fn new(samplerate: f32, decay_time: f32) -> Self {
+        let mut this = Self {
+            samplerate,
+            decay_time,
+            size: 1.0,
+            damping: T::zero(),
+            lines: std::array::from_fn(|_| DelayLine::new(1)),
+            gains: [T::zero(); NUM_LINES],
+            damping_state: [T::zero(); NUM_LINES],
+            diffusers: std::array::from_fn(|_| {
+                std::array::from_fn(|_| Allpass::new(1, T::from_f64(DIFFUSER_GAIN)))
+            }),
+        };
+        this.set_samplerate(samplerate);
+        this
+    }
+
+    /// Time for the reverb to decay by 60 dB (s)
+    pub fn decay_time(&self) -> f32 {
+        self.decay_time
+    }
+
+    /// Sets the time for the reverb to decay by 60 dB (s).
+    pub fn set_decay_time(&mut self, decay_time: f32) {
+        self.decay_time = decay_time.max(1e-3);
+        self.update_gains();
+    }
+
+    /// Size of the reverb, as a scale factor of the delay line lengths.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Sets the size of the reverb, as a scale factor of the delay line lengths. The size is
+    /// clamped between [`MIN_SIZE`] and [`MAX_SIZE`]. The decay time is kept as-is.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.clamp(MIN_SIZE, MAX_SIZE);
+        for (line, ms) in self.lines.iter_mut().zip(LINE_MS) {
+            line.set_delay(ms_to_samples(ms, self.size, self.samplerate));
+        }
+        for channel in &mut self.diffusers {
+            for (diffuser, ms) in channel.iter_mut().zip(DIFFUSER_MS) {
+                diffuser.set_delay(ms_to_samples(ms, self.size, self.samplerate));
+            }
+        }
+        self.update_gains();
+    }
+
+    /// Sets the amount of high-frequency damping, in the 0..1 range. 0 disables damping.
+    pub fn set_damping(&mut self, damping: T) {
+        self.damping = damping.simd_clamp(T::zero(), T::from_f64(0.99));
+    }
+
+    fn update_gains(&mut self) {
+        for (gain, ms) in self.gains.iter_mut().zip(LINE_MS) {
+            let samples = ms_to_samples(ms, self.size, self.samplerate).max(1) as f64;
+            // -60 dB after `decay_time` seconds
+            let db_per_sample = -60.0 / (self.decay_time * self.samplerate) as f64;
+            *gain = T::from_f64(10f64.powf(db_per_sample * samples / 20.0));
+        }
+    }
+}
+
+fn ms_to_samples(ms: f32, size: f32, samplerate: f32) -> usize {
+    (ms * 1e-3 * size * samplerate).round() as usize
+}
+
+impl<T: Scalar> DSPMeta for Reverb<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        let max_samples = |ms: f32| (ms * 1e-3 * MAX_SIZE * samplerate).ceil() as usize;
+        self.lines = LINE_MS.map(|ms| DelayLine::new(max_samples(ms)));
+        self.diffusers = std::array::from_fn(|_| {
+            DIFFUSER_MS.map(|ms| Allpass::new(max_samples(ms), T::from_f64(DIFFUSER_GAIN)))
+        });
+        self.damping_state = [T::zero(); NUM_LINES];
+        self.set_size(self.size);
+    }
+
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.reset();
+        }
+        for diffuser in self.diffusers.iter_mut().flatten() {
+            diffuser.reset();
+        }
+        self.damping_state = [T::zero(); NUM_LINES];
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<2, 2> for Reverb<T> {
+    fn process(&mut self, x: [Self::Sample; 2]) -> [Self::Sample; 2] {
+        let inputs: [T; 2] = std::array::from_fn(|ch| {
+            self.diffusers[ch]
+                .iter_mut()
+                .fold(x[ch], |x, diffuser| diffuser.process([x])[0])
+        });
+
+        let outputs: [T; NUM_LINES] = std::array::from_fn(|i| {
+            let y = self.lines[i].read() * self.gains[i];
+            self.damping_state[i] = y + (self.damping_state[i] - y) * self.damping;
+            self.damping_state[i]
+        });
+
+        // Householder feedback matrix
+        let sum = outputs.iter().copied().fold(T::zero(), |a, b| a + b)
+            * T::from_f64(2.0 / NUM_LINES as f64);
+        for (i, line) in self.lines.iter_mut().enumerate() {
+            line.push(outputs[i] - sum + inputs[i % 2]);
+        }
+
+        // Alternate signs to decorrelate the output channels
+        let mut out = [T::zero(); 2];
+        for (i, y) in outputs.into_iter().enumerate() {
+            if (i / 2) % 2 == 0 {
+                out[i % 2] += y;
+            } else {
+                out[i % 2] -= y;
+            }
+        }
+        let scale = T::from_f64(1.0 / ((NUM_LINES / 2) as f64).sqrt());
+        out.map(|y| y * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    const SAMPLERATE: f32 = 16000.0;
+
+    /// Measure the RT60 from the T30 of the Schroeder energy decay curve
+    fn measure_rt60(output: &[f32]) -> f32 {
+        let mut edc = output
+            .iter()
+            .rev()
+            .scan(0.0, |acc, y| {
+                *acc += y * y;
+                Some(*acc)
+            })
+            .collect::<Vec<f32>>();
+        edc.reverse();
+        let db = |e: f32| 10.0 * (e / edc[0]).log10();
+        let t5 = edc.iter().position(|&e| db(e) <= -5.0).unwrap();
+        let t35 = edc.iter().position(|&e| db(e) <= -35.0).unwrap();
+        2.0 * (t35 - t5) as f32 / SAMPLERATE
+    }
+
+    #[rstest]
+    fn reverb_rt60_matches_decay_time(#[values(0.5, 1.5)] decay_time: f32) {
+        let mut reverb = Reverb::new(SAMPLERATE, 1.0);
+        reverb.set_decay_time(decay_time);
+        let len = (1.5 * decay_time * SAMPLERATE) as usize;
+        let output = (0..len)
+            .map(|i| {
+                let x = if i == 0 { 1.0 } else { 0.0 };
+                let [l, r]: [f32; 2] = reverb.process([x, x]);
+                (0.5 * (l * l + r * r)).sqrt()
+            })
+            .collect::<Vec<f32>>();
+
+        let rt60 = measure_rt60(&output);
+        assert!(
+            (rt60 - decay_time).abs() < 0.1 * decay_time,
+            "Expected RT60 of {decay_time} s, measured {rt60} s"
+        );
+    }
+
+    #[test]
+    fn reverb_damping_shortens_decay() {
+        let mut reverb = Reverb::new(SAMPLERATE, 1.0);
+        reverb.set_size(0.5);
+        reverb.set_damping(0.5);
+        let output = (0..SAMPLERATE as usize)
+            .map(|i| {
+                let x = if i == 0 { 1.0 } else { 0.0 };
+                reverb.process([x, x])[0]
+            })
+            .collect::<Vec<f32>>();
+        assert!(measure_rt60(&output) < 0.9);
+    }
+}
@@ -0,0 +1,8 @@
+#![warn(missing_docs)]
+//! # Reverbs
+//!
+//! This crate provides reverberation algorithms, and the building blocks they are made of, for
+//! `valib`.
+
+pub mod allpass;
+pub mod fdn;
@@ -5,11 +5,11 @@
 use super::adaa::Antiderivative;
 use crate::MultiSaturator;
 use crate::Saturator;
+use core::num::NonZeroUsize;
 use nalgebra as na;
 use nalgebra::{Dim, OMatrix, OVector, SVector, VectorView};
 use num_traits::Float;
 use numeric_literals::replace_float_literals;
-use std::num::NonZeroUsize;
 use valib_core::dsp::DSPMeta;
 use valib_core::dsp::DSPProcess;
 use valib_core::math::nr::{NewtonRhapson, RootEq};
@@ -67,9 +67,6 @@ impl<T: Scalar> RootEq for DiodeClipper<T> {
         let vout = input[0];
         let v = T::simd_recip(self.n * self.vt);
         let expin = vout * v;
-        if vout.simd_gt(16.0).any() {
-            println!();
-        }
         let expn = T::simd_exp(expin / self.num_diodes_fwd).simd_min(1e35);
         let expm = T::simd_exp(-expin / self.num_diodes_bwd).simd_min(1e35);
         let res = self.isat * (expn - expm) + 2. * vout - self.vin;
@@ -188,8 +185,31 @@ where
     }
 }
 
+/// Type of diode for which fitted [`DiodeClipperModel`] parameters are built in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DiodeType {
+    /// Silicon diode
+    Silicon,
+    /// Germanium diode
+    Germanium,
+    /// Light-emitting diode
+    Led,
+}
+
+impl DiodeType {
+    /// All diode types with built-in parameters.
+    pub const ALL: [Self; 3] = [Self::Silicon, Self::Germanium, Self::Led];
+}
+
+/// Maximum number of diodes in each direction for which model parameters are built in.
+pub const MAX_PRESET_DIODES: u8 = 5;
+
+/// Maximum number of Levenberg-Marquardt iterations of [`DiodeClipperModel::fit_from_points`]
+const FIT_MAX_ITER: usize = 200;
+
 /// Analytical model of the diode clipper, described in the clippers notebook.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiodeClipperModel<T> {
     /// A parameter
     pub a: T,
@@ -206,6 +226,159 @@ impl<T: Scalar> DSPMeta for DiodeClipperModel<T> {
 }
 
 impl<T: Scalar> DiodeClipperModel<T> {
+    /// Create a new diode clipper model from its raw parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `a`: A parameter, knee of the negative half
+    /// * `b`: B parameter, knee of the positive half
+    /// * `si`: Input scaling
+    /// * `so`: Output scaling
+    ///
+    /// returns: DiodeClipperModel<T>
+    pub fn from_params(a: T, b: T, si: T, so: T) -> Self {
+        Self { a, b, si, so }
+    }
+
+    /// Create a new diode clipper model from the built-in fitted parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `diode`: Type of diode
+    /// * `nf`: Number of diode in the forward direction (up to [`MAX_PRESET_DIODES`])
+    /// * `nb`: Number of diode in the backward direction (up to [`MAX_PRESET_DIODES`])
+    ///
+    /// returns: DiodeClipperModel<T>
+    pub fn preset(diode: DiodeType, nf: u8, nb: u8) -> Self {
+        match diode {
+            DiodeType::Silicon => Self::new_silicon(nf, nb),
+            DiodeType::Germanium => Self::new_germanium(nf, nb),
+            DiodeType::Led => Self::new_led(nf, nb),
+        }
+    }
+
+    /// List the built-in fitted parameters, as arguments to [`Self::preset`].
+    pub fn presets() -> impl Iterator<Item = (DiodeType, u8, u8)> {
+        DiodeType::ALL.into_iter().flat_map(|diode| {
+            (1..=MAX_PRESET_DIODES)
+                .flat_map(move |nf| (1..=MAX_PRESET_DIODES).map(move |nb| (diode, nf, nb)))
+        })
+    }
+
+    /// Fit the model parameters against measured `(input, output)` points, with least squares.
+    ///
+    /// The fit starts from the built-in preset closest to the data, and is refined with the
+    /// Levenberg-Marquardt algorithm. Each lane of SIMD scalars is fit independently. The points
+    /// should cover the clipping region of the measured clipper in both directions, or the knee
+    /// parameters cannot be recovered.
+    ///
+    /// # Arguments
+    ///
+    /// * `points`: Measured `(input, output)` points
+    ///
+    /// returns: DiodeClipperModel<T>
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn fit_from_points(points: &[(T, T)]) -> Self {
+        let mut model = Self::presets()
+            .map(|(diode, nf, nb)| Self::preset(diode, nf, nb))
+            .fold(None::<(Self, T)>, |best, model| {
+                let cost = model.fit_cost(points);
+                Some(match best {
+                    None => (model, cost),
+                    Some((best, best_cost)) => {
+                        let better = cost.simd_lt(best_cost);
+                        (
+                            model.select_lanes(better, best),
+                            cost.select(better, best_cost),
+                        )
+                    }
+                })
+            })
+            .map(|(model, _)| model)
+            .unwrap();
+
+        let mut cost = model.fit_cost(points);
+        let mut lambda = 1e-3;
+        for _ in 0..FIT_MAX_ITER {
+            let mut jtj = na::Matrix4::<T>::zeros();
+            let mut jtr = na::Vector4::<T>::zeros();
+            for &(x, y) in points {
+                let (f, jac) = model.eval_with_jacobian(x);
+                let jac = na::Vector4::from(jac);
+                jtj += jac * jac.transpose();
+                jtr += jac * (y - f);
+            }
+            let mut damped = jtj;
+            for i in 0..4 {
+                damped[(i, i)] += lambda * jtj[(i, i)].simd_max(1e-12);
+            }
+            let delta = na::Cholesky::new_unchecked(damped).solve(&jtr);
+
+            let [a, b, si, so] = [0, 1, 2, 3].map(|i| (model.param(i) + delta[i]).simd_max(1e-6));
+            let candidate = Self { a, b, si, so };
+            let candidate_cost = candidate.fit_cost(points);
+            let improved = candidate_cost.simd_lt(cost);
+            model = candidate.select_lanes(improved, model);
+            cost = candidate_cost.select(improved, cost);
+            lambda = (lambda * 0.3).select(improved, lambda * 10.0);
+
+            let converged = (0..4)
+                .map(|i| {
+                    delta[i]
+                        .simd_abs()
+                        .simd_le(1e-9 * model.param(i).simd_abs())
+                })
+                .reduce(|a, b| a & b)
+                .unwrap();
+            if (converged | lambda.simd_gt(1e12)).all() {
+                break;
+            }
+        }
+        model
+    }
+
+    fn param(&self, i: usize) -> T {
+        [self.a, self.b, self.si, self.so][i]
+    }
+
+    fn select_lanes(self, mask: T::SimdBool, other: Self) -> Self {
+        Self {
+            a: self.a.select(mask, other.a),
+            b: self.b.select(mask, other.b),
+            si: self.si.select(mask, other.si),
+            so: self.so.select(mask, other.so),
+        }
+    }
+
+    fn fit_cost(&self, points: &[(T, T)]) -> T {
+        points.iter().fold(T::zero(), |acc, &(x, y)| {
+            let e = y - self.eval(x);
+            acc + e * e
+        })
+    }
+
+    /// Evaluate the model along with its derivatives with respect to `a`, `b`, `si` and `so`.
+    #[replace_float_literals(T::from_f64(literal))]
+    fn eval_with_jacobian(&self, x: T) -> (T, [T; 4]) {
+        let u = self.si * x;
+        let lower = u.simd_lt(-self.a);
+        let higher = u.simd_gt(self.b);
+        let den_lower = 1. - u - self.a;
+        let den_higher = 1. + u - self.b;
+
+        let g_lower = -T::simd_ln(den_lower) - self.a;
+        let g_higher = T::simd_ln(den_higher) + self.b;
+        let g = g_lower.select(lower, g_higher.select(higher, u));
+        let dg_du = den_lower
+            .simd_recip()
+            .select(lower, den_higher.simd_recip().select(higher, 1.));
+        let dg_da = (den_lower.simd_recip() - 1.).select(lower, 0.);
+        let dg_db = (1. - den_higher.simd_recip()).select(higher, 0.);
+
+        let so = self.so;
+        (g * so, [dg_da * so, dg_db * so, dg_du * x * so, g])
+    }
+
     /// Evaluate the saturator
     ///
     /// # Arguments
@@ -295,7 +468,7 @@ mod tests {
     use std::hint;
     use valib_core::simd::SimdValue;
 
-    use super::{DiodeClipper, DiodeClipperModel};
+    use super::{DiodeClipper, DiodeClipperModel, DiodeType};
     use valib_core::dsp::DSPProcess;
     use valib_core::util::tests::{Plot, Series};
 
@@ -358,4 +531,61 @@ mod tests {
         dc_sweep("regressions/clipper_model", clipper);
         drive_test("regressions/clipper_model", clipper);
     }
+
+    #[test]
+    fn list_presets() {
+        let presets = DiodeClipperModel::<f32>::presets().collect::<Vec<_>>();
+        assert_eq!(75, presets.len());
+        assert!(presets.contains(&(DiodeType::Germanium, 2, 3)));
+        assert_eq!(
+            DiodeClipperModel::new_led(3, 5),
+            DiodeClipperModel::<f32>::preset(DiodeType::Led, 3, 5)
+        );
+    }
+
+    #[test]
+    fn fit_from_points_recovers_parameters() {
+        let reference = DiodeClipperModel::<f64>::new_germanium(2, 3);
+        // Move the parameters away from the preset so that the fit has to refine them
+        let expected = DiodeClipperModel::from_params(
+            reference.a * 1.2,
+            reference.b * 0.9,
+            reference.si * 1.1,
+            reference.so * 0.95,
+        );
+        let points = (-500..=500)
+            .map(|i| i as f64 / 50.)
+            .map(|x| (x, expected.eval(x)))
+            .collect::<Vec<_>>();
+
+        let fitted = DiodeClipperModel::fit_from_points(&points);
+        for (name, actual, expected) in [
+            ("a", fitted.a, expected.a),
+            ("b", fitted.b, expected.b),
+            ("si", fitted.si, expected.si),
+            ("so", fitted.so, expected.so),
+        ] {
+            let rel_error = (actual - expected).abs() / expected;
+            assert!(
+                rel_error < 1e-3,
+                "Parameter {name}: expected {expected}, got {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn diode_clipper_simd_lanes_match_scalar() {
+        use valib_core::simd::AutoF32x2;
+
+        // A tiny input converges in a couple iterations, a huge one needs many more
+        let inputs = [1e-4, 10.0];
+        let mut clipper = DiodeClipper::new_led(3, 5, AutoF32x2::splat(0.0));
+        let [actual] = clipper.process([AutoF32x2::new(inputs[0], inputs[1])]);
+
+        for (i, x) in inputs.into_iter().enumerate() {
+            let [expected] = DiodeClipper::new_led(3, 5, 0.0).process([x]);
+            assert!(expected.is_finite());
+            assert_eq!(expected, actual.extract(i), "Lane {i} differs");
+        }
+    }
 }
@@ -0,0 +1,284 @@
+//! # Magnetic hysteresis
+//!
+//! Provides a saturator modeling the magnetic hysteresis of tape and transformer cores, using the
+//! Jiles-Atherton model.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::state::DspState;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
+
+use crate::Saturator;
+
+/// Inter-domain coupling of the Jiles-Atherton model
+const ALPHA: f64 = 1.6e-3;
+/// Coercivity of the Jiles-Atherton model, setting the width of the loop along with reversibility
+const K: f64 = 0.47875;
+
+/// Langevin function `L(x) = coth(x) - 1/x` and its derivative, using their series expansion near
+/// zero where the closed forms cancel out catastrophically.
+#[replace_float_literals(T::from_f64(literal))]
+fn langevin<T: Scalar>(x: T) -> (T, T) {
+    let small = x.simd_abs().simd_lt(0.1);
+    let x2 = x * x;
+    let series = (
+        x * (1.0 / 3.0 - x2 * (1.0 / 45.0 - x2 * 2.0 / 945.0)),
+        1.0 / 3.0 - x2 * (1.0 / 15.0 - x2 * 2.0 / 189.0),
+    );
+    let coth = x.simd_tanh().simd_recip();
+    let xinv = x.simd_recip();
+    let closed = (coth - xinv, xinv * xinv - coth * coth + 1.0);
+    (
+        series.0.select(small, closed.0),
+        series.1.select(small, closed.1),
+    )
+}
+
+/// Magnetic hysteresis saturator, modeling tape and transformer cores.
+///
+/// The input is the magnetizing field, and the output the resulting magnetization, normalized so
+/// that it saturates at ±1. Per the Jiles-Atherton model, the magnetization lags behind the field
+/// and depends on its history: the output follows a different path when the input rises than when
+/// it falls, tracing a loop.
+///
+/// The model is rate-independent, and is solved by integrating the magnetization over the change
+/// of the field from the previous input to the current one, with a 4th-order Runge-Kutta step.
+/// This makes the result independent of the sample rate, however, as with other saturators, the
+/// generated harmonics are prone to aliasing, and oversampling is recommended.
+#[derive(Debug, Copy, Clone)]
+pub struct Hysteresis<T> {
+    drive: T,
+    saturation: T,
+    width: T,
+    /// Saturation magnetization
+    ms: T,
+    /// Shape of the anhysteretic magnetization curve
+    a: T,
+    /// Ratio of reversible magnetization
+    c: T,
+    h: T,
+    m: T,
+}
+
+impl<T: Scalar> Default for Hysteresis<T> {
+    fn default() -> Self {
+        let half = T::from_f64(0.5);
+        Self::new(half, half, half)
+    }
+}
+
+impl<T: Scalar> Hysteresis<T> {
+    /// Create a new hysteresis saturator.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive`: Amount of drive, in the 0..1 range, increasing the gain and the distortion
+    /// * `saturation`: Saturation amount, in the 0..1 range, lowering the level at which the
+    ///     magnetization saturates
+    /// * `width`: Width of the hysteresis loop, in the 0..1 range
+    ///
+    /// returns: Hysteresis<T>
+    pub fn new(drive: T, saturation: T, width: T) -> Self {
+        let mut this = Self {
+            drive,
+            saturation,
+            width,
+            ms: T::one(),
+            a: T::one(),
+            c: T::zero(),
+            h: T::zero(),
+            m: T::zero(),
+        };
+        this.update_coefficients();
+        this
+    }
+
+    /// Sets the amount of drive, in the 0..1 range.
+    pub fn set_drive(&mut self, drive: T) {
+        self.drive = drive;
+        self.update_coefficients();
+    }
+
+    /// Sets the saturation amount, in the 0..1 range.
+    pub fn set_saturation(&mut self, saturation: T) {
+        // Keep the normalized output continuous
+        let output = self.m / self.ms;
+        self.saturation = saturation;
+        self.update_coefficients();
+        self.m = output * self.ms;
+    }
+
+    /// Sets the width of the hysteresis loop, in the 0..1 range.
+    pub fn set_width(&mut self, width: T) {
+        self.width = width;
+        self.update_coefficients();
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn update_coefficients(&mut self) {
+        let drive = self.drive.simd_clamp(0.0, 1.0);
+        let saturation = self.saturation.simd_clamp(0.0, 1.0);
+        let width = self.width.simd_clamp(0.0, 1.0);
+        self.ms = 0.5 + 1.5 * (1.0 - saturation);
+        self.a = self.ms / (0.01 + 6.0 * drive);
+        // Bounded away from 1 to keep the irreversible term from diverging
+        self.c = 0.95 - 0.9 * width;
+    }
+
+    /// Derivative of the magnetization with respect to the field, moving in the direction given by
+    /// `delta` (±1).
+    #[replace_float_literals(T::from_f64(literal))]
+    fn dm_dh(&self, h: T, m: T, delta: T) -> T {
+        let (alpha, k) = (T::from_f64(ALPHA), T::from_f64(K));
+        let ms_a = self.ms / self.a;
+        let (l, l_prime) = langevin((h + alpha * m) / self.a);
+        let m_diff = self.ms * l - m;
+        // The irreversible magnetization only moves towards the anhysteretic curve
+        let delta_m = T::one().select((delta * m_diff).simd_gt(0.0), 0.0);
+        let irreversible =
+            (1.0 - self.c) * delta_m * m_diff / ((1.0 - self.c) * delta * k - alpha * m_diff);
+        let reversible = self.c * ms_a * l_prime;
+        (irreversible + reversible) / (1.0 - self.c * alpha * ms_a * l_prime)
+    }
+
+    /// Magnetization after moving the field to `h` from the current state
+    #[replace_float_literals(T::from_f64(literal))]
+    fn next_magnetization(&self, h: T) -> T {
+        let dh = h - self.h;
+        let delta = T::one().select(dh.simd_ge(0.0), -1.0);
+        let half = dh * 0.5;
+        let k1 = self.dm_dh(self.h, self.m, delta);
+        let k2 = self.dm_dh(self.h + half, self.m + half * k1, delta);
+        let k3 = self.dm_dh(self.h + half, self.m + half * k2, delta);
+        let k4 = self.dm_dh(h, self.m + dh * k3, delta);
+        self.m + dh / 6.0 * (k1 + 2.0 * (k2 + k3) + k4)
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> Saturator<T> for Hysteresis<T> {
+    fn saturate(&self, x: T) -> T {
+        self.next_magnetization(x) / self.ms
+    }
+
+    fn update_state(&mut self, x: T, y: T) {
+        self.h = x;
+        self.m = y * self.ms;
+    }
+}
+
+impl<T: Scalar> DSPMeta for Hysteresis<T> {
+    type Sample = T;
+
+    fn reset(&mut self) {
+        self.h = T::zero();
+        self.m = T::zero();
+    }
+}
+
+/// The state is made of the current field and magnetization.
+impl<T: Scalar> DspState for Hysteresis<T> {
+    type State = [T; 2];
+
+    fn snapshot(&self) -> Self::State {
+        [self.h, self.m]
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        [self.h, self.m] = *state;
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Hysteresis<T> {
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let y = self.saturate(x);
+        self.update_state(x, y);
+        [y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plotters::prelude::BLUE;
+    use valib_core::util::tests::{Plot, Series};
+
+    /// Input sweeping up from 0 to 2, down to -2 and back up to 2
+    fn sweep() -> impl Iterator<Item = f32> {
+        let up = |from: i32| (from..200).map(|i| i as f32 / 100.0);
+        up(0)
+            .chain(up(-200).map(|x| -x))
+            .chain(up(-200))
+            .chain([2.0])
+    }
+
+    #[test]
+    fn snapshot_dc_sweep() {
+        let mut hysteresis = Hysteresis::<f32>::default();
+        let results = Vec::from_iter(sweep().map(|x| hysteresis.process([x])[0]));
+        Plot {
+            title: "DC sweep: Hysteresis",
+            bode: false,
+            series: &[Series {
+                label: "Output",
+                samplerate: 100.0,
+                series: &results,
+                color: &BLUE,
+            }],
+        }
+        .create_svg("plots/saturators/hysteresis/dc_sweep.svg");
+        insta::assert_csv_snapshot!(results, { "[]" => insta::rounded_redaction(4) });
+    }
+
+    #[test]
+    fn rising_and_falling_differ() {
+        let mut hysteresis = Hysteresis::<f32>::default();
+        let outputs = Vec::from_iter(sweep().map(|x| (x, hysteresis.process([x])[0])));
+        assert!(outputs.iter().all(|(_, y)| y.is_finite() && y.abs() <= 1.0));
+
+        // Output when crossing zero, on the falling and the rising branches of the loop
+        let (_, falling) = outputs[200..600]
+            .iter()
+            .find(|(x, _)| *x <= 0.0)
+            .copied()
+            .unwrap();
+        let (_, rising) = outputs[600..]
+            .iter()
+            .find(|(x, _)| *x >= 0.0)
+            .copied()
+            .unwrap();
+        // Remanent magnetization of opposite signs
+        assert!(falling > 0.05, "Falling branch at {falling}");
+        assert!(rising < -0.05, "Rising branch at {rising}");
+    }
+
+    #[test]
+    fn narrow_loop_has_less_remanence() {
+        let remanence = |width: f32| {
+            let mut hysteresis = Hysteresis::new(0.5, 0.5, width);
+            for x in (0..200).map(|i| i as f32 / 100.0) {
+                hysteresis.process([x]);
+            }
+            (0..200)
+                .map(|i| hysteresis.process([2.0 - i as f32 / 100.0])[0])
+                .last()
+                .unwrap()
+        };
+        assert!(remanence(0.1) < remanence(0.9));
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut hysteresis = Hysteresis::<f32>::default();
+        for x in sweep().take(150) {
+            hysteresis.process([x]);
+        }
+        let state = hysteresis.snapshot();
+        let expected = Vec::from_iter(sweep().map(|x| hysteresis.process([x])[0]));
+
+        hysteresis.process([-2.0]);
+        hysteresis.restore(&state);
+        let actual = Vec::from_iter(sweep().map(|x| hysteresis.process([x])[0]));
+        assert_eq!(expected, actual);
+    }
+}
@@ -1,4 +1,5 @@
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 //! # Saturators
 //!
 //! This crate provides abstractions over saturators, as well as several standard saturator
@@ -7,18 +8,25 @@
 //! Saturators are set up so that their processing and their updating are separate; this allows
 //! setting up iterative methods to improve accuracy. They should also provide a differentiation
 //! function for more complex iteration schemes and feedback processing.
+
+extern crate alloc;
+
+use core::ops;
 use num_traits::One;
 use numeric_literals::replace_float_literals;
-use std::ops;
 
 use clippers::DiodeClipperModel;
 
-use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
+use valib_core::dsp::state::DspState;
+use valib_core::dsp::{DSPMeta, DSPProcess, DSPProcessBlock};
+use valib_core::simd::SimdBool;
 use valib_core::Scalar;
 
 pub mod adaa;
 pub mod bjt;
 pub mod clippers;
+pub mod hysteresis;
 
 /// Trait for types which are saturators.
 ///
@@ -114,6 +122,7 @@ impl<T: Scalar, F: Fn(T) -> T> Saturator<T> for F {
 
 /// Linear "saturator", a noop saturator which can be used when wanting no saturation.
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Linear;
 
 impl<S: Scalar> Saturator<S> for Linear {
@@ -146,6 +155,7 @@ impl<S: Scalar, const N: usize> MultiSaturator<S, N> for Linear {
 
 /// The `tanh` function as a saturator.
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tanh;
 
 #[profiling::all_functions]
@@ -164,6 +174,7 @@ impl<S: Scalar> Saturator<S> for Tanh {
 
 /// The `asinh` function as a saturator.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Asinh;
 
 #[profiling::all_functions]
@@ -180,6 +191,7 @@ impl<T: Scalar> Saturator<T> for Asinh {
 
 /// Hard-clipper saturator, keeping the output within the provided bounds.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Clipper<T> {
     /// Minimum bound
     pub min: T,
@@ -226,6 +238,7 @@ impl<T: Scalar, const N: usize> MultiSaturator<T, N> for Clipper<T> {
 
 /// Blend the output of a saturator with its input by the given amount.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Blend<T, S> {
     /// Amount of blending of the input to add to the output. The output will be scaled down to keep
     pub amt: T,
@@ -250,6 +263,20 @@ impl<T: Scalar, S: Saturator<T>> Saturator<T> for Blend<T, S> {
     }
 }
 
+impl<T, S> Blend<T, S> {
+    /// Create a new blended saturator.
+    ///
+    /// # Arguments
+    ///
+    /// * `amt`: Amount of saturation, 0 leaving the input untouched and 1 being fully saturated
+    /// * `inner`: Saturator to blend with the input
+    ///
+    /// returns: Blend<T, S>
+    pub fn new(amt: T, inner: S) -> Self {
+        Self { amt, inner }
+    }
+}
+
 impl<T: Scalar, S: Default> Default for Blend<T, S> {
     fn default() -> Self {
         Self {
@@ -259,10 +286,12 @@ impl<T: Scalar, S: Default> Default for Blend<T, S> {
     }
 }
 
-/// Runtime-switchable dynamic saturator
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum Dynamic<T> {
+/// Kind of saturator used by a [`Dynamic`] saturator
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DynamicKind {
     /// Linear "saturator". No saturation.
+    #[default]
     Linear,
     /// `tanh` function
     Tanh,
@@ -271,41 +300,114 @@ pub enum Dynamic<T> {
     /// Hard clipping between -1 and 1
     HardClipper,
     /// Diode clipper model
-    DiodeClipper(DiodeClipperModel<T>),
+    DiodeClipper,
     /// "Overdrive" clipper model
-    SoftClipper(Blend<T, DiodeClipperModel<T>>),
+    SoftClipper,
+}
+
+/// Runtime-switchable dynamic saturator.
+///
+/// The configuration of every parameterized kind of saturator is kept alongside the active kind,
+/// so that switching to another kind and back again restores the previous configuration.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dynamic<T> {
+    kind: DynamicKind,
+    diode_clipper: DiodeClipperModel<T>,
+    soft_clipper: Blend<T, DiodeClipperModel<T>>,
+}
+
+impl<T: Scalar> Dynamic<T> {
+    /// Create a new dynamic saturator of the given kind, with default configurations.
+    pub fn new(kind: DynamicKind) -> Self {
+        Self {
+            kind,
+            diode_clipper: DiodeClipperModel::default(),
+            soft_clipper: Blend::default(),
+        }
+    }
+
+    /// Sets the diode clipper model used by the [`DynamicKind::DiodeClipper`] kind, in a builder
+    /// fashion.
+    pub fn with_diode_clipper(mut self, model: DiodeClipperModel<T>) -> Self {
+        self.diode_clipper = model;
+        self
+    }
+
+    /// Sets the blended diode clipper used by the [`DynamicKind::SoftClipper`] kind, in a builder
+    /// fashion.
+    pub fn with_soft_clipper(mut self, blend: Blend<T, DiodeClipperModel<T>>) -> Self {
+        self.soft_clipper = blend;
+        self
+    }
+
+    /// Currently active kind of saturator
+    pub fn kind(&self) -> DynamicKind {
+        self.kind
+    }
+
+    /// Switch to another kind of saturator. The configuration of the other kinds is retained.
+    pub fn set_variant(&mut self, kind: DynamicKind) {
+        self.kind = kind;
+    }
+
+    /// Diode clipper model used by the [`DynamicKind::DiodeClipper`] kind
+    pub fn diode_clipper(&self) -> &DiodeClipperModel<T> {
+        &self.diode_clipper
+    }
+
+    /// Mutable access to the diode clipper model used by the [`DynamicKind::DiodeClipper`] kind
+    pub fn diode_clipper_mut(&mut self) -> &mut DiodeClipperModel<T> {
+        &mut self.diode_clipper
+    }
+
+    /// Blended diode clipper used by the [`DynamicKind::SoftClipper`] kind
+    pub fn soft_clipper(&self) -> &Blend<T, DiodeClipperModel<T>> {
+        &self.soft_clipper
+    }
+
+    /// Mutable access to the blended diode clipper used by the [`DynamicKind::SoftClipper`] kind
+    pub fn soft_clipper_mut(&mut self) -> &mut Blend<T, DiodeClipperModel<T>> {
+        &mut self.soft_clipper
+    }
 }
 
 #[profiling::all_functions]
 impl<T: Scalar> Saturator<T> for Dynamic<T> {
     #[inline(always)]
     fn saturate(&self, x: T) -> T {
-        match self {
-            Self::Linear => Linear.saturate(x),
-            Self::HardClipper => Clipper::default().saturate(x),
-            Self::Tanh => Tanh.saturate(x),
-            Self::Asinh => Asinh.saturate(x),
-            Self::DiodeClipper(clip) => clip.saturate(x),
-            Self::SoftClipper(clip) => clip.saturate(x),
+        match self.kind {
+            DynamicKind::Linear => Linear.saturate(x),
+            DynamicKind::HardClipper => Clipper::default().saturate(x),
+            DynamicKind::Tanh => Tanh.saturate(x),
+            DynamicKind::Asinh => Asinh.saturate(x),
+            DynamicKind::DiodeClipper => self.diode_clipper.saturate(x),
+            DynamicKind::SoftClipper => self.soft_clipper.saturate(x),
         }
     }
 
     #[inline(always)]
     fn sat_diff(&self, x: T) -> T {
-        match self {
-            Self::Linear => Linear.sat_diff(x),
-            Self::HardClipper => Clipper::default().sat_diff(x),
-            Self::Asinh => Asinh.sat_diff(x),
-            Self::Tanh => Tanh.sat_diff(x),
-            Self::DiodeClipper(clip) => clip.sat_diff(x),
-            Self::SoftClipper(clip) => clip.sat_diff(x),
+        match self.kind {
+            DynamicKind::Linear => Linear.sat_diff(x),
+            DynamicKind::HardClipper => Clipper::default().sat_diff(x),
+            DynamicKind::Asinh => Asinh.sat_diff(x),
+            DynamicKind::Tanh => Tanh.sat_diff(x),
+            DynamicKind::DiodeClipper => self.diode_clipper.sat_diff(x),
+            DynamicKind::SoftClipper => self.soft_clipper.sat_diff(x),
         }
     }
 }
 
-impl<T> Default for Dynamic<T> {
+impl<T: Scalar> Default for Dynamic<T> {
     fn default() -> Self {
-        Self::Linear
+        Self::new(DynamicKind::default())
+    }
+}
+
+impl<T: Scalar> From<DynamicKind> for Dynamic<T> {
+    fn from(kind: DynamicKind) -> Self {
+        Self::new(kind)
     }
 }
 
@@ -356,6 +458,18 @@ impl<T: Scalar> DSPMeta for Slew<T> {
     }
 }
 
+impl<T: Scalar> DspState for Slew<T> {
+    type State = T;
+
+    fn snapshot(&self) -> Self::State {
+        self.last_out
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.last_out = *state;
+    }
+}
+
 #[profiling::all_functions]
 impl<T: Scalar> DSPProcess<1, 1> for Slew<T> {
     fn process(&mut self, x: [Self::Sample; 1]) -> [Self::Sample; 1] {
@@ -365,6 +479,26 @@ impl<T: Scalar> DSPProcess<1, 1> for Slew<T> {
     }
 }
 
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcessBlock<1, 1> for Slew<T> {
+    fn process_block(
+        &mut self,
+        inputs: AudioBufferRef<Self::Sample, 1>,
+        mut outputs: AudioBufferMut<Self::Sample, 1>,
+    ) {
+        let input = inputs.get_channel(0);
+        // Early-out when the target has already been reached for the whole block
+        if input.iter().all(|&x| self.is_changing(x).none()) {
+            outputs.fill(self.last_out);
+            return;
+        }
+
+        for (x, y) in input.iter().zip(outputs.get_channel_mut(0).iter_mut()) {
+            [*y] = self.process([*x]);
+        }
+    }
+}
+
 impl<T: Scalar> Slew<T> {
     /// Create a new slew rate limiter.
     ///
@@ -460,3 +594,62 @@ impl<T: Scalar, S: Saturator<T>> Saturator<T> for Driven<T, S> {
         self.saturator.sat_diff(x * self.drive)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use valib_core::dsp::buffer::AudioBuffer;
+
+    #[test]
+    fn slew_block_matches_per_sample() {
+        let input = (0..256)
+            .map(|i| if (64..160).contains(&i) { 1.0 } else { 0.25 })
+            .collect::<Vec<f32>>();
+
+        let mut slew = Slew::new(1000.0, 50.0);
+        let expected = input
+            .iter()
+            .map(|&x| slew.process([x])[0])
+            .collect::<Vec<_>>();
+
+        let mut slew = Slew::new(1000.0, 50.0);
+        let mut output = vec![0.0; input.len()];
+        for (input, output) in input.chunks(32).zip(output.chunks_mut(32)) {
+            slew.process_block(
+                AudioBuffer::new([input]).unwrap(),
+                AudioBuffer::new([output]).unwrap(),
+            );
+        }
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn dynamic_keeps_config_across_variants() {
+        let model = DiodeClipperModel::new_germanium(2, 3);
+        let mut sat = Dynamic::<f32>::new(DynamicKind::Tanh);
+        sat.set_variant(DynamicKind::DiodeClipper);
+        *sat.diode_clipper_mut() = model;
+        let expected = sat.saturate(0.8);
+        assert_eq!(model.saturate(0.8), expected);
+
+        sat.set_variant(DynamicKind::Tanh);
+        assert_eq!(0.8f32.tanh(), sat.saturate(0.8));
+
+        sat.set_variant(DynamicKind::DiodeClipper);
+        assert_eq!(&model, sat.diode_clipper());
+        assert_eq!(expected, sat.saturate(0.8));
+    }
+
+    #[test]
+    fn slew_snapshot_round_trip() {
+        let mut slew = Slew::new(1000.0, 50.0);
+        slew.process([1.0]);
+        let state = slew.snapshot();
+        let expected = (0..32).map(|_| slew.process([1.0])[0]).collect::<Vec<_>>();
+
+        slew.process([-1.0]);
+        slew.restore(&state);
+        let actual = (0..32).map(|_| slew.process([1.0])[0]).collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
+}
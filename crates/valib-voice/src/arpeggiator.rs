@@ -0,0 +1,362 @@
+//! # Arpeggiator
+//!
+//! Provides an arpeggiator, which plays the held notes one after the other in time with the host
+//! tempo.
+use crate::{NoteData, Voice, VoiceManager};
+use valib_core::dsp::DSPMeta;
+use valib_core::util::rng::Rng;
+use valib_core::Scalar;
+
+/// Number of MIDI notes
+const NUM_NOTES: usize = 128;
+
+/// Order in which the arpeggiator plays the held notes
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ArpPattern {
+    /// From the lowest note to the highest, then starting over
+    #[default]
+    Up,
+    /// From the highest note to the lowest, then starting over
+    Down,
+    /// From the lowest note to the highest and back down, without repeating the outermost notes
+    UpDown,
+    /// Held notes picked at random
+    Random,
+}
+
+/// Arpeggiator, playing the held notes one at a time on a [`VoiceManager`].
+///
+/// Notes are held and released with [`Self::note_on`] and [`Self::note_off`], which do not play
+/// anything by themselves. Instead, [`Self::process`] is called with the current position of the
+/// host transport and the tempo, and starts the next note of the pattern on the voice manager
+/// every time a step boundary is crossed, releasing the previous one.
+///
+/// Held notes are stored by note number, so that holding and releasing notes never allocates.
+pub struct Arpeggiator<T, ID> {
+    samplerate: f32,
+    pattern: ArpPattern,
+    rate_beats: f64,
+    /// Note data of the held notes, indexed by note number
+    held: Box<[Option<NoteData<T>>]>,
+    num_held: usize,
+    /// Number of steps played since notes started being held
+    step: usize,
+    /// Index of the last step played, in time since the start of the transport
+    last_step: Option<u64>,
+    playing: Option<ID>,
+    seed: u64,
+    rng: Rng,
+}
+
+impl<T: Scalar, ID: Copy> Arpeggiator<T, ID> {
+    /// Create a new arpeggiator.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the arpeggiator is run
+    /// * `pattern`: Order in which the held notes are played
+    /// * `rate_beats`: Length of a step, in beats (ie. 0.25 for sixteenth notes in 4/4)
+    ///
+    /// returns: Arpeggiator<T, ID>
+    pub fn new(samplerate: f32, pattern: ArpPattern, rate_beats: f64) -> Self {
+        Self {
+            samplerate,
+            pattern,
+            rate_beats,
+            held: vec![None; NUM_NOTES].into_boxed_slice(),
+            num_held: 0,
+            step: 0,
+            last_step: None,
+            playing: None,
+            seed: 0,
+            rng: Rng::new(0),
+        }
+    }
+
+    /// Sets the seed of the random order of [`ArpPattern::Random`], in a builder fashion.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.set_seed(seed);
+        self
+    }
+
+    /// Sets the seed of the random order of [`ArpPattern::Random`]. Arpeggiators with the same
+    /// seed play the same random sequence of notes.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+    }
+
+    /// Order in which the held notes are played
+    pub fn pattern(&self) -> ArpPattern {
+        self.pattern
+    }
+
+    /// Sets the order in which the held notes are played.
+    pub fn set_pattern(&mut self, pattern: ArpPattern) {
+        self.pattern = pattern;
+    }
+
+    /// Length of a step, in beats
+    pub fn rate(&self) -> f64 {
+        self.rate_beats
+    }
+
+    /// Sets the length of a step, in beats.
+    pub fn set_rate(&mut self, rate_beats: f64) {
+        self.rate_beats = rate_beats;
+    }
+
+    /// Held notes, sorted by note number
+    pub fn held_notes(&self) -> impl '_ + Iterator<Item = u8> {
+        self.held
+            .iter()
+            .enumerate()
+            .filter(|(_, data)| data.is_some())
+            .map(|(note, _)| note as u8)
+    }
+
+    /// Hold a note, to be played by the arpeggiator.
+    ///
+    /// # Arguments
+    ///
+    /// * `note`: MIDI note number (0..128), setting the order of the note in the pattern
+    /// * `note_data`: Note data the note is played with
+    pub fn note_on(&mut self, note: u8, note_data: NoteData<T>) {
+        if let Some(slot) = self.held.get_mut(note as usize) {
+            if slot.replace(note_data).is_none() {
+                self.num_held += 1;
+            }
+        }
+    }
+
+    /// Release a held note. A note currently playing keeps playing until the next step.
+    pub fn note_off(&mut self, note: u8) {
+        if let Some(slot) = self.held.get_mut(note as usize) {
+            if slot.take().is_some() {
+                self.num_held -= 1;
+            }
+        }
+    }
+
+    /// Advance the arpeggiator to the given position, playing the next note on the voice manager
+    /// if a new step has started since the last call.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager`: Voice manager to play the notes on
+    /// * `position`: Position of the host transport, in samples
+    /// * `tempo`: Tempo of the host (BPM)
+    ///
+    /// returns: The note number of the note started, if any
+    pub fn process<V: Voice<Sample = T>, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        position: u64,
+        tempo: f64,
+    ) -> Option<u8> {
+        if self.num_held == 0 {
+            self.stop(manager);
+            return None;
+        }
+
+        let samples_per_step = self.rate_beats * 60.0 / tempo * self.samplerate as f64;
+        let step = (position as f64 / samples_per_step) as u64;
+        if self.last_step == Some(step) {
+            return None;
+        }
+        self.last_step = Some(step);
+
+        if let Some(id) = self.playing.take() {
+            manager.note_off(id);
+        }
+        let index = self.next_index();
+        let (note, note_data) = self
+            .held
+            .iter()
+            .enumerate()
+            .filter_map(|(note, data)| Some((note as u8, (*data)?)))
+            .nth(index)?;
+        self.playing = Some(manager.note_on(note_data));
+        self.step += 1;
+        Some(note)
+    }
+
+    /// Release the playing note, and start the pattern over.
+    pub fn stop<V: Voice<Sample = T>, M: VoiceManager<V, ID = ID>>(&mut self, manager: &mut M) {
+        if let Some(id) = self.playing.take() {
+            manager.note_off(id);
+        }
+        self.step = 0;
+        self.last_step = None;
+    }
+
+    /// Index into the held notes of the note to play at the current step
+    fn next_index(&mut self) -> usize {
+        let len = self.num_held;
+        match self.pattern {
+            ArpPattern::Up => self.step % len,
+            ArpPattern::Down => len - 1 - self.step % len,
+            ArpPattern::UpDown if len == 1 => 0,
+            ArpPattern::UpDown => {
+                let period = 2 * len - 2;
+                let i = self.step % period;
+                if i < len {
+                    i
+                } else {
+                    period - i
+                }
+            }
+            ArpPattern::Random => self.rng.next_index(len),
+        }
+    }
+}
+
+impl<T: Scalar, ID> DSPMeta for Arpeggiator<T, ID> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+    }
+
+    fn reset(&mut self) {
+        self.step = 0;
+        self.last_step = None;
+        self.playing = None;
+        self.rng = Rng::new(self.seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monophonic::Monophonic;
+    use crate::polyphonic::Polyphonic;
+    use crate::{Gain, Velocity};
+    use valib_core::util::midi_to_freq;
+
+    /// Voice which becomes inactive as soon as it is released
+    struct NoteVoice(NoteData<f32>, bool);
+
+    impl DSPMeta for NoteVoice {
+        type Sample = f32;
+    }
+
+    impl Voice for NoteVoice {
+        fn active(&self) -> bool {
+            self.1
+        }
+
+        fn note_data(&self) -> &NoteData<Self::Sample> {
+            &self.0
+        }
+
+        fn note_data_mut(&mut self) -> &mut NoteData<Self::Sample> {
+            &mut self.0
+        }
+
+        fn release(&mut self) {
+            self.1 = false;
+        }
+
+        fn reuse(&mut self) {
+            self.1 = true;
+        }
+    }
+
+    fn note_data(note: u8) -> NoteData<f32> {
+        NoteData {
+            frequency: midi_to_freq(note),
+            velocity: Velocity::new(1.0),
+            gain: Gain::from_linear(1.0),
+            pan: 0.0,
+            pressure: 0.0,
+            modulation_st: 0.0,
+        }
+    }
+
+    /// Notes played by the arpeggiator over the given number of steps of sixteenth notes at 120
+    /// BPM, as seen by the voice manager
+    fn play(pattern: ArpPattern, steps: u64) -> Vec<f32> {
+        const SAMPLERATE: f32 = 1000.0;
+        let mut manager = Monophonic::new(SAMPLERATE, |_, note| NoteVoice(note, true), false);
+        let mut arp = Arpeggiator::new(SAMPLERATE, pattern, 0.25);
+        for note in [64, 60, 67] {
+            arp.note_on(note, note_data(note));
+        }
+
+        // A sixteenth note at 120 BPM lasts 125 samples
+        let mut notes = vec![];
+        for position in 0..125 * steps {
+            if arp.process(&mut manager, position, 120.0).is_some() {
+                notes.push(manager.get_voice(()).unwrap().note_data().frequency);
+            }
+        }
+        notes
+    }
+
+    #[test]
+    fn arpeggiator_follows_pattern() {
+        let freqs = |notes: &[u8]| notes.iter().map(|&n| midi_to_freq(n)).collect::<Vec<f32>>();
+        assert_eq!(freqs(&[60, 64, 67, 60, 64]), play(ArpPattern::Up, 5));
+        assert_eq!(freqs(&[67, 64, 60, 67, 64]), play(ArpPattern::Down, 5));
+        assert_eq!(
+            freqs(&[60, 64, 67, 64, 60, 64, 67]),
+            play(ArpPattern::UpDown, 7)
+        );
+
+        let random = play(ArpPattern::Random, 16);
+        assert_eq!(16, random.len());
+        assert!(random.iter().all(|f| freqs(&[60, 64, 67]).contains(f)));
+    }
+
+    #[test]
+    fn arpeggiator_releases_note_when_notes_are_released() {
+        let mut manager = Monophonic::new(1000.0, |_, note| NoteVoice(note, true), false);
+        let mut arp = Arpeggiator::new(1000.0, ArpPattern::Up, 1.0);
+        arp.note_on(60, note_data(60));
+        assert_eq!(Some(60), arp.process(&mut manager, 0, 120.0));
+        assert_eq!(None, arp.process(&mut manager, 1, 120.0));
+        assert_eq!(1, manager.active());
+
+        arp.note_off(60);
+        assert_eq!(None, arp.process(&mut manager, 2, 120.0));
+        assert_eq!(0, arp.held_notes().count());
+        assert_eq!(0, manager.active());
+    }
+
+    #[test]
+    fn arpeggiator_on_polyphonic() {
+        let mut manager = Polyphonic::new(1000.0, 4, |_, note| NoteVoice(note, true));
+        let mut arp = Arpeggiator::new(1000.0, ArpPattern::Up, 0.25).with_seed(42);
+        for note in [67, 60] {
+            arp.note_on(note, note_data(note));
+        }
+
+        let mut notes = vec![];
+        for position in 0..125 * 6 {
+            if let Some(note) = arp.process(&mut manager, position, 120.0) {
+                notes.push(note);
+                // Only the note of the current step is held
+                assert_eq!(1, manager.active());
+            }
+            manager.clean_inactive_voices();
+        }
+        assert_eq!(vec![60, 67, 60, 67, 60, 67], notes);
+    }
+
+    #[test]
+    fn arpeggiator_random_seed() {
+        let run = |seed| {
+            let mut manager = Polyphonic::new(1000.0, 4, |_, note| NoteVoice(note, true));
+            let mut arp = Arpeggiator::new(1000.0, ArpPattern::Random, 0.25).with_seed(seed);
+            for note in 60..72 {
+                arp.note_on(note, note_data(note));
+            }
+            (0..125 * 16)
+                .filter_map(|position| arp.process(&mut manager, position, 120.0))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+}
@@ -0,0 +1,295 @@
+//! # Envelopes
+//!
+//! Provides envelope generators to be used within voices.
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::simd::SimdBool;
+use valib_core::Scalar;
+
+/// Shape of the envelope segments.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AdsrCurve {
+    /// Segments are straight lines
+    Linear,
+    /// Segments follow exponential curves, like analog envelope generators
+    #[default]
+    Exponential,
+}
+
+/// Overshoot of the attack target in exponential mode, controlling the curvature of the attack.
+const ATTACK_RATIO: f64 = 0.3;
+/// Overshoot of the decay and release targets in exponential mode, controlling the curvature of
+/// the decay and release segments.
+const DECAY_RATIO: f64 = 1e-4;
+
+const STAGE_IDLE: f64 = 0.0;
+const STAGE_ATTACK: f64 = 1.0;
+const STAGE_DECAY: f64 = 2.0;
+const STAGE_SUSTAIN: f64 = 3.0;
+const STAGE_RELEASE: f64 = 4.0;
+
+/// ADSR envelope generator.
+///
+/// The input is the gate signal (on when above 0.5), and the output is the envelope level. A
+/// rising edge of the gate (re)starts the attack from the current level, and a falling edge starts
+/// the release. Each lane of SIMD scalars runs its own independent envelope.
+///
+/// The attack time is the time taken to go from 0 to 1, the decay time the time to go from 1 to
+/// the sustain level, and the release time the time taken to go from 1 to 0.
+#[derive(Debug, Copy, Clone)]
+pub struct Adsr<T> {
+    attack: T,
+    decay: T,
+    sustain: T,
+    release: T,
+    curve: AdsrCurve,
+    samplerate: T,
+    attack_rate: T,
+    decay_rate: T,
+    release_rate: T,
+    stage: T,
+    gate: T,
+    level: T,
+}
+
+impl<T: Scalar> Adsr<T> {
+    /// Create a new ADSR envelope generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate of the envelope
+    /// * `attack`: Attack time (s)
+    /// * `decay`: Decay time (s)
+    /// * `sustain`: Sustain level (0..=1)
+    /// * `release`: Release time (s)
+    /// * `curve`: Shape of the envelope segments
+    ///
+    /// returns: Adsr<T>
+    pub fn new(
+        samplerate: T,
+        attack: T,
+        decay: T,
+        sustain: T,
+        release: T,
+        curve: AdsrCurve,
+    ) -> Self {
+        let mut this = Self {
+            attack,
+            decay,
+            sustain,
+            release,
+            curve,
+            samplerate,
+            attack_rate: T::zero(),
+            decay_rate: T::zero(),
+            release_rate: T::zero(),
+            stage: T::zero(),
+            gate: T::zero(),
+            level: T::zero(),
+        };
+        this.update_rates();
+        this
+    }
+
+    /// Sets the attack time (s).
+    pub fn set_attack(&mut self, attack: T) {
+        self.attack = attack;
+        self.update_rates();
+    }
+
+    /// Sets the decay time (s).
+    pub fn set_decay(&mut self, decay: T) {
+        self.decay = decay;
+        self.update_rates();
+    }
+
+    /// Sets the sustain level (0..=1).
+    pub fn set_sustain(&mut self, sustain: T) {
+        self.sustain = sustain;
+    }
+
+    /// Sets the release time (s).
+    pub fn set_release(&mut self, release: T) {
+        self.release = release;
+        self.update_rates();
+    }
+
+    /// Shape of the envelope segments.
+    pub fn curve(&self) -> AdsrCurve {
+        self.curve
+    }
+
+    /// Sets the shape of the envelope segments.
+    pub fn set_curve(&mut self, curve: AdsrCurve) {
+        self.curve = curve;
+        self.update_rates();
+    }
+
+    /// Current level of the envelope.
+    pub fn level(&self) -> T {
+        self.level
+    }
+
+    /// Returns true while the envelope is running on any lane, that is, until the release stage
+    /// completes.
+    pub fn active(&self) -> bool {
+        self.stage.simd_ne(T::from_f64(STAGE_IDLE)).any()
+    }
+
+    #[replace_float_literals(T::from_f64(literal))]
+    fn update_rates(&mut self) {
+        let rate = |time: T, ratio: f64| {
+            let samples = time * self.samplerate;
+            match self.curve {
+                // Per-sample increment, reaching the end of the segment in at least one sample
+                AdsrCurve::Linear => samples.simd_max(1.0).simd_recip(),
+                // Per-sample coefficient of the one-pole filter tracking the overshot target
+                AdsrCurve::Exponential => {
+                    let log_ratio = T::from_f64(ratio.recip().ln_1p());
+                    let coeff = (-log_ratio / samples).simd_exp();
+                    coeff.select(samples.simd_gt(0.0), 0.0)
+                }
+            }
+        };
+        self.attack_rate = rate(self.attack, ATTACK_RATIO);
+        self.decay_rate = rate(self.decay, DECAY_RATIO);
+        self.release_rate = rate(self.release, DECAY_RATIO);
+    }
+}
+
+impl<T: Scalar> DSPMeta for Adsr<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = T::from_f64(samplerate as _);
+        self.update_rates();
+    }
+
+    fn reset(&mut self) {
+        self.stage = T::zero();
+        self.gate = T::zero();
+        self.level = T::zero();
+    }
+}
+
+#[profiling::all_functions]
+impl<T: Scalar> DSPProcess<1, 1> for Adsr<T> {
+    #[replace_float_literals(T::from_f64(literal))]
+    fn process(&mut self, [gate]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        let gate_on = gate.simd_gt(0.5);
+        let was_on = self.gate.simd_gt(0.5);
+        self.gate = T::one().select(gate_on, T::zero());
+
+        let idle = self.stage.simd_eq(T::from_f64(STAGE_IDLE));
+        self.stage = T::from_f64(STAGE_ATTACK).select(gate_on & !was_on, self.stage);
+        self.stage = T::from_f64(STAGE_RELEASE).select(!gate_on & was_on & !idle, self.stage);
+
+        let level = self.level;
+        let sustain = self.sustain;
+        let (attack, decay, release) = match self.curve {
+            AdsrCurve::Linear => (
+                level + self.attack_rate,
+                level - self.decay_rate * (1.0 - sustain),
+                level - self.release_rate,
+            ),
+            AdsrCurve::Exponential => {
+                let attack_target = 1.0 + T::from_f64(ATTACK_RATIO);
+                let decay_target = sustain - T::from_f64(DECAY_RATIO);
+                let release_target = -T::from_f64(DECAY_RATIO);
+                (
+                    attack_target + (level - attack_target) * self.attack_rate,
+                    decay_target + (level - decay_target) * self.decay_rate,
+                    release_target + (level - release_target) * self.release_rate,
+                )
+            }
+        };
+
+        let in_attack = self.stage.simd_eq(T::from_f64(STAGE_ATTACK));
+        let in_decay = self.stage.simd_eq(T::from_f64(STAGE_DECAY));
+        let in_sustain = self.stage.simd_eq(T::from_f64(STAGE_SUSTAIN));
+        let in_release = self.stage.simd_eq(T::from_f64(STAGE_RELEASE));
+        let mut level = T::zero();
+        level = attack.select(in_attack, level);
+        level = decay.select(in_decay, level);
+        level = sustain.select(in_sustain, level);
+        level = release.select(in_release, level);
+
+        // Stage transitions
+        let attack_done = in_attack & level.simd_ge(1.0);
+        level = T::one().select(attack_done, level);
+        self.stage = T::from_f64(STAGE_DECAY).select(attack_done, self.stage);
+
+        let decay_done = in_decay & level.simd_le(sustain);
+        level = sustain.select(decay_done, level);
+        self.stage = T::from_f64(STAGE_SUSTAIN).select(decay_done, self.stage);
+
+        let release_done = in_release & level.simd_le(0.0);
+        level = T::zero().select(release_done, level);
+        self.stage = T::from_f64(STAGE_IDLE).select(release_done, self.stage);
+
+        self.level = level;
+        [level]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const SAMPLERATE: f32 = 1000.0;
+
+    fn first_index(output: &[f32], f: impl Fn(f32) -> bool) -> usize {
+        output.iter().copied().position(f).unwrap()
+    }
+
+    #[rstest]
+    fn adsr_stages(#[values(AdsrCurve::Linear, AdsrCurve::Exponential)] curve: AdsrCurve) {
+        let mut adsr = Adsr::new(SAMPLERATE, 0.01, 0.02, 0.5, 0.05, curve);
+        assert!(!adsr.active());
+
+        let output = (0..200)
+            .map(|i| {
+                let gate = if i < 100 { 1.0 } else { 0.0 };
+                adsr.process([gate])[0]
+            })
+            .collect::<Vec<_>>();
+
+        // Attack reaches the peak after 10 samples, decay reaches sustain 20 samples later
+        let peak = first_index(&output, |x| x >= 1.0);
+        assert!((9..=10).contains(&peak), "Attack ended at sample {peak}");
+        let sustain = peak + first_index(&output[peak..], |x| x <= 0.5);
+        assert!(
+            (29..=30).contains(&sustain),
+            "Decay ended at sample {sustain}"
+        );
+        assert!(output[31..100].iter().all(|&x| x == 0.5));
+
+        // Release from the sustain level takes at most the release time
+        let release_end = first_index(&output, |x| x == 0.0);
+        assert!(
+            (100..150).contains(&release_end),
+            "Release ended at sample {release_end}"
+        );
+        assert_eq!(0.0, adsr.level());
+        assert!(!adsr.active());
+    }
+
+    #[test]
+    fn adsr_retrigger_during_release() {
+        let mut adsr = Adsr::new(SAMPLERATE, 0.01, 0.01, 1.0, 0.1, AdsrCurve::Linear);
+        for _ in 0..20 {
+            adsr.process([1.0]);
+        }
+        for _ in 0..50 {
+            adsr.process([0.0]);
+        }
+        let released = adsr.level();
+        assert!((released - 0.5).abs() < 1e-4, "Level is {released}");
+
+        // Attack restarts from the current level
+        let [level] = adsr.process([1.0]);
+        assert!((level - 0.6).abs() < 1e-4, "Level is {level}");
+        assert!(adsr.active());
+    }
+}
@@ -2,12 +2,18 @@
 //! # Voice abstractions
 //!
 //! This crate provides abstractions around voice processing and voice management.
-use valib_core::dsp::DSPMeta;
-use valib_core::simd::SimdRealField;
+use numeric_literals::replace_float_literals;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::simd::{SimdBool, SimdRealField};
+use valib_core::util::semitone_to_ratio;
 use valib_core::Scalar;
 
+pub mod arpeggiator;
+pub mod envelope;
 pub mod monophonic;
+pub mod mpe;
 pub mod polyphonic;
+pub mod unison;
 #[cfg(feature = "resampled")]
 pub mod upsample;
 
@@ -27,6 +33,7 @@ pub trait Voice: DSPMeta {
 
 /// Value representing velocity. The square root is precomputed to be used in voices directly.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Velocity<T> {
     value: T,
     sqrt: T,
@@ -62,6 +69,7 @@ impl<T: Copy + SimdRealField> Velocity<T> {
 
 /// Gain type, with precomputed linear and decibel values
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gain<T> {
     linear: T,
     db: T,
@@ -111,6 +119,7 @@ impl<T: Scalar> Gain<T> {
 
 /// Note data type containing major data about voice expression
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NoteData<T> {
     /// Note frequency
     pub frequency: T,
@@ -122,6 +131,162 @@ pub struct NoteData<T> {
     pub pan: T,
     /// Note pressure
     pub pressure: T,
+    /// Frequency modulation of the note (semitones), ie. from pitch bend or glide
+    pub modulation_st: T,
+}
+
+/// Lowest frequency returned by [`NoteData::resolve_frequency_clamped`] (Hz).
+pub const MIN_FREQUENCY_HZ: f64 = 1.0;
+
+impl<T: Scalar> NoteData<T> {
+    /// Resolve the frequency of the note, applying the frequency modulation.
+    ///
+    /// The result is not limited in any way, and large modulation amounts can push it above the
+    /// Nyquist frequency; use [`Self::resolve_frequency_clamped`] when feeding it to oscillators or
+    /// filters.
+    pub fn resolve_frequency(&self) -> T {
+        semitone_to_ratio(self.modulation_st) * self.frequency
+    }
+
+    /// Resolve the frequency of the note, applying the frequency modulation, and limit it to the
+    /// `[MIN_FREQUENCY_HZ, 0.49 * samplerate]` range.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate the note is played at
+    ///
+    /// returns: T
+    #[replace_float_literals(T::from_f64(literal))]
+    pub fn resolve_frequency_clamped(&self, samplerate: T) -> T {
+        self.resolve_frequency()
+            .simd_clamp(T::from_f64(MIN_FREQUENCY_HZ), 0.49 * samplerate)
+    }
+}
+
+/// Glide (portamento) behavior of the voice managers, applied when gliding notes to a new pitch.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum GlideMode {
+    /// Jump to the new pitch immediately
+    #[default]
+    Off,
+    /// Reach the new pitch in constant time, given in seconds
+    Constant(f32),
+    /// Move toward the new pitch at a constant rate, given in semitones per second
+    ConstantRate(f32),
+}
+
+/// Glide smoother, moving a semitone offset toward its target according to a [`GlideMode`].
+#[derive(Debug, Copy, Clone)]
+pub struct Glide<T> {
+    /// Glide mode. Changes are taken into account at the next call to [`Self::set_target`].
+    pub mode: GlideMode,
+    samplerate: f32,
+    current: T,
+    target: T,
+    step: T,
+}
+
+impl<T: Scalar> Glide<T> {
+    /// Create a new glide smoother, starting at 0 semitones.
+    ///
+    /// # Arguments
+    ///
+    /// * `samplerate`: Sample rate at which the smoother is run
+    /// * `mode`: Glide mode
+    ///
+    /// returns: Glide<T>
+    pub fn new(samplerate: f32, mode: GlideMode) -> Self {
+        Self {
+            mode,
+            samplerate,
+            current: T::zero(),
+            target: T::zero(),
+            step: T::zero(),
+        }
+    }
+
+    /// Current semitone offset.
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// Target semitone offset.
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Returns true while the glide has not reached its target.
+    pub fn is_gliding(&self) -> bool {
+        self.current.simd_ne(self.target).any()
+    }
+
+    /// Sets a new semitone offset to glide to.
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: Target semitone offset
+    ///
+    /// returns: ()
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+        match self.mode {
+            GlideMode::Off => {
+                self.current = target;
+                self.step = T::zero();
+            }
+            GlideMode::Constant(seconds) => {
+                let samples = (seconds * self.samplerate).max(1.0);
+                self.step = (target - self.current).simd_abs() / T::from_f64(samples as _);
+            }
+            GlideMode::ConstantRate(rate) => {
+                self.step = T::from_f64((rate / self.samplerate) as _);
+            }
+        }
+    }
+
+    /// Jump to the given semitone offset without gliding.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: New semitone offset
+    ///
+    /// returns: ()
+    pub fn set_immediate(&mut self, value: T) {
+        self.current = value;
+        self.target = value;
+        self.step = T::zero();
+    }
+
+    /// Advance the glide by the given number of samples, returning the new semitone offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples`: Number of samples to advance by
+    ///
+    /// returns: T
+    pub fn advance(&mut self, samples: usize) -> T {
+        let max_step = self.step * T::from_f64(samples as _);
+        self.current += (self.target - self.current).simd_clamp(-max_step, max_step);
+        self.current
+    }
+}
+
+impl<T: Scalar> DSPMeta for Glide<T> {
+    type Sample = T;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+    }
+
+    fn reset(&mut self) {
+        self.set_immediate(T::zero());
+    }
+}
+
+impl<T: Scalar> DSPProcess<0, 1> for Glide<T> {
+    fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+        [self.advance(1)]
+    }
 }
 
 /// Trait for types which manage voices.
@@ -160,6 +325,20 @@ pub trait VoiceManager<V: Voice>: DSPMeta<Sample = V::Sample> {
     fn choke(&mut self, id: Self::ID);
     /// Choke all the notes.
     fn panic(&mut self);
+    /// Free the voices which are no longer active, for example once their release has finished.
+    fn clean_inactive_voices(&mut self) {
+        // Choke the inactive voices one at a time, as the voice IDs cannot be collected without
+        // allocating. Bounded by the capacity in case choking does not free the voice.
+        for _ in 0..self.capacity() {
+            let inactive = self
+                .all_voices()
+                .find(|id| self.get_voice(*id).is_some_and(|v| !v.active()));
+            let Some(id) = inactive else {
+                break;
+            };
+            self.choke(id);
+        }
+    }
 
     // Channel modulation
     /// Set the pitch bend amount on the channel
@@ -177,3 +356,44 @@ pub trait VoiceManager<V: Voice>: DSPMeta<Sample = V::Sample> {
     /// Note gain
     fn gain(&mut self, id: Self::ID, gain: f32) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note_data(frequency: f32, modulation_st: f32) -> NoteData<f32> {
+        NoteData {
+            frequency,
+            velocity: Velocity::new(1.0),
+            gain: Gain::from_linear(1.0),
+            pan: 0.0,
+            pressure: 0.0,
+            modulation_st,
+        }
+    }
+
+    #[test]
+    fn resolve_frequency_applies_modulation() {
+        let note = note_data(440.0, 12.0);
+        assert!((note.resolve_frequency() - 880.0).abs() < 1e-3);
+        assert_eq!(880.0, note.resolve_frequency_clamped(48e3).round());
+    }
+
+    #[test]
+    fn resolve_frequency_clamped_stays_below_nyquist() {
+        const SAMPLERATE: f32 = 48e3;
+        let note = note_data(440.0, 1000.0);
+        assert!(note.resolve_frequency() > SAMPLERATE);
+        let clamped = note.resolve_frequency_clamped(SAMPLERATE);
+        assert!(
+            clamped < SAMPLERATE / 2.0,
+            "Frequency {clamped} above Nyquist"
+        );
+
+        let note = note_data(440.0, -1000.0);
+        assert_eq!(
+            MIN_FREQUENCY_HZ as f32,
+            note.resolve_frequency_clamped(SAMPLERATE)
+        );
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Provides a monophonic voice manager which can optionally do legato.
 
-use crate::{NoteData, Voice, VoiceManager};
+use crate::{Glide, GlideMode, NoteData, Voice, VoiceManager};
 use num_traits::zero;
 use valib_core::dsp::buffer::{AudioBufferMut, AudioBufferRef};
 use valib_core::dsp::{DSPMeta, DSPProcess, DSPProcessBlock};
@@ -19,6 +19,7 @@ pub struct Monophonic<V: Voice> {
     voice: Option<V>,
     base_frequency: V::Sample,
     pitch_bend_st: V::Sample,
+    glide: Glide<V::Sample>,
     released: bool,
     legato: bool,
     samplerate: f32,
@@ -29,6 +30,7 @@ impl<V: Voice> DSPMeta for Monophonic<V> {
 
     fn set_samplerate(&mut self, samplerate: f32) {
         self.samplerate = samplerate;
+        self.glide.set_samplerate(samplerate);
         if let Some(voice) = &mut self.voice {
             voice.set_samplerate(samplerate);
         }
@@ -40,6 +42,7 @@ impl<V: Voice> DSPMeta for Monophonic<V> {
 
     fn reset(&mut self) {
         self.voice = None;
+        self.glide.reset();
     }
 }
 
@@ -66,6 +69,7 @@ impl<V: Voice> Monophonic<V> {
             released: false,
             base_frequency: V::Sample::from_f64(440.),
             pitch_bend_st: zero(),
+            glide: Glide::new(samplerate, GlideMode::Off),
             legato,
             samplerate,
         }
@@ -80,6 +84,24 @@ impl<V: Voice> Monophonic<V> {
     pub fn set_legato(&mut self, legato: bool) {
         self.legato = legato;
     }
+
+    /// Glide mode applied when gliding the note with [`VoiceManager::glide`]
+    pub fn glide_mode(&self) -> GlideMode {
+        self.glide.mode
+    }
+
+    /// Set the glide mode applied when gliding the note with [`VoiceManager::glide`]
+    pub fn set_glide_mode(&mut self, mode: GlideMode) {
+        self.glide.mode = mode;
+    }
+
+    /// Advance the glide by the given number of samples, and update the voice modulation.
+    fn update_modulation(&mut self, samples: usize) {
+        let glide_st = self.glide.advance(samples);
+        if let Some(voice) = &mut self.voice {
+            voice.note_data_mut().modulation_st = self.pitch_bend_st + glide_st;
+        }
+    }
 }
 
 impl<V: Voice> VoiceManager<V> for Monophonic<V> {
@@ -110,8 +132,14 @@ impl<V: Voice> VoiceManager<V> for Monophonic<V> {
     }
 
     fn note_on(&mut self, note_data: NoteData<V::Sample>) -> Self::ID {
+        // The pitch bend applies to the channel, and the modulation of the note is where gliding
+        // starts from
         self.base_frequency = note_data.frequency;
-        self.pitch_bend_st = zero();
+        self.glide.set_immediate(note_data.modulation_st);
+        let note_data = NoteData {
+            modulation_st: self.pitch_bend_st + note_data.modulation_st,
+            ..note_data
+        };
         if let Some(voice) = &mut self.voice {
             *voice.note_data_mut() = note_data;
             if self.released || !self.legato {
@@ -142,6 +170,7 @@ impl<V: Voice> VoiceManager<V> for Monophonic<V> {
             self.pitch_bend_min_st,
             self.pitch_bend_max_st,
         );
+        self.update_modulation(0);
     }
 
     fn aftertouch(&mut self, amount: f64) {
@@ -152,16 +181,18 @@ impl<V: Voice> VoiceManager<V> for Monophonic<V> {
 
     fn pressure(&mut self, _: Self::ID, pressure: f32) {
         if let Some(voice) = &mut self.voice {
-            voice.note_data_mut().pressure = V::Sample::from_f64(pressure as _);
+            voice.note_data_mut().pressure = V::Sample::from_f32(pressure);
         }
     }
     fn glide(&mut self, _: Self::ID, semitones: f32) {
-        self.pitch_bend_st = V::Sample::from_f64(semitones as _);
+        self.glide.set_target(V::Sample::from_f32(semitones));
+        self.update_modulation(0);
     }
 }
 
 impl<V: Voice + DSPProcess<0, 1>> DSPProcess<0, 1> for Monophonic<V> {
     fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+        self.update_modulation(1);
         if let Some(voice) = &mut self.voice {
             voice.process([])
         } else {
@@ -176,13 +207,135 @@ impl<V: Voice + DSPProcessBlock<0, 1>> DSPProcessBlock<0, 1> for Monophonic<V> {
         inputs: AudioBufferRef<Self::Sample, 0>,
         mut outputs: AudioBufferMut<Self::Sample, 1>,
     ) {
-        if let Some(voice) = &mut self.voice {
-            voice.process_block(inputs, outputs);
-        } else {
-            outputs.fill(zero())
+        let Some(voice) = &mut self.voice else {
+            self.glide.advance(outputs.samples());
+            outputs.fill(zero());
+            return;
+        };
+
+        // Modulation is updated every sample while gliding, then once for the rest of the block
+        let len = outputs.samples();
+        let mut i = 0;
+        while i < len && self.glide.is_gliding() {
+            voice.note_data_mut().modulation_st = self.pitch_bend_st + self.glide.advance(1);
+            voice.process_block(inputs.slice(i..i + 1), outputs.slice_mut(i..i + 1));
+            i += 1;
+        }
+        if i < len {
+            voice.note_data_mut().modulation_st = self.pitch_bend_st + self.glide.advance(len - i);
+            voice.process_block(inputs.slice(i..), outputs.slice_mut(i..));
         }
     }
     fn max_block_size(&self) -> Option<usize> {
         self.voice.as_ref().and_then(|v| v.max_block_size())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Gain, Velocity};
+
+    struct FrequencyVoice(NoteData<f32>);
+
+    impl DSPMeta for FrequencyVoice {
+        type Sample = f32;
+    }
+
+    impl Voice for FrequencyVoice {
+        fn active(&self) -> bool {
+            true
+        }
+
+        fn note_data(&self) -> &NoteData<Self::Sample> {
+            &self.0
+        }
+
+        fn note_data_mut(&mut self) -> &mut NoteData<Self::Sample> {
+            &mut self.0
+        }
+
+        fn release(&mut self) {}
+
+        fn reuse(&mut self) {}
+    }
+
+    impl DSPProcess<0, 1> for FrequencyVoice {
+        fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+            [self.0.resolve_frequency()]
+        }
+    }
+
+    impl DSPProcessBlock<0, 1> for FrequencyVoice {
+        fn process_block(
+            &mut self,
+            _: AudioBufferRef<Self::Sample, 0>,
+            mut outputs: AudioBufferMut<Self::Sample, 1>,
+        ) {
+            outputs.fill(self.0.resolve_frequency());
+        }
+    }
+
+    fn note_data(frequency: f32) -> NoteData<f32> {
+        NoteData {
+            frequency,
+            velocity: Velocity::new(1.0),
+            gain: Gain::from_linear(1.0),
+            pan: 0.0,
+            pressure: 0.0,
+            modulation_st: 0.0,
+        }
+    }
+
+    #[test]
+    fn glide_ramps_frequency() {
+        let mut manager = Monophonic::new(1000.0, |_, note| FrequencyVoice(note), false);
+        manager.set_glide_mode(GlideMode::Constant(0.01));
+        manager.note_on(note_data(440.0));
+        manager.glide((), 12.0);
+
+        let output = (0..20).map(|_| manager.process([])[0]).collect::<Vec<_>>();
+        assert!(output.windows(2).take(9).all(|w| w[1] > w[0]));
+        assert!(output[..9].iter().all(|&f| f < 880.0 - 1e-3));
+        assert!(output[9..].iter().all(|&f| (f - 880.0).abs() < 1e-3));
+    }
+
+    #[test]
+    fn block_glide_matches_per_sample_glide() {
+        let run = |block: bool| {
+            let mut manager = Monophonic::new(1000.0, |_, note| FrequencyVoice(note), false);
+            manager.set_glide_mode(GlideMode::Constant(0.01));
+            manager.note_on(note_data(440.0));
+            manager.glide((), 12.0);
+            let mut output = [0.0; 20];
+            if block {
+                manager.process_block(
+                    AudioBufferRef::empty(output.len()),
+                    AudioBufferMut::new([&mut output]).unwrap(),
+                );
+            } else {
+                output.fill_with(|| manager.process([])[0]);
+            }
+            output
+        };
+        assert_eq!(run(false), run(true));
+    }
+
+    #[test]
+    fn note_on_keeps_pitch_bend() {
+        let mut manager = Monophonic::new(1000.0, |_, note| FrequencyVoice(note), false);
+        manager.pitch_bend(1.0);
+        manager.note_on(note_data(440.0));
+        assert_eq!(
+            2.0,
+            manager.get_voice(()).unwrap().note_data().modulation_st
+        );
+
+        manager.note_on(NoteData {
+            modulation_st: 1.0,
+            ..note_data(220.0)
+        });
+        let [f] = manager.process([]);
+        assert!((f - 220.0 * f32::powf(2.0, 3.0 / 12.0)).abs() < 1e-3, "{f}");
+    }
+}
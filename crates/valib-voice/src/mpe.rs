@@ -0,0 +1,341 @@
+//! # MPE routing
+//!
+//! Provides routing of MIDI Polyphonic Expression (MPE) events to the voices of a voice manager.
+use crate::{NoteData, Voice, VoiceManager};
+use valib_core::Scalar;
+
+/// Number of MIDI channels
+const NUM_CHANNELS: usize = 16;
+
+/// Default pitch bend range of MPE member channels (semitones), as per the MPE specification
+pub const DEFAULT_MPE_PITCH_BEND_RANGE_ST: f32 = 48.0;
+
+/// Routes MPE events to voices of a [`VoiceManager`].
+///
+/// In MPE, each note is played on its own member channel, and per-channel messages (pitch bend,
+/// channel pressure, ...) only apply to that note. This router keeps track of the voice playing on
+/// each member channel, and forwards per-channel messages to that voice with
+/// [`VoiceManager::glide`], [`VoiceManager::pressure`] and [`VoiceManager::pan`].
+///
+/// Messages on the master channel apply to all voices instead, through
+/// [`VoiceManager::pitch_bend`] and [`VoiceManager::aftertouch`].
+///
+/// Per-channel messages received before the note on event of a channel are remembered, and
+/// applied to the note when it starts.
+///
+/// Channels are numbered from 0 to 15.
+#[derive(Debug, Copy, Clone)]
+pub struct MpeRouter<ID> {
+    master_channel: u8,
+    pitch_bend_range_st: f32,
+    channels: [ChannelState<ID>; NUM_CHANNELS],
+}
+
+/// State of a member channel
+#[derive(Debug, Copy, Clone)]
+struct ChannelState<ID> {
+    /// Note number and voice ID of the note playing on the channel
+    voice: Option<(u8, ID)>,
+    /// Last pitch bend amount, in the -1..1 range
+    pitch_bend: f32,
+    /// Last channel pressure
+    pressure: Option<f32>,
+    /// Last pan
+    pan: Option<f32>,
+}
+
+impl<ID> Default for ChannelState<ID> {
+    fn default() -> Self {
+        Self {
+            voice: None,
+            pitch_bend: 0.0,
+            pressure: None,
+            pan: None,
+        }
+    }
+}
+
+impl<ID: Copy> Default for MpeRouter<ID> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<ID: Copy> MpeRouter<ID> {
+    /// Create a new MPE router.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_channel`: Master channel of the MPE zone (0 for the lower zone, 15 for the upper
+    ///     zone)
+    ///
+    /// returns: MpeRouter<ID>
+    pub fn new(master_channel: u8) -> Self {
+        Self {
+            master_channel,
+            pitch_bend_range_st: DEFAULT_MPE_PITCH_BEND_RANGE_ST,
+            channels: [ChannelState::default(); NUM_CHANNELS],
+        }
+    }
+
+    /// Sets the pitch bend range of the member channels, in a builder fashion.
+    pub fn with_pitch_bend_range(mut self, semitones: f32) -> Self {
+        self.set_pitch_bend_range(semitones);
+        self
+    }
+
+    /// Sets the pitch bend range of the member channels (semitones).
+    pub fn set_pitch_bend_range(&mut self, semitones: f32) {
+        self.pitch_bend_range_st = semitones;
+    }
+
+    /// Master channel of the MPE zone
+    pub fn master_channel(&self) -> u8 {
+        self.master_channel
+    }
+
+    /// Voice ID currently playing on the given member channel, if any
+    pub fn voice(&self, channel: u8) -> Option<ID> {
+        self.channels.get(channel as usize)?.voice.map(|(_, id)| id)
+    }
+
+    /// Start a note on the given member channel. A note still playing on that channel is released
+    /// first, and the pitch bend, pressure and pan last received on the channel are applied to the
+    /// new note.
+    ///
+    /// Notes on channels out of the 0..16 range are played as-is, without being tracked.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager`: Voice manager to play the note on
+    /// * `channel`: Member channel of the note
+    /// * `note`: MIDI note number, used to match the corresponding note off event
+    /// * `note_data`: Note data to start the voice with
+    ///
+    /// returns: ID of the voice playing the note
+    pub fn note_on<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        note: u8,
+        note_data: NoteData<V::Sample>,
+    ) -> ID {
+        let Some(state) = self.channels.get_mut(channel as usize) else {
+            return manager.note_on(note_data);
+        };
+        if let Some((_, id)) = state.voice.take() {
+            manager.note_off(id);
+        }
+        let note_data = NoteData {
+            pressure: state
+                .pressure
+                .map_or(note_data.pressure, V::Sample::from_f32),
+            pan: state.pan.map_or(note_data.pan, V::Sample::from_f32),
+            ..note_data
+        };
+        let id = manager.note_on(note_data);
+        state.voice = Some((note, id));
+        if state.pitch_bend != 0.0 {
+            manager.glide(id, state.pitch_bend * self.pitch_bend_range_st);
+        }
+        id
+    }
+
+    /// Release the note on the given member channel, if it is the given note.
+    pub fn note_off<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        note: u8,
+    ) {
+        if let Some(id) = self.take_note(channel, note) {
+            manager.note_off(id);
+        }
+    }
+
+    /// Choke the note on the given member channel, if it is the given note.
+    pub fn choke<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        note: u8,
+    ) {
+        if let Some(id) = self.take_note(channel, note) {
+            manager.choke(id);
+        }
+    }
+
+    /// Route a pitch bend message. On a member channel, the voice of that channel is glided by the
+    /// pitch bend range, and on the master channel, the pitch bend applies to all voices.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager`: Voice manager playing the notes
+    /// * `channel`: Channel of the message
+    /// * `amount`: Pitch bend amount, in the -1..1 range
+    pub fn pitch_bend<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        amount: f64,
+    ) {
+        if channel == self.master_channel {
+            manager.pitch_bend(amount);
+            return;
+        }
+        if let Some(state) = self.channels.get_mut(channel as usize) {
+            state.pitch_bend = amount as f32;
+        }
+        if let Some(id) = self.voice(channel) {
+            manager.glide(id, amount as f32 * self.pitch_bend_range_st);
+        }
+    }
+
+    /// Route a channel pressure message. On a member channel, it sets the pressure of the voice of
+    /// that channel, and on the master channel, it sets the aftertouch of all voices.
+    pub fn pressure<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        pressure: f32,
+    ) {
+        if channel == self.master_channel {
+            manager.aftertouch(pressure as _);
+            return;
+        }
+        if let Some(state) = self.channels.get_mut(channel as usize) {
+            state.pressure = Some(pressure);
+        }
+        if let Some(id) = self.voice(channel) {
+            manager.pressure(id, pressure);
+        }
+    }
+
+    /// Route a pan message to the voice of the given member channel.
+    pub fn pan<V: Voice, M: VoiceManager<V, ID = ID>>(
+        &mut self,
+        manager: &mut M,
+        channel: u8,
+        pan: f32,
+    ) {
+        if let Some(state) = self.channels.get_mut(channel as usize) {
+            state.pan = Some(pan);
+        }
+        if let Some(id) = self.voice(channel) {
+            manager.pan(id, pan);
+        }
+    }
+
+    /// Forget all the notes and channel state, for example after [`VoiceManager::panic`].
+    pub fn reset(&mut self) {
+        self.channels = [ChannelState::default(); NUM_CHANNELS];
+    }
+
+    fn take_note(&mut self, channel: u8, note: u8) -> Option<ID> {
+        let slot = &mut self.channels.get_mut(channel as usize)?.voice;
+        match *slot {
+            Some((n, id)) if n == note => {
+                *slot = None;
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polyphonic::Polyphonic;
+    use crate::{Gain, Velocity};
+    use valib_core::dsp::{DSPMeta, DSPProcess};
+    use valib_core::util::midi_to_freq;
+
+    struct NoteVoice(NoteData<f32>);
+
+    impl DSPMeta for NoteVoice {
+        type Sample = f32;
+    }
+
+    impl Voice for NoteVoice {
+        fn active(&self) -> bool {
+            true
+        }
+
+        fn note_data(&self) -> &NoteData<Self::Sample> {
+            &self.0
+        }
+
+        fn note_data_mut(&mut self) -> &mut NoteData<Self::Sample> {
+            &mut self.0
+        }
+
+        fn release(&mut self) {}
+
+        fn reuse(&mut self) {}
+    }
+
+    impl DSPProcess<0, 1> for NoteVoice {
+        fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
+            [0.0]
+        }
+    }
+
+    fn note_data(note: u8) -> NoteData<f32> {
+        NoteData {
+            frequency: midi_to_freq(note),
+            velocity: Velocity::new(1.0),
+            gain: Gain::from_linear(1.0),
+            pan: 0.0,
+            pressure: 0.0,
+            modulation_st: 0.0,
+        }
+    }
+
+    #[test]
+    fn mpe_routes_channels_to_distinct_voices() {
+        let mut manager = Polyphonic::new(1000.0, 8, |_, note| NoteVoice(note));
+        let mut router = MpeRouter::default();
+        let ids = [(1, 60), (2, 64), (3, 67)]
+            .map(|(channel, note)| router.note_on(&mut manager, channel, note, note_data(note)));
+        assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+
+        router.pitch_bend(&mut manager, 1, 0.25);
+        router.pressure(&mut manager, 2, 0.5);
+        router.pan(&mut manager, 3, -1.0);
+        manager.process([]);
+
+        let voice = |id| *manager.get_voice(id).unwrap().note_data();
+        let [a, b, c] = ids.map(voice);
+        assert_eq!([12.0, 0.0, 0.0], [a, b, c].map(|n| n.modulation_st));
+        assert_eq!([0.0, 0.5, 0.0], [a, b, c].map(|n| n.pressure));
+        assert_eq!([0.0, 0.0, -1.0], [a, b, c].map(|n| n.pan));
+
+        // Note offs only apply to the note playing on the channel
+        router.note_off(&mut manager, 2, 60);
+        assert_eq!(Some(ids[1]), router.voice(2));
+        router.note_off(&mut manager, 2, 64);
+        assert_eq!(None, router.voice(2));
+    }
+
+    #[test]
+    fn mpe_applies_channel_state_received_before_note_on() {
+        let mut manager = Polyphonic::new(1000.0, 8, |_, note| NoteVoice(note));
+        let mut router = MpeRouter::default();
+        router.pitch_bend(&mut manager, 4, -0.5);
+        router.pressure(&mut manager, 4, 0.25);
+        router.pan(&mut manager, 4, 0.5);
+        let id = router.note_on(&mut manager, 4, 60, note_data(60));
+        manager.process([]);
+
+        let note = *manager.get_voice(id).unwrap().note_data();
+        assert_eq!(-24.0, note.modulation_st);
+        assert_eq!(0.25, note.pressure);
+        assert_eq!(0.5, note.pan);
+
+        // Channels out of range are played without being tracked
+        let id = router.note_on(&mut manager, 16, 64, note_data(64));
+        assert!(manager.is_voice_active(id));
+        assert_eq!(None, router.voice(16));
+    }
+}
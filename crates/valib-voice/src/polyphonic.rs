@@ -1,14 +1,20 @@
 //! # Polyphonic voice manager
 //!
-//! Provides a polyphonic voice manager with rotating voice allocation.
-use crate::{NoteData, Voice, VoiceManager};
+//! Provides a polyphonic voice manager, which steals voices in rotation when all of them are
+//! playing.
+use crate::{Gain, Glide, GlideMode, NoteData, Voice, VoiceManager};
 use num_traits::zero;
 use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::Scalar;
 
-/// Polyphonic voice manager with rotating voice allocation
+/// Polyphonic voice manager.
+///
+/// Notes are allocated to free or inactive voices first; when all voices are playing, voices are
+/// stolen in rotation.
 pub struct Polyphonic<V: Voice> {
     create_voice: Box<dyn Fn(f32, NoteData<V::Sample>) -> V>,
     voice_pool: Box<[Option<V>]>,
+    glides: Box<[Glide<V::Sample>]>,
     next_voice: usize,
     samplerate: f32,
 }
@@ -32,9 +38,24 @@ impl<V: Voice> Polyphonic<V> {
             create_voice: Box::new(create_voice),
             next_voice: 0,
             voice_pool: (0..voice_capacity).map(|_| None).collect(),
+            glides: (0..voice_capacity)
+                .map(|_| Glide::new(samplerate, GlideMode::Off))
+                .collect(),
             samplerate,
         }
     }
+
+    /// Glide mode applied when gliding notes with [`VoiceManager::glide`]
+    pub fn glide_mode(&self) -> GlideMode {
+        self.glides.first().map(|g| g.mode).unwrap_or_default()
+    }
+
+    /// Set the glide mode applied when gliding notes with [`VoiceManager::glide`]
+    pub fn set_glide_mode(&mut self, mode: GlideMode) {
+        for glide in &mut self.glides {
+            glide.mode = mode;
+        }
+    }
 }
 
 impl<V: Voice> DSPMeta for Polyphonic<V> {
@@ -45,6 +66,9 @@ impl<V: Voice> DSPMeta for Polyphonic<V> {
         for voice in self.voice_pool.iter_mut().flatten() {
             voice.set_samplerate(samplerate);
         }
+        for glide in &mut self.glides {
+            glide.set_samplerate(samplerate);
+        }
     }
 
     fn latency(&self) -> usize {
@@ -58,6 +82,7 @@ impl<V: Voice> DSPMeta for Polyphonic<V> {
 
     fn reset(&mut self) {
         self.voice_pool.iter_mut().flatten().for_each(|v| v.reset());
+        self.glides.iter_mut().for_each(|g| g.reset());
     }
 }
 
@@ -81,8 +106,23 @@ impl<V: Voice> VoiceManager<V> for Polyphonic<V> {
     }
 
     fn note_on(&mut self, note_data: NoteData<V::Sample>) -> Self::ID {
-        let id = self.next_voice;
-        self.next_voice += 1;
+        // Use a free slot, or reuse an inactive voice, or steal voices in rotation
+        let id = self
+            .voice_pool
+            .iter()
+            .position(|v| v.is_none())
+            .or_else(|| {
+                self.voice_pool
+                    .iter()
+                    .position(|v| v.as_ref().is_some_and(|v| !v.active()))
+            })
+            .unwrap_or_else(|| {
+                let id = self.next_voice % self.voice_pool.len();
+                self.next_voice = id + 1;
+                id
+            });
+        // The modulation of the note is where gliding starts from
+        self.glides[id].set_immediate(note_data.modulation_st);
 
         if let Some(voice) = &mut self.voice_pool[id] {
             *voice.note_data_mut() = note_data;
@@ -107,15 +147,152 @@ impl<V: Voice> VoiceManager<V> for Polyphonic<V> {
     fn panic(&mut self) {
         self.voice_pool.fill_with(|| None);
     }
+
+    fn clean_inactive_voices(&mut self) {
+        for slot in self.voice_pool.iter_mut() {
+            if slot.as_ref().is_some_and(|v| !v.active()) {
+                slot.take();
+            }
+        }
+    }
+
+    fn pressure(&mut self, id: Self::ID, pressure: f32) {
+        if let Some(voice) = &mut self.voice_pool[id] {
+            voice.note_data_mut().pressure = V::Sample::from_f32(pressure);
+        }
+    }
+
+    fn glide(&mut self, id: Self::ID, semitones: f32) {
+        self.glides[id].set_target(V::Sample::from_f32(semitones));
+    }
+
+    fn pan(&mut self, id: Self::ID, pan: f32) {
+        if let Some(voice) = &mut self.voice_pool[id] {
+            voice.note_data_mut().pan = V::Sample::from_f32(pan);
+        }
+    }
+
+    fn gain(&mut self, id: Self::ID, gain: f32) {
+        if let Some(voice) = &mut self.voice_pool[id] {
+            voice.note_data_mut().gain = Gain::from_linear(V::Sample::from_f32(gain));
+        }
+    }
 }
 
 impl<V: Voice + DSPProcess<0, 1>> DSPProcess<0, 1> for Polyphonic<V> {
     fn process(&mut self, _: [Self::Sample; 0]) -> [Self::Sample; 1] {
         let mut out = zero();
-        for voice in self.voice_pool.iter_mut().flatten() {
+        for (voice, glide) in self.voice_pool.iter_mut().zip(&mut self.glides) {
+            let glide_st = glide.advance(1);
+            let Some(voice) = voice else {
+                continue;
+            };
+            voice.note_data_mut().modulation_st = glide_st;
             let [y] = voice.process([]);
             out += y;
         }
         [out]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Velocity;
+
+    /// Voice which becomes inactive as soon as it is released
+    struct GateVoice {
+        note_data: NoteData<f32>,
+        gate: bool,
+    }
+
+    impl DSPMeta for GateVoice {
+        type Sample = f32;
+    }
+
+    impl Voice for GateVoice {
+        fn active(&self) -> bool {
+            self.gate
+        }
+
+        fn note_data(&self) -> &NoteData<Self::Sample> {
+            &self.note_data
+        }
+
+        fn note_data_mut(&mut self) -> &mut NoteData<Self::Sample> {
+            &mut self.note_data
+        }
+
+        fn release(&mut self) {
+            self.gate = false;
+        }
+
+        fn reuse(&mut self) {
+            self.gate = true;
+        }
+    }
+
+    #[test]
+    fn clean_inactive_voices_frees_released_voices() {
+        let mut manager = Polyphonic::new(1000.0, 4, |_, note_data| GateVoice {
+            note_data,
+            gate: true,
+        });
+        let ids = [220.0, 330.0, 440.0].map(|frequency| {
+            manager.note_on(NoteData {
+                frequency,
+                velocity: Velocity::new(1.0),
+                gain: Gain::from_linear(1.0),
+                pan: 0.0,
+                pressure: 0.0,
+                modulation_st: 0.0,
+            })
+        });
+        assert_eq!(3, manager.active());
+
+        for id in ids {
+            manager.note_off(id);
+        }
+        assert_eq!(0, manager.active());
+        assert!(ids.iter().all(|&id| manager.get_voice(id).is_some()));
+
+        manager.clean_inactive_voices();
+        assert_eq!(0, manager.active());
+        assert!(ids.iter().all(|&id| manager.get_voice(id).is_none()));
+    }
+
+    #[test]
+    fn note_on_past_capacity_reuses_voices() {
+        let mut manager = Polyphonic::new(1000.0, 2, |_, note_data| GateVoice {
+            note_data,
+            gate: true,
+        });
+        let note_on = |manager: &mut Polyphonic<GateVoice>, frequency| {
+            manager.note_on(NoteData {
+                frequency,
+                velocity: Velocity::new(1.0),
+                gain: Gain::from_linear(1.0),
+                pan: 0.0,
+                pressure: 0.0,
+                modulation_st: 0.0,
+            })
+        };
+
+        let a = note_on(&mut manager, 220.0);
+        let b = note_on(&mut manager, 330.0);
+        assert_ne!(a, b);
+
+        // Released voices are reused before any playing voice is stolen
+        manager.note_off(a);
+        assert_eq!(a, note_on(&mut manager, 440.0));
+        assert_eq!(2, manager.active());
+
+        // All voices playing: voices are stolen in rotation
+        let stolen = (0..5)
+            .map(|i| note_on(&mut manager, 100.0 * i as f32))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0, 1, 0, 1, 0], stolen);
+        assert_eq!(2, manager.active());
+        assert_eq!(400.0, manager.get_voice(0).unwrap().note_data().frequency);
+    }
+}
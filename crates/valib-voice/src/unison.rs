@@ -0,0 +1,399 @@
+//! # Unison voice manager
+//!
+//! Provides a voice manager wrapper stacking several detuned voices per note.
+use crate::{NoteData, Voice, VoiceManager};
+use num_traits::one;
+use std::marker::PhantomData;
+use valib_core::dsp::{DSPMeta, DSPProcess};
+use valib_core::simd::SimdPartialOrd;
+use valib_core::util::semitone_to_ratio;
+use valib_core::Scalar;
+
+/// Voice manager wrapper stacking several voices per note (unison).
+///
+/// Each note on event spawns `count` voices in the inner voice manager, detuned and panned evenly
+/// across the given ranges. The voices of a note are tracked as a group, and note events on the
+/// group apply to all of its voices.
+pub struct UnisonVoiceManager<V: Voice, M: VoiceManager<V>> {
+    /// Inner voice manager, which allocates and processes the individual voices
+    pub inner: M,
+    count: usize,
+    detune_cents: f32,
+    spread: f32,
+    groups: Vec<Vec<M::ID>>,
+    next_group: usize,
+    __voice: PhantomData<V>,
+}
+
+impl<V: Voice, M: VoiceManager<V>> UnisonVoiceManager<V, M> {
+    /// Create a new unison voice manager, with one voice per note.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Inner voice manager
+    ///
+    /// returns: UnisonVoiceManager<V, M>
+    pub fn new(inner: M) -> Self {
+        let mut this = Self {
+            inner,
+            count: 1,
+            detune_cents: 0.0,
+            spread: 0.0,
+            groups: Vec::new(),
+            next_group: 0,
+            __voice: PhantomData,
+        };
+        this.allocate_groups();
+        this
+    }
+
+    /// Set the unison parameters, in a builder fashion. See [`Self::set_unison`].
+    pub fn with_unison(mut self, count: usize, detune_cents: f32, spread: f32) -> Self {
+        self.set_unison(count, detune_cents, spread);
+        self
+    }
+
+    /// Set the unison parameters. Changes are taken into account at the next note on event.
+    ///
+    /// Changing the number of voices per note changes the number of notes which can be played at
+    /// once, and allocates the groups of voices accordingly; notes which do not fit anymore are
+    /// choked.
+    ///
+    /// # Arguments
+    ///
+    /// * `count`: Number of voices per note (at least 1, and at most the capacity of the inner voice
+    ///     manager)
+    /// * `detune_cents`: Detune of the outermost voices, in cents. Voices are spread evenly in
+    ///     between `-detune_cents` and `detune_cents`.
+    /// * `spread`: Stereo spread, in the 0..1 range. Voices are panned evenly in between `-spread`
+    ///     and `spread`, around the pan of the note.
+    pub fn set_unison(&mut self, count: usize, detune_cents: f32, spread: f32) {
+        let count = count.clamp(1, self.inner.capacity().max(1));
+        self.detune_cents = detune_cents;
+        self.spread = spread.clamp(0.0, 1.0);
+        if count != self.count {
+            self.count = count;
+            self.allocate_groups();
+        }
+    }
+
+    /// Number of voices per note
+    pub fn unison_count(&self) -> usize {
+        self.count
+    }
+
+    /// Detune of the unison voice at the given index, in cents.
+    pub fn detune_offset_cents(&self, index: usize) -> f32 {
+        self.detune_cents * position(self.count, index)
+    }
+
+    /// Inner voice IDs of the given group
+    pub fn group_voices(&self, id: usize) -> &[M::ID] {
+        self.groups.get(id).map(|g| g.as_slice()).unwrap_or(&[])
+    }
+
+    /// Unwrap this adaptor and give back the inner voice manager.
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+
+    /// Allocate one group per note which can be played at once, so that no allocation happens
+    /// when playing notes
+    fn allocate_groups(&mut self) {
+        let num_groups = self.capacity();
+        for id in num_groups..self.groups.len() {
+            self.choke(id);
+        }
+        self.groups.truncate(num_groups);
+        self.groups
+            .resize_with(num_groups, || Vec::with_capacity(self.count));
+        for group in &mut self.groups {
+            group.reserve(self.count.saturating_sub(group.len()));
+        }
+        self.next_group = 0;
+    }
+
+    /// Group to play the next note on: a free group, or else an inactive one, or else a group
+    /// stolen in rotation. The group is choked before being returned, so that its voices can be
+    /// reused by the inner voice manager.
+    fn free_group(&mut self) -> usize {
+        let id = self
+            .groups
+            .iter()
+            .position(|g| g.is_empty())
+            .or_else(|| (0..self.groups.len()).find(|&id| !self.is_voice_active(id)))
+            .unwrap_or_else(|| {
+                let id = self.next_group % self.groups.len();
+                self.next_group = id + 1;
+                id
+            });
+        self.choke(id);
+        id
+    }
+
+    /// Apply the function to each voice of the group, along with its index within the group
+    fn for_each_voice(&mut self, id: usize, mut f: impl FnMut(&mut M, usize, M::ID)) {
+        if let Some(group) = self.groups.get(id) {
+            for (i, &voice) in group.iter().enumerate() {
+                f(&mut self.inner, i, voice);
+            }
+        }
+    }
+}
+
+/// Position of the unison voice at the given index, in the -1..1 range
+fn position(count: usize, index: usize) -> f32 {
+    if count == 1 {
+        0.0
+    } else {
+        2.0 * index as f32 / (count - 1) as f32 - 1.0
+    }
+}
+
+impl<V: Voice, M: VoiceManager<V>> DSPMeta for UnisonVoiceManager<V, M> {
+    type Sample = V::Sample;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.inner.set_samplerate(samplerate);
+    }
+
+    fn latency(&self) -> usize {
+        self.inner.latency()
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+impl<V: Voice, M: VoiceManager<V>> VoiceManager<V> for UnisonVoiceManager<V, M> {
+    type ID = usize;
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity() / self.count
+    }
+
+    fn get_voice(&self, id: Self::ID) -> Option<&V> {
+        self.groups
+            .get(id)?
+            .iter()
+            .find_map(|&voice| self.inner.get_voice(voice))
+    }
+
+    fn get_voice_mut(&mut self, id: Self::ID) -> Option<&mut V> {
+        let voice = self
+            .groups
+            .get(id)?
+            .iter()
+            .copied()
+            .find(|&voice| self.inner.get_voice(voice).is_some())?;
+        self.inner.get_voice_mut(voice)
+    }
+
+    fn is_voice_active(&self, id: Self::ID) -> bool {
+        self.group_voices(id)
+            .iter()
+            .any(|&voice| self.inner.is_voice_active(voice))
+    }
+
+    fn all_voices(&self) -> impl Iterator<Item = Self::ID> {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| !group.is_empty())
+            .map(|(id, _)| id)
+    }
+
+    fn note_on(&mut self, note_data: NoteData<V::Sample>) -> Self::ID {
+        let id = self.free_group();
+        let (count, detune_cents, spread) = (self.count, self.detune_cents, self.spread);
+        for i in 0..count {
+            let position = position(count, i) as f64;
+            let detune =
+                semitone_to_ratio(V::Sample::from_f64(detune_cents as f64 * position / 100.0));
+            let pan_offset = V::Sample::from_f64(spread as f64 * position);
+            let voice = self.inner.note_on(NoteData {
+                frequency: note_data.frequency * detune,
+                pan: (note_data.pan + pan_offset).simd_clamp(-one::<V::Sample>(), one()),
+                ..note_data
+            });
+            self.groups[id].push(voice);
+        }
+        id
+    }
+
+    fn note_off(&mut self, id: Self::ID) {
+        self.for_each_voice(id, |inner, _, voice| inner.note_off(voice));
+    }
+
+    fn choke(&mut self, id: Self::ID) {
+        if let Some(group) = self.groups.get_mut(id) {
+            for voice in group.drain(..) {
+                self.inner.choke(voice);
+            }
+        }
+    }
+
+    fn panic(&mut self) {
+        self.groups.iter_mut().for_each(Vec::clear);
+        self.inner.panic();
+    }
+
+    fn clean_inactive_voices(&mut self) {
+        // Inactive groups are dropped as a whole, before their IDs can be reused by the inner
+        // voice manager
+        for id in 0..self.groups.len() {
+            if !self.is_voice_active(id) {
+                self.choke(id);
+            }
+        }
+        self.inner.clean_inactive_voices();
+    }
+
+    fn pitch_bend(&mut self, amount: f64) {
+        self.inner.pitch_bend(amount);
+    }
+
+    fn aftertouch(&mut self, amount: f64) {
+        self.inner.aftertouch(amount);
+    }
+
+    fn pressure(&mut self, id: Self::ID, pressure: f32) {
+        self.for_each_voice(id, |inner, _, voice| inner.pressure(voice, pressure));
+    }
+
+    fn glide(&mut self, id: Self::ID, semitones: f32) {
+        self.for_each_voice(id, |inner, _, voice| inner.glide(voice, semitones));
+    }
+
+    fn pan(&mut self, id: Self::ID, pan: f32) {
+        let (count, spread) = (self.count, self.spread);
+        self.for_each_voice(id, |inner, i, voice| {
+            inner.pan(voice, (pan + spread * position(count, i)).clamp(-1.0, 1.0))
+        });
+    }
+
+    fn gain(&mut self, id: Self::ID, gain: f32) {
+        self.for_each_voice(id, |inner, _, voice| inner.gain(voice, gain));
+    }
+}
+
+impl<V: Voice, M: VoiceManager<V> + DSPProcess<0, O>, const O: usize> DSPProcess<0, O>
+    for UnisonVoiceManager<V, M>
+{
+    fn process(&mut self, x: [Self::Sample; 0]) -> [Self::Sample; O] {
+        self.inner.process(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polyphonic::Polyphonic;
+    use crate::{Gain, Velocity};
+
+    struct GateVoice {
+        note_data: NoteData<f32>,
+        gate: bool,
+    }
+
+    impl DSPMeta for GateVoice {
+        type Sample = f32;
+    }
+
+    impl Voice for GateVoice {
+        fn active(&self) -> bool {
+            self.gate
+        }
+
+        fn note_data(&self) -> &NoteData<Self::Sample> {
+            &self.note_data
+        }
+
+        fn note_data_mut(&mut self) -> &mut NoteData<Self::Sample> {
+            &mut self.note_data
+        }
+
+        fn release(&mut self) {
+            self.gate = false;
+        }
+
+        fn reuse(&mut self) {
+            self.gate = true;
+        }
+    }
+
+    #[test]
+    fn unison_spawns_detuned_voices() {
+        let polyphonic = Polyphonic::new(1000.0, 8, |_, note_data| GateVoice {
+            note_data,
+            gate: true,
+        });
+        let mut manager = UnisonVoiceManager::new(polyphonic).with_unison(3, 20.0, 0.5);
+        let id = manager.note_on(NoteData {
+            frequency: 440.0,
+            velocity: Velocity::new(1.0),
+            gain: Gain::from_linear(1.0),
+            pan: 0.0,
+            pressure: 0.0,
+            modulation_st: 0.0,
+        });
+        assert_eq!(1, manager.active());
+        assert_eq!(3, manager.inner.active());
+
+        let voices = manager
+            .group_voices(id)
+            .iter()
+            .map(|&voice| *manager.inner.get_voice(voice).unwrap().note_data())
+            .collect::<Vec<_>>();
+        for (note, cents) in voices.iter().zip([-20.0, 0.0, 20.0]) {
+            let expected = 440.0 * f32::powf(2.0, cents / 1200.0);
+            assert!((note.frequency - expected).abs() < 1e-3, "{note:?}");
+        }
+        let pans = voices.iter().map(|n| n.pan).collect::<Vec<_>>();
+        assert_eq!(vec![-0.5, 0.0, 0.5], pans);
+
+        manager.note_off(id);
+        assert_eq!(0, manager.inner.active());
+        manager.clean_inactive_voices();
+        assert!(manager.group_voices(id).is_empty());
+        assert_eq!(0, manager.all_voices().count());
+    }
+
+    #[test]
+    fn unison_steals_groups_past_capacity() {
+        let polyphonic = Polyphonic::new(1000.0, 8, |_, note_data| GateVoice {
+            note_data,
+            gate: true,
+        });
+        let mut manager = UnisonVoiceManager::new(polyphonic).with_unison(3, 20.0, 0.5);
+        assert_eq!(2, manager.capacity());
+
+        let ids = [220.0, 330.0, 440.0, 550.0, 660.0].map(|frequency| {
+            manager.note_on(NoteData {
+                frequency,
+                velocity: Velocity::new(1.0),
+                gain: Gain::from_linear(1.0),
+                pan: 0.0,
+                pressure: 0.0,
+                modulation_st: 0.0,
+            })
+        });
+        assert_eq!([0, 1, 0, 1, 0], ids);
+        assert_eq!(2, manager.active());
+        assert_eq!(6, manager.inner.active());
+
+        // Each group still owns its own voices, playing its own note
+        let mut voices = manager.group_voices(0).to_vec();
+        voices.extend_from_slice(manager.group_voices(1));
+        voices.sort_unstable();
+        voices.dedup();
+        assert_eq!(6, voices.len());
+        for (id, frequency) in [(0, 660.0), (1, 550.0)] {
+            for &voice in manager.group_voices(id) {
+                let note = manager.inner.get_voice(voice).unwrap().note_data();
+                assert!((note.frequency / frequency - 1.0).abs() < 0.02, "{note:?}");
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Nodes which can take other nodes, and "adapt them" in some fashion.
 use crate::dsl::{node_mut, node_ref};
-use crate::{AdaptedWdf, Node, Wave, Wdf};
+use crate::{AdaptedWdf, Node, Wave, Wdf, WdfNode};
 use num_traits::Zero;
 use valib_core::simd::SimdComplexField;
 
@@ -80,6 +80,11 @@ impl<A: AdaptedWdf, B: AdaptedWdf<Scalar = A::Scalar>> Wdf for Series<A, B> {
         self.a.set_zero();
         self.b.set_zero();
     }
+
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {
+        visitor(&self.left);
+        visitor(&self.right);
+    }
 }
 
 impl<A: AdaptedWdf, B: AdaptedWdf<Scalar = A::Scalar>> AdaptedWdf for Series<A, B> {
@@ -169,6 +174,11 @@ impl<A: AdaptedWdf, B: AdaptedWdf<Scalar = A::Scalar>> Wdf for Parallel<A, B> {
         self.btemp.set_zero();
         self.bdiff.set_zero();
     }
+
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {
+        visitor(&self.left);
+        visitor(&self.right);
+    }
 }
 
 impl<A: AdaptedWdf, B: AdaptedWdf<Scalar = A::Scalar>> AdaptedWdf for Parallel<A, B> {
@@ -235,6 +245,10 @@ impl<A: AdaptedWdf> Wdf for Inverter<A> {
         self.a.set_zero();
         self.b.set_zero();
     }
+
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {
+        visitor(&self.inner);
+    }
 }
 
 impl<A: AdaptedWdf> AdaptedWdf for Inverter<A> {
@@ -246,3 +260,77 @@ impl<A: AdaptedWdf> AdaptedWdf for Inverter<A> {
         node_ref(&self.inner).admittance()
     }
 }
+
+/// Ideal transformer node, connecting its inner node to the secondary winding.
+///
+/// With a turns ratio `n` (primary to secondary), the primary voltage is `n` times the secondary
+/// voltage, and the primary current is the secondary current divided by `n`. The port resistance
+/// seen from the primary is therefore `n^2` times the resistance of the secondary.
+pub struct Transformer<A: AdaptedWdf> {
+    /// Inner node, connected to the secondary winding
+    pub inner: Node<A>,
+    /// Turns ratio (primary to secondary)
+    pub n: A::Scalar,
+    a: A::Scalar,
+    b: A::Scalar,
+}
+
+impl<A: AdaptedWdf> Transformer<A> {
+    /// Create a new ideal transformer node adapter.
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: Turns ratio (primary to secondary)
+    /// * `inner`: Inner node, connected to the secondary winding
+    ///
+    /// returns: Transformer<A>
+    pub fn new(n: A::Scalar, inner: Node<A>) -> Self {
+        Self {
+            inner,
+            n,
+            a: A::Scalar::zero(),
+            b: A::Scalar::zero(),
+        }
+    }
+}
+
+impl<A: AdaptedWdf> Wdf for Transformer<A> {
+    type Scalar = A::Scalar;
+
+    fn wave(&self) -> Wave<Self::Scalar> {
+        Wave {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn incident(&mut self, x: Self::Scalar) {
+        node_mut(&self.inner).incident(x / self.n);
+        self.a = x;
+    }
+
+    fn reflected(&mut self) -> Self::Scalar {
+        self.b = self.n * node_mut(&self.inner).reflected();
+        self.b
+    }
+
+    fn set_samplerate(&mut self, samplerate: f64) {
+        node_mut(&self.inner).set_samplerate(samplerate);
+    }
+
+    fn reset(&mut self) {
+        node_mut(&self.inner).reset();
+        self.a.set_zero();
+        self.b.set_zero();
+    }
+
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {
+        visitor(&self.inner);
+    }
+}
+
+impl<A: AdaptedWdf> AdaptedWdf for Transformer<A> {
+    fn impedance(&self) -> Self::Scalar {
+        self.n * self.n * node_ref(&self.inner).impedance()
+    }
+}
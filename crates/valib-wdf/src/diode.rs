@@ -68,7 +68,7 @@ impl<T: Scalar> Wdf for DiodeLambert<T> {
     }
 
     fn reflected(&mut self) -> Self::Scalar {
-        let mu0 = self.nf.select(self.a.is_simd_positive(), self.nf);
+        let mu0 = self.nf.select(self.a.is_simd_positive(), self.nb);
         let mu1 = self.nb.select(self.a.is_simd_positive(), self.nf);
         let ris_vt = self.r * self.isat / self.nvt;
         let lam = self.a.simd_signum();
@@ -149,8 +149,8 @@ impl<T: Scalar> DiodeLambert<T> {
     /// * `nb`: Number of backward diodes
     ///
     /// returns: ()
-    pub fn set_num_backward(&mut self, nf: usize) {
-        self.nb = T::from_f64(nf as _);
+    pub fn set_num_backward(&mut self, nb: usize) {
+        self.nb = T::from_f64(nb as _);
     }
 }
 
@@ -202,8 +202,8 @@ impl<T: Scalar> RootEq for DiodeRootEq<T> {
         let log_m = self.nf.simd_ln();
         let log_n = self.nb.simd_ln();
         let exp_op = (self.a + b) / (2.0 * self.n * self.vt);
-        let e0 = log_m + log_risat - exp_op / self.nf;
-        let e1 = log_n + log_risat + exp_op / self.nb;
+        let e0 = log_n + log_risat + exp_op / self.nf;
+        let e1 = log_m + log_risat - exp_op / self.nb;
         let mnnvt = self.nf * self.nb * self.n * self.vt;
         let inner = 2.0 * self.r * mnnvt / (mnnvt + e0.simd_exp() + e1.simd_exp());
         Some([inner].into())
@@ -306,8 +306,8 @@ impl<T: Scalar> DiodeNR<T> {
     /// * `nb`: number of backward diodes
     ///
     /// returns: ()
-    pub fn set_num_backward(&mut self, nf: usize) {
-        self.root_eq.nb = T::from_f64(nf as _);
+    pub fn set_num_backward(&mut self, nb: usize) {
+        self.root_eq.nb = T::from_f64(nb as _);
     }
 }
 
@@ -351,6 +351,7 @@ impl<T: Scalar<Element: Float>> Wdf for DiodeNR<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use valib_core::dsp::DSPProcess;
     use valib_core::util::tests::{Plot, Series};
     use valib_saturators::clippers::DiodeClipper;
 
@@ -500,4 +501,43 @@ mod tests {
         .create_svg("plots/wdf/diode_clipper_nr.svg");
         insta::assert_csv_snapshot!(&output, { "[]" => insta::rounded_redaction(4) })
     }
+
+    #[test]
+    fn test_diode_dc_transfer_matches_clipper() {
+        // The reference clipper solves for a 1 Ohm source resistance and a 1 Ohm load
+        let mut reference = DiodeClipper::new_silicon(3, 2, 0.);
+
+        let lambert = diode_lambert(DiodeClipper::new_silicon(1, 1, 0.));
+        node_mut(&lambert).set_num_forward(3);
+        node_mut(&lambert).set_num_backward(2);
+        let rvs_lambert = rvsource(1.0, 0.);
+        let mut module_lambert = module(lambert, parallel(rvs_lambert.clone(), resistor(1.0)));
+
+        let nr = diode_nr(DiodeClipper::new_silicon(1, 1, 0.));
+        node_mut(&nr).set_num_forward(3);
+        node_mut(&nr).set_num_backward(2);
+        let rvs_nr = rvsource(1.0, 0.);
+        let mut module_nr = module(nr, parallel(rvs_nr.clone(), resistor(1.0)));
+
+        for i in -50..=50 {
+            let vin = i as f32 / 5.0;
+            let [expected] = reference.process([vin]);
+
+            node_mut(&rvs_lambert).vs = vin;
+            module_lambert.process_sample();
+            let actual = voltage(&module_lambert.root);
+            assert!(
+                (expected - actual).abs() < 1e-4,
+                "Lambert W: vin = {vin}, expected {expected}, got {actual}"
+            );
+
+            node_mut(&rvs_nr).vs = vin;
+            module_nr.process_sample();
+            let actual = voltage(&module_nr.root);
+            assert!(
+                (expected - actual).abs() < 1e-4,
+                "Newton-Rhapson: vin = {vin}, expected {expected}, got {actual}"
+            );
+        }
+    }
 }
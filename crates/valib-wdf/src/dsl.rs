@@ -55,6 +55,22 @@ pub fn capacitor<T: Scalar>(fs: T, c: T) -> Node<Capacitor<T>> {
     node(Capacitor::new(fs, c))
 }
 
+/// Create a new capacitor with an equivalent series resistance.
+///
+/// See [`LossyCapacitor::new`] for more details.
+#[inline]
+pub fn lossy_capacitor<T: Scalar>(fs: T, c: T, r: T) -> Node<LossyCapacitor<T>> {
+    node(LossyCapacitor::new(fs, c, r))
+}
+
+/// Create a new inductor with an equivalent series resistance.
+///
+/// See [`LossyInductor::new`] for more details.
+#[inline]
+pub fn lossy_inductor<T: Scalar>(fs: T, l: T, r: T) -> Node<LossyInductor<T>> {
+    node(LossyInductor::new(fs, l, r))
+}
+
 /// Create a new resistive voltage source.
 ///
 /// See [`ResistiveVoltageSource::new`] for more details.
@@ -141,6 +157,14 @@ pub fn inverter<W: AdaptedWdf>(inner: Node<W>) -> Node<Inverter<W>> {
     node(Inverter::new(inner))
 }
 
+/// Create a new ideal transformer wdf adapter node.
+///
+/// See [`Transformer::new`] for more details.
+#[inline]
+pub fn transformer<W: AdaptedWdf>(n: W::Scalar, inner: Node<W>) -> Node<Transformer<W>> {
+    node(Transformer::new(n, inner))
+}
+
 /// Create a new Lambert W function-based diode clipper node.
 ///
 /// See [`DiodeLambert::new`] for more details.
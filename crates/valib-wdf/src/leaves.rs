@@ -3,7 +3,7 @@
 //! Provides nodes which only have one port.
 use num_traits::Zero;
 
-use crate::{AdaptedWdf, Wave, Wdf};
+use crate::{AdaptedWdf, Wave, Wdf, WdfSource};
 use valib_core::Scalar;
 
 /// Resistive voltage source leaf.
@@ -44,6 +44,12 @@ impl<T: Scalar> Wdf for ResistiveVoltageSource<T> {
     }
 }
 
+impl<T: Scalar> WdfSource for ResistiveVoltageSource<T> {
+    fn set_source(&mut self, value: Self::Scalar) {
+        self.vs = value;
+    }
+}
+
 impl<T: Scalar> AdaptedWdf for ResistiveVoltageSource<T> {
     fn impedance(&self) -> Self::Scalar {
         self.r
@@ -132,6 +138,12 @@ impl<T: Scalar> Wdf for ResistiveCurrentSource<T> {
     }
 }
 
+impl<T: Scalar> WdfSource for ResistiveCurrentSource<T> {
+    fn set_source(&mut self, value: Self::Scalar) {
+        self.j = value;
+    }
+}
+
 impl<T: Scalar> AdaptedWdf for ResistiveCurrentSource<T> {
     fn impedance(&self) -> Self::Scalar {
         self.r
@@ -252,3 +264,251 @@ impl<T: Scalar> AdaptedWdf for Capacitor<T> {
         self.c * self.fs * T::from_f64(2.0)
     }
 }
+
+/// Capacitor leaf node with an equivalent series resistance (ESR).
+///
+/// This is equivalent to a [`Resistor`] and a [`Capacitor`] connected through a [`Series`]
+/// adapter, computed as a single node.
+///
+/// [`Series`]: crate::Series
+#[derive(Debug, Copy, Clone)]
+pub struct LossyCapacitor<T> {
+    /// Sample rate (Hz)
+    pub fs: T,
+    /// Capacitance (F)
+    pub c: T,
+    /// Equivalent series resistance (Ohm)
+    pub r: T,
+    a: T,
+    b: T,
+    /// Wave reflected by the capacitor itself
+    state: T,
+}
+
+impl<T: Scalar> LossyCapacitor<T> {
+    /// Create a new lossy capacitor leaf node.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs`: Sample rate (Hz)
+    /// * `c`: Capacitance (F)
+    /// * `r`: Equivalent series resistance (Ohm)
+    ///
+    /// returns: LossyCapacitor<T>
+    pub fn new(fs: T, c: T, r: T) -> Self {
+        Self {
+            fs,
+            c,
+            r,
+            a: T::zero(),
+            b: T::zero(),
+            state: T::zero(),
+        }
+    }
+
+    fn capacitor_impedance(&self) -> T {
+        (T::from_f64(2.0) * self.c * self.fs).simd_recip()
+    }
+}
+
+impl<T: Scalar> Wdf for LossyCapacitor<T> {
+    type Scalar = T;
+
+    fn wave(&self) -> Wave<Self::Scalar> {
+        Wave {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn incident(&mut self, x: Self::Scalar) {
+        // Series adaptation of the resistor (which reflects no wave) with the capacitor
+        let p = self.r / (self.r + self.capacitor_impedance());
+        self.state = p * (x + self.state) - x;
+        self.a = x;
+    }
+
+    fn reflected(&mut self) -> Self::Scalar {
+        self.b = -self.state;
+        self.b
+    }
+
+    fn set_samplerate(&mut self, samplerate: f64) {
+        self.fs = T::from_f64(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.a.set_zero();
+        self.b.set_zero();
+        self.state.set_zero();
+    }
+}
+
+impl<T: Scalar> AdaptedWdf for LossyCapacitor<T> {
+    fn impedance(&self) -> Self::Scalar {
+        self.r + self.capacitor_impedance()
+    }
+}
+
+/// Inductor leaf node with an equivalent series resistance (ESR), for example the resistance of
+/// its winding.
+///
+/// The inductor itself is discretized with the bilinear transform, like [`Capacitor`].
+#[derive(Debug, Copy, Clone)]
+pub struct LossyInductor<T> {
+    /// Sample rate (Hz)
+    pub fs: T,
+    /// Inductance (H)
+    pub l: T,
+    /// Equivalent series resistance (Ohm)
+    pub r: T,
+    a: T,
+    b: T,
+    /// Wave reflected by the inductor itself
+    state: T,
+}
+
+impl<T: Scalar> LossyInductor<T> {
+    /// Create a new lossy inductor leaf node.
+    ///
+    /// # Arguments
+    ///
+    /// * `fs`: Sample rate (Hz)
+    /// * `l`: Inductance (H)
+    /// * `r`: Equivalent series resistance (Ohm)
+    ///
+    /// returns: LossyInductor<T>
+    pub fn new(fs: T, l: T, r: T) -> Self {
+        Self {
+            fs,
+            l,
+            r,
+            a: T::zero(),
+            b: T::zero(),
+            state: T::zero(),
+        }
+    }
+
+    fn inductor_impedance(&self) -> T {
+        T::from_f64(2.0) * self.l * self.fs
+    }
+}
+
+impl<T: Scalar> Wdf for LossyInductor<T> {
+    type Scalar = T;
+
+    fn wave(&self) -> Wave<Self::Scalar> {
+        Wave {
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    fn incident(&mut self, x: Self::Scalar) {
+        // Series adaptation of the resistor (which reflects no wave) with the inductor, which
+        // reflects its incident wave inverted
+        let p = self.r / (self.r + self.inductor_impedance());
+        self.state = x - p * (x + self.state);
+        self.a = x;
+    }
+
+    fn reflected(&mut self) -> Self::Scalar {
+        self.b = -self.state;
+        self.b
+    }
+
+    fn set_samplerate(&mut self, samplerate: f64) {
+        self.fs = T::from_f64(samplerate);
+    }
+
+    fn reset(&mut self) {
+        self.a.set_zero();
+        self.b.set_zero();
+        self.state.set_zero();
+    }
+}
+
+impl<T: Scalar> AdaptedWdf for LossyInductor<T> {
+    fn impedance(&self) -> Self::Scalar {
+        self.r + self.inductor_impedance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dsl::*;
+    use crate::{AdaptedWdf, Node, Wdf};
+
+    const FS: f64 = 48e3;
+    const R: f64 = 1e3;
+    const ESR: f64 = 100.0;
+
+    /// Voltage across the load when driven through a resistor, with a signal alternating between
+    /// `x` and `-x` when `alternate` is set
+    fn drive<W: AdaptedWdf<Scalar = f64>>(load: Node<W>, x: f64, alternate: bool) -> Vec<f64> {
+        let rvs = rvsource(R, 0.0);
+        let mut module = module(open_circuit(), parallel(rvs.clone(), load));
+        (0..2000)
+            .map(|i| {
+                let sign = if alternate && i % 2 == 1 { -1.0 } else { 1.0 };
+                node_mut(&rvs).vs = sign * x;
+                module.process_sample();
+                voltage(&module.root)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lossy_capacitor_matches_series_resistor() {
+        let lossy = drive(lossy_capacitor(FS, 1e-7, ESR), 1.0, true);
+        let composed = drive(series(resistor(ESR), capacitor(FS, 1e-7)), 1.0, true);
+        for (a, b) in lossy.iter().zip(&composed) {
+            assert!((a - b).abs() < 1e-12, "{a} != {b}");
+        }
+
+        // Without ESR, this is an ideal capacitor
+        let lossless = drive(lossy_capacitor(FS, 1e-7, 0.0), 1.0, true);
+        let ideal = drive(capacitor(FS, 1e-7), 1.0, true);
+        for (a, b) in lossless.iter().zip(&ideal) {
+            assert!((a - b).abs() < 1e-12, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn lossy_rc_damping() {
+        let last = |output: Vec<f64>| output.last().copied().unwrap().abs();
+
+        // At Nyquist, the capacitor is a short: the ideal RC filter fully attenuates, while the
+        // ESR forms a voltage divider with the source resistance
+        let ideal = last(drive(capacitor(FS, 1e-7), 1.0, true));
+        let lossy = last(drive(lossy_capacitor(FS, 1e-7, ESR), 1.0, true));
+        assert!(ideal < 1e-6, "Ideal RC gain at Nyquist {ideal}");
+        let expected = ESR / (R + ESR);
+        assert!(
+            (lossy - expected).abs() < 1e-6,
+            "Lossy RC gain at Nyquist {lossy}"
+        );
+
+        // At DC, the capacitor is open and both filters let the signal through
+        let ideal = last(drive(capacitor(FS, 1e-7), 1.0, false));
+        let lossy = last(drive(lossy_capacitor(FS, 1e-7, ESR), 1.0, false));
+        assert!((ideal - 1.0).abs() < 1e-6);
+        assert!((lossy - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lossy_inductor_divides_at_dc() {
+        let inductor = lossy_inductor(FS, 1e-3, ESR);
+        assert_eq!(ESR + 2.0 * 1e-3 * FS, node_ref(&inductor).impedance());
+
+        // At DC, the inductor is a short, leaving only its ESR
+        let dc = drive(inductor.clone(), 1.0, false).last().copied().unwrap();
+        let expected = ESR / (R + ESR);
+        assert!((dc - expected).abs() < 1e-6, "Gain at DC {dc}");
+
+        // At Nyquist, the inductor is open
+        node_mut(&inductor).reset();
+        let nyquist = drive(inductor, 1.0, true).last().copied().unwrap().abs();
+        assert!((nyquist - 1.0).abs() < 1e-6, "Gain at Nyquist {nyquist}");
+    }
+}
@@ -112,6 +112,15 @@ pub trait Wdf {
     fn set_port_resistance(&mut self, resistance: Self::Scalar) {}
     /// Reset the internal state of this node.
     fn reset(&mut self);
+    /// Call the visitor on each of the nodes plugged into the downward facing ports of this node.
+    /// Leaves have no such nodes, which is the default.
+    ///
+    /// # Arguments
+    ///
+    /// * `visitor`: Function called with each child node
+    ///
+    /// returns: ()
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {}
 }
 
 impl<'a, T: Wdf> Wdf for &'a mut T {
@@ -136,6 +145,45 @@ impl<'a, T: Wdf> Wdf for &'a mut T {
     fn reset(&mut self) {
         T::reset(self)
     }
+
+    fn for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) {
+        T::for_each_child(self, visitor)
+    }
+}
+
+/// Type-erased view of a shared WDF node, used to walk the tree without knowing its types.
+pub trait WdfNode {
+    /// Identifier of the node, shared by all handles to the same node.
+    fn node_id(&self) -> usize;
+    /// Name of the type of the node, for diagnostics.
+    fn type_name(&self) -> &'static str;
+    /// Call the visitor on each child of this node, as with [`Wdf::for_each_child`]. Returns
+    /// `false` without calling the visitor if the node is currently mutably borrowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `visitor`: Function called with each child node
+    ///
+    /// returns: bool
+    fn try_for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) -> bool;
+}
+
+impl<T: Wdf> WdfNode for Node<T> {
+    fn node_id(&self) -> usize {
+        Arc::as_ptr(self) as *const () as usize
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn try_for_each_child(&self, visitor: &mut dyn FnMut(&dyn WdfNode)) -> bool {
+        let Ok(node) = self.try_borrow() else {
+            return false;
+        };
+        node.for_each_child(visitor);
+        true
+    }
 }
 
 /// Adapted WDF node. Nodes which can set a specific port resistance to prevent delay-free loops
@@ -155,6 +203,18 @@ pub trait AdaptedWdf: Wdf {
     }
 }
 
+/// WDF source node, whose source value can be driven from an external signal.
+pub trait WdfSource: Wdf {
+    /// Set the value of the source (voltage for voltage sources, current for current sources).
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: New source value
+    ///
+    /// returns: ()
+    fn set_source(&mut self, value: Self::Scalar);
+}
+
 impl<'a, T: AdaptedWdf> AdaptedWdf for &'a mut T {
     fn impedance(&self) -> Self::Scalar {
         T::impedance(self)
@@ -170,6 +230,7 @@ mod tests {
     use crate::dsl::*;
     use plotters::prelude::{BLUE, RED};
     use std::f32::consts::TAU;
+    use valib_core::dsp::{DSPMeta, DSPProcess};
     use valib_core::util::tests::Plot;
 
     #[test]
@@ -181,6 +242,23 @@ mod tests {
         assert_eq!(6.0, voltage(&out));
     }
 
+    #[test]
+    fn test_loaded_transformer() {
+        const N: f32 = 4.0;
+        const R: f32 = 100.0;
+        let load = resistor(R);
+        let xfmr = transformer(N, load.clone());
+        let mut module = module(ivsource(12.), xfmr.clone());
+        module.process_sample();
+
+        assert_eq!(12.0, voltage(&xfmr));
+        assert_eq!(3.0, voltage(&load));
+        // Secondary current is set by the load, primary current is scaled down by the turns ratio
+        let i_secondary = current(&load);
+        assert!((i_secondary.abs() - 0.03).abs() < 1e-6, "Got {i_secondary}");
+        assert!((current(&xfmr) - i_secondary / N).abs() < 1e-6);
+    }
+
     #[test]
     fn test_lowpass_filter() {
         const C: f32 = 33e-9;
@@ -224,4 +302,38 @@ mod tests {
         .create_svg("plots/wdf/low_pass.svg");
         insta::assert_csv_snapshot!(&output, { "[]" => insta::rounded_redaction(4) })
     }
+
+    #[test]
+    fn test_lowpass_filter_as_dsp() {
+        const C: f32 = 33e-9;
+        const CUTOFF: f32 = 256.0;
+        const FS: f32 = 4096.0;
+        let r = f32::recip(TAU * C * CUTOFF);
+        let input = (0..256)
+            .map(|i| 2.0 * f32::fract(50.0 * i as f32 / FS) - 1.0)
+            .collect::<Vec<_>>();
+
+        let rvs = rvsource(r, 0.);
+        let mut manual = module(open_circuit(), parallel(rvs.clone(), capacitor(FS, C)));
+        let expected = input
+            .iter()
+            .map(|&x| {
+                node_mut(&rvs).vs = x;
+                manual.process_sample();
+                voltage(&manual.root)
+            })
+            .collect::<Vec<_>>();
+
+        // Created at the wrong sample rate, to check that it gets propagated into the tree
+        let rvs = rvsource(r, 0.);
+        let root = open_circuit();
+        let mut dsp =
+            module(root.clone(), parallel(rvs.clone(), capacitor(1.0, C))).as_dsp(rvs, root);
+        dsp.set_samplerate(FS);
+        let actual = input
+            .iter()
+            .map(|&x| dsp.process([x])[0])
+            .collect::<Vec<_>>();
+        assert_eq!(expected, actual);
+    }
 }
@@ -1,13 +1,59 @@
 //! # WDF module
 //!
 //! Provides a module which can drive the entire WDF tree for each sample.
-use crate::dsl::node_mut;
-use crate::{AdaptedWdf, Node, Wdf};
+use std::fmt;
+
+use crate::dsl::{node_mut, voltage};
+use crate::{AdaptedWdf, Node, Wdf, WdfNode, WdfSource};
+use valib_core::dsp::{DSPMeta, DSPProcess};
+
+/// Error returned by [`WdfModule::validate`] when the tree cannot be processed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WdfTopologyError {
+    /// The node appears more than once in the tree. Processing the tree would borrow it mutably
+    /// while it is already borrowed, which panics.
+    SharedNode(&'static str),
+    /// The node is currently mutably borrowed outside the module, so that processing the tree
+    /// would panic.
+    NodeBorrowed(&'static str),
+}
+
+impl fmt::Display for WdfTopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SharedNode(name) => write!(f, "Node {name} appears more than once in the tree"),
+            Self::NodeBorrowed(name) => write!(f, "Node {name} is already mutably borrowed"),
+        }
+    }
+}
+
+impl std::error::Error for WdfTopologyError {}
+
+/// Walk the tree under `node`, recording the visited nodes into `seen`.
+fn validate_node(node: &dyn WdfNode, seen: &mut Vec<usize>) -> Result<(), WdfTopologyError> {
+    let id = node.node_id();
+    if seen.contains(&id) {
+        return Err(WdfTopologyError::SharedNode(node.type_name()));
+    }
+    seen.push(id);
+
+    let mut result = Ok(());
+    let borrowed = node.try_for_each_child(&mut |child| {
+        if result.is_ok() {
+            result = validate_node(child, seen);
+        }
+    });
+    if !borrowed {
+        return Err(WdfTopologyError::NodeBorrowed(node.type_name()));
+    }
+    result
+}
 
 /// WDF Module type. This type takes care of processing the whole tree when processing a sample.
 ///
 /// It does not take care of inputs and outputs; they should be manually set and manually read by
-/// cloning relevant nodes and reading/mutating them.
+/// cloning relevant nodes and reading/mutating them, or by wrapping the module into a
+/// [`DSPProcess`] implementation with [`WdfModule::as_dsp`].
 pub struct WdfModule<Root: Wdf, Leaf: AdaptedWdf<Scalar = Root::Scalar>> {
     /// Root of the tree. Can be unadaptable.
     pub root: Node<Root>,
@@ -43,6 +89,18 @@ impl<Root: Wdf, Leaf: AdaptedWdf<Scalar = Root::Scalar>> WdfModule<Root, Leaf> {
         leaf.set_samplerate(samplerate);
     }
 
+    /// Check that the tree can be processed, that is that no node appears more than once in it,
+    /// and that no node is currently mutably borrowed. Processing a module which fails validation
+    /// panics.
+    ///
+    /// This walks the entire tree and allocates, and is meant to be called once after building
+    /// the module, for example in a debug assertion.
+    pub fn validate(&self) -> Result<(), WdfTopologyError> {
+        let mut seen = Vec::new();
+        validate_node(&self.root, &mut seen)?;
+        validate_node(&self.leaf, &mut seen)
+    }
+
     /// Process a single sample, propagating all waves downwards and back up.
     pub fn process_sample(&mut self) {
         let mut root = node_mut(&self.root);
@@ -57,4 +115,112 @@ impl<Root: Wdf, Leaf: AdaptedWdf<Scalar = Root::Scalar>> WdfModule<Root, Leaf> {
         node_mut(&self.root).reset();
         node_mut(&self.leaf).reset();
     }
+
+    /// Wrap this module into a [`DSPProcess<1, 1>`] implementation, where the input drives the
+    /// given source node, and the output is the voltage across the given output node.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: Source node driven by the input signal. Must be part of the tree.
+    /// * `output`: Node whose voltage is used as the output signal. Must be part of the tree.
+    ///
+    /// returns: WdfModuleDsp<Root, Leaf, In, Out>
+    pub fn as_dsp<In, Out>(
+        self,
+        input: Node<In>,
+        output: Node<Out>,
+    ) -> WdfModuleDsp<Root, Leaf, In, Out>
+    where
+        In: WdfSource<Scalar = Root::Scalar>,
+        Out: Wdf<Scalar = Root::Scalar>,
+    {
+        WdfModuleDsp {
+            module: self,
+            input,
+            output,
+        }
+    }
+}
+
+/// [`DSPProcess`] adaptor over a [`WdfModule`], created with [`WdfModule::as_dsp`].
+pub struct WdfModuleDsp<Root: Wdf, Leaf: AdaptedWdf<Scalar = Root::Scalar>, In, Out> {
+    /// Inner WDF module
+    pub module: WdfModule<Root, Leaf>,
+    /// Source node driven by the input signal
+    pub input: Node<In>,
+    /// Node whose voltage is used as the output signal
+    pub output: Node<Out>,
+}
+
+impl<Root, Leaf, In, Out> DSPMeta for WdfModuleDsp<Root, Leaf, In, Out>
+where
+    Root: Wdf,
+    Leaf: AdaptedWdf<Scalar = Root::Scalar>,
+{
+    type Sample = Root::Scalar;
+
+    fn set_samplerate(&mut self, samplerate: f32) {
+        self.module.set_samplerate(samplerate as _);
+    }
+
+    fn reset(&mut self) {
+        self.module.reset();
+    }
+}
+
+#[profiling::all_functions]
+impl<Root, Leaf, In, Out> DSPProcess<1, 1> for WdfModuleDsp<Root, Leaf, In, Out>
+where
+    Root: Wdf,
+    Leaf: AdaptedWdf<Scalar = Root::Scalar>,
+    In: WdfSource<Scalar = Root::Scalar>,
+    Out: Wdf<Scalar = Root::Scalar>,
+{
+    fn process(&mut self, [x]: [Self::Sample; 1]) -> [Self::Sample; 1] {
+        node_mut(&self.input).set_source(x);
+        self.module.process_sample();
+        [voltage(&self.output)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsl::*;
+
+    #[test]
+    fn validate_detects_shared_nodes() {
+        let r = resistor(100.0);
+        let valid = module(ivsource(12.0), series(r.clone(), resistor(100.0)));
+        assert_eq!(Ok(()), valid.validate());
+
+        // Same node on both sides of an adapter
+        let invalid = module(ivsource(12.0), series(r.clone(), r.clone()));
+        assert!(matches!(
+            invalid.validate(),
+            Err(WdfTopologyError::SharedNode(name)) if name.contains("Resistor")
+        ));
+
+        // Same node in two different subtrees
+        let invalid = module(
+            ivsource(12.0),
+            parallel(
+                series(r.clone(), capacitor(48e3, 1e-6)),
+                inverter(r.clone()),
+            ),
+        );
+        assert!(matches!(
+            invalid.validate(),
+            Err(WdfTopologyError::SharedNode(_))
+        ));
+
+        // Node mutably borrowed elsewhere
+        let guard = node_mut(&r);
+        assert!(matches!(
+            valid.validate(),
+            Err(WdfTopologyError::NodeBorrowed(_))
+        ));
+        drop(guard);
+        assert_eq!(Ok(()), valid.validate());
+    }
 }
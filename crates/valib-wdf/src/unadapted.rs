@@ -1,7 +1,7 @@
 //! # WDF unadapted nodes
 //!
 //! Provides nodes which cannot be adapted anywhere in the tree, and have to sit at the root.
-use crate::{Wave, Wdf};
+use crate::{Wave, Wdf, WdfSource};
 use num_traits::Zero;
 use valib_core::dsp::{DSPMeta, DSPProcess};
 use valib_core::simd::SimdBool;
@@ -58,6 +58,12 @@ impl<T: Scalar> Wdf for IdealVoltageSource<T> {
     }
 }
 
+impl<T: Scalar> WdfSource for IdealVoltageSource<T> {
+    fn set_source(&mut self, value: Self::Scalar) {
+        self.vs = value;
+    }
+}
+
 /// Ideal current source WDF node.
 #[derive(Debug, Copy, Clone)]
 pub struct IdealCurrentSource<T> {
@@ -122,6 +128,12 @@ impl<T: Scalar> Wdf for IdealCurrentSource<T> {
     }
 }
 
+impl<T: Scalar> WdfSource for IdealCurrentSource<T> {
+    fn set_source(&mut self, value: Self::Scalar) {
+        self.j = value;
+    }
+}
+
 /// Short circuit WDF node.
 #[derive(Debug, Copy, Clone)]
 pub struct ShortCircuit<T> {
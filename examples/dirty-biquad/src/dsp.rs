@@ -6,9 +6,10 @@ use nih_plug::prelude::Enum;
 use valib::dsp::parameter::{HasParameters, ParamId, ParamName, RemoteControlled, SmoothedParam};
 use valib::dsp::{BlockAdapter, DSPMeta, DSPProcess};
 use valib::filters::biquad::Biquad;
+use valib::filters::cached::Cached;
 use valib::oversample::{Oversample, Oversampled};
 use valib::saturators::clippers::DiodeClipperModel;
-use valib::saturators::{Dynamic, Linear};
+use valib::saturators::{Dynamic, DynamicKind, Linear};
 use valib::simd::{AutoF32x2, SimdComplexField, SimdValue};
 
 use crate::{MAX_BLOCK_SIZE, OVERSAMPLE};
@@ -70,10 +71,12 @@ impl fmt::Display for SaturatorType {
 impl SaturatorType {
     pub fn saturator(&self) -> Dynamic<Sample> {
         match self {
-            Self::Linear => Dynamic::Linear,
-            Self::Tanh => Dynamic::Tanh,
-            Self::DiodeSym => Dynamic::DiodeClipper(DiodeClipperModel::new_silicon(1, 1)),
-            Self::DiodeAssym => Dynamic::DiodeClipper(DiodeClipperModel::new_germanium(1, 2)),
+            Self::Linear => Dynamic::new(DynamicKind::Linear),
+            Self::Tanh => Dynamic::new(DynamicKind::Tanh),
+            Self::DiodeSym => Dynamic::new(DynamicKind::DiodeClipper)
+                .with_diode_clipper(DiodeClipperModel::new_silicon(1, 1)),
+            Self::DiodeAssym => Dynamic::new(DynamicKind::DiodeClipper)
+                .with_diode_clipper(DiodeClipperModel::new_germanium(1, 2)),
         }
     }
 }
@@ -94,6 +97,7 @@ pub struct DspInner {
     resonance: SmoothedParam,
     ftype: FilterType,
     // saturator: SaturatorType,
+    coefficients: Cached<(f32, f32, FilterType), Biquad<Sample, Linear>>,
     biquad: Biquad<Sample, Dynamic<Sample>>,
 }
 
@@ -156,10 +160,14 @@ impl HasParameters for DspInner {
 
 impl DspInner {
     fn update_biquad(&mut self) {
-        let fc = Sample::splat(self.fc.next_sample() / self.samplerate);
-        let res = Sample::splat(self.resonance.next_sample());
-        let biquad = self.ftype.as_biquad(fc, res);
-        self.biquad.update_coefficients(&biquad);
+        let fc = self.fc.next_sample() / self.samplerate;
+        let res = self.resonance.next_sample();
+        let biquad = self
+            .coefficients
+            .get_or_update((fc, res, self.ftype), |&(fc, res, ftype)| {
+                ftype.as_biquad(Sample::splat(fc), Sample::splat(res))
+            });
+        self.biquad.update_coefficients(biquad);
     }
 }
 
@@ -173,6 +181,10 @@ pub fn create(samplerate: f32) -> RemoteControlled<Dsp> {
         resonance: SmoothedParam::linear(0.5, samplerate, 10.0),
         ftype: FilterType::Lowpass,
         //saturator: SaturatorType::Linear,
+        coefficients: Cached::new(Biquad::lowpass(
+            Sample::splat(3000.0 / samplerate),
+            Sample::splat(0.5),
+        )),
         biquad: Biquad::lowpass(Sample::splat(3000.0 / samplerate), Sample::splat(0.5))
             .with_saturators(Dynamic::default(), Dynamic::default()),
     };
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use nih_plug::prelude::*;
 use nih_plug::util::db_to_gain;
 
-use valib::contrib::nih_plug::{process_buffer_simd, BindToParameter};
+use valib::contrib::nih_plug::{bind_all, process_buffer_simd};
 use valib::dsp::parameter::{RemoteControl, RemoteControlled};
 use valib::dsp::DSPMeta;
 
@@ -31,8 +31,8 @@ struct DirtyBiquadParams {
 
 impl DirtyBiquadParams {
     fn new(remote: &RemoteControl<DspParameters>) -> Arc<Self> {
-        Arc::new(Self {
-            drive: FloatParam::new(
+        Arc::new(bind_all!(remote, DspParameters => Self {
+            Drive => drive: FloatParam::new(
                 "Drive",
                 1.0,
                 FloatRange::Skewed {
@@ -43,9 +43,8 @@ impl DirtyBiquadParams {
             )
             .with_unit(" dB")
             .with_string_to_value(formatters::s2v_f32_gain_to_db())
-            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
-            .bind_to_parameter(remote, DspParameters::Drive),
-            cutoff: FloatParam::new(
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2)),
+            Cutoff => cutoff: FloatParam::new(
                 "Cutoff",
                 3000.0,
                 FloatRange::Skewed {
@@ -55,9 +54,8 @@ impl DirtyBiquadParams {
                 },
             )
             .with_string_to_value(formatters::s2v_f32_hz_then_khz())
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, true))
-            .bind_to_parameter(remote, DspParameters::Cutoff),
-            resonance: FloatParam::new(
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, true)),
+            Resonance => resonance: FloatParam::new(
                 "Resonance",
                 0.5,
                 FloatRange::Skewed {
@@ -66,19 +64,10 @@ impl DirtyBiquadParams {
                     factor: FloatRange::skew_factor(-2.0),
                 },
             )
-            .with_value_to_string(formatters::v2s_f32_rounded(2))
-            .bind_to_parameter(remote, DspParameters::Resonance),
-            filter_type: {
-                //enum_int_param::<FilterType>("Filter type", FilterType::Lowpass).bind_to_parameter(remote, )
-                EnumParam::new("Filter type", FilterType::Lowpass)
-                    .bind_to_parameter(remote, DspParameters::FilterType)
-            },
-            saturator_type: {
-                //enum_int_param::<SaturatorType>("Saturator type", SaturatorType::Linear).into()
-                EnumParam::new("Saturator type", SaturatorType::Linear)
-                    .bind_to_parameter(remote, DspParameters::SaturatorType)
-            },
-        })
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            FilterType => filter_type: EnumParam::new("Filter type", FilterType::Lowpass),
+            SaturatorType => saturator_type: EnumParam::new("Saturator type", SaturatorType::Linear),
+        }))
     }
 }
 
@@ -12,22 +12,22 @@ impl SpectrumAnalyzer {
     }
 
     fn draw_analyzer(&self, cx: &mut DrawContext, canvas: &mut Canvas, bounds: BoundingBox) {
-        let mut spectrum = self.spectrum.lock().unwrap();
-        let spectrum = spectrum.read();
-        let samplerate = spectrum.samplerate;
+        let mut reader = self.spectrum.lock().unwrap();
+        let samplerate = reader.samplerate();
+        let data = reader.read_latest();
         let nyquist = samplerate / 2.0;
         let range = LogRange::new(2.0, 20.0, 24e3);
         let line_paint = vg::Paint::color(cx.font_color().into());
 
         let mut path = vg::Path::new();
 
-        for (i, y) in spectrum.data.iter().copied().enumerate() {
+        for (i, y) in data.iter().copied().enumerate() {
             if i == 0 {
                 path.move_to(bounds.x - 100., bounds.y + bounds.h);
                 continue;
             }
 
-            let freq_norm = i as f32 / spectrum.data.len() as f32;
+            let freq_norm = i as f32 / data.len() as f32;
             let frequency = freq_norm * nyquist;
             let x = range.normalize(frequency);
             let slope = 3.;
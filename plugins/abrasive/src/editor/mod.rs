@@ -1,5 +1,5 @@
 use crate::editor::background::Background;
-use crate::spectrum::Spectrum;
+use crate::spectrum::SpectrumReader;
 use analyzer::SpectrumAnalyzer;
 use atomic_float::AtomicF32;
 use nih_plug::prelude::*;
@@ -8,7 +8,6 @@ use nih_plug_vizia::{create_vizia_editor, vizia::prelude::*, ViziaState, ViziaTh
 use resource::resource;
 use std::fs;
 use std::sync::{Arc, Mutex};
-use triple_buffer::Output;
 
 mod analyzer;
 
@@ -20,7 +19,9 @@ pub mod components;
 mod eq;
 mod util;
 
-pub type SpectrumUI = Arc<Mutex<Output<Spectrum>>>;
+// The mutex is only ever locked from the UI thread, as the audio thread publishes through the
+// lock-free triple buffer behind `SpectrumReader`.
+pub type SpectrumUI = Arc<Mutex<SpectrumReader>>;
 
 #[derive(Lens, Clone)]
 pub(crate) struct Data {
@@ -1,5 +1,6 @@
 use std::{
     cell::Cell,
+    f32::consts::TAU,
     fmt::{self, Formatter},
     sync::{atomic::Ordering::Relaxed, Arc},
 };
@@ -11,17 +12,21 @@ use nih_plug::{
 };
 use realfft::{num_complex::Complex32, num_traits::Zero, RealFftPlanner, RealToComplex};
 use triple_buffer::{Input, Output, TripleBuffer};
+use valib::math::window::WindowFunction;
 use valib::util::lerp;
 
 pub struct Spectrum {
     pub window_size: usize,
     pub samplerate: f32,
+    /// Number of magnitude spectra stored in `data`
+    pub num_channels: usize,
+    /// Magnitude spectra, stored one after the other
     pub data: Box<[f32]>,
 }
 
 impl Clone for Spectrum {
     fn clone(&self) -> Self {
-        let mut this = Self::new(self.window_size, self.samplerate);
+        let mut this = Self::new(self.window_size, self.samplerate, self.num_channels);
         this.data.copy_from_slice(&self.data);
         this
     }
@@ -29,61 +34,333 @@ impl Clone for Spectrum {
     fn clone_from(&mut self, source: &Self) {
         self.window_size = source.window_size;
         self.samplerate = source.samplerate;
+        self.num_channels = source.num_channels;
         self.data.copy_from_slice(&source.data);
     }
 }
 
 impl Spectrum {
-    fn new(window_size: usize, samplerate: f32) -> Spectrum {
+    fn new(window_size: usize, samplerate: f32, num_channels: usize) -> Spectrum {
         Self {
             window_size,
             samplerate,
-            data: vec![0.; window_size / 2 + 1].into_boxed_slice(),
+            num_channels,
+            data: vec![0.; num_channels * (window_size / 2 + 1)].into_boxed_slice(),
         }
     }
+
+    /// Number of frequency bins in each magnitude spectrum.
+    pub fn num_bins(&self) -> usize {
+        self.window_size / 2 + 1
+    }
+
+    /// Magnitude spectrum of the given channel. What the channels are depends on the
+    /// [`AnalyzerMode`] of the analyzer which produced this spectrum.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `channel` is out of bounds.
+    pub fn channel(&self, channel: usize) -> &[f32] {
+        let bins = self.num_bins();
+        &self.data[channel * bins..][..bins]
+    }
 }
 
 impl fmt::Debug for Spectrum {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Spectrum")
             .field("window_size", &self.window_size)
+            .field("num_channels", &self.num_channels)
             .finish_non_exhaustive()
     }
 }
 
-pub struct Analyzer {
-    stft: StftHelper,
-    input: Input<Spectrum>,
-    scratch: Spectrum,
+/// UI-side handle to the spectrum published by an [`Analyzer`].
+///
+/// The handoff goes through a triple buffer: the audio thread never waits on the reader, and the
+/// reader always gets a complete frame.
+pub struct SpectrumReader {
+    output: Output<Spectrum>,
     samplerate: Arc<AtomicF32>,
+    mode: AnalyzerMode,
+}
+
+impl SpectrumReader {
+    /// Returns the magnitudes of the first channel of the latest spectrum published by the
+    /// analyzer (the summed signal, the left channel, or the mid channel depending on the
+    /// [`AnalyzerMode`]).
+    ///
+    /// This needs exclusive access because the reader owns one of the three buffers, which gets
+    /// swapped with the latest published one.
+    pub fn read_latest(&mut self) -> &[f32] {
+        self.read_latest_spectrum().channel(0)
+    }
+
+    /// Returns the latest spectrum published by the analyzer, with all of its channels.
+    pub fn read_latest_spectrum(&mut self) -> &Spectrum {
+        self.output.read()
+    }
+
+    /// Channel mode of the analyzer, which gives the meaning of the spectrum channels.
+    pub fn mode(&self) -> AnalyzerMode {
+        self.mode
+    }
+
+    /// Sample rate of the analyzed signal.
+    pub fn samplerate(&self) -> f32 {
+        self.samplerate.load(Relaxed)
+    }
+}
+
+/// Window function applied to each analysis frame.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum WindowType {
+    /// Hann window, good general-purpose frequency resolution.
+    #[default]
+    Hann,
+    /// 4-term Blackman-Harris window, with a very low sidelobe level at the cost of a wider main
+    /// lobe.
+    BlackmanHarris,
+    /// Flat-top window, with very accurate amplitude readings at the cost of a very wide main lobe.
+    FlatTop,
+}
+
+impl WindowType {
+    fn function(&self) -> WindowFunction {
+        match self {
+            Self::Hann => WindowFunction::Hann,
+            Self::BlackmanHarris => WindowFunction::BlackmanHarris,
+            Self::FlatTop => WindowFunction::FlatTop,
+        }
+    }
+
+    /// Generate the (periodic) window of the given size.
+    pub fn generate(&self, size: usize) -> Vec<f32> {
+        // Periodic window, from a symmetric window one sample longer
+        let mut window = vec![0.0; size + 1];
+        self.function().fill(&mut window);
+        window.pop();
+        window
+    }
+}
+
+/// Selects which signals are analyzed from the input channels.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AnalyzerMode {
+    /// Single spectrum of the average of all input channels.
+    Summed,
+    /// One spectrum per input channel.
+    #[default]
+    PerChannel,
+    /// Two spectra, of the mid and side channels of a stereo input.
+    MidSide,
+}
+
+impl AnalyzerMode {
+    /// Number of spectra produced in this mode. There is always at least one spectrum, which
+    /// stays silent when there are no input channels.
+    pub fn num_spectra(&self, num_channels: usize) -> usize {
+        match self {
+            Self::Summed => 1,
+            Self::PerChannel => num_channels.max(1),
+            Self::MidSide => 2,
+        }
+    }
+}
+
+/// FFT analysis of single frames, accumulating the magnitudes into its scratch spectrum.
+struct FrameAnalyzer {
+    scratch: Spectrum,
     plan: Arc<dyn RealToComplex<f32>>,
     fft_buffer: Vec<Complex32>,
     window: Vec<f32>,
+}
+
+impl FrameAnalyzer {
+    fn new(samplerate: f32, fft_size: usize, window: WindowType) -> Self {
+        Self {
+            scratch: Spectrum::new(fft_size, samplerate, 1),
+            plan: RealFftPlanner::new().plan_fft_forward(fft_size),
+            fft_buffer: vec![Complex32::zero(); fft_size / 2 + 1],
+            window: window
+                .generate(fft_size)
+                .into_iter()
+                .map(|x| x / fft_size as f32)
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, frame: &mut [f32], hop_size: usize, decay: f32) {
+        multiply_with_window(frame, &self.window);
+        if self
+            .plan
+            .process_with_scratch(frame, &mut self.fft_buffer, &mut [])
+            .is_err()
+        {
+            self.fft_buffer.fill(Complex32::zero());
+        }
+        for (scratch, fft) in self
+            .scratch
+            .data
+            .iter_mut()
+            .zip(self.fft_buffer.iter_mut().map(|c| c.norm()))
+        {
+            // let mix = 1.
+            //     - f32::exp(
+            //         -self.scratch.samplerate / self.window.len() as f32 / 2. * self.decay.get(),
+            //     );
+            let decay = f32::ln(1e-3) / decay;
+            let mix = f32::exp(decay * hop_size as f32 / self.scratch.samplerate);
+            *scratch = lerp(mix, fft, *scratch).max(fft);
+        }
+    }
+}
+
+/// Combines the frames of each input channel according to the [`AnalyzerMode`], and analyzes the
+/// resulting signals.
+struct ChannelMixer {
+    mode: AnalyzerMode,
+    fft_size: usize,
+    frames: Vec<FrameAnalyzer>,
+    channel_frames: Vec<Vec<f32>>,
+}
+
+impl ChannelMixer {
+    fn new(
+        samplerate: f32,
+        num_channels: usize,
+        fft_size: usize,
+        window: WindowType,
+        mode: AnalyzerMode,
+    ) -> Self {
+        let num_spectra = mode.num_spectra(num_channels);
+        Self {
+            mode,
+            fft_size,
+            frames: (0..num_spectra)
+                .map(|_| FrameAnalyzer::new(samplerate, fft_size, window))
+                .collect(),
+            channel_frames: vec![vec![0.; fft_size]; num_channels],
+        }
+    }
+
+    /// Process the frame of a single channel. The STFT gives the frames of all channels in order
+    /// for each hop, so the modes combining channels analyze them once the last channel is in.
+    fn process(&mut self, channel_idx: usize, frame: &mut [f32], hop_size: usize, decay: f32) {
+        if self.mode == AnalyzerMode::PerChannel {
+            self.frames[channel_idx].process(frame, hop_size, decay);
+            return;
+        }
+
+        self.channel_frames[channel_idx].copy_from_slice(frame);
+        if channel_idx + 1 < self.channel_frames.len() {
+            return;
+        }
+
+        let num_channels = self.channel_frames.len();
+        match self.mode {
+            AnalyzerMode::Summed => {
+                for i in 0..frame.len() {
+                    let sum = self.channel_frames.iter().map(|f| f[i]).sum::<f32>();
+                    self.channel_frames[0][i] = sum / num_channels as f32;
+                }
+            }
+            AnalyzerMode::MidSide => {
+                let [left, right] = &mut self.channel_frames[..] else {
+                    unreachable!()
+                };
+                for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                    (*l, *r) = (0.5 * (*l + *r), 0.5 * (*l - *r));
+                }
+            }
+            AnalyzerMode::PerChannel => unreachable!(),
+        }
+        for (analyzer, frame) in self.frames.iter_mut().zip(&mut self.channel_frames) {
+            analyzer.process(frame, hop_size, decay);
+        }
+    }
+}
+
+/// Number of overlapping frames per FFT window.
+const OVERLAP: usize = 2;
+
+pub struct Analyzer {
+    stft: StftHelper,
+    input: Input<Spectrum>,
+    mixer: ChannelMixer,
+    samplerate: Arc<AtomicF32>,
     decay: Cell<f32>,
 }
 
 impl Analyzer {
-    pub fn new(
+    pub fn new(samplerate: f32, num_channels: usize, window_size: usize) -> (Self, SpectrumReader) {
+        Self::with_config(samplerate, num_channels, window_size, WindowType::default())
+    }
+
+    /// Create a new analyzer with the given FFT size and window function, analyzing each channel
+    /// into its own spectrum.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `fft_size` is not a power of two.
+    pub fn with_config(
+        samplerate: f32,
+        num_channels: usize,
+        fft_size: usize,
+        window: WindowType,
+    ) -> (Self, SpectrumReader) {
+        Self::with_mode(
+            samplerate,
+            num_channels,
+            fft_size,
+            window,
+            AnalyzerMode::default(),
+        )
+    }
+
+    /// Create a new analyzer with the given FFT size, window function and channel mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `fft_size` is not a power of two, or when using [`AnalyzerMode::MidSide`]
+    /// with a number of channels other than 2.
+    pub fn with_mode(
         samplerate: f32,
         num_channels: usize,
-        window_size: usize,
-    ) -> (Self, Output<Spectrum>) {
-        let scratch = Spectrum::new(window_size, samplerate);
-        let (input, output) = TripleBuffer::new(&scratch).split();
+        fft_size: usize,
+        window: WindowType,
+        mode: AnalyzerMode,
+    ) -> (Self, SpectrumReader) {
+        assert!(
+            fft_size.is_power_of_two(),
+            "FFT size must be a power of two, got {fft_size}"
+        );
+        assert!(
+            mode != AnalyzerMode::MidSide || num_channels == 2,
+            "Mid/side analysis needs 2 channels, got {num_channels}"
+        );
+        let mixer = ChannelMixer::new(samplerate, num_channels, fft_size, window, mode);
+        let spectrum = Spectrum::new(fft_size, samplerate, mixer.frames.len());
+        let (input, output) = TripleBuffer::new(&spectrum).split();
+        let samplerate = Arc::new(AtomicF32::new(samplerate));
+        let reader = SpectrumReader {
+            output,
+            samplerate: samplerate.clone(),
+            mode,
+        };
         let this = Self {
-            stft: StftHelper::new(num_channels, window_size, 0),
+            stft: StftHelper::new(num_channels, fft_size, 0),
             input,
-            scratch,
-            samplerate: Arc::new(AtomicF32::new(samplerate)),
-            plan: RealFftPlanner::new().plan_fft_forward(window_size),
-            fft_buffer: vec![Complex32::zero(); window_size / 2 + 1],
-            window: util::window::hann(window_size)
-                .into_iter()
-                .map(|x| x / window_size as f32)
-                .collect(),
+            mixer,
+            samplerate,
             decay: Cell::new(100e-3),
         };
-        (this, output)
+        (this, reader)
+    }
+
+    /// Channel mode of the analyzer.
+    pub fn mode(&self) -> AnalyzerMode {
+        self.mixer.mode
     }
 
     pub fn set_samplerate(&self, samplerate: f32) {
@@ -91,15 +368,15 @@ impl Analyzer {
     }
 
     #[cfg(never)]
-    pub fn set_window_size(&mut self, window_size: usize) {
+    pub fn set_window_size(&mut self, window_size: usize, window: WindowType) {
         self.stft.set_block_size(window_size);
-        self.plan = RealFftPlanner::new().plan_fft_forward(window_size);
-        self.fft_buffer
-            .resize(window_size / 2 + 1, Complex32::zero());
-        self.window = util::window::hann(window_size)
-            .into_iter()
-            .map(|x| x / window_size as f32)
-            .collect();
+        self.mixer = ChannelMixer::new(
+            self.samplerate.load(Relaxed),
+            self.mixer.channel_frames.len(),
+            window_size,
+            window,
+            self.mixer.mode,
+        );
     }
 
     pub fn set_decay(&self, ms: f32) {
@@ -107,32 +384,166 @@ impl Analyzer {
     }
 
     pub fn process_buffer(&mut self, buffer: &Buffer) {
-        self.scratch.samplerate = self.samplerate.load(Relaxed);
-        self.stft.process_analyze_only(buffer, 2, |_, buffer| {
-            multiply_with_window(buffer, &self.window);
-            if self
-                .plan
-                .process_with_scratch(buffer, &mut self.fft_buffer, &mut [])
-                .is_err()
-            {
-                self.fft_buffer.fill(Complex32::zero());
-            }
-            for (scratch, fft) in self
-                .scratch
-                .data
-                .iter_mut()
-                .zip(self.fft_buffer.iter_mut().map(|c| c.norm()))
-            {
-                // let mix = 1.
-                //     - f32::exp(
-                //         -self.scratch.samplerate / self.window.len() as f32 / 2. * self.decay.get(),
-                //     );
-                let decay = f32::ln(1e-3) / self.decay.get();
-                let mix = f32::exp(decay * 1024. / self.scratch.samplerate);
-                *scratch = lerp(mix, fft, *scratch).max(fft);
+        let samplerate = self.samplerate.load(Relaxed);
+        for frame in &mut self.mixer.frames {
+            frame.scratch.samplerate = samplerate;
+        }
+        let hop_size = self.mixer.fft_size / OVERLAP;
+        let decay = self.decay.get();
+        self.stft
+            .process_analyze_only(buffer, OVERLAP, |channel_idx, buffer| {
+                self.mixer.process(channel_idx, buffer, hop_size, decay)
+            });
+        self.publish();
+    }
+
+    fn publish(&mut self) {
+        let spectrum = self.input.input_buffer();
+        spectrum.samplerate = self.samplerate.load(Relaxed);
+        let bins = spectrum.num_bins();
+        for (data, frame) in spectrum.data.chunks_exact_mut(bins).zip(&self.mixer.frames) {
+            data.copy_from_slice(&frame.scratch.data);
+        }
+        self.input.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FFT_SIZE: usize = 2048;
+    const SAMPLERATE: f32 = 44.1e3;
+
+    /// Analyze a single frame of a sine wave centered on the given bin, and returns the magnitude
+    /// spectrum.
+    fn analyze_tone(window: WindowType, bin: usize) -> Box<[f32]> {
+        let mut analyzer = FrameAnalyzer::new(SAMPLERATE, FFT_SIZE, window);
+        let mut frame = (0..FFT_SIZE)
+            .map(|i| f32::sin(TAU * (bin * i) as f32 / FFT_SIZE as f32))
+            .collect::<Vec<_>>();
+        analyzer.process(&mut frame, FFT_SIZE / OVERLAP, 100e-3);
+        analyzer.scratch.data
+    }
+
+    fn check_main_lobe(window: WindowType, expected_width: usize) {
+        const BIN: usize = 100;
+        let spectrum = analyze_tone(window, BIN);
+        let (peak_bin, peak) = spectrum
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+        assert_eq!(BIN, peak_bin);
+
+        let threshold = peak * 1e-4;
+        let width = spectrum.iter().filter(|x| **x > threshold).count();
+        assert_eq!(
+            expected_width, width,
+            "{window:?}: unexpected main lobe width {width}"
+        );
+    }
+
+    #[test]
+    fn hann_tone_in_bin() {
+        check_main_lobe(WindowType::Hann, 3);
+    }
+
+    #[test]
+    fn blackman_harris_tone_in_bin() {
+        check_main_lobe(WindowType::BlackmanHarris, 7);
+    }
+
+    #[test]
+    fn flat_top_tone_in_bin() {
+        check_main_lobe(WindowType::FlatTop, 9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fft_size_must_be_power_of_two() {
+        let _ = Analyzer::with_config(SAMPLERATE, 2, 1000, WindowType::Hann);
+    }
+
+    #[test]
+    fn reader_sees_consistent_frames() {
+        const FRAMES: usize = 10_000;
+        let (mut input, output) =
+            TripleBuffer::new(&Spectrum::new(FFT_SIZE, SAMPLERATE, 1)).split();
+        let mut reader = SpectrumReader {
+            output,
+            samplerate: Arc::new(AtomicF32::new(SAMPLERATE)),
+            mode: AnalyzerMode::Summed,
+        };
+
+        let producer = std::thread::spawn(move || {
+            for i in 1..=FRAMES {
+                input.input_buffer().data.fill(i as f32);
+                input.publish();
             }
         });
-        self.input.input_buffer().clone_from(&self.scratch);
-        self.input.publish();
+
+        let mut last = 0.0;
+        while !producer.is_finished() || last < FRAMES as f32 {
+            let data = reader.read_latest();
+            let first = data[0];
+            assert!(
+                data.iter().all(|x| *x == first),
+                "Torn frame: frame starts with {first}"
+            );
+            assert!(first >= last, "Frame went back from {last} to {first}");
+            last = first;
+        }
+        producer.join().unwrap();
+    }
+
+    /// Analyze a tone panned hard left with the given mode, and returns the published spectrum.
+    fn analyze_hard_left(mode: AnalyzerMode) -> Spectrum {
+        let (mut analyzer, mut reader) =
+            Analyzer::with_mode(SAMPLERATE, 2, FFT_SIZE, WindowType::Hann, mode);
+        for _ in 0..4 {
+            let mut left = (0..FFT_SIZE)
+                .map(|i| f32::sin(TAU * (100 * i) as f32 / FFT_SIZE as f32))
+                .collect::<Vec<_>>();
+            let mut right = vec![0.; FFT_SIZE];
+            analyzer
+                .mixer
+                .process(0, &mut left, FFT_SIZE / OVERLAP, 100e-3);
+            analyzer
+                .mixer
+                .process(1, &mut right, FFT_SIZE / OVERLAP, 100e-3);
+        }
+        analyzer.publish();
+        reader.read_latest_spectrum().clone()
+    }
+
+    #[test]
+    fn per_channel_hard_left() {
+        let spectrum = analyze_hard_left(AnalyzerMode::PerChannel);
+        assert_eq!(2, spectrum.num_channels);
+        assert!(spectrum.channel(0)[100] > 0.1);
+        assert!(spectrum.channel(1).iter().all(|x| *x == 0.));
+    }
+
+    #[test]
+    fn summed_and_mid_side_hard_left() {
+        let summed = analyze_hard_left(AnalyzerMode::Summed);
+        assert_eq!(1, summed.num_channels);
+
+        let mid_side = analyze_hard_left(AnalyzerMode::MidSide);
+        assert_eq!(2, mid_side.num_channels);
+        // A hard-panned signal has as much energy in the mid and side channels, which both
+        // match the average of both channels
+        assert_eq!(mid_side.channel(0), mid_side.channel(1));
+        assert_eq!(summed.channel(0), mid_side.channel(0));
+    }
+
+    #[test]
+    fn per_channel_without_channels() {
+        let (mut analyzer, mut reader) = Analyzer::new(SAMPLERATE, 0, FFT_SIZE);
+        assert_eq!(AnalyzerMode::PerChannel, analyzer.mode());
+        analyzer.publish();
+        assert!(reader.read_latest().iter().all(|x| *x == 0.));
     }
 }
@@ -1,4 +1,5 @@
 #![doc = include_str!("./README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate core;
 
 pub use valib_core::*;
@@ -6,12 +7,22 @@ pub use valib_core::*;
 #[cfg(any(feature = "fundsp", feature = "nih-plug"))]
 pub mod contrib;
 
+#[cfg(feature = "convolution")]
+pub use valib_convolution as convolution;
+#[cfg(feature = "dynamics")]
+pub use valib_dynamics as dynamics;
+#[cfg(feature = "effects")]
+pub use valib_effects as effects;
 #[cfg(feature = "filters")]
 pub use valib_filters as filters;
 #[cfg(feature = "oscillators")]
 pub use valib_oscillators as oscillators;
 #[cfg(feature = "oversample")]
 pub use valib_oversample as oversample;
+#[cfg(feature = "pitch")]
+pub use valib_pitch as pitch;
+#[cfg(feature = "reverb")]
+pub use valib_reverb as reverb;
 #[cfg(feature = "saturators")]
 pub use valib_saturators as saturators;
 #[cfg(feature = "voice")]